@@ -10,7 +10,9 @@ use nightscript_android::codegen::{
     },
     x86_64::{elf_writer::write_elf, emitter::emit_x86_64, lower::lower_ir},
 };
-use nightscript_android::ir::{build_ir, format_ir};
+use nightscript_android::ir::passes::check_module;
+use nightscript_android::ir::{build_ir, format_ir, IrModule, PassManager};
+use nightscript_android::{diagnostics, type_checker};
 
 use crate::{parse_source, ProjectContext};
 
@@ -49,12 +51,23 @@ pub fn build_project(
     let source = fs::read_to_string(&main_path)
         .with_context(|| format!("failed to read {}", main_path.display()))?;
     let ast = parse_source(&source).context("stage: parse")?;
-    type_check_stub(&ast).context("stage: type_check")?;
+    type_check(&source, &ast).context("stage: type_check")?;
 
-    let ir_module = build_ir(&ast);
+    let mut ir_module = lower_ast(&source, &ast).context("stage: build_ir")?;
     if dump_ir {
         println!("{}", format_ir(&ir_module));
     }
+    check_module(&ir_module).context("stage: verify_ir")?;
+    if let BuildProfile::Release = profile {
+        PassManager::release()
+            .run_with(&mut ir_module, |pass, module| {
+                if dump_ir {
+                    println!("; IR after {pass}");
+                    println!("{}", format_ir(module));
+                }
+            })
+            .context("stage: optimize_ir")?;
+    }
 
     let artifact = match target {
         BuildTarget::X86_64 => {
@@ -170,6 +183,26 @@ fn validate_elf(path: &PathBuf, target: BuildTarget) -> Result<()> {
     Ok(())
 }
 
-fn type_check_stub(_ast: &nightscript_android::ast::File) -> Result<()> {
-    Ok(())
+fn type_check(source: &str, ast: &nightscript_android::ast::File) -> Result<()> {
+    let errors = type_checker::check_file(ast);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let message = errors
+        .iter()
+        .map(|err| diagnostics::format_diagnostic(source, Some(err.span), &err.message))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Err(anyhow!("type checking failed\n{message}"))
+}
+
+fn lower_ast(source: &str, ast: &nightscript_android::ast::File) -> Result<IrModule> {
+    build_ir(ast).map_err(|errors| {
+        let message = errors
+            .iter()
+            .map(|err| diagnostics::format_diagnostic(source, Some(err.span), &err.message))
+            .collect::<Vec<_>>()
+            .join("\n");
+        anyhow!("native build failed\n{message}")
+    })
 }
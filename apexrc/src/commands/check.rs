@@ -1,7 +1,7 @@
 use std::fs;
 
 use anyhow::Result;
-use nightscript_android::{diagnostics, lexer, parser, validation};
+use nightscript_android::{diagnostics, lexer, parser, type_checker, validation};
 use walkdir::WalkDir;
 
 use crate::ProjectContext;
@@ -47,6 +47,15 @@ pub fn check_project(ctx: &ProjectContext) -> Result<()> {
                 had_errors = true;
                 continue;
             }
+            let type_errors = type_checker::check_file(&report.file);
+            if !type_errors.is_empty() {
+                for err in type_errors {
+                    let msg =
+                        diagnostics::format_diagnostic(&contents, Some(err.span), &err.message);
+                    println!("{}:\n{}", entry.path().display(), msg);
+                }
+                had_errors = true;
+            }
         }
     }
     if had_errors {
@@ -9,8 +9,12 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use anyhow::{anyhow, Context, Result};
+use nightscript_android::bytecode::{compiler, vm::Vm, AfbcModule};
+use nightscript_android::{diagnostics, validation};
 use sha2::{Digest, Sha256};
 
+use crate::parse_source;
+
 /// Web build target output directory
 const WEB_TARGET_DIR: &str = "target/web";
 
@@ -58,13 +62,10 @@ pub fn build_web(manifest_path: &Path) -> Result<WebBuildOutput> {
     let target_dir = target_base.join(&project_id).join(&build_id);
     fs::create_dir_all(&target_dir).context("Failed to create target directory")?;
 
-    // For MVP, we create a placeholder .afbc file
-    // In full implementation, this would compile AFML -> bytecode
     let afbc_name = format!("afns_app.{}.afbc", &build_id);
     let afbc_path = target_dir.join(&afbc_name);
 
-    // Create placeholder bytecode (will be replaced with real compiler)
-    create_placeholder_bytecode(&afbc_path, &afml_source)?;
+    compile_bytecode(&afbc_path, &afml_source)?;
 
     // Compute app hash
     let afbc_content = fs::read(&afbc_path)?;
@@ -130,6 +131,22 @@ pub fn run_web(manifest_path: &Path, port: Option<u16>) -> Result<()> {
     Ok(())
 }
 
+/// Compile `src/main.afml` to AFBC in memory and execute it with the native
+/// bytecode VM, without writing the web bundle.
+pub fn run_vm(manifest_path: &Path) -> Result<()> {
+    let project_root = manifest_path.parent().unwrap_or(Path::new("."));
+    let main_path = project_root.join("src").join("main.afml");
+    let source = fs::read_to_string(&main_path)
+        .with_context(|| format!("failed to read {}", main_path.display()))?;
+    let module = compile_module(&source)?;
+
+    println!("Running {} on the bytecode VM", main_path.display());
+    let mut vm = Vm::new(&module).map_err(|err| anyhow!("{}", err.message()))?;
+    vm.run()
+        .map_err(|err| anyhow!("runtime error: {}", err.message()))?;
+    Ok(())
+}
+
 /// Web build output info
 pub struct WebBuildOutput {
     pub target_dir: PathBuf,
@@ -160,56 +177,36 @@ fn compute_build_id(afml_source: &str, apex_toml: &str) -> String {
     hex::encode(&result[..6]) // 12 hex chars
 }
 
-/// Create placeholder bytecode file
-fn create_placeholder_bytecode(path: &Path, source: &str) -> Result<()> {
-    // Create a minimal .afbc file that includes the FULL source
+/// Compile main.afml to AFBC and write it to `path`
+fn compile_bytecode(path: &Path, source: &str) -> Result<()> {
+    let module = compile_module(source)?;
     let mut buf = Vec::new();
-
-    // Magic: "AFBC"
-    buf.extend_from_slice(b"AFBC");
-
-    // Version: 1 (u16 LE)
-    buf.extend_from_slice(&1u16.to_le_bytes());
-
-    // Flags: 0 (u32 LE)
-    buf.extend_from_slice(&0u32.to_le_bytes());
-
-    // Constant pool count: 2
-    buf.extend_from_slice(&2u32.to_le_bytes());
-
-    // Constant 0: "apex" (utf8)
-    buf.push(1); // tag = utf8
-    buf.extend_from_slice(&4u32.to_le_bytes()); // len
-    buf.extend_from_slice(b"apex");
-
-    // Constant 1: FULL source code (NOT truncated!)
-    // This is critical for project isolation - the VM parses this to render UI
-    let source_bytes = source.as_bytes();
-    buf.push(1); // tag = utf8
-    buf.extend_from_slice(&(source_bytes.len() as u32).to_le_bytes());
-    buf.extend_from_slice(source_bytes);
-
-    // Function count: 1
-    buf.extend_from_slice(&1u32.to_le_bytes());
-
-    // Function 0: apex
-    buf.extend_from_slice(&0u32.to_le_bytes()); // name_idx
-    buf.extend_from_slice(&0u16.to_le_bytes()); // arity
-    buf.extend_from_slice(&0u16.to_le_bytes()); // locals
-    buf.extend_from_slice(&0u32.to_le_bytes()); // code_offset
-    buf.extend_from_slice(&1u32.to_le_bytes()); // code_len
-
-    // Bytecode: just RET
-    buf.extend_from_slice(&1u32.to_le_bytes()); // bytecode len
-    buf.push(0x11); // RET opcode
-
-    // No debug section
-    buf.push(0);
-
+    module.write(&mut buf)?;
     fs::write(path, &buf)?;
     Ok(())
 }
 
+fn compile_module(source: &str) -> Result<AfbcModule> {
+    let ast = parse_source(source).context("stage: parse")?;
+    let errors = validation::validate_file(&ast);
+    if !errors.is_empty() {
+        let message = errors
+            .iter()
+            .map(|err| diagnostics::format_diagnostic(source, Some(err.span), &err.message))
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Err(anyhow!("validation failed\n{message}"));
+    }
+    compiler::compile_file(&ast).map_err(|errors| {
+        let message = errors
+            .iter()
+            .map(|err| diagnostics::format_diagnostic(source, Some(err.span), &err.message))
+            .collect::<Vec<_>>()
+            .join("\n");
+        anyhow!("bytecode compilation failed\n{message}")
+    })
+}
+
 /// Generate manifest JSON with project isolation fields
 fn generate_manifest(
     project_id: &str,
@@ -426,85 +423,415 @@ window.AFNS_VM = (function() {
     const bytecodeLen = view.getUint32(offset, true);
     offset += 4;
     const bytecode = new Uint8Array(buffer, offset, bytecodeLen);
+    offset += bytecodeLen;
+    
+    // Optional source map
+    const sourceMap = [];
+    if (offset < buffer.byteLength && view.getUint8(offset) === 1) {
+      const count = view.getUint32(offset + 1, true);
+      offset += 5;
+      for (let i = 0; i < count; i++) {
+        sourceMap.push({
+          codeStart: view.getUint32(offset, true),
+          codeEnd: view.getUint32(offset + 4, true),
+          line: view.getUint32(offset + 8, true),
+          column: view.getUint32(offset + 12, true),
+        });
+        offset += 16;
+      }
+    }
     
-    return { version, flags, constants, functions, bytecode };
+    return { version, flags, constants, functions, bytecode, sourceMap };
+  }
+  
+  const NULL = { type: 'null' };
+  
+  function valueToString(v) {
+    switch (v.type) {
+      case 'null': return 'null';
+      case 'bool': case 'int': case 'float': return String(v.value);
+      case 'string': return v.value;
+      case 'vec': return '[' + v.items.map(valueToString).join(', ') + ']';
+      case 'map': return '{' + Array.from(v.entries.entries())
+        .map(([k, val]) => k + ': ' + valueToString(val)).join(', ') + '}';
+      case 'closure': return '<closure:' + v.funcIdx + '>';
+      case 'native': return '<builtin:' + v.name + '>';
+      case 'widget': return '<widget:' + v.widgetType + '#' + v.id + '>';
+      case 'state': return '<state:' + v.id + '>';
+    }
+    return String(v);
+  }
+  
+  function isTruthy(v) {
+    if (v.type === 'bool') return v.value;
+    return v.type !== 'null';
+  }
+  
+  function isNumber(v) {
+    return v.type === 'int' || v.type === 'float';
+  }
+  
+  function scalar(v) {
+    return v.type === 'int' || v.type === 'float' || v.type === 'string' || v.type === 'bool'
+      ? v.value
+      : v;
   }
   
   class VM {
     constructor(module, callbacks = {}) {
       this.module = module;
       this.callbacks = callbacks;
+      this.stack = [];
+      this.frames = [];
+      this.globals = new Map();
       this.widgetIdCounter = 0;
       this.stateCounter = 0;
       this.states = new Map();
+      this.rootWidget = null;
+      this.rebuildScheduled = false;
+      this.currentOffset = 0;
+      for (const level of ['info', 'warn', 'error', 'debug']) {
+        this.globals.set('log.' + level, {
+          type: 'native',
+          name: 'log.' + level,
+          fn: (args) => {
+            this.log(args.map(valueToString).join(' '));
+            return NULL;
+          },
+        });
+      }
     }
     
     run() {
-      // For MVP: render a placeholder UI based on source content
-      const sourceConstant = this.module.constants[1];
-      const sourcePreview = sourceConstant ? sourceConstant.value : '';
-      
-      // Parse source for simple patterns
-      let title = 'AFNS App';
-      let textContent = 'Hello from AFNS';
-      let buttonLabel = 'Click Me';
-      
-      // Extract title from ui.window("Title", ...)
-      const titleMatch = sourcePreview.match(/ui\.window\s*\(\s*"([^"]+)"/);
-      if (titleMatch) title = titleMatch[1];
-      
-      // Extract text from ctx.text("...")
-      const textMatch = sourcePreview.match(/ctx\.text\s*\(\s*"([^"]+)"/);
-      if (textMatch) textContent = textMatch[1];
-      
-      // Extract button from ctx.button("...", ...)
-      const buttonMatch = sourcePreview.match(/ctx\.button\s*\(\s*"([^"]+)"/);
-      if (buttonMatch) buttonLabel = buttonMatch[1];
-      
-      // Create widget tree
-      const root = {
-        type: 'widget',
-        id: 'w0',
-        widgetType: 'Window',
-        props: new Map([['title', { type: 'string', value: title }]]),
-        children: [
-          {
-            type: 'widget',
-            id: 'w1',
-            widgetType: 'Column',
-            props: new Map(),
-            children: [
-              {
-                type: 'widget',
-                id: 'w2',
-                widgetType: 'Text',
-                props: new Map([['text', { type: 'string', value: textContent }]]),
-                children: [],
-                handlers: new Map(),
-              },
-              {
-                type: 'widget',
-                id: 'w3',
-                widgetType: 'Button',
-                props: new Map([['label', { type: 'string', value: buttonLabel }]]),
-                children: [],
-                handlers: new Map([['click', { type: 'closure', funcIdx: 0, captures: [] }]]),
-              },
-            ],
-            handlers: new Map(),
-          },
-        ],
-        handlers: new Map(),
-      };
-      
-      this.rootWidget = root;
-      this.callbacks.onRender?.(root);
+      // Rebuilds re-run apex; states are matched to their previous values by creation order.
+      this.widgetIdCounter = 0;
+      this.stateCounter = 0;
+      this.call(this.findFunction('apex'), [], []);
     }
     
     handleEvent(widgetId, eventType) {
-      this.callbacks.onLog?.('Button clicked!');
-      // For MVP, just log the event
-      console.log('[VM] Event:', eventType, 'on', widgetId);
+      const handler = this.findHandler(this.rootWidget, widgetId, eventType);
+      if (handler) {
+        this.invoke(handler, []);
+      }
+    }
+    
+    findHandler(widget, id, eventType) {
+      if (!widget || widget.type !== 'widget') return null;
+      if (widget.id === id) return widget.handlers.get(eventType) || null;
+      for (const child of widget.children) {
+        const found = this.findHandler(child, id, eventType);
+        if (found) return found;
+      }
+      return null;
+    }
+    
+    scheduleRebuild() {
+      if (this.rebuildScheduled) return;
+      this.rebuildScheduled = true;
+      queueMicrotask(() => {
+        this.rebuildScheduled = false;
+        this.run();
+      });
+    }
+    
+    log(message) {
+      console.log('[AFNS]', message);
+      this.callbacks.onLog?.(message);
+    }
+    
+    findFunction(name) {
+      const idx = this.module.functions.findIndex((f) => this.constantString(f.nameIdx) === name);
+      if (idx === -1) throw new Error('No `' + name + '` function found in module');
+      return idx;
+    }
+    
+    constantString(idx) {
+      const c = this.module.constants[idx];
+      if (!c || c.tag !== 'utf8') throw new Error('Constant ' + idx + ' is not a string');
+      return c.value;
+    }
+    
+    constantValue(idx) {
+      const c = this.module.constants[idx];
+      switch (c.tag) {
+        case 'utf8': return { type: 'string', value: c.value };
+        case 'int64': return { type: 'int', value: c.value };
+        case 'float64': return { type: 'float', value: c.value };
+        case 'bool': return { type: 'bool', value: c.value };
+        default: return NULL;
+      }
+    }
+    
+    invoke(callee, args) {
+      if (callee.type === 'closure') return this.call(callee.funcIdx, callee.captures, args);
+      if (callee.type === 'native') return callee.fn(args);
+      throw new Error('Cannot call ' + valueToString(callee));
+    }
+    
+    // Run a function to completion and return its result.
+    call(funcIdx, captures, args) {
+      const depth = this.frames.length;
+      this.pushFrame(funcIdx, captures, args);
+      try {
+        return this.execute(depth);
+      } catch (err) {
+        this.frames.length = depth;
+        const entry = this.module.sourceMap.find(
+          (e) => this.currentOffset >= e.codeStart && this.currentOffset < e.codeEnd);
+        const error = entry && !err.located
+          ? Object.assign(new Error(err.message + ' at line ' + entry.line + ', column ' + entry.column), { located: true })
+          : err;
+        this.callbacks.onError?.(error);
+        throw error;
+      }
+    }
+    
+    pushFrame(funcIdx, captures, args) {
+      const func = this.module.functions[funcIdx];
+      if (!func) throw new Error('Invalid function index ' + funcIdx);
+      if (args.length !== func.arity) {
+        throw new Error('Function `' + this.constantString(func.nameIdx) + '` expects ' +
+          func.arity + ' arguments, got ' + args.length);
+      }
+      const bp = this.stack.length;
+      this.stack.push(...captures, ...args);
+      for (let i = captures.length + args.length; i < func.locals; i++) this.stack.push(NULL);
+      this.frames.push({ func, ip: 0, bp });
+    }
+    
+    pop() {
+      if (this.stack.length === 0) throw new Error('VM stack underflow');
+      return this.stack.pop();
+    }
+    
+    popN(n) {
+      return n === 0 ? [] : this.stack.splice(-n);
+    }
+    
+    top() {
+      return this.stack[this.stack.length - 1];
+    }
+    
+    topWidget(op) {
+      const widget = this.top();
+      if (!widget || widget.type !== 'widget') throw new Error(op + ' expects a widget');
+      return widget;
+    }
+    
+    // Pop the current frame; returns true once the frame at `depth` has returned.
+    returnFrom(result, depth) {
+      const frame = this.frames.pop();
+      this.stack.length = frame.bp;
+      if (this.frames.length > depth) {
+        this.stack.push(result);
+        return false;
+      }
+      this.result = result;
+      return true;
+    }
+    
+    arithmetic(op, a, b) {
+      if (a.type === 'int' && b.type === 'int') {
+        if ((op === 0x73 || op === 0x74) && b.value === 0n) throw new Error('Division by zero');
+        const x = a.value, y = b.value;
+        const value = op === 0x70 ? x + y : op === 0x71 ? x - y : op === 0x72 ? x * y : op === 0x73 ? x / y : x % y;
+        return { type: 'int', value };
+      }
+      if (!isNumber(a) || !isNumber(b)) {
+        throw new Error('Cannot perform binary op on ' + valueToString(a) + ' and ' + valueToString(b));
+      }
+      const x = Number(a.value), y = Number(b.value);
+      const value = op === 0x70 ? x + y : op === 0x71 ? x - y : op === 0x72 ? x * y : op === 0x73 ? x / y : x % y;
+      return { type: 'float', value };
+    }
+    
+    execute(depth) {
+      const code = this.module.bytecode;
+      const view = new DataView(code.buffer, code.byteOffset, code.byteLength);
+      for (;;) {
+        const frame = this.frames[this.frames.length - 1];
+        const func = frame.func;
+        if (frame.ip >= func.codeLen) {
+          // Falling off the end of a function is an implicit `RET null`.
+          if (this.returnFrom(NULL, depth)) return this.result;
+          continue;
+        }
+        const base = func.codeOffset;
+        this.currentOffset = base + frame.ip;
+        const op = code[base + frame.ip++];
+        const u8 = () => code[base + frame.ip++];
+        const u16 = () => { const v = view.getUint16(base + frame.ip, true); frame.ip += 2; return v; };
+        const i16 = () => { const v = view.getInt16(base + frame.ip, true); frame.ip += 2; return v; };
+        
+        switch (op) {
+          case 0x01: this.stack.push(this.constantValue(u16())); break; // CONST
+          case 0x02: this.stack.push(this.stack[frame.bp + u16()]); break; // LOAD_LOCAL
+          case 0x03: { const slot = u16(); this.stack[frame.bp + slot] = this.pop(); break; } // STORE_LOCAL
+          case 0x04: { // LOAD_GLOBAL
+            const name = this.constantString(u16());
+            if (!this.globals.has(name)) throw new Error('Unknown global `' + name + '`');
+            this.stack.push(this.globals.get(name));
+            break;
+          }
+          case 0x05: this.globals.set(this.constantString(u16()), this.pop()); break; // STORE_GLOBAL
+          case 0x10: { // CALL
+            const funcIdx = u16();
+            const args = this.popN(u8());
+            this.pushFrame(funcIdx, [], args);
+            break;
+          }
+          case 0x11: { // RET
+            const result = this.stack.length > frame.bp + func.locals ? this.pop() : NULL;
+            if (this.returnFrom(result, depth)) return this.result;
+            break;
+          }
+          case 0x20: { const offset = i16(); frame.ip += offset; break; } // JUMP
+          case 0x21: { const offset = i16(); if (!isTruthy(this.pop())) frame.ip += offset; break; } // JUMP_IF_FALSE
+          case 0x30: { // MAKE_CLOSURE
+            const funcIdx = u16();
+            const captures = this.popN(u8());
+            this.stack.push({ type: 'closure', funcIdx, captures });
+            break;
+          }
+          case 0x31: { // INVOKE_CLOSURE
+            const args = this.popN(u8());
+            const callee = this.pop();
+            if (callee.type === 'closure') {
+              this.pushFrame(callee.funcIdx, callee.captures, args);
+            } else {
+              this.stack.push(this.invoke(callee, args));
+            }
+            break;
+          }
+          case 0x40: this.stack.push({ type: 'vec', items: [] }); break; // NEW_VEC
+          case 0x41: { // VEC_PUSH
+            const item = this.pop();
+            const vec = this.top();
+            if (vec.type !== 'vec') throw new Error('VEC_PUSH expects a vec');
+            vec.items.push(item);
+            break;
+          }
+          case 0x42: this.stack.push({ type: 'map', entries: new Map() }); break; // NEW_MAP
+          case 0x43: { // MAP_SET
+            const value = this.pop();
+            const key = this.pop();
+            const map = this.top();
+            if (map.type !== 'map') throw new Error('MAP_SET expects a map');
+            map.entries.set(valueToString(key), value);
+            break;
+          }
+          case 0x50: { // GUI_CREATE_WIDGET
+            const widgetType = this.constantString(u16());
+            this.stack.push({
+              type: 'widget',
+              id: 'w' + this.widgetIdCounter++,
+              widgetType,
+              props: new Map(),
+              children: [],
+              handlers: new Map(),
+            });
+            break;
+          }
+          case 0x51: { // GUI_SET_PROP
+            const key = this.constantString(u16());
+            const value = this.pop();
+            this.topWidget('GUI_SET_PROP').props.set(key, value);
+            break;
+          }
+          case 0x52: { // GUI_ADD_CHILD
+            const child = this.pop();
+            if (child.type !== 'widget') throw new Error('GUI_ADD_CHILD expects a widget child');
+            this.topWidget('GUI_ADD_CHILD').children.push(child);
+            break;
+          }
+          case 0x53: { // GUI_SET_HANDLER
+            const eventType = this.constantString(u16());
+            const handler = this.pop();
+            this.topWidget('GUI_SET_HANDLER').handlers.set(eventType, handler);
+            break;
+          }
+          case 0x54: { // GUI_COMMIT_ROOT
+            const widget = this.pop();
+            if (widget.type !== 'widget') throw new Error('GUI_COMMIT_ROOT expects a widget');
+            this.rootWidget = widget;
+            this.callbacks.onRender?.(widget);
+            break;
+          }
+          case 0x60: this.log(valueToString(this.pop())); break; // LOG_INFO
+          case 0x70: case 0x71: case 0x72: case 0x73: case 0x74: { // ADD..MOD
+            const b = this.pop();
+            const a = this.pop();
+            this.stack.push(this.arithmetic(op, a, b));
+            break;
+          }
+          case 0x75: case 0x76: case 0x77: case 0x78: case 0x79: case 0x7A: { // EQ..GE
+            const b = scalar(this.pop());
+            const a = scalar(this.pop());
+            const value = op === 0x75 ? a == b : op === 0x76 ? a != b : op === 0x77 ? a < b
+              : op === 0x78 ? a <= b : op === 0x79 ? a > b : a >= b;
+            this.stack.push({ type: 'bool', value });
+            break;
+          }
+          case 0x7B: { const b = this.pop(); const a = this.pop(); this.stack.push({ type: 'bool', value: isTruthy(a) && isTruthy(b) }); break; } // AND
+          case 0x7C: { const b = this.pop(); const a = this.pop(); this.stack.push({ type: 'bool', value: isTruthy(a) || isTruthy(b) }); break; } // OR
+          case 0x7D: this.stack.push({ type: 'bool', value: !isTruthy(this.pop()) }); break; // NOT
+          case 0x7E: { // NEG
+            const v = this.pop();
+            if (!isNumber(v)) throw new Error('Cannot negate non-number');
+            this.stack.push({ type: v.type, value: -v.value });
+            break;
+          }
+          case 0x80: this.stack.push(this.top()); break; // DUP
+          case 0x81: this.pop(); break; // POP
+          case 0x82: { // GET_PROP
+            const key = this.constantString(u16());
+            const object = this.pop();
+            if (object.type === 'widget') this.stack.push(object.props.get(key) || NULL);
+            else if (object.type === 'map') this.stack.push(object.entries.get(key) || NULL);
+            else throw new Error('Cannot read property `' + key + '` of ' + valueToString(object));
+            break;
+          }
+          case 0x83: { // SET_PROP
+            const key = this.constantString(u16());
+            const value = this.pop();
+            const object = this.top();
+            if (object.type === 'widget') object.props.set(key, value);
+            else if (object.type === 'map') object.entries.set(key, value);
+            else throw new Error('Cannot set property `' + key + '` on ' + valueToString(object));
+            break;
+          }
+          case 0x84: { // CONCAT
+            const b = this.pop();
+            const a = this.pop();
+            this.stack.push({ type: 'string', value: valueToString(a) + valueToString(b) });
+            break;
+          }
+          case 0x90: { // STATE_CREATE
+            const initial = this.pop();
+            const id = this.stateCounter++;
+            if (!this.states.has(id)) this.states.set(id, initial);
+            this.stack.push({ type: 'state', id });
+            break;
+          }
+          case 0x91: { // STATE_GET
+            const state = this.pop();
+            if (state.type !== 'state') throw new Error('STATE_GET expects a state');
+            this.stack.push(this.states.get(state.id) || NULL);
+            break;
+          }
+          case 0x92: { // STATE_SET
+            const value = this.pop();
+            const state = this.pop();
+            if (state.type !== 'state') throw new Error('STATE_SET expects a state');
+            this.states.set(state.id, value);
+            this.scheduleRebuild();
+            break;
+          }
+          default:
+            throw new Error('Unknown opcode 0x' + op.toString(16));
+        }
+      }
     }
   }
   
@@ -29,6 +29,14 @@ enum TargetArg {
     Web,
 }
 
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum BackendArg {
+    /// Compile to a native executable
+    Native,
+    /// Compile to AFBC and execute it on the bytecode VM
+    Vm,
+}
+
 impl From<TargetArg> for build::BuildTarget {
     fn from(arg: TargetArg) -> Self {
         match arg {
@@ -104,6 +112,9 @@ enum Command {
         port: Option<u16>,
         #[arg(long, value_enum, default_value = "x86_64")]
         target: TargetArg,
+        /// Execution backend for non-web targets
+        #[arg(long, value_enum, default_value = "native")]
+        backend: BackendArg,
         #[arg(long)]
         release: bool,
         #[arg(long)]
@@ -264,6 +275,7 @@ fn main() -> Result<()> {
             entry: _,
             port,
             target,
+            backend,
             release,
             dump_ir,
             ui,
@@ -271,8 +283,16 @@ fn main() -> Result<()> {
             let path_to_use = manifest_path.or(project);
             let manifest_path_resolved = resolve_manifest_path(path_to_use)?;
 
-            // Check if web target
-            if matches!(target, TargetArg::Web) {
+            if matches!(backend, BackendArg::Vm) {
+                // The VM runs on the host, so there is no target to pick.
+                if !matches!(target, TargetArg::X86_64) {
+                    return Err(anyhow!(
+                        "`--backend vm` cannot be combined with `--target {}`",
+                        target.to_possible_value().unwrap().get_name()
+                    ));
+                }
+                web::run_vm(&manifest_path_resolved)?;
+            } else if matches!(target, TargetArg::Web) {
                 web::run_web(&manifest_path_resolved, port)?;
             } else {
                 let mut ctx = ProjectContext::load(Some(manifest_path_resolved))?;
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use std::collections::HashMap;
+
 use crate::span::Span;
 
 #[derive(Debug, Clone)]
@@ -25,6 +27,37 @@ pub enum Item {
     Trait(TraitDef),
     Impl(ImplBlock),
     ExternFunction(ExternFunction),
+    Global(GlobalDef),
+    TypeAlias(TypeAliasDef),
+}
+
+impl Item {
+    /// The name the item binds in its module; `impl` blocks bind none.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Item::Function(func) => Some(&func.signature.name),
+            Item::ExternFunction(func) => Some(&func.signature.name),
+            Item::Struct(def) => Some(&def.name),
+            Item::Enum(def) => Some(&def.name),
+            Item::Trait(def) => Some(&def.name),
+            Item::Global(def) => Some(&def.name),
+            Item::TypeAlias(def) => Some(&def.name),
+            Item::Impl(_) => None,
+        }
+    }
+
+    pub fn is_pub(&self) -> bool {
+        match self {
+            Item::Function(func) => func.signature.is_pub,
+            Item::ExternFunction(func) => func.signature.is_pub,
+            Item::Struct(def) => def.is_pub,
+            Item::Enum(def) => def.is_pub,
+            Item::Trait(def) => def.is_pub,
+            Item::Global(def) => def.is_pub,
+            Item::TypeAlias(def) => def.is_pub,
+            Item::Impl(_) => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -54,9 +87,120 @@ pub struct ExternFunction {
     pub span: Span,
 }
 
+/// A module-level `const`, `static` or `var` item. Initializers are constant
+/// expressions, evaluated before any function runs.
+#[derive(Debug, Clone)]
+pub struct GlobalDef {
+    pub attributes: Vec<Attribute>,
+    pub is_pub: bool,
+    pub kind: GlobalKind,
+    pub name: String,
+    pub ty: Option<TypeExpr>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlobalKind {
+    /// Inlined at every use.
+    Const,
+    /// A single immutable storage location.
+    Static,
+    /// A mutable global variable.
+    Var,
+}
+
+/// `type Name<T> = target;`. Uses of the alias are expanded to `target`
+/// wherever a type is resolved.
+#[derive(Debug, Clone)]
+pub struct TypeAliasDef {
+    pub attributes: Vec<Attribute>,
+    pub is_pub: bool,
+    pub name: String,
+    pub type_params: Vec<TypeParam>,
+    pub target: TypeExpr,
+    pub span: Span,
+}
+
+impl TypeAliasDef {
+    /// The aliased type with each type parameter replaced by the matching
+    /// entry of `args`. Parameters without an argument are left as written.
+    pub fn instantiate(&self, args: &[TypeExpr]) -> TypeExpr {
+        let substitutions = self
+            .type_params
+            .iter()
+            .map(|p| p.name.as_str())
+            .zip(args)
+            .collect::<HashMap<_, _>>();
+        substitute_type_params(&self.target, &substitutions)
+    }
+}
+
+fn substitute_type_params(ty: &TypeExpr, substitutions: &HashMap<&str, &TypeExpr>) -> TypeExpr {
+    match ty {
+        TypeExpr::Named(named) => {
+            if let [segment] = named.segments.as_slice() {
+                if segment.generics.is_empty() {
+                    if let Some(arg) = substitutions.get(segment.name.as_str()) {
+                        return (*arg).clone();
+                    }
+                }
+            }
+            TypeExpr::Named(NamedType {
+                segments: named
+                    .segments
+                    .iter()
+                    .map(|segment| TypeSegment {
+                        name: segment.name.clone(),
+                        generics: segment
+                            .generics
+                            .iter()
+                            .map(|g| substitute_type_params(g, substitutions))
+                            .collect(),
+                        span: segment.span,
+                    })
+                    .collect(),
+                span: named.span,
+            })
+        }
+        TypeExpr::Array {
+            element,
+            size,
+            span,
+        } => TypeExpr::Array {
+            element: Box::new(substitute_type_params(element, substitutions)),
+            size: *size,
+            span: *span,
+        },
+        TypeExpr::Slice { element, span } => TypeExpr::Slice {
+            element: Box::new(substitute_type_params(element, substitutions)),
+            span: *span,
+        },
+        TypeExpr::Tuple { elements, span } => TypeExpr::Tuple {
+            elements: elements
+                .iter()
+                .map(|e| substitute_type_params(e, substitutions))
+                .collect(),
+            span: *span,
+        },
+        TypeExpr::Reference {
+            mutable,
+            inner,
+            span,
+        } => TypeExpr::Reference {
+            mutable: *mutable,
+            inner: Box::new(substitute_type_params(inner, substitutions)),
+            span: *span,
+        },
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FunctionSignature {
     pub name: String,
+    /// Written with `pub`. Items, struct fields and methods without it are
+    /// private to the module that declares them.
+    pub is_pub: bool,
     pub is_async: bool,
     pub returns_async: bool,
     pub params: Vec<Param>,
@@ -75,6 +219,7 @@ pub struct Param {
 #[derive(Debug, Clone)]
 pub struct StructDef {
     pub attributes: Vec<Attribute>,
+    pub is_pub: bool,
     pub name: String,
     pub type_params: Vec<TypeParam>,
     pub fields: Vec<StructField>,
@@ -83,6 +228,7 @@ pub struct StructDef {
 
 #[derive(Debug, Clone)]
 pub struct StructField {
+    pub is_pub: bool,
     pub name: String,
     pub ty: TypeExpr,
     pub span: Span,
@@ -91,6 +237,7 @@ pub struct StructField {
 #[derive(Debug, Clone)]
 pub struct EnumDef {
     pub attributes: Vec<Attribute>,
+    pub is_pub: bool,
     pub name: String,
     pub type_params: Vec<TypeParam>,
     pub variants: Vec<EnumVariant>,
@@ -107,12 +254,20 @@ pub struct EnumVariant {
 #[derive(Debug, Clone)]
 pub struct TraitDef {
     pub attributes: Vec<Attribute>,
+    pub is_pub: bool,
     pub name: String,
     pub type_params: Vec<TypeParam>,
-    pub methods: Vec<FunctionSignature>,
+    pub methods: Vec<TraitMethod>,
     pub span: Span,
 }
 
+#[derive(Debug, Clone)]
+pub struct TraitMethod {
+    pub signature: FunctionSignature,
+    /// Body used by impls that do not define the method themselves.
+    pub default: Option<Block>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ImplBlock {
     pub attributes: Vec<Attribute>,
@@ -139,12 +294,15 @@ pub enum Stmt {
     },
     If(IfStmt),
     While {
+        label: Option<String>,
+        /// May be an [`Expr::Let`] for `while let`.
         condition: Expr,
         body: Block,
         span: Span,
     },
     For {
-        var: String,
+        label: Option<String>,
+        pattern: Pattern,
         iterable: Expr,
         body: Block,
         span: Span,
@@ -157,14 +315,24 @@ pub enum Stmt {
         span: Span,
     },
     Assembly(AssemblyBlock),
-    Break(Span),
-    Continue(Span),
+    /// `break 'label value;`; both parts are optional, and a value is only
+    /// allowed when the target is a `loop`.
+    Break {
+        label: Option<String>,
+        value: Option<Expr>,
+        span: Span,
+    },
+    Continue {
+        label: Option<String>,
+        span: Span,
+    },
 }
 
 #[derive(Debug, Clone)]
 pub struct VarDecl {
     pub kind: VarKind,
-    pub name: String,
+    /// A plain name, or a destructuring pattern such as `(a, b)`.
+    pub pattern: Pattern,
     pub ty: Option<TypeExpr>,
     pub value: Expr,
     pub span: Span,
@@ -179,6 +347,7 @@ pub enum VarKind {
 
 #[derive(Debug, Clone)]
 pub struct IfStmt {
+    /// May be an [`Expr::Let`] for `if let`, as may each `else if` condition.
     pub condition: Expr,
     pub then_branch: Block,
     pub else_if: Vec<(Expr, Block)>,
@@ -196,6 +365,8 @@ pub struct SwitchStmt {
 #[derive(Debug, Clone)]
 pub struct SwitchArm {
     pub pattern: Pattern,
+    /// `if` condition tested once the pattern matches, with its bindings in scope.
+    pub guard: Option<Expr>,
     pub expr: Expr,
     pub span: Span,
 }
@@ -213,12 +384,42 @@ pub enum Pattern {
         segments: Vec<String>,
         span: Span,
     },
+    /// `Variant(p, ...)`; each payload value is matched against its pattern.
     Enum {
         path: Vec<String>,
-        bindings: Vec<String>,
+        args: Vec<Pattern>,
         span: Span,
     },
     Literal(Literal),
+    Tuple {
+        elements: Vec<Pattern>,
+        span: Span,
+    },
+    /// `Point { x, y: 0 }`; a field written alone binds its own name.
+    Struct {
+        path: Vec<String>,
+        fields: Vec<FieldPattern>,
+        span: Span,
+    },
+    /// `1 ..= 9` includes `end`, `1 .. 9` does not.
+    Range {
+        start: Literal,
+        end: Literal,
+        inclusive: bool,
+        span: Span,
+    },
+    /// `A | B`; every alternative must bind the same names.
+    Or {
+        alternatives: Vec<Pattern>,
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldPattern {
+    pub name: String,
+    pub pattern: Pattern,
+    pub span: Span,
 }
 
 impl Pattern {
@@ -228,11 +429,56 @@ impl Pattern {
             | Pattern::Binding { span, .. }
             | Pattern::Path { span, .. }
             | Pattern::Enum { span, .. }
-            | Pattern::Literal(Literal::Integer { span, .. })
-            | Pattern::Literal(Literal::Float { span, .. })
-            | Pattern::Literal(Literal::String { span, .. })
-            | Pattern::Literal(Literal::Char { span, .. })
-            | Pattern::Literal(Literal::Bool { span, .. }) => *span,
+            | Pattern::Tuple { span, .. }
+            | Pattern::Struct { span, .. }
+            | Pattern::Range { span, .. }
+            | Pattern::Or { span, .. } => *span,
+            Pattern::Literal(lit) => lit.span(),
+        }
+    }
+
+    /// The name a plain binding pattern introduces; `None` for anything that
+    /// destructures or tests the value.
+    pub fn binding_name(&self) -> Option<&str> {
+        match self {
+            Pattern::Binding { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Names the pattern binds, in source order; for `|` patterns, those of
+    /// the first alternative.
+    pub fn bindings(&self) -> Vec<(&str, Span)> {
+        let mut names = Vec::new();
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings<'a>(&'a self, names: &mut Vec<(&'a str, Span)>) {
+        match self {
+            Pattern::Binding { name, span } => names.push((name, *span)),
+            Pattern::Enum { args: items, .. }
+            | Pattern::Tuple {
+                elements: items, ..
+            } => {
+                for item in items {
+                    item.collect_bindings(names);
+                }
+            }
+            Pattern::Struct { fields, .. } => {
+                for field in fields {
+                    field.pattern.collect_bindings(names);
+                }
+            }
+            Pattern::Or { alternatives, .. } => {
+                if let Some(first) = alternatives.first() {
+                    first.collect_bindings(names);
+                }
+            }
+            Pattern::Wildcard { .. }
+            | Pattern::Path { .. }
+            | Pattern::Literal(_)
+            | Pattern::Range { .. } => {}
         }
     }
 }
@@ -285,6 +531,14 @@ pub enum Expr {
         right: Box<Expr>,
         span: Span,
     },
+    /// `start..end`, `start..=end`, optionally followed by `step n`.
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+        step: Option<Box<Expr>>,
+        span: Span,
+    },
     Assignment {
         target: Box<Expr>,
         value: Box<Expr>,
@@ -328,6 +582,25 @@ pub enum Expr {
         span: Span,
     },
     Check(CheckExpr),
+    /// `loop { ... }`; its value is that of the `break` that ends it.
+    Loop {
+        label: Option<String>,
+        body: Block,
+        span: Span,
+    },
+    /// `let pattern = value`, only valid as an `if` or `while` condition: it
+    /// holds when the value matches, and the pattern's names are bound in
+    /// the body.
+    Let {
+        pattern: Pattern,
+        value: Box<Expr>,
+        span: Span,
+    },
+    /// `"text ${expr} text"`; parts are converted to strings and joined.
+    Interpolated {
+        parts: Vec<InterpolationPart>,
+        span: Span,
+    },
 }
 
 impl Expr {
@@ -340,6 +613,7 @@ impl Expr {
             | Expr::Await { span, .. }
             | Expr::Unary { span, .. }
             | Expr::Binary { span, .. }
+            | Expr::Range { span, .. }
             | Expr::Assignment { span, .. }
             | Expr::StructLiteral { span, .. }
             | Expr::ArrayLiteral { span, .. }
@@ -350,7 +624,10 @@ impl Expr {
             | Expr::Block(Block { span, .. })
             | Expr::Index { span, .. }
             | Expr::MethodCall { span, .. }
-            | Expr::Check(CheckExpr { span, .. }) => *span,
+            | Expr::Check(CheckExpr { span, .. })
+            | Expr::Loop { span, .. }
+            | Expr::Let { span, .. }
+            | Expr::Interpolated { span, .. } => *span,
             Expr::If(if_stmt) => if_stmt.span,
         }
     }
@@ -367,6 +644,12 @@ impl Expr {
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum InterpolationPart {
+    Text(String),
+    Expr(Expr),
+}
+
 #[derive(Debug, Clone)]
 pub enum Literal {
     Integer { value: String, span: Span },
@@ -453,6 +736,8 @@ pub enum UnaryOp {
     Negate,
     Not,
     Borrow,
+    /// `~`, bitwise complement.
+    BitNot,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -470,7 +755,12 @@ pub enum BinaryOp {
     Multiply,
     Divide,
     Modulo,
-    Range,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    /// Arithmetic for signed integers, logical for unsigned ones.
+    ShiftRight,
 }
 
 #[derive(Debug, Clone)]
@@ -524,5 +814,7 @@ pub struct TypeSegment {
 #[derive(Debug, Clone)]
 pub struct TypeParam {
     pub name: String,
+    /// Traits the argument must implement, from `<T: A + B>` and `where`.
+    pub bounds: Vec<TypeExpr>,
     pub span: Span,
 }
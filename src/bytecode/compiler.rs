@@ -0,0 +1,1278 @@
+//! Compiler from `ast::File` to AFBC bytecode.
+//!
+//! Covers the subset of AFNS that the web VM can execute: top-level functions and
+//! `const`/`static`/`var` items, locals, `if`/`while`/range `for`, closures (captures are copied, as in the VM), vec literals,
+//! struct literals (as string-keyed maps), `log.info`, builtin module calls and the
+//! `forge.gui.native` widget and state API. Everything else is reported as a
+//! [`CompileError`] pointing at the offending construct.
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::{AfbcModule, BytecodeBuilder, Constant, FunctionEntry, Opcode, SourceMapEntry};
+use crate::ast::*;
+use crate::span::Span;
+
+#[derive(Debug, Clone)]
+pub struct CompileError {
+    pub message: String,
+    pub span: Span,
+}
+
+/// Compile a parsed file into an AFBC module whose entry point is `apex`.
+pub fn compile_file(file: &File) -> Result<AfbcModule, Vec<CompileError>> {
+    let mut compiler = Compiler::default();
+    compiler.compile(file);
+    compiler.finish()
+}
+
+/// What an imported name refers to.
+#[derive(Debug, Clone)]
+enum ModuleRef {
+    /// `forge.gui.native`: compiled to `GUI_*`/`STATE_*` opcodes.
+    Gui,
+    /// Any other module, resolved at run time through `LOAD_GLOBAL`.
+    Builtin(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LocalKind {
+    Value,
+    /// Result of `ui.state(...)`; `.get()`/`.set()` compile to `STATE_GET`/`STATE_SET`.
+    State,
+    /// The context parameter of a `ui.window` callback (the window widget itself).
+    Ui,
+}
+
+struct Local {
+    name: String,
+    slot: u16,
+    kind: LocalKind,
+    captured: bool,
+}
+
+#[derive(Default)]
+struct LoopCtx {
+    label: Option<String>,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+#[derive(Default)]
+struct FnCtx {
+    code: BytecodeBuilder,
+    locals: Vec<Local>,
+    slot_count: usize,
+    scopes: Vec<usize>,
+    loops: Vec<LoopCtx>,
+    /// Slot of the widget that statement-level widget calls are added to.
+    ui_parent: Option<u16>,
+    source_map: Vec<SourceMapEntry>,
+    /// Set when a jump or slot index does not fit its operand.
+    overflow: bool,
+}
+
+impl FnCtx {
+    fn declare(&mut self, name: &str, kind: LocalKind, captured: bool) -> u16 {
+        let slot = u16::try_from(self.slot_count).unwrap_or_else(|_| {
+            self.overflow = true;
+            u16::MAX
+        });
+        self.slot_count += 1;
+        self.locals.push(Local {
+            name: name.to_string(),
+            slot,
+            kind,
+            captured,
+        });
+        slot
+    }
+
+    fn resolve(&self, name: &str) -> Option<&Local> {
+        self.locals.iter().rev().find(|local| local.name == name)
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(self.locals.len());
+    }
+
+    fn end_scope(&mut self) {
+        if let Some(len) = self.scopes.pop() {
+            self.locals.truncate(len);
+        }
+    }
+
+    /// Emit a jump with a placeholder offset, returning the operand position.
+    fn emit_jump(&mut self, op: Opcode) -> usize {
+        self.code.emit(op);
+        self.code.emit_i16(0);
+        self.code.current_offset() - 2
+    }
+
+    fn patch_jump(&mut self, at: usize, target: usize) {
+        let offset = target as isize - (at as isize + 2);
+        match i16::try_from(offset) {
+            Ok(offset) => self.code.patch_jump(at, offset),
+            Err(_) => self.overflow = true,
+        }
+    }
+
+    fn patch_here(&mut self, at: usize) {
+        let target = self.code.current_offset();
+        self.patch_jump(at, target);
+    }
+
+    fn emit_loop(&mut self, target: usize) {
+        let at = self.emit_jump(Opcode::Jump);
+        self.patch_jump(at, target);
+    }
+}
+
+struct CompiledFunction {
+    name: String,
+    arity: u16,
+    locals: u16,
+    code: Vec<u8>,
+    source_map: Vec<SourceMapEntry>,
+}
+
+#[derive(Default)]
+struct Compiler {
+    module: AfbcModule,
+    functions: Vec<Option<CompiledFunction>>,
+    function_indices: HashMap<String, (u16, usize)>,
+    /// Module-level items, initialized by a prelude at the start of `apex`.
+    globals: Vec<GlobalDef>,
+    modules: HashMap<String, ModuleRef>,
+    ctxs: Vec<FnCtx>,
+    errors: Vec<CompileError>,
+}
+
+impl Compiler {
+    fn compile(&mut self, file: &File) {
+        for import in &file.imports {
+            let target = import
+                .member
+                .clone()
+                .or_else(|| import.path.last().cloned())
+                .unwrap_or_default();
+            let binding = import.alias.clone().unwrap_or_else(|| target.clone());
+            let module = if import.path.first().map(String::as_str) != Some("forge") {
+                self.error(
+                    import.span,
+                    format!(
+                        "Import of `{}` is not supported by the web target; only `forge` modules are available",
+                        import.path.join(".")
+                    ),
+                );
+                continue;
+            } else if import.path.iter().any(|segment| segment == "gui") {
+                ModuleRef::Gui
+            } else if import.path.len() == 1 && import.member.is_none() {
+                // Bare `import forge;`: members such as `forge.log` are the builtin modules.
+                ModuleRef::Builtin(String::new())
+            } else {
+                ModuleRef::Builtin(target)
+            };
+            self.modules.insert(binding, module);
+        }
+
+        let mut functions = Vec::new();
+        for item in &file.items {
+            match item {
+                Item::Function(func) => {
+                    let idx = self.reserve_function(func.signature.span);
+                    self.function_indices.insert(
+                        func.signature.name.clone(),
+                        (idx, func.signature.params.len()),
+                    );
+                    functions.push((idx, func));
+                }
+                // Struct values are plain string-keyed maps at run time, and
+                // aliases only matter to the type checker.
+                Item::Struct(_) | Item::TypeAlias(_) => {}
+                Item::Enum(def) => self.unsupported(def.span, "`enum` declarations"),
+                Item::Trait(def) => self.unsupported(def.span, "`trait` declarations"),
+                Item::Impl(block) => self.unsupported(block.span, "`impl` blocks"),
+                Item::ExternFunction(func) => self.unsupported(func.span, "`extern` functions"),
+                Item::Global(global) => self.globals.push(global.clone()),
+            }
+        }
+
+        if !self.function_indices.contains_key("apex") {
+            self.error(
+                file.span,
+                "No `apex` function found; the web target needs an entry point",
+            );
+        }
+
+        for (idx, func) in functions {
+            self.compile_function(idx, func);
+        }
+    }
+
+    fn finish(mut self) -> Result<AfbcModule, Vec<CompileError>> {
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+        for function in self.functions.into_iter().flatten() {
+            let name_idx = self.module.add_string(&function.name);
+            let code_offset = self.module.bytecode.len() as u32;
+            self.module
+                .source_map
+                .extend(function.source_map.into_iter().map(|entry| SourceMapEntry {
+                    code_start: entry.code_start + code_offset,
+                    code_end: entry.code_end + code_offset,
+                    ..entry
+                }));
+            self.module.add_function(FunctionEntry {
+                name_idx,
+                arity: function.arity,
+                locals: function.locals,
+                code_offset,
+                code_len: function.code.len() as u32,
+            });
+            self.module.bytecode.extend(function.code);
+        }
+        Ok(self.module)
+    }
+
+    fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.errors.push(CompileError {
+            message: message.into(),
+            span,
+        });
+    }
+
+    fn unsupported(&mut self, span: Span, what: &str) {
+        self.error(span, format!("{what} are not supported by the web target"));
+    }
+
+    fn ctx(&mut self) -> &mut FnCtx {
+        self.ctxs.last_mut().expect("function context")
+    }
+
+    fn reserve_function(&mut self, span: Span) -> u16 {
+        let idx = self.functions.len();
+        self.functions.push(None);
+        u16::try_from(idx).unwrap_or_else(|_| {
+            self.error(span, "Too many functions for the AFBC function table");
+            0
+        })
+    }
+
+    fn emit(&mut self, op: Opcode) {
+        self.ctx().code.emit(op);
+    }
+
+    fn emit_constant(&mut self, constant: Constant, span: Span) {
+        let idx = self.constant_index(constant, span);
+        self.ctx().code.emit_const(idx);
+    }
+
+    fn emit_with_name(&mut self, op: Opcode, name: &str, span: Span) {
+        let idx = self.constant_index(Constant::Utf8(name.to_string()), span);
+        let code = &mut self.ctx().code;
+        code.emit(op);
+        code.emit_u16(idx);
+    }
+
+    fn constant_index(&mut self, constant: Constant, span: Span) -> u16 {
+        let idx = self.module.add_constant(constant);
+        u16::try_from(idx).unwrap_or_else(|_| {
+            self.error(span, "Too many constants for the AFBC constant pool");
+            0
+        })
+    }
+
+    fn emit_argc(&mut self, argc: usize, span: Span) {
+        let argc = u8::try_from(argc).unwrap_or_else(|_| {
+            self.error(span, "Too many arguments in call");
+            0
+        });
+        self.ctx().code.emit_u8(argc);
+    }
+
+    fn finish_function(&mut self, name: &str, arity: usize, span: Span) -> CompiledFunction {
+        let ctx = self.ctxs.pop().expect("function context");
+        if ctx.overflow || ctx.slot_count > u16::MAX as usize {
+            self.error(
+                span,
+                format!("Function `{name}` is too large for AFBC operands"),
+            );
+        }
+        CompiledFunction {
+            name: name.to_string(),
+            arity: arity as u16,
+            locals: ctx.slot_count.min(u16::MAX as usize) as u16,
+            code: ctx.code.finish(),
+            source_map: ctx.source_map,
+        }
+    }
+
+    fn compile_function(&mut self, idx: u16, func: &Function) {
+        let sig = &func.signature;
+        if sig.is_async {
+            self.unsupported(sig.span, "`async` functions");
+        }
+        let mut ctx = FnCtx::default();
+        for param in &sig.params {
+            ctx.declare(&param.name, LocalKind::Value, false);
+        }
+        self.ctxs.push(ctx);
+        if sig.name == "apex" {
+            // Initializers are constant expressions, so running them on entry
+            // is indistinguishable from static initialization.
+            for global in std::mem::take(&mut self.globals) {
+                self.compile_expr(&global.value);
+                self.emit_with_name(Opcode::StoreGlobal, &global.name, global.span);
+            }
+        }
+        self.compile_block(&func.body);
+        let compiled = self.finish_function(&sig.name, sig.params.len(), sig.span);
+        self.functions[idx as usize] = Some(compiled);
+    }
+
+    fn compile_block(&mut self, block: &Block) {
+        self.ctx().begin_scope();
+        for stmt in &block.statements {
+            self.compile_stmt(stmt);
+        }
+        self.ctx().end_scope();
+    }
+
+    fn compile_stmt(&mut self, stmt: &Stmt) {
+        let start = self.ctx().code.current_offset();
+        let span = stmt_span(stmt);
+        match stmt {
+            Stmt::VarDecl(decl) => {
+                let Some(name) = decl.pattern.binding_name() else {
+                    self.unsupported(decl.span, "destructuring declarations");
+                    return;
+                };
+                self.compile_expr(&decl.value);
+                let kind = self.value_kind(&decl.value);
+                let slot = self.ctx().declare(name, kind, false);
+                self.ctx().code.emit_store_local(slot);
+            }
+            Stmt::Expr(expr) => self.compile_expr_stmt(expr),
+            Stmt::Return { value, span } => {
+                if self.ctx().ui_parent.is_some() {
+                    self.unsupported(*span, "`return` statements inside `ui.window` callbacks");
+                }
+                match value {
+                    Some(value) => self.compile_expr(value),
+                    None => self.emit_constant(Constant::Null, *span),
+                }
+                self.emit(Opcode::Ret);
+            }
+            Stmt::If(if_stmt) => self.compile_if(if_stmt),
+            Stmt::While {
+                label,
+                condition,
+                body,
+                ..
+            } => {
+                let loop_start = self.ctx().code.current_offset();
+                self.compile_expr(condition);
+                let exit = self.ctx().emit_jump(Opcode::JumpIfFalse);
+                self.ctx().loops.push(LoopCtx {
+                    label: label.clone(),
+                    ..LoopCtx::default()
+                });
+                self.compile_block(body);
+                let loop_ctx = self.ctx().loops.pop().unwrap_or_default();
+                for at in loop_ctx.continues {
+                    self.ctx().patch_jump(at, loop_start);
+                }
+                self.ctx().emit_loop(loop_start);
+                self.ctx().patch_here(exit);
+                for at in loop_ctx.breaks {
+                    self.ctx().patch_here(at);
+                }
+            }
+            Stmt::For {
+                label,
+                pattern,
+                iterable,
+                body,
+                span,
+            } => match pattern.binding_name() {
+                Some(var) => self.compile_for(label.clone(), var, iterable, body, *span),
+                None => self.unsupported(*span, "destructuring `for` bindings"),
+            },
+            Stmt::Block(block) | Stmt::Unsafe { body: block, .. } => self.compile_block(block),
+            Stmt::Break {
+                value: Some(value), ..
+            } => self.unsupported(value.span(), "`break` with a value"),
+            Stmt::Break { label, span, .. } | Stmt::Continue { label, span } => {
+                let target = match label {
+                    Some(label) => self
+                        .ctx()
+                        .loops
+                        .iter()
+                        .rposition(|ctx| ctx.label.as_ref() == Some(label)),
+                    None => self.ctx().loops.len().checked_sub(1),
+                };
+                if let Some(target) = target {
+                    let at = self.ctx().emit_jump(Opcode::Jump);
+                    let is_break = matches!(stmt, Stmt::Break { .. });
+                    let loop_ctx = &mut self.ctx().loops[target];
+                    if is_break {
+                        loop_ctx.breaks.push(at);
+                    } else {
+                        loop_ctx.continues.push(at);
+                    }
+                } else {
+                    self.error(*span, "`break`/`continue` outside of a loop");
+                }
+            }
+            Stmt::Switch(switch) => self.unsupported(switch.span, "`switch` statements"),
+            Stmt::Try(try_catch) => self.unsupported(try_catch.span, "`try`/`catch` blocks"),
+            Stmt::Assembly(asm) => self.unsupported(asm.span, "`asm` blocks"),
+        }
+        let end = self.ctx().code.current_offset();
+        if end > start {
+            // Nested statements were recorded first, so lookups find the innermost entry.
+            self.ctx().source_map.push(SourceMapEntry {
+                code_start: start as u32,
+                code_end: end as u32,
+                line: span.line as u32,
+                column: span.column as u32,
+            });
+        }
+    }
+
+    fn compile_expr_stmt(&mut self, expr: &Expr) {
+        if let Expr::Loop { label, body, .. } = expr {
+            self.compile_loop(label.clone(), body);
+            return;
+        }
+        if let Expr::Assignment {
+            target,
+            value,
+            span,
+        } = expr
+        {
+            self.compile_assignment(target, value, *span);
+            return;
+        }
+        let parent = self.ctx().ui_parent;
+        match parent {
+            Some(parent) if self.is_widget_call(expr) => {
+                self.ctx().code.emit_load_local(parent);
+                self.compile_expr(expr);
+                self.emit(Opcode::GuiAddChild);
+            }
+            _ => self.compile_expr(expr),
+        }
+        self.emit(Opcode::Pop);
+    }
+
+    fn compile_assignment(&mut self, target: &Expr, value: &Expr, span: Span) {
+        match target {
+            Expr::Identifier { name, .. } => {
+                let local = self
+                    .ctx()
+                    .resolve(name)
+                    .map(|local| (local.slot, local.captured));
+                match local {
+                    Some((_, true)) => self.error(
+                        span,
+                        format!(
+                            "Cannot assign to captured variable `{name}`; closures capture by value in the web target (use `ui.state`)"
+                        ),
+                    ),
+                    Some((slot, false)) => {
+                        self.compile_expr(value);
+                        self.ctx().code.emit_store_local(slot);
+                    }
+                    None => {
+                        self.compile_expr(value);
+                        self.emit_with_name(Opcode::StoreGlobal, name, span);
+                    }
+                }
+            }
+            Expr::Access { base, member, .. } => {
+                self.compile_expr(base);
+                self.compile_expr(value);
+                self.emit_with_name(Opcode::SetProp, member, span);
+                self.emit(Opcode::Pop);
+            }
+            other => self.error(other.span(), "Invalid assignment target for the web target"),
+        }
+    }
+
+    fn compile_if(&mut self, if_stmt: &IfStmt) {
+        let mut end_jumps = Vec::new();
+        let branches = std::iter::once((&if_stmt.condition, &if_stmt.then_branch))
+            .chain(if_stmt.else_if.iter().map(|(cond, block)| (cond, block)));
+        for (condition, block) in branches {
+            self.compile_expr(condition);
+            let next = self.ctx().emit_jump(Opcode::JumpIfFalse);
+            self.compile_block(block);
+            end_jumps.push(self.ctx().emit_jump(Opcode::Jump));
+            self.ctx().patch_here(next);
+        }
+        if let Some(block) = &if_stmt.else_branch {
+            self.compile_block(block);
+        }
+        for at in end_jumps {
+            self.ctx().patch_here(at);
+        }
+    }
+
+    /// A `loop` statement; as an expression with a value it is unsupported.
+    fn compile_loop(&mut self, label: Option<String>, body: &Block) {
+        let loop_start = self.ctx().code.current_offset();
+        self.ctx().loops.push(LoopCtx {
+            label,
+            ..LoopCtx::default()
+        });
+        self.compile_block(body);
+        let loop_ctx = self.ctx().loops.pop().unwrap_or_default();
+        for at in loop_ctx.continues {
+            self.ctx().patch_jump(at, loop_start);
+        }
+        self.ctx().emit_loop(loop_start);
+        for at in loop_ctx.breaks {
+            self.ctx().patch_here(at);
+        }
+    }
+
+    fn compile_for(
+        &mut self,
+        label: Option<String>,
+        var: &str,
+        iterable: &Expr,
+        body: &Block,
+        span: Span,
+    ) {
+        let Expr::Range {
+            start,
+            end,
+            inclusive,
+            step,
+            ..
+        } = iterable
+        else {
+            self.unsupported(iterable.span(), "`for` loops over non-range iterables");
+            return;
+        };
+        self.ctx().begin_scope();
+        self.compile_expr(start);
+        let index = self.ctx().declare(var, LocalKind::Value, false);
+        self.ctx().code.emit_store_local(index);
+        self.compile_expr(end);
+        // Not valid identifiers, so these can never be named by user code.
+        let bound = self.ctx().declare("<for-end>", LocalKind::Value, false);
+        self.ctx().code.emit_store_local(bound);
+        let step = step.as_ref().map(|step| {
+            self.compile_expr(step);
+            let local = self.ctx().declare("<for-step>", LocalKind::Value, false);
+            self.ctx().code.emit_store_local(local);
+            local
+        });
+
+        let loop_start = self.ctx().code.current_offset();
+        self.ctx().code.emit_load_local(index);
+        self.ctx().code.emit_load_local(bound);
+        self.emit(if *inclusive { Opcode::Le } else { Opcode::Lt });
+        let exit = self.ctx().emit_jump(Opcode::JumpIfFalse);
+        self.ctx().loops.push(LoopCtx {
+            label,
+            ..LoopCtx::default()
+        });
+        self.compile_block(body);
+        let loop_ctx = self.ctx().loops.pop().unwrap_or_default();
+        for at in loop_ctx.continues {
+            self.ctx().patch_here(at);
+        }
+        self.ctx().code.emit_load_local(index);
+        match step {
+            Some(local) => self.ctx().code.emit_load_local(local),
+            None => self.emit_constant(Constant::Int64(1), span),
+        }
+        self.emit(Opcode::Add);
+        self.ctx().code.emit_store_local(index);
+        self.ctx().emit_loop(loop_start);
+        self.ctx().patch_here(exit);
+        for at in loop_ctx.breaks {
+            self.ctx().patch_here(at);
+        }
+        self.ctx().end_scope();
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(lit) => self.compile_literal(lit),
+            Expr::Identifier { name, span } => self.compile_identifier(name, *span),
+            Expr::Access {
+                base, member, span, ..
+            } => match self.global_path(expr) {
+                Some(path) => self.emit_with_name(Opcode::LoadGlobal, &path, *span),
+                None => {
+                    self.compile_expr(base);
+                    self.emit_with_name(Opcode::GetProp, member, *span);
+                }
+            },
+            Expr::Call {
+                callee, args, span, ..
+            } => match callee.as_ref() {
+                Expr::Access { base, member, .. } => {
+                    self.compile_method_call(base, member, args, *span)
+                }
+                Expr::Identifier { name, .. }
+                    if self.ctx().resolve(name).is_none()
+                        && self.function_indices.contains_key(name) =>
+                {
+                    let (idx, arity) = self.function_indices[name];
+                    if args.len() != arity {
+                        self.error(
+                            *span,
+                            format!(
+                                "Function `{name}` expects {arity} arguments, got {}",
+                                args.len()
+                            ),
+                        );
+                    }
+                    self.compile_args(args);
+                    let code = &mut self.ctx().code;
+                    code.emit(Opcode::Call);
+                    code.emit_u16(idx);
+                    self.emit_argc(args.len(), *span);
+                }
+                _ => {
+                    self.compile_expr(callee);
+                    self.compile_args(args);
+                    self.emit(Opcode::InvokeClosure);
+                    self.emit_argc(args.len(), *span);
+                }
+            },
+            Expr::MethodCall {
+                object,
+                method,
+                args,
+                span,
+            } => self.compile_method_call(object, method, args, *span),
+            Expr::Unary { op, expr, span } => {
+                self.compile_expr(expr);
+                match op {
+                    UnaryOp::Negate => self.emit(Opcode::Neg),
+                    UnaryOp::Not => self.emit(Opcode::Not),
+                    UnaryOp::Borrow => {}
+                    UnaryOp::BitNot => self.unsupported(*span, "Bitwise operators"),
+                }
+            }
+            Expr::Binary {
+                left,
+                op,
+                right,
+                span,
+            } => self.compile_binary(left, *op, right, *span),
+            Expr::Range { span, .. } => self.unsupported(*span, "Ranges outside `for` loops"),
+            Expr::ArrayLiteral { elements, .. } => {
+                self.emit(Opcode::NewVec);
+                for element in elements {
+                    self.compile_expr(element);
+                    self.emit(Opcode::VecPush);
+                }
+            }
+            Expr::StructLiteral { fields, .. } => {
+                self.emit(Opcode::NewMap);
+                for field in fields {
+                    self.emit_constant(Constant::Utf8(field.name.clone()), field.span);
+                    self.compile_expr(&field.expr);
+                    self.emit(Opcode::MapSet);
+                }
+            }
+            Expr::Lambda(lambda) => self.compile_lambda(lambda),
+            Expr::Assignment { span, .. } => self.unsupported(*span, "Assignments used as values"),
+            Expr::Await { span, .. } => self.unsupported(*span, "`await` expressions"),
+            Expr::TupleLiteral { span, .. } => self.unsupported(*span, "Tuples"),
+            Expr::Cast { span, .. } => self.unsupported(*span, "`as` casts"),
+            Expr::Block(block) => self.unsupported(block.span, "Block expressions"),
+            Expr::If(if_stmt) => self.unsupported(if_stmt.span, "`if` expressions"),
+            Expr::Try { span, .. } => self.unsupported(*span, "`?` operators"),
+            Expr::Index { span, .. } => self.unsupported(*span, "Index expressions"),
+            Expr::Check(check) => self.unsupported(check.span, "`check` expressions"),
+            Expr::Interpolated { span, .. } => self.unsupported(*span, "String interpolation"),
+            Expr::Loop { span, .. } => self.unsupported(*span, "`loop` expressions"),
+            Expr::Let { span, .. } => {
+                self.unsupported(*span, "`if let` and `while let` conditions")
+            }
+        }
+    }
+
+    fn compile_args(&mut self, args: &[Expr]) {
+        for arg in args {
+            if let Expr::Assignment { span, .. } = arg {
+                self.unsupported(*span, "Named arguments outside `ui` calls");
+            } else {
+                self.compile_expr(arg);
+            }
+        }
+    }
+
+    fn compile_literal(&mut self, lit: &Literal) {
+        let span = lit.span();
+        let constant = match lit {
+            Literal::Integer { value, .. } => match value.replace('_', "").parse::<i64>() {
+                Ok(value) => Constant::Int64(value),
+                Err(_) => {
+                    self.error(span, format!("Invalid integer literal `{value}`"));
+                    return;
+                }
+            },
+            Literal::Float { value, .. } => match value.parse::<f64>() {
+                Ok(value) => Constant::Float64(value),
+                Err(_) => {
+                    self.error(span, format!("Invalid float literal `{value}`"));
+                    return;
+                }
+            },
+            Literal::String { value, .. } => Constant::Utf8(value.clone()),
+            Literal::Char { value, .. } => Constant::Utf8(value.to_string()),
+            Literal::Bool { value, .. } => Constant::Bool(*value),
+        };
+        self.emit_constant(constant, span);
+    }
+
+    fn compile_identifier(&mut self, name: &str, span: Span) {
+        if let Some(slot) = self.ctx().resolve(name).map(|local| local.slot) {
+            self.ctx().code.emit_load_local(slot);
+        } else if let Some(&(idx, _)) = self.function_indices.get(name) {
+            // A function used as a value becomes a closure without captures.
+            let code = &mut self.ctx().code;
+            code.emit(Opcode::MakeClosure);
+            code.emit_u16(idx);
+            code.emit_u8(0);
+        } else {
+            match self.modules.get(name) {
+                Some(ModuleRef::Gui) => {
+                    self.error(span, format!("Module `{name}` cannot be used as a value"))
+                }
+                Some(ModuleRef::Builtin(module)) if module.is_empty() => {
+                    self.error(span, format!("Module `{name}` cannot be used as a value"))
+                }
+                Some(ModuleRef::Builtin(module)) => {
+                    let module = module.clone();
+                    self.emit_with_name(Opcode::LoadGlobal, &module, span);
+                }
+                None => self.emit_with_name(Opcode::LoadGlobal, name, span),
+            }
+        }
+    }
+
+    fn compile_binary(&mut self, left: &Expr, op: BinaryOp, right: &Expr, span: Span) {
+        match op {
+            BinaryOp::LogicalAnd => {
+                // Short-circuit: leave `left` as the result when it is falsy.
+                self.compile_expr(left);
+                self.emit(Opcode::Dup);
+                let end = self.ctx().emit_jump(Opcode::JumpIfFalse);
+                self.emit(Opcode::Pop);
+                self.compile_expr(right);
+                self.ctx().patch_here(end);
+            }
+            BinaryOp::LogicalOr => {
+                self.compile_expr(left);
+                self.emit(Opcode::Dup);
+                let rhs = self.ctx().emit_jump(Opcode::JumpIfFalse);
+                let end = self.ctx().emit_jump(Opcode::Jump);
+                self.ctx().patch_here(rhs);
+                self.emit(Opcode::Pop);
+                self.compile_expr(right);
+                self.ctx().patch_here(end);
+            }
+            BinaryOp::BitAnd
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::ShiftLeft
+            | BinaryOp::ShiftRight => self.unsupported(span, "Bitwise operators"),
+            _ => {
+                self.compile_expr(left);
+                self.compile_expr(right);
+                let opcode = match op {
+                    BinaryOp::Add if is_string_expr(left) || is_string_expr(right) => {
+                        Opcode::Concat
+                    }
+                    BinaryOp::Add => Opcode::Add,
+                    BinaryOp::Subtract => Opcode::Sub,
+                    BinaryOp::Multiply => Opcode::Mul,
+                    BinaryOp::Divide => Opcode::Div,
+                    BinaryOp::Modulo => Opcode::Mod,
+                    BinaryOp::Equal => Opcode::Eq,
+                    BinaryOp::NotEqual => Opcode::Ne,
+                    BinaryOp::Less => Opcode::Lt,
+                    BinaryOp::LessEqual => Opcode::Le,
+                    BinaryOp::Greater => Opcode::Gt,
+                    _ => Opcode::Ge,
+                };
+                self.emit(opcode);
+            }
+        }
+    }
+
+    fn compile_method_call(&mut self, object: &Expr, method: &str, args: &[Expr], span: Span) {
+        if let Expr::Identifier { name, .. } = object {
+            let local = self
+                .ctx()
+                .resolve(name)
+                .map(|local| (local.slot, local.kind));
+            match local {
+                Some((slot, LocalKind::State)) => {
+                    return self.compile_state_call(slot, name, method, args, span)
+                }
+                Some((_, LocalKind::Ui)) => return self.compile_gui_call(method, args, span),
+                Some((_, LocalKind::Value)) => {}
+                None => {
+                    if matches!(self.modules.get(name), Some(ModuleRef::Gui)) {
+                        return self.compile_gui_call(method, args, span);
+                    }
+                }
+            }
+        }
+
+        match self.global_path(object) {
+            Some(path) if path == "log" && method == "info" => {
+                let mut args = args.iter();
+                match args.next() {
+                    Some(first) => self.compile_expr(first),
+                    None => self.emit_constant(Constant::Utf8(String::new()), span),
+                }
+                // Multiple arguments are joined with spaces, like the `log.info` builtin.
+                for arg in args {
+                    self.emit_constant(Constant::Utf8(" ".to_string()), span);
+                    self.emit(Opcode::Concat);
+                    self.compile_expr(arg);
+                    self.emit(Opcode::Concat);
+                }
+                self.emit(Opcode::LogInfo);
+                self.emit_constant(Constant::Null, span);
+            }
+            Some(path) => {
+                self.emit_with_name(Opcode::LoadGlobal, &format!("{path}.{method}"), span);
+                self.compile_args(args);
+                self.emit(Opcode::InvokeClosure);
+                self.emit_argc(args.len(), span);
+            }
+            None => self.error(
+                span,
+                format!("Method `{method}` cannot be called on a value in the web target"),
+            ),
+        }
+    }
+
+    fn compile_state_call(
+        &mut self,
+        slot: u16,
+        name: &str,
+        method: &str,
+        args: &[Expr],
+        span: Span,
+    ) {
+        match (method, args) {
+            ("get", []) => {
+                self.ctx().code.emit_load_local(slot);
+                self.emit(Opcode::StateGet);
+            }
+            ("set", [value]) => {
+                self.ctx().code.emit_load_local(slot);
+                self.compile_expr(value);
+                self.emit(Opcode::StateSet);
+                self.emit_constant(Constant::Null, span);
+            }
+            _ => self.error(
+                span,
+                format!("State `{name}` only supports `get()` and `set(value)`"),
+            ),
+        }
+    }
+
+    fn compile_gui_call(&mut self, method: &str, args: &[Expr], span: Span) {
+        let (positional, named): (Vec<&Expr>, Vec<&Expr>) = args
+            .iter()
+            .partition(|arg| !matches!(arg, Expr::Assignment { .. }));
+        let expected = match method {
+            "window" | "button" => 2,
+            "text" | "column" | "row" | "container" | "state" => 1,
+            _ => {
+                self.error(
+                    span,
+                    format!("`ui.{method}` is not supported by the web target"),
+                );
+                return;
+            }
+        };
+        if positional.len() != expected {
+            self.error(
+                span,
+                format!(
+                    "`ui.{method}` expects {expected} argument(s), got {}",
+                    positional.len()
+                ),
+            );
+            return;
+        }
+        if method == "state" {
+            self.compile_expr(positional[0]);
+            self.emit(Opcode::StateCreate);
+            return;
+        }
+        if method == "window" {
+            return self.compile_window(positional[0], positional[1], span);
+        }
+
+        let widget_type = match method {
+            "text" => "Text",
+            "button" => "Button",
+            "column" => "Column",
+            "row" => "Row",
+            _ => "Container",
+        };
+        self.emit_with_name(Opcode::GuiCreateWidget, widget_type, span);
+        match method {
+            "text" => {
+                self.compile_expr(positional[0]);
+                self.emit_with_name(Opcode::GuiSetProp, "text", span);
+            }
+            "button" => {
+                self.compile_expr(positional[0]);
+                self.emit_with_name(Opcode::GuiSetProp, "label", span);
+                self.compile_expr(positional[1]);
+                self.emit_with_name(Opcode::GuiSetHandler, "click", span);
+            }
+            "column" | "row" => match positional[0] {
+                Expr::ArrayLiteral { elements, .. } => {
+                    for element in elements {
+                        self.compile_expr(element);
+                        self.emit(Opcode::GuiAddChild);
+                    }
+                }
+                other => self.error(
+                    other.span(),
+                    format!("`ui.{method}` expects a list literal of widgets"),
+                ),
+            },
+            _ => {
+                self.compile_expr(positional[0]);
+                self.emit(Opcode::GuiAddChild);
+            }
+        }
+        for arg in named {
+            let Expr::Assignment {
+                target,
+                value,
+                span,
+            } = arg
+            else {
+                continue;
+            };
+            match target.as_ref() {
+                Expr::Identifier { name, .. } => {
+                    self.compile_expr(value);
+                    self.emit_with_name(Opcode::GuiSetProp, name, *span);
+                }
+                other => self.error(other.span(), "Named arguments must be plain identifiers"),
+            }
+        }
+    }
+
+    /// `ui.window(title, fun(ctx) { ... })` runs its callback inline: `ctx` is the
+    /// window widget, and widget calls in statement position become its children.
+    fn compile_window(&mut self, title: &Expr, callback: &Expr, span: Span) {
+        let lambda = match callback {
+            Expr::Lambda(lambda) if lambda.params.len() == 1 => lambda,
+            other => {
+                self.error(
+                    other.span(),
+                    "`ui.window` expects a callback literal taking the window context",
+                );
+                return;
+            }
+        };
+        self.emit_with_name(Opcode::GuiCreateWidget, "Window", span);
+        self.compile_expr(title);
+        self.emit_with_name(Opcode::GuiSetProp, "title", span);
+
+        self.ctx().begin_scope();
+        let slot = self
+            .ctx()
+            .declare(&lambda.params[0].name, LocalKind::Ui, false);
+        self.ctx().code.emit_store_local(slot);
+        let saved = self.ctx().ui_parent.replace(slot);
+        self.compile_block(&lambda.body);
+        self.ctx().ui_parent = saved;
+        self.ctx().code.emit_load_local(slot);
+        self.emit(Opcode::GuiCommitRoot);
+        self.ctx().end_scope();
+        self.emit_constant(Constant::Null, span);
+    }
+
+    fn compile_lambda(&mut self, lambda: &LambdaExpr) {
+        if lambda.is_async {
+            self.unsupported(lambda.span, "`async` closures");
+        }
+        let mut names = BTreeSet::new();
+        collect_block(&lambda.body, &mut names);
+        let captures: Vec<(String, u16, LocalKind)> = names
+            .into_iter()
+            .filter(|name| lambda.params.iter().all(|param| &param.name != name))
+            .filter_map(|name| {
+                let local = self.ctx().resolve(&name)?;
+                Some((name, local.slot, local.kind))
+            })
+            .collect();
+
+        let idx = self.reserve_function(lambda.span);
+        let mut ctx = FnCtx::default();
+        for (name, _, kind) in &captures {
+            ctx.declare(name, *kind, true);
+        }
+        for param in &lambda.params {
+            ctx.declare(&param.name, LocalKind::Value, false);
+        }
+        self.ctxs.push(ctx);
+        self.compile_block(&lambda.body);
+        let compiled = self.finish_function("<lambda>", lambda.params.len(), lambda.span);
+        self.functions[idx as usize] = Some(compiled);
+
+        for (_, slot, _) in &captures {
+            self.ctx().code.emit_load_local(*slot);
+        }
+        let code = &mut self.ctx().code;
+        code.emit(Opcode::MakeClosure);
+        code.emit_u16(idx);
+        self.emit_argc(captures.len(), lambda.span);
+    }
+
+    /// Dotted global name for module members (`log.info`, `math.pi`), or `None` when
+    /// the expression refers to a local, a user function or the gui module.
+    fn global_path(&mut self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Identifier { name, .. } => {
+                if self.ctx().resolve(name).is_some() || self.function_indices.contains_key(name) {
+                    return None;
+                }
+                match self.modules.get(name) {
+                    Some(ModuleRef::Gui) => None,
+                    Some(ModuleRef::Builtin(module)) => Some(module.clone()),
+                    None => Some(name.clone()),
+                }
+            }
+            Expr::Access { base, member, .. } => match self.global_path(base)? {
+                base if base.is_empty() => Some(member.clone()),
+                base => Some(format!("{base}.{member}")),
+            },
+            _ => None,
+        }
+    }
+
+    fn value_kind(&mut self, expr: &Expr) -> LocalKind {
+        match expr {
+            Expr::MethodCall { object, method, .. } if method == "state" => match object.as_ref() {
+                Expr::Identifier { name, .. }
+                    if self.ctx().resolve(name).is_none()
+                        && matches!(self.modules.get(name), Some(ModuleRef::Gui)) =>
+                {
+                    LocalKind::State
+                }
+                _ => LocalKind::Value,
+            },
+            Expr::Identifier { name, .. } => self
+                .ctx()
+                .resolve(name)
+                .map(|local| local.kind)
+                .unwrap_or(LocalKind::Value),
+            _ => LocalKind::Value,
+        }
+    }
+
+    fn is_widget_call(&mut self, expr: &Expr) -> bool {
+        let Expr::MethodCall { object, method, .. } = expr else {
+            return false;
+        };
+        if !matches!(
+            method.as_str(),
+            "text" | "button" | "column" | "row" | "container"
+        ) {
+            return false;
+        }
+        let Expr::Identifier { name, .. } = object.as_ref() else {
+            return false;
+        };
+        match self.ctx().resolve(name) {
+            Some(local) => local.kind == LocalKind::Ui,
+            None => matches!(self.modules.get(name), Some(ModuleRef::Gui)),
+        }
+    }
+}
+
+/// `+` compiles to `CONCAT` when either side is statically a string.
+fn is_string_expr(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(Literal::String { .. }) => true,
+        Expr::Binary {
+            left,
+            op: BinaryOp::Add,
+            right,
+            ..
+        } => is_string_expr(left) || is_string_expr(right),
+        _ => false,
+    }
+}
+
+fn stmt_span(stmt: &Stmt) -> Span {
+    match stmt {
+        Stmt::VarDecl(decl) => decl.span,
+        Stmt::Expr(expr) => expr.span(),
+        Stmt::Return { span, .. }
+        | Stmt::While { span, .. }
+        | Stmt::For { span, .. }
+        | Stmt::Unsafe { span, .. }
+        | Stmt::Break { span, .. }
+        | Stmt::Continue { span, .. } => *span,
+        Stmt::If(if_stmt) => if_stmt.span,
+        Stmt::Switch(switch) => switch.span,
+        Stmt::Try(try_catch) => try_catch.span,
+        Stmt::Block(block) => block.span,
+        Stmt::Assembly(asm) => asm.span,
+    }
+}
+
+// Free-variable collection for closure captures. Every identifier used in the body is
+// collected; names that do not resolve to an enclosing local are simply not captured.
+
+fn collect_block(block: &Block, names: &mut BTreeSet<String>) {
+    for stmt in &block.statements {
+        collect_stmt(stmt, names);
+    }
+}
+
+fn collect_stmt(stmt: &Stmt, names: &mut BTreeSet<String>) {
+    match stmt {
+        Stmt::VarDecl(decl) => collect_expr(&decl.value, names),
+        Stmt::Expr(expr) => collect_expr(expr, names),
+        Stmt::Return { value, .. } => {
+            if let Some(value) = value {
+                collect_expr(value, names);
+            }
+        }
+        Stmt::If(if_stmt) => collect_if(if_stmt, names),
+        Stmt::While {
+            condition, body, ..
+        } => {
+            collect_expr(condition, names);
+            collect_block(body, names);
+        }
+        Stmt::For { iterable, body, .. } => {
+            collect_expr(iterable, names);
+            collect_block(body, names);
+        }
+        Stmt::Switch(switch) => {
+            collect_expr(&switch.expr, names);
+            for arm in &switch.arms {
+                collect_expr(&arm.expr, names);
+            }
+        }
+        Stmt::Try(try_catch) => {
+            collect_block(&try_catch.try_block, names);
+            collect_block(&try_catch.catch_block, names);
+        }
+        Stmt::Block(block) | Stmt::Unsafe { body: block, .. } => collect_block(block, names),
+        Stmt::Break { value, .. } => {
+            if let Some(value) = value {
+                collect_expr(value, names);
+            }
+        }
+        Stmt::Assembly(_) | Stmt::Continue { .. } => {}
+    }
+}
+
+fn collect_if(if_stmt: &IfStmt, names: &mut BTreeSet<String>) {
+    collect_expr(&if_stmt.condition, names);
+    collect_block(&if_stmt.then_branch, names);
+    for (condition, block) in &if_stmt.else_if {
+        collect_expr(condition, names);
+        collect_block(block, names);
+    }
+    if let Some(block) = &if_stmt.else_branch {
+        collect_block(block, names);
+    }
+}
+
+fn collect_expr(expr: &Expr, names: &mut BTreeSet<String>) {
+    match expr {
+        Expr::Literal(_) => {}
+        Expr::Identifier { name, .. } => {
+            names.insert(name.clone());
+        }
+        Expr::Access { base, .. } => collect_expr(base, names),
+        Expr::Call { callee, args, .. } => {
+            collect_expr(callee, names);
+            args.iter().for_each(|arg| collect_expr(arg, names));
+        }
+        Expr::MethodCall { object, args, .. } => {
+            collect_expr(object, names);
+            args.iter().for_each(|arg| collect_expr(arg, names));
+        }
+        Expr::Await { expr, .. }
+        | Expr::Unary { expr, .. }
+        | Expr::Cast { expr, .. }
+        | Expr::Try { expr, .. } => collect_expr(expr, names),
+        Expr::Binary { left, right, .. } => {
+            collect_expr(left, names);
+            collect_expr(right, names);
+        }
+        Expr::Range {
+            start, end, step, ..
+        } => {
+            collect_expr(start, names);
+            collect_expr(end, names);
+            if let Some(step) = step {
+                collect_expr(step, names);
+            }
+        }
+        Expr::Assignment { target, value, .. } => {
+            collect_expr(target, names);
+            collect_expr(value, names);
+        }
+        Expr::StructLiteral { fields, .. } => {
+            fields
+                .iter()
+                .for_each(|field| collect_expr(&field.expr, names));
+        }
+        Expr::ArrayLiteral { elements, .. } | Expr::TupleLiteral { elements, .. } => {
+            elements
+                .iter()
+                .for_each(|element| collect_expr(element, names));
+        }
+        Expr::Block(block) => collect_block(block, names),
+        Expr::If(if_stmt) => collect_if(if_stmt, names),
+        Expr::Lambda(lambda) => collect_block(&lambda.body, names),
+        Expr::Index { base, index, .. } => {
+            collect_expr(base, names);
+            collect_expr(index, names);
+        }
+        Expr::Check(check) => {
+            if let Some(target) = &check.target {
+                collect_expr(target, names);
+            }
+            for arm in &check.arms {
+                if let CheckPattern::Guard(guard) = &arm.pattern {
+                    collect_expr(guard, names);
+                }
+                collect_expr(&arm.expr, names);
+            }
+        }
+        Expr::Interpolated { parts, .. } => {
+            for part in parts {
+                if let InterpolationPart::Expr(expr) = part {
+                    collect_expr(expr, names);
+                }
+            }
+        }
+        Expr::Loop { body, .. } => collect_block(body, names),
+        Expr::Let { value, .. } => collect_expr(value, names),
+    }
+}
@@ -15,6 +15,9 @@ use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
 use std::io::{self, Read, Write};
 
+pub mod compiler;
+pub mod vm;
+
 /// AFBC file magic bytes
 pub const MAGIC: &[u8; 4] = b"AFBC";
 
@@ -0,0 +1,791 @@
+//! Stack-based VM for AFBC modules.
+//!
+//! Mirrors `packages/afns_web_vm/src/vm.ts` instruction for instruction so the two can be
+//! checked against each other. Values that the tree-walking runtime understands are
+//! carried as `runtime::Value`, which lets `LOAD_GLOBAL` resolve the interpreter's
+//! builtin modules (`log`, `vec`, `map`, `math`, ...) and call them unchanged.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{AfbcModule, Constant, FunctionEntry, Opcode};
+use crate::module_loader::ModuleLoader;
+use crate::runtime::{
+    map_key_from_value, Interpreter, MapValue, RuntimeError, RuntimeResult, Value, VecValue,
+};
+
+/// A value on the VM stack.
+#[derive(Clone, Debug)]
+pub enum VmValue {
+    Value(Value),
+    Closure(Rc<VmClosure>),
+    Widget(Rc<RefCell<Widget>>),
+    State(usize),
+}
+
+#[derive(Debug)]
+pub struct VmClosure {
+    pub func_idx: usize,
+    pub captures: Vec<VmValue>,
+}
+
+#[derive(Debug)]
+pub struct Widget {
+    pub id: String,
+    pub widget_type: String,
+    pub props: HashMap<String, VmValue>,
+    pub children: Vec<Rc<RefCell<Widget>>>,
+    pub handlers: HashMap<String, VmValue>,
+}
+
+impl VmValue {
+    const NULL: VmValue = VmValue::Value(Value::Null);
+
+    fn is_truthy(&self) -> bool {
+        match self {
+            VmValue::Value(Value::Bool(b)) => *b,
+            VmValue::Value(Value::Null) => false,
+            _ => true,
+        }
+    }
+
+    pub fn to_display(&self) -> String {
+        match self {
+            VmValue::Value(value) => value.to_string_value(),
+            VmValue::Closure(closure) => format!("<closure:{}>", closure.func_idx),
+            VmValue::Widget(widget) => {
+                let widget = widget.borrow();
+                format!("<widget:{}#{}>", widget.widget_type, widget.id)
+            }
+            VmValue::State(id) => format!("<state:{id}>"),
+        }
+    }
+
+    fn into_value(self, context: &str) -> RuntimeResult<Value> {
+        match self {
+            VmValue::Value(value) => Ok(value),
+            other => Err(RuntimeError::new(format!(
+                "{context}: {} cannot be passed to the runtime",
+                other.to_display()
+            ))),
+        }
+    }
+}
+
+struct Frame {
+    func_idx: usize,
+    ip: usize,
+    bp: usize,
+}
+
+pub struct Vm<'m> {
+    module: &'m AfbcModule,
+    interp: Interpreter,
+    rt: tokio::runtime::Runtime,
+    stack: Vec<VmValue>,
+    globals: HashMap<String, VmValue>,
+    frames: Vec<Frame>,
+    states: Vec<VmValue>,
+    widget_counter: usize,
+    root: Option<Rc<RefCell<Widget>>>,
+    /// Offset of the instruction being executed, for source-map lookups.
+    current_offset: usize,
+}
+
+impl<'m> Vm<'m> {
+    pub fn new(module: &'m AfbcModule) -> RuntimeResult<Self> {
+        Self::with_interpreter(module, Interpreter::new(ModuleLoader::new()))
+    }
+
+    pub fn with_interpreter(module: &'m AfbcModule, interp: Interpreter) -> RuntimeResult<Self> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| RuntimeError::new(format!("failed to start async runtime: {err}")))?;
+        Ok(Self {
+            module,
+            interp,
+            rt,
+            stack: Vec::new(),
+            globals: HashMap::new(),
+            frames: Vec::new(),
+            states: Vec::new(),
+            widget_counter: 0,
+            root: None,
+            current_offset: 0,
+        })
+    }
+
+    /// Run the module's `apex` function.
+    pub fn run(&mut self) -> RuntimeResult<VmValue> {
+        self.call("apex", Vec::new())
+    }
+
+    /// Call a function from the module's function table by name.
+    pub fn call(&mut self, name: &str, args: Vec<VmValue>) -> RuntimeResult<VmValue> {
+        let func_idx = self
+            .find_function(name)
+            .ok_or_else(|| RuntimeError::new(format!("No `{name}` function found in module")))?;
+        let depth = self.frames.len();
+        self.push_frame(func_idx, Vec::new(), args)?;
+        self.execute(depth)
+    }
+
+    /// Root widget committed by the last `GUI_COMMIT_ROOT`.
+    pub fn root_widget(&self) -> Option<Rc<RefCell<Widget>>> {
+        self.root.clone()
+    }
+
+    /// Run the handler registered for `event_type` on widget `widget_id`, if any.
+    pub fn handle_event(&mut self, widget_id: &str, event_type: &str) -> RuntimeResult<bool> {
+        let handler = self
+            .root
+            .as_ref()
+            .and_then(|root| find_handler(root, widget_id, event_type));
+        match handler {
+            Some(handler) => {
+                self.invoke(handler, Vec::new())?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn find_function(&self, name: &str) -> Option<usize> {
+        self.module.functions.iter().position(|func| {
+            matches!(
+                self.module.constants.get(func.name_idx as usize),
+                Some(Constant::Utf8(n)) if n == name
+            )
+        })
+    }
+
+    fn function(&self, func_idx: usize) -> RuntimeResult<&'m FunctionEntry> {
+        let module: &'m AfbcModule = self.module;
+        module
+            .functions
+            .get(func_idx)
+            .ok_or_else(|| self.error(format!("Invalid function index {func_idx}")))
+    }
+
+    fn push_frame(
+        &mut self,
+        func_idx: usize,
+        captures: Vec<VmValue>,
+        args: Vec<VmValue>,
+    ) -> RuntimeResult<()> {
+        let func = self.function(func_idx)?;
+        if args.len() != func.arity as usize {
+            return Err(self.error(format!(
+                "Function `{}` expects {} arguments, got {}",
+                self.function_name(func),
+                func.arity,
+                args.len()
+            )));
+        }
+        let bp = self.stack.len();
+        let provided = captures.len() + args.len();
+        self.stack.extend(captures);
+        self.stack.extend(args);
+        for _ in provided..func.locals as usize {
+            self.stack.push(VmValue::NULL);
+        }
+        self.frames.push(Frame {
+            func_idx,
+            ip: 0,
+            bp,
+        });
+        Ok(())
+    }
+
+    fn function_name(&self, func: &FunctionEntry) -> String {
+        match self.module.constants.get(func.name_idx as usize) {
+            Some(Constant::Utf8(name)) => name.clone(),
+            _ => format!("#{}", func.name_idx),
+        }
+    }
+
+    /// Call a closure or runtime callable to completion and return its result.
+    fn invoke(&mut self, callee: VmValue, args: Vec<VmValue>) -> RuntimeResult<VmValue> {
+        match callee {
+            VmValue::Closure(closure) => {
+                let depth = self.frames.len();
+                self.push_frame(closure.func_idx, closure.captures.clone(), args)?;
+                self.execute(depth)
+            }
+            VmValue::Value(callee) => {
+                let args = args
+                    .into_iter()
+                    .map(|arg| arg.into_value("call argument"))
+                    .collect::<RuntimeResult<Vec<_>>>()?;
+                let interp = self.interp.clone();
+                let result = self
+                    .rt
+                    .block_on(async move { interp.call_value(callee, args).await })?;
+                Ok(VmValue::Value(result))
+            }
+            other => Err(self.error(format!("Cannot call {}", other.to_display()))),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> RuntimeError {
+        let message = message.into();
+        let location = self
+            .module
+            .source_map
+            .iter()
+            .find(|entry| {
+                (entry.code_start as usize..entry.code_end as usize).contains(&self.current_offset)
+            })
+            .map(|entry| format!(" at line {}, column {}", entry.line, entry.column));
+        RuntimeError::new(format!("{message}{}", location.unwrap_or_default()))
+    }
+
+    fn pop(&mut self) -> RuntimeResult<VmValue> {
+        self.stack
+            .pop()
+            .ok_or_else(|| self.error("VM stack underflow"))
+    }
+
+    fn pop_n(&mut self, count: usize) -> RuntimeResult<Vec<VmValue>> {
+        if self.stack.len() < count {
+            return Err(self.error("VM stack underflow"));
+        }
+        Ok(self.stack.split_off(self.stack.len() - count))
+    }
+
+    fn top(&self) -> RuntimeResult<&VmValue> {
+        self.stack
+            .last()
+            .ok_or_else(|| self.error("VM stack underflow"))
+    }
+
+    fn read_u8(&mut self) -> RuntimeResult<u8> {
+        let frame = self.frames.last_mut().expect("active frame");
+        let func = &self.module.functions[frame.func_idx];
+        if frame.ip >= func.code_len as usize {
+            return Err(RuntimeError::new("Truncated instruction operand"));
+        }
+        let byte = self
+            .module
+            .bytecode
+            .get(func.code_offset as usize + frame.ip)
+            .copied()
+            .ok_or_else(|| RuntimeError::new("Function code extends past bytecode section"))?;
+        frame.ip += 1;
+        Ok(byte)
+    }
+
+    fn read_u16(&mut self) -> RuntimeResult<u16> {
+        let lo = self.read_u8()?;
+        let hi = self.read_u8()?;
+        Ok(u16::from_le_bytes([lo, hi]))
+    }
+
+    fn read_i16(&mut self) -> RuntimeResult<i16> {
+        Ok(self.read_u16()? as i16)
+    }
+
+    fn constant_string(&self, idx: u16) -> RuntimeResult<String> {
+        match self.module.constants.get(idx as usize) {
+            Some(Constant::Utf8(s)) => Ok(s.clone()),
+            _ => Err(self.error(format!("Constant {idx} is not a string"))),
+        }
+    }
+
+    fn load_global(&self, name: &str) -> RuntimeResult<VmValue> {
+        if let Some(value) = self.globals.get(name) {
+            return Ok(value.clone());
+        }
+        // `log.info`-style names resolve through the interpreter's builtin modules.
+        let mut segments = name.split('.');
+        let head = segments.next().unwrap_or_default();
+        let mut value = self
+            .interp
+            .global(head)
+            .ok_or_else(|| self.error(format!("Unknown global `{name}`")))?;
+        for segment in segments {
+            value = match value {
+                Value::Module(module) => module
+                    .fields
+                    .get(segment)
+                    .cloned()
+                    .ok_or_else(|| self.error(format!("Unknown global `{name}`")))?,
+                _ => return Err(self.error(format!("Unknown global `{name}`"))),
+            };
+        }
+        Ok(VmValue::Value(value))
+    }
+
+    /// Finish the current frame. Returns `Some(result)` once the frame at `depth` returns.
+    fn return_from_frame(&mut self, result: VmValue, depth: usize) -> Option<VmValue> {
+        let frame = self.frames.pop().expect("active frame");
+        self.stack.truncate(frame.bp);
+        if self.frames.len() > depth {
+            self.stack.push(result);
+            None
+        } else {
+            Some(result)
+        }
+    }
+
+    fn execute(&mut self, depth: usize) -> RuntimeResult<VmValue> {
+        loop {
+            let (func_idx, ip, bp) = {
+                let frame = self.frames.last().expect("active frame");
+                (frame.func_idx, frame.ip, frame.bp)
+            };
+            let func = self.function(func_idx)?;
+            if ip >= func.code_len as usize {
+                // Falling off the end of a function is an implicit `RET null`.
+                if let Some(result) = self.return_from_frame(VmValue::NULL, depth) {
+                    return Ok(result);
+                }
+                continue;
+            }
+            self.current_offset = func.code_offset as usize + ip;
+            let byte = self.read_u8()?;
+            let op = Opcode::from_u8(byte)
+                .ok_or_else(|| self.error(format!("Unknown opcode 0x{byte:02X}")))?;
+            match op {
+                Opcode::Const => {
+                    let idx = self.read_u16()?;
+                    let constant = self
+                        .module
+                        .constants
+                        .get(idx as usize)
+                        .ok_or_else(|| self.error(format!("Invalid constant index {idx}")))?;
+                    self.stack.push(VmValue::Value(constant_to_value(constant)));
+                }
+                Opcode::LoadLocal => {
+                    let slot = self.read_u16()? as usize;
+                    let value = self
+                        .stack
+                        .get(bp + slot)
+                        .cloned()
+                        .ok_or_else(|| self.error(format!("Invalid local slot {slot}")))?;
+                    self.stack.push(value);
+                }
+                Opcode::StoreLocal => {
+                    let slot = self.read_u16()? as usize;
+                    let value = self.pop()?;
+                    if bp + slot >= self.stack.len() {
+                        return Err(self.error(format!("Invalid local slot {slot}")));
+                    }
+                    self.stack[bp + slot] = value;
+                }
+                Opcode::LoadGlobal => {
+                    let idx = self.read_u16()?;
+                    let name = self.constant_string(idx)?;
+                    let value = self.load_global(&name)?;
+                    self.stack.push(value);
+                }
+                Opcode::StoreGlobal => {
+                    let idx = self.read_u16()?;
+                    let name = self.constant_string(idx)?;
+                    let value = self.pop()?;
+                    self.globals.insert(name, value);
+                }
+                Opcode::Call => {
+                    let callee = self.read_u16()? as usize;
+                    let argc = self.read_u8()? as usize;
+                    let args = self.pop_n(argc)?;
+                    self.push_frame(callee, Vec::new(), args)?;
+                }
+                Opcode::Ret => {
+                    let result = if self.stack.len() > bp + func.locals as usize {
+                        self.pop()?
+                    } else {
+                        VmValue::NULL
+                    };
+                    if let Some(result) = self.return_from_frame(result, depth) {
+                        return Ok(result);
+                    }
+                }
+                Opcode::Jump => {
+                    let offset = self.read_i16()?;
+                    self.jump(offset)?;
+                }
+                Opcode::JumpIfFalse => {
+                    let offset = self.read_i16()?;
+                    if !self.pop()?.is_truthy() {
+                        self.jump(offset)?;
+                    }
+                }
+                Opcode::MakeClosure => {
+                    let func_idx = self.read_u16()? as usize;
+                    let capture_count = self.read_u8()? as usize;
+                    let captures = self.pop_n(capture_count)?;
+                    self.stack
+                        .push(VmValue::Closure(Rc::new(VmClosure { func_idx, captures })));
+                }
+                Opcode::InvokeClosure => {
+                    let argc = self.read_u8()? as usize;
+                    let args = self.pop_n(argc)?;
+                    match self.pop()? {
+                        VmValue::Closure(closure) => {
+                            self.push_frame(closure.func_idx, closure.captures.clone(), args)?;
+                        }
+                        other => {
+                            let result = self.invoke(other, args)?;
+                            self.stack.push(result);
+                        }
+                    }
+                }
+                Opcode::NewVec => {
+                    self.stack
+                        .push(VmValue::Value(Value::Vec(Rc::new(RefCell::new(
+                            VecValue {
+                                elem_type: None,
+                                items: Vec::new(),
+                            },
+                        )))));
+                }
+                Opcode::VecPush => {
+                    let item = self.pop()?.into_value("VEC_PUSH")?;
+                    match self.top()? {
+                        VmValue::Value(Value::Vec(vec)) => vec.borrow_mut().items.push(item),
+                        _ => return Err(self.error("VEC_PUSH expects a vec")),
+                    }
+                }
+                Opcode::NewMap => {
+                    self.stack
+                        .push(VmValue::Value(Value::Map(Rc::new(RefCell::new(
+                            MapValue {
+                                key_type: None,
+                                value_type: None,
+                                entries: HashMap::new(),
+                            },
+                        )))));
+                }
+                Opcode::MapSet => {
+                    let value = self.pop()?.into_value("MAP_SET")?;
+                    let key = self.pop()?.into_value("MAP_SET")?;
+                    let key = map_key_from_value(&key, "MAP_SET")?;
+                    match self.top()? {
+                        VmValue::Value(Value::Map(map)) => {
+                            map.borrow_mut().entries.insert(key, value);
+                        }
+                        _ => return Err(self.error("MAP_SET expects a map")),
+                    }
+                }
+                Opcode::GuiCreateWidget => {
+                    let idx = self.read_u16()?;
+                    let widget_type = self.constant_string(idx)?;
+                    let id = format!("w{}", self.widget_counter);
+                    self.widget_counter += 1;
+                    self.stack
+                        .push(VmValue::Widget(Rc::new(RefCell::new(Widget {
+                            id,
+                            widget_type,
+                            props: HashMap::new(),
+                            children: Vec::new(),
+                            handlers: HashMap::new(),
+                        }))));
+                }
+                Opcode::GuiSetProp => {
+                    let idx = self.read_u16()?;
+                    let key = self.constant_string(idx)?;
+                    let value = self.pop()?;
+                    self.top_widget("GUI_SET_PROP")?
+                        .borrow_mut()
+                        .props
+                        .insert(key, value);
+                }
+                Opcode::GuiAddChild => {
+                    let child = match self.pop()? {
+                        VmValue::Widget(child) => child,
+                        _ => return Err(self.error("GUI_ADD_CHILD expects a widget child")),
+                    };
+                    self.top_widget("GUI_ADD_CHILD")?
+                        .borrow_mut()
+                        .children
+                        .push(child);
+                }
+                Opcode::GuiSetHandler => {
+                    let idx = self.read_u16()?;
+                    let event = self.constant_string(idx)?;
+                    let handler = self.pop()?;
+                    self.top_widget("GUI_SET_HANDLER")?
+                        .borrow_mut()
+                        .handlers
+                        .insert(event, handler);
+                }
+                Opcode::GuiCommitRoot => match self.pop()? {
+                    VmValue::Widget(widget) => self.root = Some(widget),
+                    _ => return Err(self.error("GUI_COMMIT_ROOT expects a widget")),
+                },
+                Opcode::LogInfo => {
+                    let value = self.pop()?;
+                    let log_info = self.load_global("log.info")?;
+                    let arg = match value {
+                        VmValue::Value(value) => value,
+                        other => Value::String(other.to_display()),
+                    };
+                    self.invoke(log_info, vec![VmValue::Value(arg)])?;
+                }
+                Opcode::Add | Opcode::Sub | Opcode::Mul | Opcode::Div | Opcode::Mod => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    let result = arithmetic(op, &a, &b).map_err(|msg| self.error(msg))?;
+                    self.stack.push(VmValue::Value(result));
+                }
+                Opcode::Eq | Opcode::Ne => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    let equal = values_equal(&a, &b);
+                    self.push_bool(if op == Opcode::Eq { equal } else { !equal });
+                }
+                Opcode::Lt | Opcode::Le | Opcode::Gt | Opcode::Ge => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    let ordering = compare(&a, &b).ok_or_else(|| {
+                        self.error(format!(
+                            "Cannot compare {} and {}",
+                            a.to_display(),
+                            b.to_display()
+                        ))
+                    })?;
+                    let result = match op {
+                        Opcode::Lt => ordering.is_lt(),
+                        Opcode::Le => ordering.is_le(),
+                        Opcode::Gt => ordering.is_gt(),
+                        _ => ordering.is_ge(),
+                    };
+                    self.push_bool(result);
+                }
+                Opcode::And | Opcode::Or => {
+                    let b = self.pop()?.is_truthy();
+                    let a = self.pop()?.is_truthy();
+                    self.push_bool(if op == Opcode::And { a && b } else { a || b });
+                }
+                Opcode::Not => {
+                    let value = self.pop()?;
+                    self.push_bool(!value.is_truthy());
+                }
+                Opcode::Neg => {
+                    let value = match self.pop()? {
+                        VmValue::Value(Value::Int(i)) => Value::Int(-i),
+                        VmValue::Value(Value::Float(f)) => Value::Float(-f),
+                        _ => return Err(self.error("Cannot negate non-number")),
+                    };
+                    self.stack.push(VmValue::Value(value));
+                }
+                Opcode::Dup => {
+                    let value = self.top()?.clone();
+                    self.stack.push(value);
+                }
+                Opcode::Pop => {
+                    self.pop()?;
+                }
+                Opcode::GetProp => {
+                    let idx = self.read_u16()?;
+                    let key = self.constant_string(idx)?;
+                    let object = self.pop()?;
+                    let value = self.get_prop(&object, &key)?;
+                    self.stack.push(value);
+                }
+                Opcode::SetProp => {
+                    let idx = self.read_u16()?;
+                    let key = self.constant_string(idx)?;
+                    let value = self.pop()?;
+                    match self.top()? {
+                        VmValue::Widget(widget) => {
+                            widget.borrow_mut().props.insert(key, value);
+                        }
+                        VmValue::Value(Value::Map(map)) => {
+                            let value = value.into_value("SET_PROP")?;
+                            map.borrow_mut()
+                                .entries
+                                .insert(crate::runtime::MapKey::Str(key), value);
+                        }
+                        other => {
+                            return Err(self.error(format!(
+                                "Cannot set property `{key}` on {}",
+                                other.to_display()
+                            )))
+                        }
+                    }
+                }
+                Opcode::Concat => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.stack.push(VmValue::Value(Value::String(format!(
+                        "{}{}",
+                        a.to_display(),
+                        b.to_display()
+                    ))));
+                }
+                Opcode::StateCreate => {
+                    let initial = self.pop()?;
+                    self.states.push(initial);
+                    self.stack.push(VmValue::State(self.states.len() - 1));
+                }
+                Opcode::StateGet => match self.pop()? {
+                    VmValue::State(id) => {
+                        let value = self.states.get(id).cloned().unwrap_or(VmValue::NULL);
+                        self.stack.push(value);
+                    }
+                    _ => return Err(self.error("STATE_GET expects a state")),
+                },
+                Opcode::StateSet => {
+                    let value = self.pop()?;
+                    match self.pop()? {
+                        VmValue::State(id) if id < self.states.len() => self.states[id] = value,
+                        _ => return Err(self.error("STATE_SET expects a state")),
+                    }
+                }
+            }
+        }
+    }
+
+    fn jump(&mut self, offset: i16) -> RuntimeResult<()> {
+        let frame = self.frames.last_mut().expect("active frame");
+        let target = frame.ip as isize + offset as isize;
+        if target < 0 {
+            return Err(RuntimeError::new("Jump target before start of function"));
+        }
+        frame.ip = target as usize;
+        Ok(())
+    }
+
+    fn push_bool(&mut self, value: bool) {
+        self.stack.push(VmValue::Value(Value::Bool(value)));
+    }
+
+    fn top_widget(&self, context: &str) -> RuntimeResult<Rc<RefCell<Widget>>> {
+        match self.top()? {
+            VmValue::Widget(widget) => Ok(widget.clone()),
+            _ => Err(self.error(format!("{context} expects a widget"))),
+        }
+    }
+
+    fn get_prop(&self, object: &VmValue, key: &str) -> RuntimeResult<VmValue> {
+        match object {
+            VmValue::Widget(widget) => Ok(widget
+                .borrow()
+                .props
+                .get(key)
+                .cloned()
+                .unwrap_or(VmValue::NULL)),
+            VmValue::Value(Value::Module(module)) => module
+                .fields
+                .get(key)
+                .cloned()
+                .map(VmValue::Value)
+                .ok_or_else(|| {
+                    self.error(format!("Module `{}` has no member `{key}`", module.name))
+                }),
+            VmValue::Value(Value::Map(map)) => Ok(map
+                .borrow()
+                .entries
+                .get(&crate::runtime::MapKey::Str(key.to_string()))
+                .cloned()
+                .map(VmValue::Value)
+                .unwrap_or(VmValue::NULL)),
+            VmValue::Value(Value::Struct(instance)) => instance
+                .fields
+                .get(key)
+                .cloned()
+                .map(VmValue::Value)
+                .ok_or_else(|| self.error(format!("Unknown field `{key}`"))),
+            other => Err(self.error(format!(
+                "Cannot read property `{key}` of {}",
+                other.to_display()
+            ))),
+        }
+    }
+}
+
+fn find_handler(widget: &Rc<RefCell<Widget>>, id: &str, event_type: &str) -> Option<VmValue> {
+    let widget = widget.borrow();
+    if widget.id == id {
+        return widget.handlers.get(event_type).cloned();
+    }
+    widget
+        .children
+        .iter()
+        .find_map(|child| find_handler(child, id, event_type))
+}
+
+fn constant_to_value(constant: &Constant) -> Value {
+    match constant {
+        Constant::Utf8(s) => Value::String(s.clone()),
+        Constant::Int64(i) => Value::Int(*i as i128),
+        Constant::Float64(f) => Value::Float(*f),
+        Constant::Bool(b) => Value::Bool(*b),
+        Constant::Null => Value::Null,
+    }
+}
+
+fn as_number(value: &VmValue) -> Option<f64> {
+    match value {
+        VmValue::Value(Value::Int(i)) => Some(*i as f64),
+        VmValue::Value(Value::Float(f)) => Some(*f),
+        _ => None,
+    }
+}
+
+fn arithmetic(op: Opcode, a: &VmValue, b: &VmValue) -> Result<Value, String> {
+    if let (VmValue::Value(Value::Int(x)), VmValue::Value(Value::Int(y))) = (a, b) {
+        let (x, y) = (*x, *y);
+        if matches!(op, Opcode::Div | Opcode::Mod) && y == 0 {
+            return Err("Division by zero".to_string());
+        }
+        let result = match op {
+            Opcode::Add => x.checked_add(y),
+            Opcode::Sub => x.checked_sub(y),
+            Opcode::Mul => x.checked_mul(y),
+            Opcode::Div => x.checked_div(y),
+            _ => x.checked_rem(y),
+        };
+        return result
+            .map(Value::Int)
+            .ok_or_else(|| "Integer overflow".to_string());
+    }
+    match (as_number(a), as_number(b)) {
+        (Some(x), Some(y)) => Ok(Value::Float(match op {
+            Opcode::Add => x + y,
+            Opcode::Sub => x - y,
+            Opcode::Mul => x * y,
+            Opcode::Div => x / y,
+            _ => x % y,
+        })),
+        _ => Err(format!(
+            "Cannot perform binary op on {} and {}",
+            a.to_display(),
+            b.to_display()
+        )),
+    }
+}
+
+fn values_equal(a: &VmValue, b: &VmValue) -> bool {
+    match (a, b) {
+        (VmValue::Value(x), VmValue::Value(y)) => match (x, y) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(x), Value::Bool(y)) => x == y,
+            (Value::Int(x), Value::Int(y)) => x == y,
+            (Value::String(x), Value::String(y)) => x == y,
+            (Value::Char(x), Value::Char(y)) => x == y,
+            (Value::Vec(x), Value::Vec(y)) => Rc::ptr_eq(x, y),
+            (Value::Map(x), Value::Map(y)) => Rc::ptr_eq(x, y),
+            _ => match (as_number(a), as_number(b)) {
+                (Some(x), Some(y)) => x == y,
+                _ => false,
+            },
+        },
+        (VmValue::Closure(x), VmValue::Closure(y)) => Rc::ptr_eq(x, y),
+        (VmValue::Widget(x), VmValue::Widget(y)) => Rc::ptr_eq(x, y),
+        (VmValue::State(x), VmValue::State(y)) => x == y,
+        _ => false,
+    }
+}
+
+fn compare(a: &VmValue, b: &VmValue) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (VmValue::Value(Value::Int(x)), VmValue::Value(Value::Int(y))) => Some(x.cmp(y)),
+        (VmValue::Value(Value::String(x)), VmValue::Value(Value::String(y))) => Some(x.cmp(y)),
+        _ => as_number(a)?.partial_cmp(&as_number(b)?),
+    }
+}
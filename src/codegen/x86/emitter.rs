@@ -28,7 +28,10 @@ struct JumpPatch {
 #[derive(Debug, Clone, Copy)]
 enum JumpKind {
     Jmp,
+    Call,
     Je,
+    Jne,
+    Jns,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -53,12 +56,22 @@ pub fn emit_x86(lowered: &LoweredModule) -> Result<MachineCode> {
             ctx.emit_terminator(block.id, &block.term)?;
         }
     }
+    let true_sid = lowered.strings.len() as u32;
+    ctx.emit_runtime(true_sid, true_sid + 1);
     ctx.patch_jumps()?;
 
+    // Strings are NUL-terminated so string values can be printed without a
+    // separate length.
     let strings = lowered
         .strings
         .iter()
-        .map(|s| s.clone().into_bytes())
+        .map(String::as_str)
+        .chain(["true", "false"])
+        .map(|s| {
+            let mut bytes = s.as_bytes().to_vec();
+            bytes.push(0);
+            bytes
+        })
         .collect();
 
     Ok(MachineCode {
@@ -68,6 +81,11 @@ pub fn emit_x86(lowered: &LoweredModule) -> Result<MachineCode> {
     })
 }
 
+const WRITE_LABEL: &str = "__write";
+const PRINT_STR_LABEL: &str = "__print_str";
+const PRINT_INT_LABEL: &str = "__print_int";
+const PRINT_BOOL_LABEL: &str = "__print_bool";
+
 fn build_phi_map(blocks: &[LoweredBlock]) -> HashMap<(u32, u32), Vec<(u32, u32)>> {
     let mut map: HashMap<(u32, u32), Vec<(u32, u32)>> = HashMap::new();
     for block in blocks {
@@ -154,6 +172,26 @@ impl CodegenCtx {
                     self.consume(*a);
                     self.consume(*b);
                 }
+                IrInstr::Mul { dst, a, b, .. } => {
+                    let dst_reg = self.ensure_reg(*dst)?;
+                    let lhs = self.ensure_reg(*a)?;
+                    let rhs = self.ensure_reg(*b)?;
+                    if dst_reg != lhs {
+                        emit_mov_reg_reg(&mut self.code, dst_reg, lhs);
+                    }
+                    emit_imul_reg_reg(&mut self.code, dst_reg, rhs);
+                    self.consume(*a);
+                    self.consume(*b);
+                }
+                IrInstr::Neg { dst, val, .. } => {
+                    let dst_reg = self.ensure_reg(*dst)?;
+                    let src_reg = self.ensure_reg(*val)?;
+                    if dst_reg != src_reg {
+                        emit_mov_reg_reg(&mut self.code, dst_reg, src_reg);
+                    }
+                    emit_neg_reg(&mut self.code, dst_reg);
+                    self.consume(*val);
+                }
                 IrInstr::CmpEq { dst, a, b } => {
                     self.emit_cmp_set(*dst, *a, *b, CmpKind::Eq)?;
                 }
@@ -211,11 +249,30 @@ impl CodegenCtx {
                         .get(*sid as usize)
                         .map(|s| s.len() as u32)
                         .unwrap_or(0);
-                    emit_mov_eax(&mut self.code, 4); // sys_write
-                    emit_mov_ebx(&mut self.code, 1); // fd=stdout
+                    emit_push_reg(&mut self.code, Reg::ECX);
+                    emit_push_reg(&mut self.code, Reg::EDX);
                     emit_mov_ecx_placeholder(&mut self.code, *sid, &mut self.string_patches);
                     emit_mov_edx(&mut self.code, len);
-                    emit_int80(&mut self.code);
+                    self.emit_call(WRITE_LABEL);
+                    emit_pop_reg(&mut self.code, Reg::EDX);
+                    emit_pop_reg(&mut self.code, Reg::ECX);
+                }
+                IrInstr::PrintValue { value, ty } => {
+                    let reg = self.ensure_reg(*value)?;
+                    match ty {
+                        crate::ir::IrType::Str => self.emit_runtime_call(PRINT_STR_LABEL, reg),
+                        crate::ir::IrType::Bool => self.emit_runtime_call(PRINT_BOOL_LABEL, reg),
+                        crate::ir::IrType::I8
+                        | crate::ir::IrType::I16
+                        | crate::ir::IrType::I32
+                        | crate::ir::IrType::I64
+                        | crate::ir::IrType::U8
+                        | crate::ir::IrType::U16
+                        | crate::ir::IrType::U32
+                        | crate::ir::IrType::U64 => self.emit_runtime_call(PRINT_INT_LABEL, reg),
+                        _ => {}
+                    }
+                    self.consume(*value);
                 }
                 IrInstr::Phi { dst, .. } => {
                     self.ensure_reg(*dst)?;
@@ -270,6 +327,115 @@ impl CodegenCtx {
         Ok(())
     }
 
+    /// Calls one of the embedded print routines with its operand in `eax`.
+    /// The routines preserve every other register, so only `eax` is saved.
+    fn emit_runtime_call(&mut self, label: &str, reg: Reg) {
+        emit_push_reg(&mut self.code, Reg::EAX);
+        if reg != Reg::EAX {
+            emit_mov_reg_reg(&mut self.code, Reg::EAX, reg);
+        }
+        self.emit_call(label);
+        emit_pop_reg(&mut self.code, Reg::EAX);
+    }
+
+    /// Output routines linked into every executable. They take their operand
+    /// in registers and preserve all registers they clobber.
+    fn emit_runtime(&mut self, true_sid: u32, false_sid: u32) {
+        // write(1, ecx, edx)
+        self.emit_label(WRITE_LABEL);
+        emit_push_reg(&mut self.code, Reg::EAX);
+        emit_push_reg(&mut self.code, Reg::EBX);
+        emit_mov_eax(&mut self.code, 4); // sys_write
+        emit_mov_ebx(&mut self.code, 1); // fd=stdout
+        emit_int80(&mut self.code);
+        emit_pop_reg(&mut self.code, Reg::EBX);
+        emit_pop_reg(&mut self.code, Reg::EAX);
+        self.code.push(0xC3);
+
+        // Prints the NUL-terminated string at eax.
+        self.emit_label(PRINT_STR_LABEL);
+        emit_push_reg(&mut self.code, Reg::ECX);
+        emit_push_reg(&mut self.code, Reg::EDX);
+        emit_mov_reg_reg(&mut self.code, Reg::ECX, Reg::EAX);
+        self.code.extend_from_slice(&[0x31, 0xD2]); // xor edx, edx
+        self.emit_label("__print_str_scan");
+        self.code.extend_from_slice(&[0x80, 0x3C, 0x11, 0x00]); // cmp byte [ecx+edx], 0
+        self.emit_conditional_jump(JumpKind::Je, "__print_str_done");
+        self.code.push(0x42); // inc edx
+        self.emit_jmp("__print_str_scan");
+        self.emit_label("__print_str_done");
+        self.emit_call(WRITE_LABEL);
+        emit_pop_reg(&mut self.code, Reg::EDX);
+        emit_pop_reg(&mut self.code, Reg::ECX);
+        self.code.push(0xC3);
+
+        // Prints eax as a signed decimal, building the digits backwards in a
+        // 16-byte stack buffer.
+        self.emit_label(PRINT_INT_LABEL);
+        for reg in [Reg::EAX, Reg::ECX, Reg::EDX, Reg::ESI, Reg::EDI] {
+            emit_push_reg(&mut self.code, reg);
+        }
+        self.code.extend_from_slice(&[0x83, 0xEC, 0x10]); // sub esp, 16
+        emit_mov_reg_reg(&mut self.code, Reg::EDI, Reg::EAX);
+        self.code.extend_from_slice(&[0x8D, 0x74, 0x24, 0x10]); // lea esi, [esp+16]
+        emit_test_reg(&mut self.code, Reg::EAX);
+        self.emit_conditional_jump(JumpKind::Jns, "__print_int_digits");
+        self.code.extend_from_slice(&[0xF7, 0xD8]); // neg eax
+        self.emit_label("__print_int_digits");
+        emit_mov_reg_imm(&mut self.code, Reg::ECX, 10);
+        self.emit_label("__print_int_loop");
+        self.code.extend_from_slice(&[0x31, 0xD2]); // xor edx, edx
+        self.code.extend_from_slice(&[0xF7, 0xF1]); // div ecx
+        self.code.extend_from_slice(&[0x80, 0xC2, b'0']); // add dl, '0'
+        self.code.push(0x4E); // dec esi
+        self.code.extend_from_slice(&[0x88, 0x16]); // mov [esi], dl
+        emit_test_reg(&mut self.code, Reg::EAX);
+        self.emit_conditional_jump(JumpKind::Jne, "__print_int_loop");
+        emit_test_reg(&mut self.code, Reg::EDI);
+        self.emit_conditional_jump(JumpKind::Jns, "__print_int_write");
+        self.code.push(0x4E); // dec esi
+        self.code.extend_from_slice(&[0xC6, 0x06, b'-']); // mov byte [esi], '-'
+        self.emit_label("__print_int_write");
+        emit_mov_reg_reg(&mut self.code, Reg::ECX, Reg::ESI);
+        self.code.extend_from_slice(&[0x8D, 0x54, 0x24, 0x10]); // lea edx, [esp+16]
+        emit_sub_reg_reg(&mut self.code, Reg::EDX, Reg::ESI);
+        self.emit_call(WRITE_LABEL);
+        self.code.extend_from_slice(&[0x83, 0xC4, 0x10]); // add esp, 16
+        for reg in [Reg::EDI, Reg::ESI, Reg::EDX, Reg::ECX, Reg::EAX] {
+            emit_pop_reg(&mut self.code, reg);
+        }
+        self.code.push(0xC3);
+
+        // Prints `true` or `false` for eax, like the interpreter does.
+        self.emit_label(PRINT_BOOL_LABEL);
+        emit_push_reg(&mut self.code, Reg::ECX);
+        emit_push_reg(&mut self.code, Reg::EDX);
+        emit_test_reg(&mut self.code, Reg::EAX);
+        self.emit_conditional_jump(JumpKind::Je, "__print_bool_false");
+        emit_mov_ecx_placeholder(&mut self.code, true_sid, &mut self.string_patches);
+        emit_mov_edx(&mut self.code, 4);
+        self.emit_jmp("__print_bool_write");
+        self.emit_label("__print_bool_false");
+        emit_mov_ecx_placeholder(&mut self.code, false_sid, &mut self.string_patches);
+        emit_mov_edx(&mut self.code, 5);
+        self.emit_label("__print_bool_write");
+        self.emit_call(WRITE_LABEL);
+        emit_pop_reg(&mut self.code, Reg::EDX);
+        emit_pop_reg(&mut self.code, Reg::ECX);
+        self.code.push(0xC3);
+    }
+
+    fn emit_call(&mut self, label: &str) {
+        self.code.push(0xE8);
+        let offset = self.code.len();
+        self.code.extend_from_slice(&0i32.to_le_bytes());
+        self.jumps.push(JumpPatch {
+            offset,
+            label: label.to_string(),
+            kind: JumpKind::Call,
+        });
+    }
+
     fn emit_exit(&mut self) {
         emit_mov_eax(&mut self.code, 1); // sys_exit
         emit_mov_ebx(&mut self.code, 0);
@@ -297,7 +463,15 @@ impl CodegenCtx {
                 self.code.push(0x0F);
                 self.code.push(0x84);
             }
-            JumpKind::Jmp => unreachable!(),
+            JumpKind::Jne => {
+                self.code.push(0x0F);
+                self.code.push(0x85);
+            }
+            JumpKind::Jns => {
+                self.code.push(0x0F);
+                self.code.push(0x89);
+            }
+            JumpKind::Jmp | JumpKind::Call => unreachable!(),
         }
         let offset = self.code.len();
         self.code.extend_from_slice(&0i32.to_le_bytes());
@@ -358,15 +532,19 @@ impl CodegenCtx {
         let lhs = self.ensure_reg(a)?;
         let rhs = self.ensure_reg(b)?;
         emit_cmp_reg_reg(&mut self.code, lhs, rhs);
-        match kind {
-            CmpKind::Eq => emit_sete_reg(&mut self.code, dst_reg),
-            CmpKind::Ne => emit_setne_reg(&mut self.code, dst_reg),
-            CmpKind::Lt => emit_setl_reg(&mut self.code, dst_reg),
-            CmpKind::Le => emit_setle_reg(&mut self.code, dst_reg),
-            CmpKind::Gt => emit_setg_reg(&mut self.code, dst_reg),
-            CmpKind::Ge => emit_setge_reg(&mut self.code, dst_reg),
-        }
-        emit_movzx_reg8(&mut self.code, dst_reg);
+        // ESI/EDI have no byte form in 32-bit mode, so materialise the flag
+        // with a short branch instead of `setcc`.
+        emit_mov_reg_imm(&mut self.code, dst_reg, 0);
+        let skip = match kind {
+            CmpKind::Eq => 0x75,
+            CmpKind::Ne => 0x74,
+            CmpKind::Lt => 0x7D,
+            CmpKind::Le => 0x7F,
+            CmpKind::Gt => 0x7E,
+            CmpKind::Ge => 0x7C,
+        };
+        self.code.extend_from_slice(&[skip, 0x05]);
+        emit_mov_reg_imm(&mut self.code, dst_reg, 1);
         self.consume(a);
         self.consume(b);
         Ok(())
@@ -442,6 +620,16 @@ fn emit_sub_reg_reg(code: &mut Vec<u8>, dst: Reg, src: Reg) {
     code.push(modrm_byte(src, dst));
 }
 
+fn emit_imul_reg_reg(code: &mut Vec<u8>, dst: Reg, src: Reg) {
+    code.extend_from_slice(&[0x0F, 0xAF]);
+    code.push(modrm_byte(dst, src));
+}
+
+fn emit_neg_reg(code: &mut Vec<u8>, reg: Reg) {
+    code.push(0xF7);
+    code.push(0xD8 | reg.low_bits());
+}
+
 fn emit_cmp_reg_reg(code: &mut Vec<u8>, lhs: Reg, rhs: Reg) {
     code.push(0x39);
     code.push(modrm_byte(rhs, lhs));
@@ -452,34 +640,6 @@ fn emit_test_reg(code: &mut Vec<u8>, reg: Reg) {
     code.push(modrm_byte(reg, reg));
 }
 
-fn emit_sete_reg(code: &mut Vec<u8>, reg: Reg) {
-    code.extend_from_slice(&[0x0F, 0x94, 0xC0 | reg.low_bits()]);
-}
-
-fn emit_setne_reg(code: &mut Vec<u8>, reg: Reg) {
-    code.extend_from_slice(&[0x0F, 0x95, 0xC0 | reg.low_bits()]);
-}
-
-fn emit_setl_reg(code: &mut Vec<u8>, reg: Reg) {
-    code.extend_from_slice(&[0x0F, 0x9C, 0xC0 | reg.low_bits()]);
-}
-
-fn emit_setle_reg(code: &mut Vec<u8>, reg: Reg) {
-    code.extend_from_slice(&[0x0F, 0x9E, 0xC0 | reg.low_bits()]);
-}
-
-fn emit_setg_reg(code: &mut Vec<u8>, reg: Reg) {
-    code.extend_from_slice(&[0x0F, 0x9F, 0xC0 | reg.low_bits()]);
-}
-
-fn emit_setge_reg(code: &mut Vec<u8>, reg: Reg) {
-    code.extend_from_slice(&[0x0F, 0x9D, 0xC0 | reg.low_bits()]);
-}
-
-fn emit_movzx_reg8(code: &mut Vec<u8>, reg: Reg) {
-    code.extend_from_slice(&[0x0F, 0xB6, 0xC0 | reg.low_bits()]);
-}
-
 fn emit_mov_eax(code: &mut Vec<u8>, imm: u32) {
     code.push(0xB8);
     code.extend_from_slice(&imm.to_le_bytes());
@@ -516,3 +676,11 @@ fn emit_int80(code: &mut Vec<u8>) {
     code.push(0xCD);
     code.push(0x80);
 }
+
+fn emit_push_reg(code: &mut Vec<u8>, reg: Reg) {
+    code.push(0x50 + reg.low_bits());
+}
+
+fn emit_pop_reg(code: &mut Vec<u8>, reg: Reg) {
+    code.push(0x58 + reg.low_bits());
+}
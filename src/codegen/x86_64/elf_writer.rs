@@ -4,16 +4,23 @@ use std::path::Path;
 
 use anyhow::{anyhow, Context, Result};
 
-use super::emitter::{MachineCode, Patch};
+use super::emitter::{DataSlot, MachineCode, Patch};
 
 pub const ELF_HEADER_SIZE: usize = 64;
 pub const PROGRAM_HEADER_SIZE: usize = 56;
 pub const LOAD_ADDR: u64 = 0x4000_0000;
+const PAGE_SIZE: u64 = 0x1000;
 
+/// Writes a static executable: one read/execute segment with the headers,
+/// code and strings, plus a read/write segment holding the globals when the
+/// program has any.
 pub fn write_elf(machine: &MachineCode, path: &Path) -> Result<()> {
     let mut file = File::create(path)
         .with_context(|| format!("failed to create executable {}", path.display()))?;
 
+    let phnum = if machine.data.is_empty() { 1 } else { 2 };
+    let headers_size = ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE * phnum;
+
     let mut text = machine.code.clone();
     let mut string_offsets = Vec::new();
     for data in &machine.strings {
@@ -22,13 +29,31 @@ pub fn write_elf(machine: &MachineCode, path: &Path) -> Result<()> {
         text.extend_from_slice(data);
     }
 
-    let text_base = (ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE) as u64;
+    let text_base = headers_size as u64;
     patch_strings(&mut text, text_base, &string_offsets, &machine.patches)?;
 
+    let text_size = (headers_size + text.len()) as u64;
+    // The data segment starts on the next page so it can be mapped writable.
+    let data_offset = text_size.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+    let data_addr = LOAD_ADDR + data_offset;
+    patch_globals(&mut text, data_addr, machine)?;
+    let mut data = Vec::with_capacity(machine.data.len() * 8);
+    for slot in &machine.data {
+        let value = match slot {
+            DataSlot::Int(value) => *value as u64,
+            DataSlot::Str(sid) => {
+                let offset = string_offsets
+                    .get(*sid as usize)
+                    .ok_or_else(|| anyhow!("invalid string id {}", sid))?;
+                LOAD_ADDR + text_base + *offset as u64
+            }
+        };
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+
     let entry = LOAD_ADDR + text_base;
-    let file_size = (ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE + text.len()) as u64;
 
-    let mut elf = Vec::with_capacity(file_size as usize);
+    let mut elf = Vec::with_capacity(text_size as usize);
 
     elf.extend_from_slice(b"\x7FELF");
     elf.push(2);
@@ -46,30 +71,43 @@ pub fn write_elf(machine: &MachineCode, path: &Path) -> Result<()> {
     elf.extend_from_slice(&u32_to_le(0));
     elf.extend_from_slice(&u16_to_le(ELF_HEADER_SIZE as u16));
     elf.extend_from_slice(&u16_to_le(PROGRAM_HEADER_SIZE as u16));
-    elf.extend_from_slice(&u16_to_le(1));
+    elf.extend_from_slice(&u16_to_le(phnum as u16));
     elf.extend_from_slice(&u16_to_le(0));
     elf.extend_from_slice(&u16_to_le(0));
     elf.extend_from_slice(&u16_to_le(0));
 
-    elf.extend_from_slice(&u32_to_le(1));
-    elf.extend_from_slice(&u32_to_le(5));
-    elf.extend_from_slice(&u64_to_le(0));
-    elf.extend_from_slice(&u64_to_le(LOAD_ADDR));
-    elf.extend_from_slice(&u64_to_le(LOAD_ADDR));
-    elf.extend_from_slice(&u64_to_le(file_size));
-    elf.extend_from_slice(&u64_to_le(file_size));
-    elf.extend_from_slice(&u64_to_le(0x1000));
+    push_load_segment(&mut elf, 5, 0, text_size);
+    if !data.is_empty() {
+        push_load_segment(&mut elf, 6, data_offset, data.len() as u64);
+    }
 
-    while elf.len() < ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE {
+    while elf.len() < headers_size {
         elf.push(0);
     }
     elf.extend_from_slice(&text);
+    if !data.is_empty() {
+        elf.resize(data_offset as usize, 0);
+        elf.extend_from_slice(&data);
+    }
 
     file.write_all(&elf)?;
     set_executable_permissions(path)?;
     Ok(())
 }
 
+/// A `PT_LOAD` program header mapping `size` bytes at file `offset` to
+/// `LOAD_ADDR + offset`.
+fn push_load_segment(elf: &mut Vec<u8>, flags: u32, offset: u64, size: u64) {
+    elf.extend_from_slice(&u32_to_le(1));
+    elf.extend_from_slice(&u32_to_le(flags));
+    elf.extend_from_slice(&u64_to_le(offset));
+    elf.extend_from_slice(&u64_to_le(LOAD_ADDR + offset));
+    elf.extend_from_slice(&u64_to_le(LOAD_ADDR + offset));
+    elf.extend_from_slice(&u64_to_le(size));
+    elf.extend_from_slice(&u64_to_le(size));
+    elf.extend_from_slice(&u64_to_le(PAGE_SIZE));
+}
+
 fn u16_to_le(value: u16) -> [u8; 2] {
     value.to_le_bytes()
 }
@@ -116,3 +154,17 @@ fn patch_strings(
     }
     Ok(())
 }
+
+fn patch_globals(text: &mut [u8], data_addr: u64, machine: &MachineCode) -> Result<()> {
+    for patch in &machine.global_patches {
+        if patch.global as usize >= machine.data.len() {
+            return Err(anyhow!("invalid global id {}", patch.global));
+        }
+        let addr = data_addr + 8 * patch.global as u64;
+        let slot = text
+            .get_mut(patch.offset..patch.offset + 8)
+            .ok_or_else(|| anyhow!("invalid patch offset"))?;
+        slot.copy_from_slice(&addr.to_le_bytes());
+    }
+    Ok(())
+}
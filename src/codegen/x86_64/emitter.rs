@@ -2,14 +2,18 @@ use std::collections::HashMap;
 
 use anyhow::{anyhow, Result};
 
-use crate::ir::{IrInstr, IrTerm};
+use crate::ir::{GlobalInit, IrInstr, IrTerm};
 
-use super::lower::{LoweredBlock, LoweredModule};
+use super::lower::{LoweredBlock, LoweredFunction, LoweredModule};
+use super::regalloc::{self, Allocation, Location};
 
 pub struct MachineCode {
     pub code: Vec<u8>,
     pub patches: Vec<Patch>,
     pub strings: Vec<Vec<u8>>,
+    /// Initial contents of the writable data segment, one slot per global.
+    pub data: Vec<DataSlot>,
+    pub global_patches: Vec<GlobalPatch>,
 }
 
 #[derive(Debug, Clone)]
@@ -18,6 +22,21 @@ pub struct Patch {
     pub string_id: u32,
 }
 
+/// An imm64 in the code that must hold the address of global `global`.
+#[derive(Debug, Clone)]
+pub struct GlobalPatch {
+    pub offset: usize,
+    pub global: u32,
+}
+
+/// Initial value of an 8-byte global slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataSlot {
+    Int(i64),
+    /// The address of a string.
+    Str(u32),
+}
+
 #[derive(Debug, Clone)]
 struct JumpPatch {
     offset: usize,
@@ -28,9 +47,11 @@ struct JumpPatch {
 #[derive(Debug, Clone, Copy)]
 enum JumpKind {
     Jmp,
+    Call,
     Je,
     Jne,
     Js,
+    Jns,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -44,29 +65,55 @@ enum CmpKind {
 }
 
 pub fn emit_x86_64(lowered: &LoweredModule) -> Result<MachineCode> {
-    let phi_map = build_phi_map(&lowered.blocks);
-    let mut ctx = CodegenCtx::new(lowered.value_count, lowered.uses.clone(), phi_map);
-    if lowered.blocks.is_empty() {
-        ctx.emit_exit();
-    } else {
-        for block in &lowered.blocks {
-            ctx.emit_label(&format!("block{}", block.id));
-            ctx.emit_block_instrs(block, &lowered.strings)?;
-            ctx.emit_terminator(block.id, &block.term)?;
-        }
+    let mut ctx = CodegenCtx::new();
+    // `_start`: call `apex` under the System V convention, then exit(0).
+    if let Some(entry) = lowered.entry {
+        ctx.emit_call(&function_label(entry));
     }
+    ctx.emit_exit();
+    for (index, func) in lowered.functions.iter().enumerate() {
+        ctx.emit_function(index, func, &lowered.strings)?;
+    }
+    let true_sid = lowered.strings.len() as u32;
+    ctx.emit_runtime(true_sid, true_sid + 1);
     ctx.patch_jumps()?;
 
+    // Strings are NUL-terminated so string values can be printed without a
+    // separate length.
     let strings = lowered
         .strings
         .iter()
-        .map(|s| s.clone().into_bytes())
+        .map(String::as_str)
+        .chain(["true", "false"])
+        .map(|s| {
+            let mut bytes = s.as_bytes().to_vec();
+            bytes.push(0);
+            bytes
+        })
+        .collect();
+
+    let data = lowered
+        .globals
+        .iter()
+        .map(|global| match global.init {
+            GlobalInit::Const { value, .. } => DataSlot::Int(value as i64),
+            GlobalInit::FromString(sid) => DataSlot::Str(sid),
+            GlobalInit::Zeroed => DataSlot::Int(0),
+            GlobalInit::Bytes(ref bytes) => {
+                let mut slot = [0u8; 8];
+                let len = bytes.len().min(8);
+                slot[..len].copy_from_slice(&bytes[..len]);
+                DataSlot::Int(i64::from_le_bytes(slot))
+            }
+        })
         .collect();
 
     Ok(MachineCode {
         code: ctx.code,
         patches: ctx.string_patches,
         strings,
+        data,
+        global_patches: ctx.global_patches,
     })
 }
 
@@ -86,92 +133,168 @@ fn build_phi_map(blocks: &[LoweredBlock]) -> HashMap<(u32, u32), Vec<(u32, u32)>
     map
 }
 
+const WRITE_LABEL: &str = "__write";
+const PRINT_STR_LABEL: &str = "__print_str";
+const PRINT_INT_LABEL: &str = "__print_int";
+const PRINT_BOOL_LABEL: &str = "__print_bool";
+
+fn function_label(index: usize) -> String {
+    format!("fn{index}")
+}
+
+/// Integer argument registers of the System V AMD64 calling convention.
+pub(super) const ARG_REGS: [Reg; 6] = [Reg::RDI, Reg::RSI, Reg::RDX, Reg::RCX, Reg::R8, Reg::R9];
+
+/// Scratch registers the allocator never hands out: spilled operands are
+/// loaded into them and parallel moves use them to break cycles.
+const SCRATCH: Reg = Reg::R10;
+const SCRATCH2: Reg = Reg::R11;
+
 struct CodegenCtx {
     code: Vec<u8>,
-    value_regs: Vec<Option<Reg>>,
-    free_regs: Vec<Reg>,
-    uses: Vec<u32>,
+    func_index: usize,
+    alloc: Option<Allocation>,
     phi_map: HashMap<(u32, u32), Vec<(u32, u32)>>,
     string_patches: Vec<Patch>,
+    global_patches: Vec<GlobalPatch>,
     labels: HashMap<String, usize>,
     jumps: Vec<JumpPatch>,
     label_counter: usize,
 }
 
 impl CodegenCtx {
-    fn new(
-        value_count: usize,
-        uses: Vec<u32>,
-        phi_map: HashMap<(u32, u32), Vec<(u32, u32)>>,
-    ) -> Self {
+    fn new() -> Self {
         Self {
             code: Vec::new(),
-            value_regs: vec![None; value_count.max(1)],
-            free_regs: vec![
-                Reg::RAX,
-                Reg::RBX,
-                Reg::RCX,
-                Reg::RDX,
-                Reg::RSI,
-                Reg::RDI,
-                Reg::R8,
-                Reg::R9,
-                Reg::R10,
-                Reg::R11,
-                Reg::R12,
-                Reg::R13,
-                Reg::R14,
-                Reg::R15,
-            ],
-            uses,
-            phi_map,
+            func_index: 0,
+            alloc: None,
+            phi_map: HashMap::new(),
             string_patches: Vec::new(),
+            global_patches: Vec::new(),
             labels: HashMap::new(),
             jumps: Vec::new(),
             label_counter: 0,
         }
     }
 
-    fn emit_block_instrs(&mut self, block: &LoweredBlock, strings: &[String]) -> Result<()> {
-        for instr in &block.instrs {
+    fn begin_function(&mut self, index: usize, func: &LoweredFunction) {
+        self.func_index = index;
+        self.alloc = Some(regalloc::allocate(func));
+        self.phi_map = build_phi_map(&func.blocks);
+    }
+
+    fn emit_function(
+        &mut self,
+        index: usize,
+        func: &LoweredFunction,
+        strings: &[String],
+    ) -> Result<()> {
+        self.begin_function(index, func);
+        self.emit_label(&function_label(index));
+        self.emit_prologue();
+        self.bind_params(func.params.len())?;
+        for (block_index, block) in func.blocks.iter().enumerate() {
+            let label = self.block_label(block.id);
+            self.emit_label(&label);
+            self.emit_block_instrs(block_index, block, strings)?;
+            self.emit_terminator(block.id, &block.term)?;
+        }
+        Ok(())
+    }
+
+    fn block_label(&self, block_id: u32) -> String {
+        format!("fn{}_block{}", self.func_index, block_id)
+    }
+
+    fn allocation(&self) -> &Allocation {
+        self.alloc.as_ref().expect("no function is being emitted")
+    }
+
+    /// `push rbp; mov rbp, rsp`, then the callee-saved registers the function
+    /// uses and its spill area, keeping `rsp` 16-byte aligned at nested calls.
+    fn emit_prologue(&mut self) {
+        self.code.push(0x55);
+        self.code.extend_from_slice(&[0x48, 0x89, 0xE5]);
+        let saved = self.allocation().callee_saved.clone();
+        for reg in saved {
+            emit_push_reg(&mut self.code, reg);
+        }
+        let frame = self.allocation().frame_size();
+        if frame > 0 {
+            emit_sub_rsp_imm(&mut self.code, frame);
+        }
+    }
+
+    fn emit_epilogue(&mut self) {
+        let saved = self.allocation().callee_saved.clone();
+        // lea rsp, [rbp - 8 * saved]
+        let disp = -8 * saved.len() as i8;
+        self.code.extend_from_slice(&[0x48, 0x8D, 0x65, disp as u8]);
+        for reg in saved.iter().rev() {
+            emit_pop_reg(&mut self.code, *reg);
+        }
+        self.code.push(0x5D);
+        self.code.push(0xC3);
+    }
+
+    /// Parameters are the first value ids of a function. The first six arrive
+    /// in `ARG_REGS` and are moved to their allocated locations; the rest stay
+    /// in the caller's frame, where the allocator already placed them.
+    fn bind_params(&mut self, count: usize) -> Result<()> {
+        let mut moves = Vec::new();
+        for (index, reg) in ARG_REGS.iter().enumerate().take(count) {
+            moves.push((self.location(index as u32)?, Location::Reg(*reg)));
+        }
+        self.emit_parallel_moves(moves);
+        Ok(())
+    }
+
+    fn emit_block_instrs(
+        &mut self,
+        block_index: usize,
+        block: &LoweredBlock,
+        strings: &[String],
+    ) -> Result<()> {
+        for (instr_index, instr) in block.instrs.iter().enumerate() {
             match instr {
                 IrInstr::LoadConstInt { dst, value, .. } => {
-                    let reg = self.ensure_reg(*dst)?;
+                    let reg = self.target_reg(*dst)?;
                     emit_mov_reg_imm(&mut self.code, reg, *value as i64);
+                    self.store_value(*dst, reg)?;
                 }
                 IrInstr::LoadConstStr { dst, sid } => {
-                    let reg = self.ensure_reg(*dst)?;
+                    let reg = self.target_reg(*dst)?;
                     emit_mov_reg_placeholder(&mut self.code, reg, *sid, &mut self.string_patches);
+                    self.store_value(*dst, reg)?;
                 }
                 IrInstr::LoadConstBool { dst, value } => {
-                    let reg = self.ensure_reg(*dst)?;
+                    let reg = self.target_reg(*dst)?;
                     emit_mov_reg_imm(&mut self.code, reg, if *value { 1 } else { 0 });
+                    self.store_value(*dst, reg)?;
                 }
                 IrInstr::LoadConstI32 { dst, value } => {
-                    let reg = self.ensure_reg(*dst)?;
+                    let reg = self.target_reg(*dst)?;
                     emit_mov_reg_imm(&mut self.code, reg, *value as i64);
+                    self.store_value(*dst, reg)?;
                 }
                 IrInstr::AddI32 { dst, a, b } | IrInstr::Add { dst, a, b, .. } => {
-                    let dst_reg = self.ensure_reg(*dst)?;
-                    let lhs = self.ensure_reg(*a)?;
-                    let rhs = self.ensure_reg(*b)?;
-                    if dst_reg != lhs {
-                        emit_mov_reg_reg(&mut self.code, dst_reg, lhs);
-                    }
-                    emit_add_reg_reg(&mut self.code, dst_reg, rhs);
-                    self.consume(*a);
-                    self.consume(*b);
+                    self.emit_binary(*dst, *a, *b, emit_add_reg_reg)?;
                 }
                 IrInstr::Sub { dst, a, b, .. } => {
-                    let dst_reg = self.ensure_reg(*dst)?;
-                    let lhs = self.ensure_reg(*a)?;
-                    let rhs = self.ensure_reg(*b)?;
-                    if dst_reg != lhs {
-                        emit_mov_reg_reg(&mut self.code, dst_reg, lhs);
-                    }
-                    emit_sub_reg_reg(&mut self.code, dst_reg, rhs);
-                    self.consume(*a);
-                    self.consume(*b);
+                    self.emit_binary(*dst, *a, *b, emit_sub_reg_reg)?;
+                }
+                IrInstr::Mul { dst, a, b, .. } => {
+                    self.emit_binary(*dst, *a, *b, emit_imul_reg_reg)?;
+                }
+                IrInstr::Div { dst, a, b, .. } => {
+                    self.emit_divide(*dst, *a, *b, false)?;
+                }
+                IrInstr::Rem { dst, a, b, .. } => {
+                    self.emit_divide(*dst, *a, *b, true)?;
+                }
+                IrInstr::Call { dst, func, args } => {
+                    let pos = self.allocation().instr_position(block_index, instr_index);
+                    self.emit_call_function(pos, *dst, *func, args)?;
                 }
                 IrInstr::CmpEq { dst, a, b } => {
                     self.emit_cmp_set(*dst, *a, *b, CmpKind::Eq)?;
@@ -193,58 +316,77 @@ impl CodegenCtx {
                     self.emit_cmp_set(*dst, *a, *b, kind)?;
                 }
                 IrInstr::And { dst, a, b, .. } => {
-                    let dst_reg = self.ensure_reg(*dst)?;
-                    let lhs = self.ensure_reg(*a)?;
-                    let rhs = self.ensure_reg(*b)?;
-                    if dst_reg != lhs {
-                        emit_mov_reg_reg(&mut self.code, dst_reg, lhs);
-                    }
-                    emit_and_reg_reg(&mut self.code, dst_reg, rhs);
-                    self.consume(*a);
-                    self.consume(*b);
+                    self.emit_binary(*dst, *a, *b, emit_and_reg_reg)?;
                 }
                 IrInstr::Or { dst, a, b, .. } => {
-                    let dst_reg = self.ensure_reg(*dst)?;
-                    let lhs = self.ensure_reg(*a)?;
-                    let rhs = self.ensure_reg(*b)?;
-                    if dst_reg != lhs {
-                        emit_mov_reg_reg(&mut self.code, dst_reg, lhs);
-                    }
-                    emit_or_reg_reg(&mut self.code, dst_reg, rhs);
-                    self.consume(*a);
-                    self.consume(*b);
+                    self.emit_binary(*dst, *a, *b, emit_or_reg_reg)?;
+                }
+                IrInstr::Xor { dst, a, b, .. } => {
+                    self.emit_binary(*dst, *a, *b, emit_xor_reg_reg)?;
+                }
+                IrInstr::Shl { dst, a, b, .. } => self.emit_shift(*dst, *a, *b, 4)?,
+                IrInstr::LShr { dst, a, b, .. } => self.emit_shift(*dst, *a, *b, 5)?,
+                IrInstr::AShr { dst, a, b, .. } => self.emit_shift(*dst, *a, *b, 7)?,
+                IrInstr::Neg { dst, val, .. } => {
+                    self.emit_unary(*dst, *val, emit_neg_reg)?;
+                }
+                IrInstr::Not {
+                    dst,
+                    val,
+                    ty: crate::ir::IrType::Bool,
+                } => {
+                    self.emit_unary(*dst, *val, emit_flip_bool_reg)?;
+                }
+                IrInstr::Not { dst, val, .. } => {
+                    self.emit_unary(*dst, *val, emit_not_reg)?;
+                }
+                IrInstr::LoadGlobal { dst, global, .. } => {
+                    let reg = self.target_reg(*dst)?;
+                    self.emit_global_address(reg, *global);
+                    emit_mov_reg_mem(&mut self.code, reg, reg);
+                    self.store_value(*dst, reg)?;
+                }
+                IrInstr::StoreGlobal { global, value, .. } => {
+                    let src = self.read_value(*value, SCRATCH)?;
+                    self.emit_global_address(SCRATCH2, *global);
+                    emit_mov_mem_reg(&mut self.code, SCRATCH2, src);
                 }
                 IrInstr::PrintStr { sid } => {
                     let len = strings
                         .get(*sid as usize)
                         .map(|s| s.len() as u64)
                         .unwrap_or(0);
-                    emit_mov_rax(&mut self.code, 1);
-                    emit_mov_rdi(&mut self.code, 1);
+                    emit_push_reg(&mut self.code, Reg::RSI);
+                    emit_push_reg(&mut self.code, Reg::RDX);
                     emit_mov_rsi_placeholder(&mut self.code, *sid, &mut self.string_patches);
                     emit_mov_rdx(&mut self.code, len);
-                    emit_syscall(&mut self.code);
+                    self.emit_call(WRITE_LABEL);
+                    emit_pop_reg(&mut self.code, Reg::RDX);
+                    emit_pop_reg(&mut self.code, Reg::RSI);
                 }
-                IrInstr::PrintValue { value, ty } => {
-                    let reg = self.ensure_reg(*value)?;
-                    match ty {
-                        crate::ir::IrType::Str => {
-                            self.emit_print_string(reg)?;
-                        }
-                        crate::ir::IrType::Bool
-                        | crate::ir::IrType::I32
-                        | crate::ir::IrType::I64
-                        | crate::ir::IrType::U32
-                        | crate::ir::IrType::U64 => {
-                            self.emit_print_int(reg)?;
-                        }
-                        _ => {}
+                IrInstr::PrintValue { value, ty } => match ty {
+                    crate::ir::IrType::Str => {
+                        self.emit_runtime_call(PRINT_STR_LABEL, *value)?;
                     }
-                    self.consume(*value);
-                }
-                IrInstr::Phi { dst, .. } => {
-                    self.ensure_reg(*dst)?;
-                }
+                    crate::ir::IrType::Bool => {
+                        self.emit_runtime_call(PRINT_BOOL_LABEL, *value)?;
+                    }
+                    crate::ir::IrType::I8
+                    | crate::ir::IrType::I16
+                    | crate::ir::IrType::I32
+                    | crate::ir::IrType::I64
+                    | crate::ir::IrType::U8
+                    | crate::ir::IrType::U16
+                    | crate::ir::IrType::U32
+                    | crate::ir::IrType::U64 => {
+                        self.emit_runtime_call(PRINT_INT_LABEL, *value)?;
+                    }
+                    other => {
+                        return Err(anyhow!("cannot print {:?} values", other));
+                    }
+                },
+                // Phis are resolved by moves at the end of each predecessor.
+                IrInstr::Phi { .. } => {}
                 _ => {
                     return Err(anyhow!("unsupported ir instr in backend: {:?}", instr));
                 }
@@ -255,50 +397,286 @@ impl CodegenCtx {
 
     fn emit_terminator(&mut self, block_id: u32, term: &IrTerm) -> Result<()> {
         match term {
-            IrTerm::Ret { .. } => {
-                self.emit_exit();
+            IrTerm::Ret { value } => {
+                if let Some(value) = value {
+                    let src = self.location(*value)?;
+                    self.emit_move(Location::Reg(Reg::RAX), src);
+                }
+                self.emit_epilogue();
             }
             IrTerm::Br { target } => {
                 self.emit_phi_moves(block_id, *target)?;
-                self.emit_jmp(&format!("block{}", target));
+                let label = self.block_label(*target);
+                self.emit_jmp(&label);
             }
             IrTerm::CondBr {
                 cond,
                 then_b,
                 else_b,
             } => {
-                let cond_reg = self.ensure_reg(*cond)?;
+                let cond_reg = self.read_value(*cond, SCRATCH)?;
                 emit_test_reg(&mut self.code, cond_reg);
-                let else_label = format!("{}_else_from_{}", else_b, block_id);
+                let else_label = format!("{}_else_from_{}", self.block_label(*else_b), block_id);
                 self.emit_conditional_jump(JumpKind::Je, &else_label);
                 self.emit_phi_moves(block_id, *then_b)?;
-                self.emit_jmp(&format!("block{}", then_b));
+                let then_target = self.block_label(*then_b);
+                self.emit_jmp(&then_target);
                 self.emit_label(&else_label);
                 self.emit_phi_moves(block_id, *else_b)?;
-                self.emit_jmp(&format!("block{}", else_b));
-                self.consume(*cond);
+                let else_target = self.block_label(*else_b);
+                self.emit_jmp(&else_target);
             }
-            IrTerm::SwitchInt { .. } | IrTerm::Unreachable | IrTerm::Invoke { .. } => {
+            IrTerm::Unreachable => emit_ud2(&mut self.code),
+            IrTerm::SwitchInt { .. } | IrTerm::Invoke { .. } => {
                 return Err(anyhow!("unsupported ir terminator in backend: {:?}", term));
             }
         }
         Ok(())
     }
 
+    /// `mov reg, imm64` with the address of `global`, patched by the ELF writer.
+    fn emit_global_address(&mut self, reg: Reg, global: u32) {
+        emit_mov_reg_imm(&mut self.code, reg, 0);
+        let offset = self.code.len() - 8;
+        self.global_patches.push(GlobalPatch { offset, global });
+    }
+
     fn emit_phi_moves(&mut self, from: u32, to: u32) -> Result<()> {
-        if let Some(entries) = self.phi_map.get(&(from, to)).cloned() {
-            for (dst, src) in entries {
-                let dst_reg = self.ensure_reg(dst)?;
-                let src_reg = self.ensure_reg(src)?;
-                if dst_reg != src_reg {
-                    emit_mov_reg_reg(&mut self.code, dst_reg, src_reg);
+        let Some(entries) = self.phi_map.get(&(from, to)).cloned() else {
+            return Ok(());
+        };
+        let mut moves = Vec::with_capacity(entries.len());
+        for (dst, src) in entries {
+            moves.push((self.location(dst)?, self.location(src)?));
+        }
+        self.emit_parallel_moves(moves);
+        Ok(())
+    }
+
+    /// Performs `(dst, src)` moves as if they all happened at once. A move is
+    /// emitted once no pending move still reads its destination; cycles are
+    /// broken by parking one source in a scratch register.
+    fn emit_parallel_moves(&mut self, moves: Vec<(Location, Location)>) {
+        let mut pending: Vec<(Location, Location)> =
+            moves.into_iter().filter(|(dst, src)| dst != src).collect();
+        while !pending.is_empty() {
+            let ready = pending
+                .iter()
+                .position(|(dst, _)| !pending.iter().any(|(_, src)| src == dst));
+            match ready {
+                Some(index) => {
+                    let (dst, src) = pending.remove(index);
+                    self.emit_move(dst, src);
+                }
+                None => {
+                    let parked = Location::Reg(SCRATCH2);
+                    self.emit_move(parked, pending[0].1);
+                    pending[0].1 = parked;
                 }
-                self.consume(src);
             }
         }
+    }
+
+    fn emit_move(&mut self, dst: Location, src: Location) {
+        match (dst, src) {
+            (Location::Reg(dst), Location::Reg(src)) => {
+                if dst != src {
+                    emit_mov_reg_reg(&mut self.code, dst, src);
+                }
+            }
+            (Location::Reg(dst), Location::Stack(disp)) => {
+                emit_mov_reg_rbp_disp(&mut self.code, dst, disp);
+            }
+            (Location::Stack(disp), Location::Reg(src)) => {
+                emit_mov_rbp_disp_reg(&mut self.code, disp, src);
+            }
+            (Location::Stack(dst), Location::Stack(src)) => {
+                if dst != src {
+                    emit_mov_reg_rbp_disp(&mut self.code, SCRATCH, src);
+                    emit_mov_rbp_disp_reg(&mut self.code, dst, SCRATCH);
+                }
+            }
+        }
+    }
+
+    fn location(&self, value: u32) -> Result<Location> {
+        self.allocation()
+            .location(value)
+            .ok_or_else(|| anyhow!("value %{} has no location", value))
+    }
+
+    /// Register holding `value`, loading it into `scratch` when spilled.
+    fn read_value(&mut self, value: u32, scratch: Reg) -> Result<Reg> {
+        match self.location(value)? {
+            Location::Reg(reg) => Ok(reg),
+            Location::Stack(disp) => {
+                emit_mov_reg_rbp_disp(&mut self.code, scratch, disp);
+                Ok(scratch)
+            }
+        }
+    }
+
+    /// Register to compute `value` in: its own, or `SCRATCH` when spilled, in
+    /// which case `store_value` writes it back.
+    fn target_reg(&self, value: u32) -> Result<Reg> {
+        match self.location(value)? {
+            Location::Reg(reg) => Ok(reg),
+            Location::Stack(_) => Ok(SCRATCH),
+        }
+    }
+
+    fn store_value(&mut self, value: u32, reg: Reg) -> Result<()> {
+        let dst = self.location(value)?;
+        self.emit_move(dst, Location::Reg(reg));
+        Ok(())
+    }
+
+    fn emit_push_value(&mut self, value: u32) -> Result<()> {
+        match self.location(value)? {
+            Location::Reg(reg) => emit_push_reg(&mut self.code, reg),
+            Location::Stack(disp) => emit_push_rbp_disp(&mut self.code, disp),
+        }
         Ok(())
     }
 
+    /// `dst = a op b` for two-operand instructions of the form `op dst, src`.
+    /// The allocator never gives `dst` the register of a live operand, so
+    /// copying `a` into it first cannot clobber `b`.
+    fn emit_binary(
+        &mut self,
+        dst: u32,
+        a: u32,
+        b: u32,
+        op: fn(&mut Vec<u8>, Reg, Reg),
+    ) -> Result<()> {
+        let lhs = self.read_value(a, SCRATCH)?;
+        let rhs = self.read_value(b, SCRATCH2)?;
+        let dst_reg = self.target_reg(dst)?;
+        if dst_reg != lhs {
+            emit_mov_reg_reg(&mut self.code, dst_reg, lhs);
+        }
+        op(&mut self.code, dst_reg, rhs);
+        self.store_value(dst, dst_reg)
+    }
+
+    /// `dst = a shift b`, where `ext` picks the shift in the `D3 /ext`
+    /// group. The count has to be in `cl`, so `rcx` is saved around it and
+    /// the shift itself runs in `SCRATCH`.
+    fn emit_shift(&mut self, dst: u32, a: u32, b: u32, ext: u8) -> Result<()> {
+        let lhs = self.read_value(a, SCRATCH)?;
+        if lhs != SCRATCH {
+            emit_mov_reg_reg(&mut self.code, SCRATCH, lhs);
+        }
+        let amount = self.read_value(b, SCRATCH2)?;
+        emit_push_reg(&mut self.code, Reg::RCX);
+        if amount != Reg::RCX {
+            emit_mov_reg_reg(&mut self.code, Reg::RCX, amount);
+        }
+        emit_shift_reg_cl(&mut self.code, SCRATCH, ext);
+        emit_pop_reg(&mut self.code, Reg::RCX);
+        let dst_reg = self.target_reg(dst)?;
+        if dst_reg != SCRATCH {
+            emit_mov_reg_reg(&mut self.code, dst_reg, SCRATCH);
+        }
+        self.store_value(dst, dst_reg)
+    }
+
+    /// `dst = op val` for single-operand instructions that rewrite their
+    /// register in place.
+    fn emit_unary(&mut self, dst: u32, val: u32, op: fn(&mut Vec<u8>, Reg)) -> Result<()> {
+        let src = self.read_value(val, SCRATCH)?;
+        let dst_reg = self.target_reg(dst)?;
+        if dst_reg != src {
+            emit_mov_reg_reg(&mut self.code, dst_reg, src);
+        }
+        op(&mut self.code, dst_reg);
+        self.store_value(dst, dst_reg)
+    }
+
+    /// System V call: caller-saved registers holding values live across the
+    /// call are pushed around it, the first six arguments go in `ARG_REGS`
+    /// and the rest on the stack, and the result comes back in `rax`.
+    fn emit_call_function(
+        &mut self,
+        pos: u32,
+        dst: Option<u32>,
+        func: u32,
+        args: &[u32],
+    ) -> Result<()> {
+        let saved: Vec<Reg> = self
+            .allocation()
+            .live_across(pos)
+            .into_iter()
+            .filter(|reg| reg.is_caller_saved())
+            .collect();
+        let stack_args = args.len().saturating_sub(ARG_REGS.len());
+        let padded = (saved.len() + stack_args) % 2 == 1;
+        if padded {
+            emit_sub_rsp_imm(&mut self.code, 8);
+        }
+        for reg in &saved {
+            emit_push_reg(&mut self.code, *reg);
+        }
+        for arg in args.iter().skip(ARG_REGS.len()).rev() {
+            self.emit_push_value(*arg)?;
+        }
+        // Route register arguments through the stack so sources that are
+        // themselves argument registers are read before being overwritten.
+        let in_regs = args.len().min(ARG_REGS.len());
+        for arg in args.iter().take(in_regs) {
+            self.emit_push_value(*arg)?;
+        }
+        for reg in ARG_REGS.iter().take(in_regs).rev() {
+            emit_pop_reg(&mut self.code, *reg);
+        }
+        self.emit_call(&function_label(func as usize));
+        if stack_args > 0 {
+            emit_add_rsp_imm(&mut self.code, (stack_args * 8) as i32);
+        }
+        // The result's register is not live across the call, so it is never
+        // among the restored ones.
+        if let Some(dst) = dst {
+            self.store_value(dst, Reg::RAX)?;
+        }
+        for reg in saved.iter().rev() {
+            emit_pop_reg(&mut self.code, *reg);
+        }
+        if padded {
+            emit_add_rsp_imm(&mut self.code, 8);
+        }
+        Ok(())
+    }
+
+    /// Signed division through `rdx:rax`, which are restored afterwards; the
+    /// result travels through `SCRATCH`.
+    fn emit_divide(&mut self, dst: u32, a: u32, b: u32, remainder: bool) -> Result<()> {
+        emit_push_reg(&mut self.code, Reg::RDX);
+        emit_push_reg(&mut self.code, Reg::RAX);
+        self.emit_push_value(b)?;
+        self.emit_push_value(a)?;
+        emit_pop_reg(&mut self.code, Reg::RAX);
+        // cqo; idiv qword [rsp]
+        self.code.extend_from_slice(&[0x48, 0x99]);
+        self.code.extend_from_slice(&[0x48, 0xF7, 0x3C, 0x24]);
+        emit_add_rsp_imm(&mut self.code, 8);
+        let result = if remainder { Reg::RDX } else { Reg::RAX };
+        emit_mov_reg_reg(&mut self.code, SCRATCH, result);
+        emit_pop_reg(&mut self.code, Reg::RAX);
+        emit_pop_reg(&mut self.code, Reg::RDX);
+        self.store_value(dst, SCRATCH)
+    }
+
+    fn emit_call(&mut self, label: &str) {
+        self.code.push(0xE8);
+        let offset = self.code.len();
+        self.code.extend_from_slice(&0i32.to_le_bytes());
+        self.jumps.push(JumpPatch {
+            offset,
+            label: label.to_string(),
+            kind: JumpKind::Call,
+        });
+    }
+
     fn emit_exit(&mut self) {
         emit_mov_rax(&mut self.code, 60);
         emit_xor_rdi(&mut self.code);
@@ -337,7 +715,10 @@ impl CodegenCtx {
             JumpKind::Js => {
                 self.code.extend_from_slice(&[0x0F, 0x88]);
             }
-            JumpKind::Jmp => unreachable!(),
+            JumpKind::Jns => {
+                self.code.extend_from_slice(&[0x0F, 0x89]);
+            }
+            JumpKind::Jmp | JumpKind::Call => unreachable!(),
         }
         let offset = self.code.len();
         self.code.extend_from_slice(&0i32.to_le_bytes());
@@ -362,42 +743,11 @@ impl CodegenCtx {
         Ok(())
     }
 
-    fn ensure_reg(&mut self, value: u32) -> Result<Reg> {
-        if value as usize >= self.value_regs.len() {
-            self.value_regs.resize(value as usize + 1, None);
-        }
-        if let Some(reg) = self.value_regs[value as usize] {
-            return Ok(reg);
-        }
-        let reg = self
-            .free_regs
-            .pop()
-            .ok_or_else(|| anyhow!("out of registers for value {}", value))?;
-        self.value_regs[value as usize] = Some(reg);
-        Ok(reg)
-    }
-
-    fn consume(&mut self, value: u32) {
-        let idx = value as usize;
-        if idx >= self.uses.len() {
-            return;
-        }
-        if self.uses[idx] == 0 {
-            return;
-        }
-        self.uses[idx] -= 1;
-        if self.uses[idx] == 0 {
-            if let Some(reg) = self.value_regs.get_mut(idx).and_then(|slot| slot.take()) {
-                self.free_regs.push(reg);
-            }
-        }
-    }
-
     fn emit_cmp_set(&mut self, dst: u32, a: u32, b: u32, kind: CmpKind) -> Result<()> {
-        let dst_reg = self.ensure_reg(dst)?;
-        let lhs = self.ensure_reg(a)?;
-        let rhs = self.ensure_reg(b)?;
+        let lhs = self.read_value(a, SCRATCH)?;
+        let rhs = self.read_value(b, SCRATCH2)?;
         emit_cmp_reg_reg(&mut self.code, lhs, rhs);
+        let dst_reg = self.target_reg(dst)?;
         match kind {
             CmpKind::Eq => emit_sete_reg(&mut self.code, dst_reg),
             CmpKind::Ne => emit_setne_reg(&mut self.code, dst_reg),
@@ -407,24 +757,112 @@ impl CodegenCtx {
             CmpKind::Ge => emit_setge_reg(&mut self.code, dst_reg),
         }
         emit_movzx_reg8(&mut self.code, dst_reg);
-        self.consume(a);
-        self.consume(b);
-        Ok(())
+        self.store_value(dst, dst_reg)
     }
 
-    fn emit_print_string(&mut self, _reg: Reg) -> Result<()> {
-        // Stub implementation to fix compilation
+    /// Calls one of the embedded print routines with `value` in `rdi`.
+    /// The routines preserve every other register, so only `rdi` is saved.
+    fn emit_runtime_call(&mut self, label: &str, value: u32) -> Result<()> {
+        emit_push_reg(&mut self.code, Reg::RDI);
+        let src = self.location(value)?;
+        self.emit_move(Location::Reg(Reg::RDI), src);
+        self.emit_call(label);
+        emit_pop_reg(&mut self.code, Reg::RDI);
         Ok(())
     }
 
-    fn emit_print_int(&mut self, _reg: Reg) -> Result<()> {
-        // Stub implementation to fix compilation
-        Ok(())
+    /// Output routines linked into every executable. They take their operand
+    /// in registers and preserve all registers they (or `syscall`) clobber.
+    fn emit_runtime(&mut self, true_sid: u32, false_sid: u32) {
+        // write(1, rsi, rdx)
+        self.emit_label(WRITE_LABEL);
+        for reg in [Reg::RAX, Reg::RDI, Reg::RCX, Reg::R11] {
+            emit_push_reg(&mut self.code, reg);
+        }
+        emit_mov_rax(&mut self.code, 1);
+        emit_mov_rdi(&mut self.code, 1);
+        emit_syscall(&mut self.code);
+        for reg in [Reg::R11, Reg::RCX, Reg::RDI, Reg::RAX] {
+            emit_pop_reg(&mut self.code, reg);
+        }
+        self.code.push(0xC3);
+
+        // Prints the NUL-terminated string at rdi.
+        self.emit_label(PRINT_STR_LABEL);
+        emit_push_reg(&mut self.code, Reg::RSI);
+        emit_push_reg(&mut self.code, Reg::RDX);
+        emit_mov_reg_reg(&mut self.code, Reg::RSI, Reg::RDI);
+        self.code.extend_from_slice(&[0x31, 0xD2]); // xor edx, edx
+        self.emit_label("__print_str_scan");
+        self.code.extend_from_slice(&[0x80, 0x3C, 0x16, 0x00]); // cmp byte [rsi+rdx], 0
+        self.emit_conditional_jump(JumpKind::Je, "__print_str_done");
+        self.code.extend_from_slice(&[0x48, 0xFF, 0xC2]); // inc rdx
+        self.emit_jmp("__print_str_scan");
+        self.emit_label("__print_str_done");
+        self.emit_call(WRITE_LABEL);
+        emit_pop_reg(&mut self.code, Reg::RDX);
+        emit_pop_reg(&mut self.code, Reg::RSI);
+        self.code.push(0xC3);
+
+        // Prints rdi as a signed decimal, building the digits backwards in a
+        // 32-byte stack buffer.
+        self.emit_label(PRINT_INT_LABEL);
+        for reg in [Reg::RAX, Reg::RCX, Reg::RDX, Reg::RSI, Reg::R8] {
+            emit_push_reg(&mut self.code, reg);
+        }
+        emit_sub_rsp_imm(&mut self.code, 32);
+        emit_mov_reg_reg(&mut self.code, Reg::RAX, Reg::RDI);
+        emit_mov_reg_reg(&mut self.code, Reg::R8, Reg::RDI);
+        self.code.extend_from_slice(&[0x48, 0x8D, 0x74, 0x24, 0x20]); // lea rsi, [rsp+32]
+        emit_test_reg(&mut self.code, Reg::RAX);
+        self.emit_conditional_jump(JumpKind::Jns, "__print_int_digits");
+        self.code.extend_from_slice(&[0x48, 0xF7, 0xD8]); // neg rax
+        self.emit_label("__print_int_digits");
+        emit_mov_reg_imm(&mut self.code, Reg::RCX, 10);
+        self.emit_label("__print_int_loop");
+        self.code.extend_from_slice(&[0x31, 0xD2]); // xor edx, edx
+        self.code.extend_from_slice(&[0x48, 0xF7, 0xF1]); // div rcx
+        self.code.extend_from_slice(&[0x80, 0xC2, b'0']); // add dl, '0'
+        self.code.extend_from_slice(&[0x48, 0xFF, 0xCE]); // dec rsi
+        self.code.extend_from_slice(&[0x88, 0x16]); // mov [rsi], dl
+        emit_test_reg(&mut self.code, Reg::RAX);
+        self.emit_conditional_jump(JumpKind::Jne, "__print_int_loop");
+        emit_test_reg(&mut self.code, Reg::R8);
+        self.emit_conditional_jump(JumpKind::Jns, "__print_int_write");
+        self.code.extend_from_slice(&[0x48, 0xFF, 0xCE]); // dec rsi
+        self.code.extend_from_slice(&[0xC6, 0x06, b'-']); // mov byte [rsi], '-'
+        self.emit_label("__print_int_write");
+        self.code.extend_from_slice(&[0x48, 0x8D, 0x54, 0x24, 0x20]); // lea rdx, [rsp+32]
+        self.code.extend_from_slice(&[0x48, 0x29, 0xF2]); // sub rdx, rsi
+        self.emit_call(WRITE_LABEL);
+        emit_add_rsp_imm(&mut self.code, 32);
+        for reg in [Reg::R8, Reg::RSI, Reg::RDX, Reg::RCX, Reg::RAX] {
+            emit_pop_reg(&mut self.code, reg);
+        }
+        self.code.push(0xC3);
+
+        // Prints `true` or `false` for rdi, like the interpreter does.
+        self.emit_label(PRINT_BOOL_LABEL);
+        emit_push_reg(&mut self.code, Reg::RSI);
+        emit_push_reg(&mut self.code, Reg::RDX);
+        emit_test_reg(&mut self.code, Reg::RDI);
+        self.emit_conditional_jump(JumpKind::Je, "__print_bool_false");
+        emit_mov_rsi_placeholder(&mut self.code, true_sid, &mut self.string_patches);
+        emit_mov_rdx(&mut self.code, 4);
+        self.emit_jmp("__print_bool_write");
+        self.emit_label("__print_bool_false");
+        emit_mov_rsi_placeholder(&mut self.code, false_sid, &mut self.string_patches);
+        emit_mov_rdx(&mut self.code, 5);
+        self.emit_label("__print_bool_write");
+        self.emit_call(WRITE_LABEL);
+        emit_pop_reg(&mut self.code, Reg::RDX);
+        emit_pop_reg(&mut self.code, Reg::RSI);
+        self.code.push(0xC3);
     }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum Reg {
+pub(super) enum Reg {
     RAX,
     RBX,
     RCX,
@@ -469,6 +907,10 @@ impl Reg {
             _ => 0,
         }
     }
+
+    fn is_caller_saved(self) -> bool {
+        !matches!(self, Reg::RBX | Reg::R12 | Reg::R13 | Reg::R14 | Reg::R15)
+    }
 }
 
 fn rex_prefix(wide: bool, reg: Reg, rm: Reg) -> u8 {
@@ -604,6 +1046,46 @@ fn emit_or_reg_reg(code: &mut Vec<u8>, dst: Reg, src: Reg) {
     code.push(modrm_byte(src, dst));
 }
 
+fn emit_xor_reg_reg(code: &mut Vec<u8>, dst: Reg, src: Reg) {
+    code.push(rex_prefix(true, src, dst));
+    code.push(0x31);
+    code.push(modrm_byte(src, dst));
+}
+
+/// `shl`/`shr`/`sar reg, cl` (`D3 /4`, `/5`, `/7`).
+fn emit_shift_reg_cl(code: &mut Vec<u8>, reg: Reg, ext: u8) {
+    code.push(rex_prefix(true, Reg::RAX, reg));
+    code.push(0xD3);
+    code.push(0xC0 | (ext << 3) | reg.low_bits());
+}
+
+fn emit_ud2(code: &mut Vec<u8>) {
+    code.extend_from_slice(&[0x0F, 0x0B]);
+}
+
+/// `neg reg` (`F7 /3`).
+fn emit_neg_reg(code: &mut Vec<u8>, reg: Reg) {
+    code.push(rex_prefix(true, Reg::RAX, reg));
+    code.push(0xF7);
+    code.push(0xD8 | reg.low_bits());
+}
+
+/// `not reg` (`F7 /2`).
+fn emit_not_reg(code: &mut Vec<u8>, reg: Reg) {
+    code.push(rex_prefix(true, Reg::RAX, reg));
+    code.push(0xF7);
+    code.push(0xD0 | reg.low_bits());
+}
+
+/// `xor reg, 1` (`83 /6 ib`), so a 0/1 bool flips without touching the
+/// upper bits.
+fn emit_flip_bool_reg(code: &mut Vec<u8>, reg: Reg) {
+    code.push(rex_prefix(true, Reg::RAX, reg));
+    code.push(0x83);
+    code.push(0xF0 | reg.low_bits());
+    code.push(0x01);
+}
+
 fn emit_mov_rax(code: &mut Vec<u8>, imm: u64) {
     code.extend_from_slice(&[0x48, 0xB8]);
     code.extend_from_slice(&imm.to_le_bytes());
@@ -627,6 +1109,31 @@ fn emit_mov_reg_placeholder(
     patches.push(Patch { offset, string_id });
 }
 
+/// `mov dst, qword [base]`.
+fn emit_mov_reg_mem(code: &mut Vec<u8>, dst: Reg, base: Reg) {
+    code.push(rex_prefix(true, dst, base));
+    code.push(0x8B);
+    emit_mem_operand(code, dst, base);
+}
+
+/// `mov qword [base], src`.
+fn emit_mov_mem_reg(code: &mut Vec<u8>, base: Reg, src: Reg) {
+    code.push(rex_prefix(true, src, base));
+    code.push(0x89);
+    emit_mem_operand(code, src, base);
+}
+
+/// ModRM (and SIB/disp8 where the encoding needs them) for `[base]`.
+fn emit_mem_operand(code: &mut Vec<u8>, reg: Reg, base: Reg) {
+    match base.low_bits() {
+        // rsp/r12 need a SIB byte.
+        4 => code.extend_from_slice(&[(reg.low_bits() << 3) | 0x04, 0x24]),
+        // rbp/r13 with mod 00 would mean rip-relative; use a zero disp8.
+        5 => code.extend_from_slice(&[0x40 | (reg.low_bits() << 3) | 0x05, 0x00]),
+        low => code.push((reg.low_bits() << 3) | low),
+    }
+}
+
 fn emit_mov_rsi_placeholder(code: &mut Vec<u8>, string_id: u32, patches: &mut Vec<Patch>) {
     code.extend_from_slice(&[0x48, 0xBE]);
     let offset = code.len();
@@ -646,3 +1153,53 @@ fn emit_syscall(code: &mut Vec<u8>) {
 fn emit_xor_rdi(code: &mut Vec<u8>) {
     code.extend_from_slice(&[0x48, 0x31, 0xFF]);
 }
+
+fn emit_push_reg(code: &mut Vec<u8>, reg: Reg) {
+    if reg.rex_bit() != 0 {
+        code.push(0x41);
+    }
+    code.push(0x50 + reg.low_bits());
+}
+
+fn emit_pop_reg(code: &mut Vec<u8>, reg: Reg) {
+    if reg.rex_bit() != 0 {
+        code.push(0x41);
+    }
+    code.push(0x58 + reg.low_bits());
+}
+
+fn emit_sub_rsp_imm(code: &mut Vec<u8>, imm: i32) {
+    code.extend_from_slice(&[0x48, 0x81, 0xEC]);
+    code.extend_from_slice(&imm.to_le_bytes());
+}
+
+fn emit_add_rsp_imm(code: &mut Vec<u8>, imm: i32) {
+    code.extend_from_slice(&[0x48, 0x81, 0xC4]);
+    code.extend_from_slice(&imm.to_le_bytes());
+}
+
+fn emit_mov_rbp_disp_reg(code: &mut Vec<u8>, disp: i32, reg: Reg) {
+    code.push(0x48 | (reg.rex_bit() << 2));
+    code.push(0x89);
+    code.push(0x80 | (reg.low_bits() << 3) | 0x05);
+    code.extend_from_slice(&disp.to_le_bytes());
+}
+
+fn emit_push_rbp_disp(code: &mut Vec<u8>, disp: i32) {
+    // push qword [rbp + disp32]
+    code.extend_from_slice(&[0xFF, 0xB5]);
+    code.extend_from_slice(&disp.to_le_bytes());
+}
+
+fn emit_mov_reg_rbp_disp(code: &mut Vec<u8>, reg: Reg, disp: i32) {
+    code.push(0x48 | (reg.rex_bit() << 2));
+    code.push(0x8B);
+    code.push(0x80 | (reg.low_bits() << 3) | 0x05);
+    code.extend_from_slice(&disp.to_le_bytes());
+}
+
+fn emit_imul_reg_reg(code: &mut Vec<u8>, dst: Reg, src: Reg) {
+    code.push(rex_prefix(true, dst, src));
+    code.extend_from_slice(&[0x0F, 0xAF]);
+    code.push(modrm_byte(dst, src));
+}
@@ -1,13 +1,26 @@
 use anyhow::{anyhow, Result};
 
-use crate::ir::{IrInstr, IrModule, IrTerm};
+use crate::ir::{IrFunction, IrGlobal, IrInstr, IrModule, IrTerm, IrType};
 
 #[derive(Debug, Clone)]
 pub struct LoweredModule {
-    pub blocks: Vec<LoweredBlock>,
+    /// One entry per `IrFunction`, in module order, so `IrInstr::Call::func`
+    /// indexes straight into this list.
+    pub functions: Vec<LoweredFunction>,
+    /// Index of `apex`, which the `_start` stub calls.
+    pub entry: Option<usize>,
     pub strings: Vec<String>,
+    /// Module globals; each gets an 8-byte slot in the data segment.
+    pub globals: Vec<IrGlobal>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoweredFunction {
+    pub name: String,
+    pub params: Vec<IrType>,
+    pub ret: IrType,
+    pub blocks: Vec<LoweredBlock>,
     pub value_count: usize,
-    pub uses: Vec<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -18,105 +31,64 @@ pub struct LoweredBlock {
 }
 
 pub fn lower_ir(module: &IrModule) -> Result<LoweredModule> {
-    if let Some(func) = module.funcs.iter().find(|f| f.name == "apex") {
-        let mut blocks = Vec::new();
-        let mut max_value = 0u32;
-        let mut uses: Vec<u32> = Vec::new();
-        for block in &func.blocks {
-            for instr in &block.body {
-                if let Some(value) = instr.result() {
-                    max_value = max_value.max(value + 1);
-                }
-                match instr {
-                    IrInstr::AddI32 { a, b, .. }
-                    | IrInstr::CmpEq { a, b, .. }
-                    | IrInstr::Add { a, b, .. }
-                    | IrInstr::Sub { a, b, .. }
-                    | IrInstr::Mul { a, b, .. }
-                    | IrInstr::Div { a, b, .. }
-                    | IrInstr::Rem { a, b, .. }
-                    | IrInstr::And { a, b, .. }
-                    | IrInstr::Or { a, b, .. }
-                    | IrInstr::Xor { a, b, .. }
-                    | IrInstr::Shl { a, b, .. }
-                    | IrInstr::LShr { a, b, .. }
-                    | IrInstr::AShr { a, b, .. }
-                    | IrInstr::Select {
-                        cond: a,
-                        then_v: b,
-                        else_v: _,
-                        ..
-                    } => {
-                        max_value = max_value.max(*a + 1);
-                        max_value = max_value.max(*b + 1);
-                        increment_use(&mut uses, *a);
-                        increment_use(&mut uses, *b);
-                    }
-                    IrInstr::Select { else_v, .. } => {
-                        max_value = max_value.max(*else_v + 1);
-                        increment_use(&mut uses, *else_v);
-                    }
-                    IrInstr::Cmp { a, b, .. } => {
-                        max_value = max_value.max(*a + 1);
-                        max_value = max_value.max(*b + 1);
-                        increment_use(&mut uses, *a);
-                        increment_use(&mut uses, *b);
-                    }
-                    IrInstr::Phi { incomings, .. } => {
-                        for (_, val) in incomings {
-                            max_value = max_value.max(*val + 1);
-                            increment_use(&mut uses, *val);
-                        }
-                    }
-                    IrInstr::Load { ptr, .. } => increment_use(&mut uses, *ptr),
-                    IrInstr::Store { src, ptr, .. } => {
-                        increment_use(&mut uses, *src);
-                        increment_use(&mut uses, *ptr);
-                    }
-                    IrInstr::PrintValue { value, .. } => {
-                        increment_use(&mut uses, *value);
-                    }
-                    _ => {
-                        // Other instructions either have no operands or are handled later in emitter.
-                    }
-                }
-            }
-            if let Some(term) = &block.term {
-                match term {
-                    IrTerm::CondBr { cond, .. } => {
-                        increment_use(&mut uses, *cond);
-                    }
-                    IrTerm::Ret { .. } | IrTerm::Br { .. } => {}
-                    _ => {}
-                }
-            }
-            let term = block.term.clone().unwrap_or(IrTerm::Ret { value: None });
-            blocks.push(LoweredBlock {
-                id: block.id,
-                instrs: block.body.clone(),
-                term,
-            });
-        }
-        return Ok(LoweredModule {
-            blocks,
-            strings: module.strings.clone(),
-            value_count: max_value as usize,
-            uses,
-        });
+    let mut functions = Vec::with_capacity(module.funcs.len());
+    for func in &module.funcs {
+        functions.push(lower_function(module, func)?);
     }
-
+    let entry = module.funcs.iter().position(|f| f.name == "apex");
     Ok(LoweredModule {
-        blocks: Vec::new(),
+        functions,
+        entry,
         strings: module.strings.clone(),
-        value_count: 0,
-        uses: Vec::new(),
+        globals: module.globals.clone(),
     })
 }
 
-fn increment_use(uses: &mut Vec<u32>, value: u32) {
-    let idx = value as usize;
-    if idx >= uses.len() {
-        uses.resize(idx + 1, 0);
+fn lower_function(module: &IrModule, func: &IrFunction) -> Result<LoweredFunction> {
+    let mut blocks = Vec::new();
+    // Parameters occupy the first value ids of every function.
+    let mut max_value = func.params.len() as u32;
+    for block in &func.blocks {
+        for instr in &block.body {
+            if let Some(value) = instr.result() {
+                max_value = max_value.max(value + 1);
+            }
+            for operand in instr.operands() {
+                max_value = max_value.max(operand + 1);
+            }
+            if let IrInstr::Call {
+                func: callee, args, ..
+            } = instr
+            {
+                let target = module
+                    .funcs
+                    .get(*callee as usize)
+                    .ok_or_else(|| anyhow!("call to unknown function %{}", callee))?;
+                if target.params.len() != args.len() {
+                    return Err(anyhow!(
+                        "call to {} passes {} arguments, expected {}",
+                        target.name,
+                        args.len(),
+                        target.params.len()
+                    ));
+                }
+            }
+        }
+        let term = block.term.clone().unwrap_or(IrTerm::Ret { value: None });
+        for operand in term.operands() {
+            max_value = max_value.max(operand + 1);
+        }
+        blocks.push(LoweredBlock {
+            id: block.id,
+            instrs: block.body.clone(),
+            term,
+        });
     }
-    uses[idx] += 1;
+    Ok(LoweredFunction {
+        name: func.name.clone(),
+        params: func.params.clone(),
+        ret: func.ret.clone(),
+        blocks,
+        value_count: max_value as usize,
+    })
 }
@@ -1,3 +1,4 @@
 pub mod elf_writer;
 pub mod emitter;
 pub mod lower;
+mod regalloc;
@@ -0,0 +1,300 @@
+//! Linear-scan register allocation for the x86_64 backend.
+//!
+//! Every instruction of a function gets a position in block order; each value
+//! is live over one interval `[start, end]` covering all of its definitions,
+//! uses and the block boundaries it is live across. Intervals are walked by
+//! start position and handed a register from `ALLOCATABLE`; when none is free
+//! the interval ending last is spilled to a stack slot.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::IrInstr;
+
+use super::emitter::{Reg, ARG_REGS};
+use super::lower::LoweredFunction;
+
+/// Registers handed out by the allocator. `r10` and `r11` are kept back as
+/// scratch registers for spilled operands and parallel moves.
+pub(super) const ALLOCATABLE: [Reg; 12] = [
+    Reg::RAX,
+    Reg::RCX,
+    Reg::RDX,
+    Reg::RSI,
+    Reg::RDI,
+    Reg::R8,
+    Reg::R9,
+    Reg::RBX,
+    Reg::R12,
+    Reg::R13,
+    Reg::R14,
+    Reg::R15,
+];
+
+/// Callee-saved registers in the order the prologue pushes them.
+pub(super) const CALLEE_SAVED: [Reg; 5] = [Reg::RBX, Reg::R12, Reg::R13, Reg::R14, Reg::R15];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Location {
+    Reg(Reg),
+    /// `[rbp + offset]`: a spill slot below the frame pointer, or a stack
+    /// argument above the return address.
+    Stack(i32),
+}
+
+#[derive(Debug)]
+pub(super) struct Allocation {
+    locations: Vec<Option<Location>>,
+    intervals: Vec<Option<(u32, u32)>>,
+    block_starts: Vec<u32>,
+    /// Callee-saved registers the function writes, in push order.
+    pub callee_saved: Vec<Reg>,
+    pub spill_slots: usize,
+}
+
+impl Allocation {
+    pub fn location(&self, value: u32) -> Option<Location> {
+        self.locations.get(value as usize).copied().flatten()
+    }
+
+    /// Position of instruction `instr` of the block at `block_index`.
+    pub fn instr_position(&self, block_index: usize, instr: usize) -> u32 {
+        self.block_starts[block_index] + 2 * (instr as u32 + 1)
+    }
+
+    /// Registers holding values that are live both before and after `pos`,
+    /// i.e. that must survive a call made there.
+    pub fn live_across(&self, pos: u32) -> Vec<Reg> {
+        let mut regs = Vec::new();
+        for (value, interval) in self.intervals.iter().enumerate() {
+            let Some((start, end)) = interval else {
+                continue;
+            };
+            if *start < pos && *end > pos {
+                if let Some(Location::Reg(reg)) = self.locations[value] {
+                    if !regs.contains(&reg) {
+                        regs.push(reg);
+                    }
+                }
+            }
+        }
+        regs
+    }
+
+    /// Bytes reserved below the callee-saved pushes, padded so `rsp` stays
+    /// 16-byte aligned.
+    pub fn frame_size(&self) -> i32 {
+        let slots = self.spill_slots + (self.callee_saved.len() + self.spill_slots) % 2;
+        (slots * 8) as i32
+    }
+}
+
+pub(super) fn allocate(func: &LoweredFunction) -> Allocation {
+    let value_count = func.value_count.max(1);
+    let index_of: HashMap<u32, usize> = func
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(idx, block)| (block.id, idx))
+        .collect();
+
+    // Positions: a block start slot (where its phis define), one slot per
+    // instruction and one for the terminator.
+    let mut block_starts = Vec::with_capacity(func.blocks.len());
+    let mut term_positions = Vec::with_capacity(func.blocks.len());
+    let mut pos = 0u32;
+    for block in &func.blocks {
+        block_starts.push(pos);
+        pos += 2 * (block.instrs.len() as u32 + 1);
+        term_positions.push(pos);
+        pos += 2;
+    }
+
+    let (live_in, live_out) = liveness(func, &index_of);
+
+    let mut intervals: Vec<Option<(u32, u32)>> = vec![None; value_count];
+    let mut touch = |value: u32, pos: u32| {
+        let idx = value as usize;
+        if idx >= intervals.len() {
+            intervals.resize(idx + 1, None);
+        }
+        intervals[idx] = Some(match intervals[idx] {
+            Some((start, end)) => (start.min(pos), end.max(pos)),
+            None => (pos, pos),
+        });
+    };
+    for param in 0..func.params.len() as u32 {
+        touch(param, 0);
+    }
+    for (idx, block) in func.blocks.iter().enumerate() {
+        let from = block_starts[idx];
+        let to = term_positions[idx];
+        for value in &live_in[idx] {
+            touch(*value, from);
+        }
+        for value in &live_out[idx] {
+            touch(*value, to);
+        }
+        for (i, instr) in block.instrs.iter().enumerate() {
+            if let IrInstr::Phi { dst, incomings } = instr {
+                // The phi is written by moves at the end of each predecessor.
+                touch(*dst, from);
+                for (pred, _) in incomings {
+                    if let Some(pred_idx) = index_of.get(pred) {
+                        touch(*dst, term_positions[*pred_idx]);
+                    }
+                }
+                continue;
+            }
+            let pos = from + 2 * (i as u32 + 1);
+            if let Some(dst) = instr.result() {
+                touch(dst, pos);
+            }
+            for operand in instr.operands() {
+                touch(operand, pos);
+            }
+        }
+        for operand in block.term.operands() {
+            touch(operand, to);
+        }
+    }
+
+    let mut locations: Vec<Option<Location>> = vec![None; intervals.len()];
+    // Arguments beyond the sixth already live in the caller's frame.
+    for param in ARG_REGS.len()..func.params.len() {
+        let offset = 16 + 8 * (param - ARG_REGS.len()) as i32;
+        locations[param] = Some(Location::Stack(offset));
+    }
+
+    let mut order: Vec<(u32, u32, u32)> = intervals
+        .iter()
+        .enumerate()
+        .filter(|(value, _)| locations[*value].is_none())
+        .filter_map(|(value, interval)| interval.map(|(start, end)| (start, end, value as u32)))
+        .collect();
+    order.sort();
+
+    let mut spilled: Vec<u32> = Vec::new();
+    let mut active: Vec<(u32, u32, Reg)> = Vec::new();
+    let mut free: Vec<Reg> = ALLOCATABLE.to_vec();
+    for (start, end, value) in order {
+        active.retain(|(active_end, _, reg)| {
+            if *active_end < start {
+                free.push(*reg);
+                false
+            } else {
+                true
+            }
+        });
+        if let Some(reg) = take_preferred(&mut free) {
+            locations[value as usize] = Some(Location::Reg(reg));
+            active.push((end, value, reg));
+            continue;
+        }
+        let (victim_idx, &(victim_end, victim, reg)) = active
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (active_end, _, _))| *active_end)
+            .expect("no free register implies an active interval");
+        if victim_end > end {
+            spilled.push(victim);
+            active.remove(victim_idx);
+            locations[value as usize] = Some(Location::Reg(reg));
+            active.push((end, value, reg));
+        } else {
+            spilled.push(value);
+        }
+    }
+
+    let callee_saved: Vec<Reg> = CALLEE_SAVED
+        .iter()
+        .copied()
+        .filter(|reg| locations.contains(&Some(Location::Reg(*reg))))
+        .collect();
+    // Spill slots sit below `rbp` and the callee-saved pushes.
+    for (slot, value) in spilled.iter().enumerate() {
+        let offset = -8 * (callee_saved.len() + slot + 1) as i32;
+        locations[*value as usize] = Some(Location::Stack(offset));
+    }
+
+    Allocation {
+        locations,
+        intervals,
+        block_starts,
+        callee_saved,
+        spill_slots: spilled.len(),
+    }
+}
+
+/// Takes the first free register in `ALLOCATABLE` order, so caller-saved
+/// registers are used before the prologue has to save callee-saved ones.
+fn take_preferred(free: &mut Vec<Reg>) -> Option<Reg> {
+    let reg = ALLOCATABLE.iter().find(|reg| free.contains(reg)).copied()?;
+    free.retain(|r| *r != reg);
+    Some(reg)
+}
+
+/// Per-block live-in and live-out sets. Phi operands count as uses at the end
+/// of the matching predecessor, phi results as definitions of their block.
+fn liveness(
+    func: &LoweredFunction,
+    index_of: &HashMap<u32, usize>,
+) -> (Vec<HashSet<u32>>, Vec<HashSet<u32>>) {
+    let count = func.blocks.len();
+    let mut uses = vec![HashSet::new(); count];
+    let mut defs = vec![HashSet::new(); count];
+    let mut phi_out = vec![HashSet::new(); count];
+    let mut succs = vec![Vec::new(); count];
+    for (idx, block) in func.blocks.iter().enumerate() {
+        for instr in &block.instrs {
+            if let IrInstr::Phi { dst, incomings } = instr {
+                defs[idx].insert(*dst);
+                for (pred, value) in incomings {
+                    if let Some(pred_idx) = index_of.get(pred) {
+                        phi_out[*pred_idx].insert(*value);
+                    }
+                }
+                continue;
+            }
+            for operand in instr.operands() {
+                if !defs[idx].contains(&operand) {
+                    uses[idx].insert(operand);
+                }
+            }
+            if let Some(dst) = instr.result() {
+                defs[idx].insert(dst);
+            }
+        }
+        for operand in block.term.operands() {
+            if !defs[idx].contains(&operand) {
+                uses[idx].insert(operand);
+            }
+        }
+        succs[idx] = block
+            .term
+            .successors()
+            .iter()
+            .filter_map(|succ| index_of.get(succ).copied())
+            .collect();
+    }
+
+    let mut live_in: Vec<HashSet<u32>> = uses.clone();
+    let mut live_out: Vec<HashSet<u32>> = vec![HashSet::new(); count];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in (0..count).rev() {
+            let mut out = phi_out[idx].clone();
+            for succ in &succs[idx] {
+                out.extend(live_in[*succ].iter().copied());
+            }
+            let mut inn = uses[idx].clone();
+            inn.extend(out.iter().filter(|v| !defs[idx].contains(v)).copied());
+            if out != live_out[idx] || inn != live_in[idx] {
+                live_out[idx] = out;
+                live_in[idx] = inn;
+                changed = true;
+            }
+        }
+    }
+    (live_in, live_out)
+}
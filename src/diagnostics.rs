@@ -16,6 +16,8 @@ pub enum LexError {
     UnexpectedChar { ch: char, span: Span },
     #[error("unterminated string literal starting at {span:?}")]
     UnterminatedString { span: Span },
+    #[error("unterminated `${{` interpolation at {span:?}")]
+    UnterminatedInterpolation { span: Span },
     #[error("unterminated block comment starting at {span:?}")]
     UnterminatedBlockComment { span: Span },
     #[error("invalid numeric literal at {span:?}")]
@@ -31,6 +33,7 @@ impl LexError {
         match self {
             LexError::UnexpectedChar { span, .. }
             | LexError::UnterminatedString { span }
+            | LexError::UnterminatedInterpolation { span }
             | LexError::UnterminatedBlockComment { span }
             | LexError::InvalidNumber { span }
             | LexError::InvalidCharLiteral { span }
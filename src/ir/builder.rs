@@ -1,4 +1,4 @@
-use super::{IrFunction, IrInstr, IrModule, IrTerm, IrType};
+use super::{GlobalInit, IrFunction, IrGlobal, IrInstr, IrModule, IrTerm, IrType};
 
 type FunctionId = usize;
 
@@ -31,6 +31,10 @@ impl IrBuilder {
         self.module.funcs.len() - 1
     }
 
+    pub fn function(&self, func_id: FunctionId) -> &IrFunction {
+        self.module.funcs.get(func_id).expect("invalid function id")
+    }
+
     pub fn new_block(&mut self, func_id: FunctionId) -> u32 {
         self.module
             .funcs
@@ -94,6 +98,14 @@ impl IrBuilder {
             .body
     }
 
+    pub fn replace_uses(&mut self, func_id: FunctionId, from: u32, to: u32) {
+        self.module
+            .funcs
+            .get_mut(func_id)
+            .expect("invalid function id")
+            .replace_uses(from, to);
+    }
+
     pub fn finish(mut self) -> IrModule {
         for func in &mut self.module.funcs {
             for block in &mut func.blocks {
@@ -105,6 +117,24 @@ impl IrBuilder {
         self.module
     }
 
+    pub fn add_global(
+        &mut self,
+        name: impl Into<String>,
+        ty: IrType,
+        mutable: bool,
+        init: GlobalInit,
+    ) -> u32 {
+        let id = self.module.globals.len() as u32;
+        self.module.globals.push(IrGlobal {
+            id,
+            name: name.into(),
+            ty,
+            mutable,
+            init,
+        });
+        id
+    }
+
     pub fn intern_string(&mut self, text: impl Into<String>) -> u32 {
         let text = text.into();
         if let Some(idx) = self.module.strings.iter().position(|s| s == &text) {
@@ -1,40 +1,264 @@
 use std::collections::HashMap;
 
 use crate::ast::{
-    BinaryOp, Block, Expr, File, Function, IfStmt, Item, Literal, Stmt, VarDecl, VarKind,
+    BinaryOp, Block, Expr, File, Function, FunctionSignature, GlobalKind, IfStmt,
+    InterpolationPart, Item, Literal, Pattern, Stmt, SwitchStmt, TypeAliasDef, TypeExpr, UnaryOp,
+    VarDecl, VarKind,
 };
 
-use super::{instr::CmpOp, IrBuilder, IrInstr, IrModule, IrTerm, IrType};
+use super::passes::reachable_blocks;
+use super::{instr::CmpOp, GlobalInit, IrBuilder, IrInstr, IrIntrinsic, IrModule, IrTerm, IrType};
+use crate::span::Span;
 
-pub fn build_ir(ast: &File) -> IrModule {
+/// A construct the IR builder cannot lower, reported instead of compiling
+/// it to something that behaves differently from the interpreter.
+#[derive(Debug, Clone)]
+pub struct IrBuildError {
+    pub message: String,
+    pub span: Span,
+}
+
+pub fn build_ir(ast: &File) -> Result<IrModule, Vec<IrBuildError>> {
     let mut builder = IrBuilder::new();
-    if let Some(func) = find_function(ast, "apex") {
-        lower_function(&mut builder, func);
-    } else {
+    let mut errors = Vec::new();
+    let aliases = collect_aliases(ast);
+    let functions = collect_functions(ast, &aliases);
+    let globals = collect_globals(&mut builder, ast, &aliases);
+    for item in &ast.items {
+        if let Item::Function(func) = item {
+            lower_function(
+                &mut builder,
+                func,
+                &functions,
+                &globals,
+                &aliases,
+                &mut errors,
+            );
+        }
+    }
+    if !functions.contains_key("apex") {
         let func_id = builder.new_function("apex", Vec::new(), IrType::Void);
         let block = builder.new_block(func_id);
         builder.set_term(func_id, block, IrTerm::Ret { value: None });
     }
-    builder.finish()
+    if errors.is_empty() {
+        Ok(builder.finish())
+    } else {
+        Err(errors)
+    }
 }
 
 pub fn format_ir(module: &IrModule) -> String {
     format!("{}", module)
 }
 
-fn find_function<'a>(ast: &'a File, name: &str) -> Option<&'a Function> {
-    ast.items.iter().find_map(|item| match item {
-        Item::Function(func) if func.signature.name == name => Some(func),
+/// Signature of a top-level function, keyed by name. `index` is the
+/// function's position in `IrModule::funcs` and the `func` operand of calls.
+struct FunctionSig {
+    index: u32,
+    ret: IrType,
+}
+
+type FunctionTable = HashMap<String, FunctionSig>;
+
+/// `type` items by name, expanded by `ir_type_from_hint`.
+type AliasTable<'a> = HashMap<&'a str, &'a TypeAliasDef>;
+
+fn collect_aliases(ast: &File) -> AliasTable<'_> {
+    ast.items
+        .iter()
+        .filter_map(|item| match item {
+            Item::TypeAlias(alias) => Some((alias.name.as_str(), alias)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn collect_functions(ast: &File, aliases: &AliasTable) -> FunctionTable {
+    let mut table = FunctionTable::new();
+    let functions = ast.items.iter().filter_map(|item| match item {
+        Item::Function(func) => Some(func),
         _ => None,
-    })
+    });
+    for (index, func) in functions.enumerate() {
+        table
+            .entry(func.signature.name.clone())
+            .or_insert_with(|| FunctionSig {
+                index: index as u32,
+                ret: return_type(func, aliases),
+            });
+    }
+    table
+}
+
+/// A module-level `const`, `static` or `var` item. Constants and statics
+/// are folded into every use; `var` items live in their `IrGlobal` and are
+/// accessed with `LoadGlobal`/`StoreGlobal`.
+struct GlobalSig {
+    id: u32,
+    ty: IrType,
+    mutable: bool,
+    value: ConstValue,
 }
 
-fn lower_function(builder: &mut IrBuilder, func: &Function) {
-    let func_id = builder.new_function(func.signature.name.clone(), Vec::new(), IrType::Void);
+type GlobalTable = HashMap<String, GlobalSig>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum ConstValue {
+    Int(i128),
+    Bool(bool),
+    Str(String),
+}
+
+impl ConstValue {
+    fn ty(&self) -> IrType {
+        match self {
+            ConstValue::Int(_) => IrType::I64,
+            ConstValue::Bool(_) => IrType::Bool,
+            ConstValue::Str(_) => IrType::Str,
+        }
+    }
+}
+
+/// Evaluates global initializers in declaration order and records an
+/// `IrGlobal` for each. Items whose value cannot be represented in the IR
+/// (floats, for example) are left out, like other unsupported constructs.
+fn collect_globals(builder: &mut IrBuilder, ast: &File, aliases: &AliasTable) -> GlobalTable {
+    let mut table = GlobalTable::new();
+    for item in &ast.items {
+        let Item::Global(global) = item else {
+            continue;
+        };
+        let Some(value) = eval_const(&global.value, &table) else {
+            continue;
+        };
+        let ty = global
+            .ty
+            .as_ref()
+            .map(|ty| ir_type_from_hint(ty, aliases))
+            .unwrap_or_else(|| value.ty());
+        let init = match &value {
+            ConstValue::Int(value) => GlobalInit::Const {
+                value: *value,
+                ty: ty.clone(),
+            },
+            ConstValue::Bool(value) => GlobalInit::Const {
+                value: *value as i128,
+                ty: IrType::Bool,
+            },
+            ConstValue::Str(text) => GlobalInit::FromString(builder.intern_string(text.clone())),
+        };
+        let mutable = global.kind == GlobalKind::Var;
+        let id = builder.add_global(global.name.clone(), ty.clone(), mutable, init);
+        table.insert(
+            global.name.clone(),
+            GlobalSig {
+                id,
+                ty,
+                mutable,
+                value,
+            },
+        );
+    }
+    table
+}
+
+fn eval_const(expr: &Expr, globals: &GlobalTable) -> Option<ConstValue> {
+    use ConstValue::*;
+
+    match expr {
+        Expr::Literal(Literal::Integer { value, .. }) => {
+            value.replace('_', "").parse::<i128>().ok().map(Int)
+        }
+        Expr::Literal(Literal::Bool { value, .. }) => Some(Bool(*value)),
+        Expr::Literal(Literal::String { value, .. }) => Some(Str(value.clone())),
+        Expr::Identifier { name, .. } => globals
+            .get(name)
+            .filter(|global| !global.mutable)
+            .map(|global| global.value.clone()),
+        Expr::Unary { op, expr, .. } => match (op, eval_const(expr, globals)?) {
+            (UnaryOp::Negate, Int(value)) => value.checked_neg().map(Int),
+            (UnaryOp::BitNot, Int(value)) => Some(Int(!value)),
+            (UnaryOp::Not, Bool(value)) => Some(Bool(!value)),
+            _ => None,
+        },
+        Expr::Binary {
+            left, op, right, ..
+        } => {
+            let left = eval_const(left, globals)?;
+            let right = eval_const(right, globals)?;
+            match (op, left, right) {
+                (BinaryOp::Equal, a, b) => Some(Bool(a == b)),
+                (BinaryOp::NotEqual, a, b) => Some(Bool(a != b)),
+                (BinaryOp::Add, Str(a), Str(b)) => Some(Str(a + &b)),
+                (BinaryOp::LogicalAnd, Bool(a), Bool(b)) => Some(Bool(a && b)),
+                (BinaryOp::LogicalOr, Bool(a), Bool(b)) => Some(Bool(a || b)),
+                (op, Int(a), Int(b)) => match op {
+                    BinaryOp::Add => a.checked_add(b).map(Int),
+                    BinaryOp::Subtract => a.checked_sub(b).map(Int),
+                    BinaryOp::Multiply => a.checked_mul(b).map(Int),
+                    BinaryOp::Divide => a.checked_div(b).map(Int),
+                    BinaryOp::Modulo => a.checked_rem(b).map(Int),
+                    BinaryOp::BitAnd => Some(Int(a & b)),
+                    BinaryOp::BitOr => Some(Int(a | b)),
+                    BinaryOp::BitXor => Some(Int(a ^ b)),
+                    BinaryOp::ShiftLeft => u32::try_from(b)
+                        .ok()
+                        .and_then(|b| a.checked_shl(b))
+                        .map(Int),
+                    BinaryOp::ShiftRight => u32::try_from(b)
+                        .ok()
+                        .and_then(|b| a.checked_shr(b))
+                        .map(Int),
+                    BinaryOp::Less => Some(Bool(a < b)),
+                    BinaryOp::LessEqual => Some(Bool(a <= b)),
+                    BinaryOp::Greater => Some(Bool(a > b)),
+                    BinaryOp::GreaterEqual => Some(Bool(a >= b)),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn return_type(func: &Function, aliases: &AliasTable) -> IrType {
+    func.signature
+        .return_type
+        .as_ref()
+        .map(|ty| ir_type_from_hint(ty, aliases))
+        .unwrap_or(IrType::Void)
+}
+
+fn lower_function(
+    builder: &mut IrBuilder,
+    func: &Function,
+    functions: &FunctionTable,
+    globals: &GlobalTable,
+    aliases: &AliasTable,
+    errors: &mut Vec<IrBuildError>,
+) {
+    let params: Vec<IrType> = func
+        .signature
+        .params
+        .iter()
+        .map(|param| ir_type_from_hint(&param.ty, aliases))
+        .collect();
+    let func_id = builder.new_function(
+        func.signature.name.clone(),
+        params.clone(),
+        return_type(func, aliases),
+    );
     let entry = builder.new_block(func_id);
-    let mut ctx = FnLower::new(builder, func_id, entry);
+    let mut ctx = FnLower::new(builder, functions, globals, aliases, errors, func_id, entry);
+    // Parameters take the first value ids, in declaration order.
+    for (param, ty) in func.signature.params.iter().zip(params) {
+        let value = ctx.builder.next_value(func_id);
+        ctx.declare_var(param.name.clone(), Binding::Value { value, ty });
+    }
     ctx.lower_block(&func.body);
-    ctx.finish();
+    ctx.finish(&func.signature);
 }
 
 #[derive(Clone)]
@@ -42,10 +266,19 @@ enum Binding {
     Value { value: u32, ty: IrType },
 }
 
-#[derive(Clone, Copy)]
+type Env = HashMap<String, Binding>;
+
+/// Targets of the innermost loop, plus the block and environment at every
+/// `break`/`continue` so the join points can build phis.
+#[derive(Clone)]
 struct LoopContext {
+    label: Option<String>,
     break_target: u32,
     continue_target: u32,
+    breaks: Vec<(u32, Env)>,
+    /// Incomings for the value of a `loop` expression, one per `break value`.
+    break_values: Vec<(u32, u32)>,
+    continues: Vec<(u32, Env)>,
 }
 
 struct BranchResult {
@@ -57,6 +290,10 @@ struct BranchResult {
 
 struct FnLower<'a> {
     builder: &'a mut IrBuilder,
+    functions: &'a FunctionTable,
+    globals: &'a GlobalTable,
+    aliases: &'a AliasTable<'a>,
+    errors: &'a mut Vec<IrBuildError>,
     func_id: usize,
     block_id: u32,
     env: HashMap<String, Binding>,
@@ -66,9 +303,21 @@ struct FnLower<'a> {
 }
 
 impl<'a> FnLower<'a> {
-    fn new(builder: &'a mut IrBuilder, func_id: usize, block_id: u32) -> Self {
+    fn new(
+        builder: &'a mut IrBuilder,
+        functions: &'a FunctionTable,
+        globals: &'a GlobalTable,
+        aliases: &'a AliasTable<'a>,
+        errors: &'a mut Vec<IrBuildError>,
+        func_id: usize,
+        block_id: u32,
+    ) -> Self {
         let mut slf = Self {
             builder,
+            functions,
+            globals,
+            aliases,
+            errors,
             func_id,
             block_id,
             env: HashMap::new(),
@@ -80,12 +329,29 @@ impl<'a> FnLower<'a> {
         slf
     }
 
-    fn finish(mut self) {
+    /// Closes the block the body fell out of. A function with a return
+    /// type may only do so from a block no path reaches.
+    fn finish(mut self, signature: &FunctionSignature) {
         self.end_scope();
-        if !self.builder.block_has_term(self.func_id, self.block_id) {
-            self.builder
-                .set_term(self.func_id, self.block_id, IrTerm::Ret { value: None });
+        if self.builder.block_has_term(self.func_id, self.block_id) {
+            return;
         }
+        let func = self.builder.function(self.func_id);
+        let term = if func.ret == IrType::Void {
+            IrTerm::Ret { value: None }
+        } else if !reachable_blocks(func).contains(&self.block_id) {
+            IrTerm::Unreachable
+        } else {
+            self.error(
+                signature.span,
+                format!(
+                    "`{}` can reach its end without returning a value",
+                    signature.name
+                ),
+            );
+            IrTerm::Unreachable
+        };
+        self.builder.set_term(self.func_id, self.block_id, term);
     }
 
     fn begin_scope(&mut self) {
@@ -107,15 +373,189 @@ impl<'a> FnLower<'a> {
         self.env.insert(name, binding);
     }
 
-    fn push_loop(&mut self, break_target: u32, continue_target: u32) {
+    fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.errors.push(IrBuildError {
+            message: message.into(),
+            span,
+        });
+    }
+
+    /// Reports `what` as not lowerable; returns `None` so expression
+    /// lowering can bail out with it.
+    fn unsupported<T>(&mut self, span: Span, what: &str) -> Option<T> {
+        self.error(span, format!("{what} are not supported by native builds"));
+        None
+    }
+
+    /// Lowers `expr` where its value is needed. An expression with no value
+    /// is reported, and a placeholder keeps the IR well formed while the rest
+    /// of the function is checked; the build fails either way.
+    fn lower_value(&mut self, expr: &Expr) -> u32 {
+        let reported = self.errors.len();
+        match self.lower_expr(expr) {
+            Some(value) => value,
+            None => {
+                if self.errors.len() == reported {
+                    self.error(expr.span(), "Expression has no value to use here");
+                }
+                self.emit_int(0)
+            }
+        }
+    }
+
+    fn push_loop(&mut self, label: Option<&str>, break_target: u32, continue_target: u32) {
         self.loop_stack.push(LoopContext {
+            label: label.map(str::to_string),
             break_target,
             continue_target,
+            breaks: Vec::new(),
+            break_values: Vec::new(),
+            continues: Vec::new(),
         });
     }
 
-    fn pop_loop(&mut self) {
-        self.loop_stack.pop();
+    fn pop_loop(&mut self) -> LoopContext {
+        self.loop_stack.pop().expect("loop stack underflow")
+    }
+
+    /// Rebinds every variable in scope to a phi at the top of the loop header
+    /// `head`, seeded with its value on entry from `pre`. The back-edge
+    /// incomings are filled in by `close_loop_phis`.
+    fn open_loop_phis(&mut self, head: u32, pre: u32) -> Vec<(String, u32)> {
+        let mut names: Vec<String> = self.env.keys().cloned().collect();
+        names.sort();
+        let mut phis = Vec::with_capacity(names.len());
+        for name in names {
+            let Some(Binding::Value { value, ty }) = self.env.get(&name).cloned() else {
+                continue;
+            };
+            let dst = self.builder.next_value(self.func_id);
+            self.builder.emit(
+                self.func_id,
+                head,
+                IrInstr::Phi {
+                    dst,
+                    incomings: vec![(pre, value)],
+                },
+            );
+            self.env
+                .insert(name.clone(), Binding::Value { value: dst, ty });
+            phis.push((name, dst));
+        }
+        phis
+    }
+
+    fn close_loop_phis(&mut self, head: u32, phis: &[(String, u32)], back_edges: &[(u32, Env)]) {
+        for (name, dst) in phis {
+            let incoming: Vec<(u32, u32)> = back_edges
+                .iter()
+                .map(|(block, env)| match env.get(name) {
+                    Some(Binding::Value { value, .. }) => (*block, *value),
+                    None => (*block, *dst),
+                })
+                .collect();
+            self.add_phi_incomings(head, *dst, incoming);
+        }
+    }
+
+    fn add_phi_incomings(&mut self, block: u32, phi: u32, extra: Vec<(u32, u32)>) {
+        if let Some(incomings) = self
+            .builder
+            .block_instrs_mut(self.func_id, block)
+            .iter_mut()
+            .find_map(|instr| match instr {
+                IrInstr::Phi { dst, incomings } if *dst == phi => Some(incomings),
+                _ => None,
+            })
+        {
+            incomings.extend(extra);
+        }
+    }
+
+    /// Removes header phis whose incomings are all the phi itself or one
+    /// other value (variables the loop never reassigns), rewriting their uses.
+    fn remove_trivial_phis(&mut self, head: u32, phis: &[(String, u32)]) {
+        let mut pending: Vec<u32> = phis.iter().map(|(_, dst)| *dst).collect();
+        loop {
+            let instrs = self.builder.block_instrs_mut(self.func_id, head);
+            let trivial = instrs.iter().find_map(|instr| match instr {
+                IrInstr::Phi { dst, incomings } if pending.contains(dst) => {
+                    let mut sources = incomings.iter().map(|(_, v)| *v).filter(|v| v != dst);
+                    let first = sources.next()?;
+                    sources.all(|v| v == first).then_some((*dst, first))
+                }
+                _ => None,
+            });
+            let Some((phi, value)) = trivial else {
+                break;
+            };
+            instrs.retain(|instr| !matches!(instr, IrInstr::Phi { dst, .. } if *dst == phi));
+            pending.retain(|dst| *dst != phi);
+            self.replace_value(phi, value);
+        }
+    }
+
+    fn replace_value(&mut self, from: u32, to: u32) {
+        self.builder.replace_uses(self.func_id, from, to);
+        for ctx in &mut self.loop_stack {
+            for (_, value) in &mut ctx.break_values {
+                if *value == from {
+                    *value = to;
+                }
+            }
+        }
+        let envs = std::iter::once(&mut self.env).chain(
+            self.loop_stack
+                .iter_mut()
+                .flat_map(|ctx| ctx.breaks.iter_mut().chain(ctx.continues.iter_mut()))
+                .map(|(_, env)| env),
+        );
+        for env in envs {
+            for binding in env.values_mut() {
+                let Binding::Value { value, .. } = binding;
+                if *value == from {
+                    *value = to;
+                }
+            }
+        }
+    }
+
+    /// Binds the variables of `base` at the start of `block`, merged from the
+    /// environments of its predecessors, with a phi wherever they disagree.
+    fn join_envs(&mut self, block: u32, base: &Env, preds: &[(u32, Env)]) -> Env {
+        let mut names: Vec<&String> = base.keys().collect();
+        names.sort();
+        let mut merged = base.clone();
+        for name in names {
+            let Binding::Value {
+                value: base_val,
+                ty,
+            } = &base[name];
+            let incomings: Vec<(u32, u32)> = preds
+                .iter()
+                .map(|(pred, env)| match env.get(name) {
+                    Some(Binding::Value { value, .. }) => (*pred, *value),
+                    None => (*pred, *base_val),
+                })
+                .collect();
+            let first = incomings.first().map(|(_, v)| *v).unwrap_or(*base_val);
+            let value = if incomings.iter().all(|(_, v)| *v == first) {
+                first
+            } else {
+                let dst = self.builder.next_value(self.func_id);
+                self.builder
+                    .emit(self.func_id, block, IrInstr::Phi { dst, incomings });
+                dst
+            };
+            merged.insert(
+                name.clone(),
+                Binding::Value {
+                    value,
+                    ty: ty.clone(),
+                },
+            );
+        }
+        merged
     }
 
     fn lower_block(&mut self, block: &Block) {
@@ -135,7 +575,7 @@ impl<'a> FnLower<'a> {
                 self.lower_expr(expr);
             }
             Stmt::Return { value, .. } => {
-                let val = value.as_ref().and_then(|expr| self.lower_expr(expr));
+                let val = value.as_ref().map(|expr| self.lower_value(expr));
                 self.builder
                     .set_term(self.func_id, self.block_id, IrTerm::Ret { value: val });
                 self.terminated = true;
@@ -147,76 +587,99 @@ impl<'a> FnLower<'a> {
                 self.lower_if(if_stmt);
             }
             Stmt::While {
-                condition, body, ..
+                label,
+                condition,
+                body,
+                ..
             } => {
-                self.lower_while(condition, body);
+                self.lower_while(label.as_deref(), condition, body);
             }
             Stmt::For {
-                var,
+                label,
+                pattern,
                 iterable,
                 body,
                 ..
-            } => {
-                self.lower_for(var, iterable, body);
-            }
+            } => match pattern.binding_name() {
+                Some(var) => self.lower_for(label.as_deref(), var, iterable, body),
+                None => {
+                    self.unsupported::<()>(pattern.span(), "Destructuring `for` bindings");
+                }
+            },
             Stmt::Block(block) => {
                 self.lower_block(block);
             }
-            Stmt::Break(_) => {
-                self.lower_loop_control(true);
+            Stmt::Break { label, value, .. } => {
+                let value = value.as_ref().map(|value| self.lower_value(value));
+                self.lower_loop_control(label.as_deref(), true, value);
+            }
+            Stmt::Continue { label, .. } => {
+                self.lower_loop_control(label.as_deref(), false, None);
+            }
+            Stmt::Switch(switch) if switch.arms.iter().all(|arm| int_pattern(&arm.pattern)) => {
+                self.lower_switch(switch);
+            }
+            Stmt::Switch(switch) => {
+                self.unsupported::<()>(switch.span, "`switch` arms other than integer patterns");
+            }
+            Stmt::Try(try_catch) => {
+                self.unsupported::<()>(try_catch.span, "`try`/`catch` blocks");
+            }
+            Stmt::Unsafe { span, .. } => {
+                self.unsupported::<()>(*span, "`unsafe` blocks");
             }
-            Stmt::Continue(_) => {
-                self.lower_loop_control(false);
+            Stmt::Assembly(asm) => {
+                self.unsupported::<()>(asm.span, "`asm` blocks");
             }
-            _ => {}
         }
     }
 
     fn lower_var_decl(&mut self, decl: &VarDecl) {
-        let init_val = self
-            .lower_expr(&decl.value)
-            .unwrap_or_else(|| self.emit_int(0));
+        let init_val = self.lower_value(&decl.value);
         let ty = decl
             .ty
             .as_ref()
-            .map(ir_type_from_hint)
+            .map(|ty| ir_type_from_hint(ty, self.aliases))
             .or_else(|| self.infer_expr_type(&decl.value))
-            .unwrap_or(IrType::I32);
-        match decl.kind {
-            VarKind::Let => {
-                self.declare_var(
-                    decl.name.clone(),
-                    Binding::Value {
-                        value: init_val,
-                        ty,
-                    },
-                );
+            .unwrap_or(IrType::I64);
+        self.bind_pattern(&decl.pattern, init_val, ty);
+    }
+
+    /// Declares the names `pattern` binds; tuple patterns take their
+    /// elements apart, other destructuring is not lowered.
+    fn bind_pattern(&mut self, pattern: &Pattern, value: u32, ty: IrType) {
+        match pattern {
+            Pattern::Binding { name, .. } => {
+                self.declare_var(name.clone(), Binding::Value { value, ty });
             }
-            VarKind::Var => {
-                self.declare_var(
-                    decl.name.clone(),
-                    Binding::Value {
-                        value: init_val,
-                        ty,
-                    },
-                );
+            Pattern::Tuple { elements, .. } => {
+                for (idx, element) in elements.iter().enumerate() {
+                    let item_ty = match &ty {
+                        IrType::Tuple(items) => items.get(idx).cloned(),
+                        _ => None,
+                    };
+                    let dst = self.builder.next_value(self.func_id);
+                    self.builder.emit(
+                        self.func_id,
+                        self.block_id,
+                        IrInstr::TupleExtract {
+                            dst,
+                            base: value,
+                            idx: idx as u32,
+                        },
+                    );
+                    self.bind_pattern(element, dst, item_ty.unwrap_or(IrType::I64));
+                }
             }
-            VarKind::Const => {
-                self.declare_var(
-                    decl.name.clone(),
-                    Binding::Value {
-                        value: init_val,
-                        ty,
-                    },
-                );
+            Pattern::Wildcard { .. } => {}
+            other => {
+                self.unsupported::<()>(other.span(), "Destructuring patterns other than tuples");
             }
         }
     }
 
     fn lower_if(&mut self, stmt: &IfStmt) {
-        let cond = self
-            .lower_expr(&stmt.condition)
-            .unwrap_or_else(|| self.emit_bool(false));
+        let cond = self.lower_value(&stmt.condition);
         let current_block = self.block_id;
         let then_block = self.builder.new_block(self.func_id);
         let merge_block = self.builder.new_block(self.func_id);
@@ -266,19 +729,36 @@ impl<'a> FnLower<'a> {
     }
 
     fn lower_branch(&mut self, block_id: u32, block: &Block, merge_target: u32) -> BranchResult {
+        self.lower_branch_with(block_id, merge_target, |this| {
+            this.lower_block(block);
+            None
+        })
+    }
+
+    /// Runs `lower` in `block_id` and falls through to `merge_target`, then
+    /// restores the current block and bindings. `lower` returns the value the
+    /// branch produces, if any.
+    fn lower_branch_with(
+        &mut self,
+        block_id: u32,
+        merge_target: u32,
+        lower: impl FnOnce(&mut Self) -> Option<u32>,
+    ) -> BranchResult {
         let saved_block = self.block_id;
         let saved_env = self.env.clone();
         let saved_terminated = self.terminated;
 
         self.block_id = block_id;
         self.terminated = false;
-        self.lower_block(block);
-        let reaches_merge = if let Some(term) = self.builder.block_term(self.func_id, block_id) {
+        let produced = lower(self);
+        // Nested control flow leaves us in a later block than the one we entered.
+        let exit_block = self.block_id;
+        let reaches_merge = if let Some(term) = self.builder.block_term(self.func_id, exit_block) {
             matches!(term, IrTerm::Br { target } if target == merge_target)
         } else {
             self.builder.set_term(
                 self.func_id,
-                block_id,
+                exit_block,
                 IrTerm::Br {
                     target: merge_target,
                 },
@@ -294,8 +774,8 @@ impl<'a> FnLower<'a> {
         BranchResult {
             env: branch_env,
             reaches_merge,
-            exit_block: if reaches_merge { Some(block_id) } else { None },
-            produced: None,
+            exit_block: reaches_merge.then_some(exit_block),
+            produced,
         }
     }
 
@@ -398,9 +878,7 @@ impl<'a> FnLower<'a> {
     }
 
     fn lower_if_expr(&mut self, stmt: &IfStmt) -> Option<u32> {
-        let cond = self
-            .lower_expr(&stmt.condition)
-            .unwrap_or_else(|| self.emit_bool(false));
+        let cond = self.lower_value(&stmt.condition);
         let current_block = self.block_id;
         let then_block = self.builder.new_block(self.func_id);
         let merge_block = self.builder.new_block(self.func_id);
@@ -481,37 +959,7 @@ impl<'a> FnLower<'a> {
         block: &Block,
         merge_target: u32,
     ) -> BranchResult {
-        let saved_block = self.block_id;
-        let saved_env = self.env.clone();
-        let saved_terminated = self.terminated;
-
-        self.block_id = block_id;
-        self.terminated = false;
-        let produced = self.lower_block_value(block);
-        let reaches_merge = if let Some(term) = self.builder.block_term(self.func_id, block_id) {
-            matches!(term, IrTerm::Br { target } if target == merge_target)
-        } else {
-            self.builder.set_term(
-                self.func_id,
-                block_id,
-                IrTerm::Br {
-                    target: merge_target,
-                },
-            );
-            true
-        };
-        let branch_env = self.env.clone();
-
-        self.block_id = saved_block;
-        self.env = saved_env;
-        self.terminated = saved_terminated;
-
-        BranchResult {
-            env: branch_env,
-            reaches_merge,
-            exit_block: if reaches_merge { Some(block_id) } else { None },
-            produced,
-        }
+        self.lower_branch_with(block_id, merge_target, |this| this.lower_block_value(block))
     }
 
     fn lower_block_value(&mut self, block: &Block) -> Option<u32> {
@@ -532,55 +980,124 @@ impl<'a> FnLower<'a> {
         last
     }
 
-    fn lower_while(&mut self, condition: &Expr, body: &Block) {
+    fn lower_while(&mut self, label: Option<&str>, condition: &Expr, body: &Block) {
         let head = self.builder.new_block(self.func_id);
         let loop_body = self.builder.new_block(self.func_id);
         let exit = self.builder.new_block(self.func_id);
 
-        let current = self.block_id;
+        let pre = self.block_id;
         self.builder
-            .set_term(self.func_id, current, IrTerm::Br { target: head });
+            .set_term(self.func_id, pre, IrTerm::Br { target: head });
         self.terminated = true;
 
         // header
         self.block_id = head;
         self.terminated = false;
-        let cond_val = self
-            .lower_expr(condition)
-            .unwrap_or_else(|| self.emit_bool(false));
+        let phis = self.open_loop_phis(head, pre);
+        let cond_val = self.lower_value(condition);
+        let cond_block = self.block_id;
         self.builder.set_term(
             self.func_id,
-            head,
+            cond_block,
             IrTerm::CondBr {
                 cond: cond_val,
                 then_b: loop_body,
                 else_b: exit,
             },
         );
+        let head_env = self.env.clone();
 
         // body
         self.block_id = loop_body;
         self.terminated = false;
-        self.push_loop(exit, head);
+        self.push_loop(label, exit, head);
         self.lower_block(body);
-        self.pop_loop();
+        let ctx = self.pop_loop();
+        let mut back_edges = ctx.continues;
         if !self.builder.block_has_term(self.func_id, self.block_id) {
             self.builder
                 .set_term(self.func_id, self.block_id, IrTerm::Br { target: head });
+            back_edges.push((self.block_id, self.env.clone()));
         }
+        self.close_loop_phis(head, &phis, &back_edges);
 
+        let mut exits = vec![(cond_block, head_env.clone())];
+        exits.extend(ctx.breaks);
         self.block_id = exit;
         self.terminated = false;
+        self.env = self.join_envs(exit, &head_env, &exits);
+        self.remove_trivial_phis(head, &phis);
+    }
+
+    /// A `loop`, whose value is a phi of the `break` values when every
+    /// `break` that leaves it carries one.
+    fn lower_loop(&mut self, label: Option<&str>, body: &Block) -> Option<u32> {
+        let head = self.builder.new_block(self.func_id);
+        let exit = self.builder.new_block(self.func_id);
+
+        let pre = self.block_id;
+        self.builder
+            .set_term(self.func_id, pre, IrTerm::Br { target: head });
+        self.block_id = head;
+        self.terminated = false;
+        let phis = self.open_loop_phis(head, pre);
+        let head_env = self.env.clone();
+
+        self.push_loop(label, exit, head);
+        self.lower_block(body);
+        let ctx = self.pop_loop();
+        let mut back_edges = ctx.continues;
+        if !self.builder.block_has_term(self.func_id, self.block_id) {
+            self.builder
+                .set_term(self.func_id, self.block_id, IrTerm::Br { target: head });
+            back_edges.push((self.block_id, self.env.clone()));
+        }
+        self.close_loop_phis(head, &phis, &back_edges);
+
+        self.block_id = exit;
+        self.env = self.join_envs(exit, &head_env, &ctx.breaks);
+        // The break phi goes in before the trivial header phis are folded so
+        // that the values flowing into it are rewritten along with the rest.
+        let value =
+            (!ctx.breaks.is_empty() && ctx.break_values.len() == ctx.breaks.len()).then(|| {
+                let dst = self.builder.next_value(self.func_id);
+                self.builder.emit(
+                    self.func_id,
+                    exit,
+                    IrInstr::Phi {
+                        dst,
+                        incomings: ctx.break_values,
+                    },
+                );
+                dst
+            });
+        self.remove_trivial_phis(head, &phis);
+        if ctx.breaks.is_empty() {
+            self.builder
+                .set_term(self.func_id, exit, IrTerm::Unreachable);
+            self.terminated = true;
+            return None;
+        }
+        self.terminated = false;
+        value
     }
 
-    fn lower_for(&mut self, var: &str, iterable: &Expr, body: &Block) {
-        if let Some((start_expr, end_expr)) = extract_range(iterable) {
-            let start = self
-                .lower_expr(start_expr)
-                .unwrap_or_else(|| self.emit_int(0));
-            let end = self
-                .lower_expr(end_expr)
-                .unwrap_or_else(|| self.emit_int(0));
+    fn lower_for(&mut self, label: Option<&str>, var: &str, iterable: &Expr, body: &Block) {
+        if let Expr::Range {
+            start: start_expr,
+            end: end_expr,
+            inclusive,
+            step: step_expr,
+            ..
+        } = iterable
+        {
+            let counter_ty = self.operand_type(start_expr, end_expr);
+            let start = self.lower_value(start_expr);
+            let end = self.lower_value(end_expr);
+            let stride = match step_expr {
+                Some(step_expr) => self.lower_value(step_expr),
+                None => self.emit_int(1),
+            };
 
             let head = self.builder.new_block(self.func_id);
             let loop_body = self.builder.new_block(self.func_id);
@@ -603,6 +1120,7 @@ impl<'a> FnLower<'a> {
                     incomings: vec![(pre_block, start)],
                 },
             );
+            let phis = self.open_loop_phis(head, pre_block);
             let cond = self.builder.next_value(self.func_id);
             self.builder.emit(
                 self.func_id,
@@ -611,8 +1129,8 @@ impl<'a> FnLower<'a> {
                     dst: cond,
                     a: idx_val,
                     b: end,
-                    cond: CmpOp::Lt,
-                    ty: IrType::I32,
+                    cond: if *inclusive { CmpOp::Le } else { CmpOp::Lt },
+                    ty: counter_ty.clone(),
                 },
             );
             self.builder.set_term(
@@ -624,6 +1142,7 @@ impl<'a> FnLower<'a> {
                     else_b: exit,
                 },
             );
+            let head_env = self.env.clone();
 
             // body
             self.block_id = loop_body;
@@ -633,63 +1152,213 @@ impl<'a> FnLower<'a> {
                 var.to_string(),
                 Binding::Value {
                     value: idx_val,
-                    ty: IrType::I32,
+                    ty: counter_ty.clone(),
                 },
             );
-            self.push_loop(exit, step);
+            self.push_loop(label, exit, step);
             self.lower_block(body);
-            self.pop_loop();
+            let ctx = self.pop_loop();
+            self.end_scope();
+            let mut step_preds = ctx.continues;
             if !self.builder.block_has_term(self.func_id, self.block_id) {
                 self.builder
                     .set_term(self.func_id, self.block_id, IrTerm::Br { target: step });
+                step_preds.push((self.block_id, self.env.clone()));
             }
-            self.end_scope();
 
             // step / continue block
             self.block_id = step;
             self.terminated = false;
+            self.env = self.join_envs(step, &head_env, &step_preds);
             let next_idx = self.builder.next_value(self.func_id);
-            let one = self.emit_int(1);
             self.builder.emit(
                 self.func_id,
                 self.block_id,
                 IrInstr::Add {
                     dst: next_idx,
                     a: idx_val,
-                    b: one,
-                    ty: IrType::I32,
+                    b: stride,
+                    ty: counter_ty,
                 },
             );
             self.builder
                 .set_term(self.func_id, self.block_id, IrTerm::Br { target: head });
 
-            // patch phi for back-edge
-            if let Some(incomings) = self
-                .builder
-                .block_instrs_mut(self.func_id, head)
-                .iter_mut()
-                .find_map(|i| match i {
-                    IrInstr::Phi { incomings, .. } => Some(incomings),
-                    _ => None,
-                })
-            {
-                incomings.push((step, next_idx));
-            }
+            // patch phis for the back-edge
+            self.add_phi_incomings(head, idx_val, vec![(step, next_idx)]);
+            let back_edges = vec![(step, self.env.clone())];
+            self.close_loop_phis(head, &phis, &back_edges);
 
+            let mut exits = vec![(head, head_env.clone())];
+            exits.extend(ctx.breaks);
             self.block_id = exit;
             self.terminated = false;
+            self.env = self.join_envs(exit, &head_env, &exits);
+            self.remove_trivial_phis(head, &phis);
         } else {
-            // Fallback: do nothing to keep IR valid.
+            self.unsupported::<()>(iterable.span(), "`for` loops over non-range iterables");
+        }
+    }
+
+    /// Lowers a `switch` over integers to a chain of tests, one block per arm,
+    /// joining at a merge block. Only integer literal, range, binding and `_`
+    /// patterns (and `|` of those) get here.
+    fn lower_switch(&mut self, stmt: &SwitchStmt) {
+        let scrutinee = self.lower_value(&stmt.expr);
+        let ty = self.infer_expr_type(&stmt.expr).unwrap_or(IrType::I64);
+        let merge = self.builder.new_block(self.func_id);
+        let pre_env = self.env.clone();
+        let mut exits = Vec::new();
+        let mut falls_through = true;
+        for arm in &stmt.arms {
+            let matched = self.lower_pattern_test(scrutinee, &arm.pattern);
+            let body = self.builder.new_block(self.func_id);
+            // Arms after one that always matches are unreachable.
+            let catch_all = matched.is_none() && arm.guard.is_none();
+            let next = (!catch_all).then(|| self.builder.new_block(self.func_id));
+            let term = match (matched, next) {
+                (Some(cond), Some(next)) => IrTerm::CondBr {
+                    cond,
+                    then_b: body,
+                    else_b: next,
+                },
+                _ => IrTerm::Br { target: body },
+            };
+            self.builder.set_term(self.func_id, self.block_id, term);
+
+            self.block_id = body;
+            self.terminated = false;
+            self.begin_scope();
+            for (name, _) in arm.pattern.bindings() {
+                let binding = Binding::Value {
+                    value: scrutinee,
+                    ty: ty.clone(),
+                };
+                self.declare_var(name.to_string(), binding);
+            }
+            if let (Some(guard), Some(next)) = (&arm.guard, next) {
+                let cond = self.lower_value(guard);
+                let guarded = self.builder.new_block(self.func_id);
+                self.builder.set_term(
+                    self.func_id,
+                    self.block_id,
+                    IrTerm::CondBr {
+                        cond,
+                        then_b: guarded,
+                        else_b: next,
+                    },
+                );
+                self.block_id = guarded;
+            }
+            self.lower_expr(&arm.expr);
+            self.end_scope();
+            if !self.builder.block_has_term(self.func_id, self.block_id) {
+                self.builder
+                    .set_term(self.func_id, self.block_id, IrTerm::Br { target: merge });
+                exits.push((self.block_id, self.env.clone()));
+            }
+            self.env = pre_env.clone();
+            match next {
+                Some(next) => self.block_id = next,
+                None => {
+                    falls_through = false;
+                    break;
+                }
+            }
+        }
+        if falls_through {
+            // No arm matched: the interpreter reports an error, compiled code
+            // carries on after the switch.
+            self.builder
+                .set_term(self.func_id, self.block_id, IrTerm::Br { target: merge });
+            exits.push((self.block_id, pre_env.clone()));
+        }
+        self.block_id = merge;
+        self.terminated = false;
+        self.env = self.join_envs(merge, &pre_env, &exits);
+    }
+
+    /// Emits the test of `value` against `pattern`, or `None` when the
+    /// pattern matches every value.
+    fn lower_pattern_test(&mut self, value: u32, pattern: &Pattern) -> Option<u32> {
+        match pattern {
+            Pattern::Literal(literal) => {
+                let expected = self.emit_int(pattern_int(literal)?);
+                Some(self.emit_cmp(value, expected, CmpOp::Eq))
+            }
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+                ..
+            } => {
+                let low = self.emit_int(pattern_int(start)?);
+                let high = self.emit_int(pattern_int(end)?);
+                let above = self.emit_cmp(value, low, CmpOp::Ge);
+                let op = if *inclusive { CmpOp::Le } else { CmpOp::Lt };
+                let below = self.emit_cmp(value, high, op);
+                Some(self.emit_logic(above, below, true))
+            }
+            Pattern::Or { alternatives, .. } => {
+                let mut tests = Vec::new();
+                for alternative in alternatives {
+                    tests.push(self.lower_pattern_test(value, alternative)?);
+                }
+                tests
+                    .into_iter()
+                    .reduce(|acc, test| self.emit_logic(acc, test, false))
+            }
+            _ => None,
         }
     }
 
-    fn lower_loop_control(&mut self, is_break: bool) {
-        let target = match self.loop_stack.last() {
-            Some(ctx) if is_break => ctx.break_target,
-            Some(ctx) => ctx.continue_target,
-            None => return,
+    fn emit_logic(&mut self, a: u32, b: u32, and: bool) -> u32 {
+        let dst = self.builder.next_value(self.func_id);
+        let instr = if and {
+            IrInstr::And {
+                dst,
+                a,
+                b,
+                ty: IrType::Bool,
+            }
+        } else {
+            IrInstr::Or {
+                dst,
+                a,
+                b,
+                ty: IrType::Bool,
+            }
+        };
+        self.builder.emit(self.func_id, self.block_id, instr);
+        dst
+    }
+
+    /// Jumps to the loop `label` names, or the innermost one; `value` is the
+    /// lowered value of a `break`, if it has one.
+    fn lower_loop_control(&mut self, label: Option<&str>, is_break: bool, value: Option<u32>) {
+        let target = match label {
+            Some(label) => self
+                .loop_stack
+                .iter()
+                .rposition(|ctx| ctx.label.as_deref() == Some(label)),
+            None => self.loop_stack.len().checked_sub(1),
+        };
+        let Some(target) = target else {
+            return;
         };
         if !self.builder.block_has_term(self.func_id, self.block_id) {
+            let edge = (self.block_id, self.env.clone());
+            let ctx = &mut self.loop_stack[target];
+            let target = if is_break {
+                if let Some(value) = value {
+                    ctx.break_values.push((edge.0, value));
+                }
+                ctx.breaks.push(edge);
+                ctx.break_target
+            } else {
+                ctx.continues.push(edge);
+                ctx.continue_target
+            };
             self.builder
                 .set_term(self.func_id, self.block_id, IrTerm::Br { target });
         }
@@ -708,7 +1377,7 @@ impl<'a> FnLower<'a> {
                     IrInstr::LoadConstInt {
                         dst,
                         value: parsed,
-                        ty: IrType::I32,
+                        ty: IrType::I64,
                     },
                 );
                 Some(dst)
@@ -724,135 +1393,266 @@ impl<'a> FnLower<'a> {
                 );
                 Some(dst)
             }
-            Expr::Identifier { name, .. } => self.read_binding(name),
+            Expr::Identifier { name, span } => match self.read_binding(name) {
+                Some(value) => Some(value),
+                None => match self.read_global(name) {
+                    Some(value) => Some(value),
+                    None => {
+                        self.error(*span, format!("`{name}` has no value in native builds"));
+                        None
+                    }
+                },
+            },
+            Expr::Range { start, end, .. } => {
+                // range values are handled by for-loop lowering; treat as tuple (start,end)
+                let start = self.lower_value(start);
+                let end = self.lower_value(end);
+                let dst = self.builder.next_value(self.func_id);
+                self.builder.emit(
+                    self.func_id,
+                    self.block_id,
+                    IrInstr::TupleInit {
+                        dst,
+                        items: vec![start, end],
+                    },
+                );
+                Some(dst)
+            }
             Expr::Binary {
                 left, op, right, ..
             } => {
-                if let BinaryOp::Range = op {
-                    // range values are handled by for-loop lowering; treat as tuple (start,end)
-                    let start = self.lower_expr(left)?;
-                    let end = self.lower_expr(right)?;
-                    let dst = self.builder.next_value(self.func_id);
-                    self.builder.emit(
-                        self.func_id,
-                        self.block_id,
-                        IrInstr::TupleInit {
-                            dst,
-                            items: vec![start, end],
-                        },
-                    );
-                    return Some(dst);
-                }
-                let lhs = self.lower_expr(left)?;
-                let rhs = self.lower_expr(right)?;
-                match op {
-                    BinaryOp::Add => {
-                        let dst = self.builder.next_value(self.func_id);
-                        Some(self.emit_bin_int(IrInstr::Add {
-                            dst,
-                            a: lhs,
-                            b: rhs,
-                            ty: IrType::I32,
-                        }))
-                    }
-                    BinaryOp::Subtract => {
-                        let dst = self.builder.next_value(self.func_id);
-                        Some(self.emit_bin_int(IrInstr::Sub {
-                            dst,
-                            a: lhs,
-                            b: rhs,
-                            ty: IrType::I32,
-                        }))
-                    }
-                    BinaryOp::Multiply => {
-                        let dst = self.builder.next_value(self.func_id);
-                        Some(self.emit_bin_int(IrInstr::Mul {
+                let lhs = self.lower_value(left);
+                let rhs = self.lower_value(right);
+                let ty = self.operand_type(left, right);
+                let dst = self.builder.next_value(self.func_id);
+                let (a, b) = (lhs, rhs);
+                let instr = match op {
+                    BinaryOp::Add => IrInstr::Add { dst, a, b, ty },
+                    BinaryOp::Subtract => IrInstr::Sub { dst, a, b, ty },
+                    BinaryOp::Multiply => IrInstr::Mul { dst, a, b, ty },
+                    BinaryOp::Divide => IrInstr::Div {
+                        dst,
+                        a,
+                        b,
+                        ty,
+                        signed: true,
+                    },
+                    BinaryOp::Modulo => IrInstr::Rem {
+                        dst,
+                        a,
+                        b,
+                        ty,
+                        signed: true,
+                    },
+                    BinaryOp::Equal => IrInstr::Cmp {
+                        dst,
+                        a,
+                        b,
+                        cond: CmpOp::Eq,
+                        ty,
+                    },
+                    BinaryOp::NotEqual => IrInstr::Cmp {
+                        dst,
+                        a,
+                        b,
+                        cond: CmpOp::Ne,
+                        ty,
+                    },
+                    BinaryOp::Less => IrInstr::Cmp {
+                        dst,
+                        a,
+                        b,
+                        cond: CmpOp::Lt,
+                        ty,
+                    },
+                    BinaryOp::LessEqual => IrInstr::Cmp {
+                        dst,
+                        a,
+                        b,
+                        cond: CmpOp::Le,
+                        ty,
+                    },
+                    BinaryOp::Greater => IrInstr::Cmp {
+                        dst,
+                        a,
+                        b,
+                        cond: CmpOp::Gt,
+                        ty,
+                    },
+                    BinaryOp::GreaterEqual => IrInstr::Cmp {
+                        dst,
+                        a,
+                        b,
+                        cond: CmpOp::Ge,
+                        ty,
+                    },
+                    BinaryOp::LogicalAnd => IrInstr::And {
+                        dst,
+                        a,
+                        b,
+                        ty: IrType::Bool,
+                    },
+                    BinaryOp::LogicalOr => IrInstr::Or {
+                        dst,
+                        a,
+                        b,
+                        ty: IrType::Bool,
+                    },
+                    BinaryOp::BitAnd => IrInstr::And { dst, a, b, ty },
+                    BinaryOp::BitOr => IrInstr::Or { dst, a, b, ty },
+                    BinaryOp::BitXor => IrInstr::Xor { dst, a, b, ty },
+                    // The result has the type of the left operand, as in the
+                    // interpreter, and bits shifted past its width are dropped.
+                    BinaryOp::ShiftLeft => {
+                        let ty = self.int_type(left);
+                        let shifted = self.emit_bin_int(IrInstr::Shl {
                             dst,
-                            a: lhs,
-                            b: rhs,
-                            ty: IrType::I32,
-                        }))
+                            a,
+                            b,
+                            ty: ty.clone(),
+                        });
+                        return Some(self.wrap_int(shifted, &ty));
                     }
-                    BinaryOp::Divide => {
-                        let dst = self.builder.next_value(self.func_id);
-                        Some(self.emit_bin_int(IrInstr::Div {
-                            dst,
-                            a: lhs,
-                            b: rhs,
-                            ty: IrType::I32,
-                            signed: true,
-                        }))
+                    // Values are kept extended to 64 bits by their signedness,
+                    // so `>>` only has to pick the matching shift.
+                    BinaryOp::ShiftRight => {
+                        let ty = self.int_type(left);
+                        if is_unsigned(&ty) {
+                            IrInstr::LShr { dst, a, b, ty }
+                        } else {
+                            IrInstr::AShr { dst, a, b, ty }
+                        }
                     }
-                    BinaryOp::Modulo => {
-                        let dst = self.builder.next_value(self.func_id);
-                        Some(self.emit_bin_int(IrInstr::Rem {
+                };
+                Some(self.emit_bin_int(instr))
+            }
+            Expr::Unary {
+                op: UnaryOp::Borrow,
+                span,
+                ..
+            } => self.unsupported(*span, "Borrow expressions"),
+            Expr::Unary { op, expr, .. } => {
+                let val = self.lower_value(expr);
+                let dst = self.builder.next_value(self.func_id);
+                let ty = self.int_type(expr);
+                // `!` flips a bool, `~` every bit of an integer.
+                match op {
+                    UnaryOp::Negate => Some(self.emit_bin_int(IrInstr::Neg { dst, val, ty })),
+                    UnaryOp::Not => Some(self.emit_bin_int(IrInstr::Not {
+                        dst,
+                        val,
+                        ty: IrType::Bool,
+                    })),
+                    UnaryOp::BitNot | UnaryOp::Borrow => {
+                        let flipped = self.emit_bin_int(IrInstr::Not {
                             dst,
-                            a: lhs,
-                            b: rhs,
-                            ty: IrType::I32,
-                            signed: true,
-                        }))
+                            val,
+                            ty: ty.clone(),
+                        });
+                        Some(self.wrap_int(flipped, &ty))
                     }
-                    BinaryOp::Equal => Some(self.emit_cmp(lhs, rhs, CmpOp::Eq)),
-                    BinaryOp::NotEqual => Some(self.emit_cmp(lhs, rhs, CmpOp::Ne)),
-                    BinaryOp::Less => Some(self.emit_cmp(lhs, rhs, CmpOp::Lt)),
-                    BinaryOp::LessEqual => Some(self.emit_cmp(lhs, rhs, CmpOp::Le)),
-                    BinaryOp::Greater => Some(self.emit_cmp(lhs, rhs, CmpOp::Gt)),
-                    BinaryOp::GreaterEqual => Some(self.emit_cmp(lhs, rhs, CmpOp::Ge)),
-                    BinaryOp::LogicalAnd => {
-                        let dst = self.builder.next_value(self.func_id);
-                        self.builder.emit(
-                            self.func_id,
-                            self.block_id,
-                            IrInstr::And {
-                                dst,
-                                a: lhs,
-                                b: rhs,
-                                ty: IrType::Bool,
-                            },
-                        );
-                        Some(dst)
-                    }
-                    BinaryOp::LogicalOr => {
-                        let dst = self.builder.next_value(self.func_id);
-                        self.builder.emit(
-                            self.func_id,
-                            self.block_id,
-                            IrInstr::Or {
-                                dst,
-                                a: lhs,
-                                b: rhs,
-                                ty: IrType::Bool,
-                            },
-                        );
-                        Some(dst)
-                    }
-                    _ => None,
                 }
             }
             Expr::Assignment { target, value, .. } => {
                 if let Expr::Identifier { name, .. } = target.as_ref() {
-                    let val = self.lower_expr(value)?;
-                    self.assign_binding(name, val);
+                    let val = self.lower_value(value);
+                    if self.env.contains_key(name) {
+                        self.assign_binding(name, val);
+                    } else {
+                        self.write_global(name, val);
+                    }
                     Some(val)
                 } else {
-                    None
+                    self.unsupported(target.span(), "Assignments to fields and elements")
                 }
             }
             Expr::MethodCall {
                 object,
                 method,
                 args,
-                ..
+                span,
             } => {
                 if method == "info" && is_log_object(object) {
                     self.emit_log_info(args);
+                    return None;
                 }
-                None
+                self.unsupported(*span, "Method calls other than `log.info`")
             }
+            Expr::Call { callee, args, .. } => self.lower_call(callee, args),
             Expr::If(stmt) => self.lower_if_expr(stmt),
-            _ => None,
+            Expr::Interpolated { parts, .. } => self.lower_interpolation(parts),
+            Expr::Loop { label, body, .. } => self.lower_loop(label.as_deref(), body),
+            other => self.unsupported(other.span(), unsupported_expr(other)),
+        }
+    }
+
+    /// Joins the parts left to right with `StringConcat`, starting from an
+    /// empty string when the literal opens with an expression.
+    fn lower_interpolation(&mut self, parts: &[InterpolationPart]) -> Option<u32> {
+        let mut joined = None;
+        for part in parts {
+            let piece = match part {
+                InterpolationPart::Text(text) => self.emit_str(text),
+                InterpolationPart::Expr(expr) => self.lower_value(expr),
+            };
+            joined = Some(match (joined, part) {
+                (Some(prefix), _) => self.emit_concat(prefix, piece),
+                (None, InterpolationPart::Text(_)) => piece,
+                (None, InterpolationPart::Expr(_)) => {
+                    let empty = self.emit_str("");
+                    self.emit_concat(empty, piece)
+                }
+            });
+        }
+        joined
+    }
+
+    fn emit_concat(&mut self, left: u32, right: u32) -> u32 {
+        let dst = self.builder.next_value(self.func_id);
+        self.builder.emit(
+            self.func_id,
+            self.block_id,
+            IrInstr::CallIntrinsic {
+                dst: Some(dst),
+                intrinsic: IrIntrinsic::StringConcat,
+                args: vec![left, right],
+            },
+        );
+        dst
+    }
+
+    fn lower_call(&mut self, callee: &Expr, args: &[Expr]) -> Option<u32> {
+        let Expr::Identifier { name, span } = callee else {
+            return self.unsupported(callee.span(), "Calls through function values");
+        };
+        let Some(sig) = self.functions.get(name) else {
+            // `print` behaves like `log.info` unless a user function shadows it.
+            if name == "print" {
+                self.emit_log_info(args);
+                return None;
+            }
+            self.error(
+                *span,
+                format!("`{name}` is not a function native builds can call"),
+            );
+            return None;
+        };
+        let (func, returns) = (sig.index, sig.ret != IrType::Void);
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.lower_value(arg));
         }
+        let dst = returns.then(|| self.builder.next_value(self.func_id));
+        self.builder.emit(
+            self.func_id,
+            self.block_id,
+            IrInstr::Call {
+                dst,
+                func,
+                args: values,
+            },
+        );
+        dst
     }
 
     fn emit_cmp(&mut self, a: u32, b: u32, cond: CmpOp) -> u32 {
@@ -865,7 +1665,7 @@ impl<'a> FnLower<'a> {
                 a,
                 b,
                 cond,
-                ty: IrType::I32,
+                ty: IrType::I64,
             },
         );
         dst
@@ -895,7 +1695,7 @@ impl<'a> FnLower<'a> {
             IrInstr::LoadConstInt {
                 dst,
                 value,
-                ty: IrType::I32,
+                ty: IrType::I64,
             },
         );
         dst
@@ -908,43 +1708,76 @@ impl<'a> FnLower<'a> {
                 self.emit_print_literal(" ");
             }
             first = false;
-            if let Expr::Literal(Literal::String { value, .. }) = arg {
-                self.emit_print_literal(value);
-                continue;
-            }
-            if let Some((value, ty)) = self.lower_printable_value(arg) {
-                self.builder.emit(
-                    self.func_id,
-                    self.block_id,
-                    IrInstr::PrintValue { value, ty },
-                );
+            match arg {
+                Expr::Literal(Literal::String { value, .. }) => self.emit_print_literal(value),
+                // Printed piecewise, so no string has to be built at runtime.
+                Expr::Interpolated { parts, .. } => {
+                    for part in parts {
+                        match part {
+                            InterpolationPart::Text(text) => self.emit_print_literal(text),
+                            InterpolationPart::Expr(expr) => self.emit_print_value(expr),
+                        }
+                    }
+                }
+                _ => self.emit_print_value(arg),
             }
         }
         self.emit_print_literal("\n");
     }
 
+    fn emit_print_value(&mut self, expr: &Expr) {
+        let Some(ty) = self.infer_expr_type(expr) else {
+            self.error(
+                expr.span(),
+                "`log.info` cannot print this argument in native builds",
+            );
+            return;
+        };
+        let value = self.lower_value(expr);
+        self.builder.emit(
+            self.func_id,
+            self.block_id,
+            IrInstr::PrintValue { value, ty },
+        );
+    }
+
+    fn emit_str(&mut self, text: &str) -> u32 {
+        let sid = self.builder.intern_string(text.to_string());
+        let dst = self.builder.next_value(self.func_id);
+        self.builder.emit(
+            self.func_id,
+            self.block_id,
+            IrInstr::LoadConstStr { dst, sid },
+        );
+        dst
+    }
+
     fn emit_print_literal(&mut self, text: &str) {
         let sid = self.builder.intern_string(text.to_string());
         self.builder
             .emit(self.func_id, self.block_id, IrInstr::PrintStr { sid });
     }
 
-    fn lower_printable_value(&mut self, expr: &Expr) -> Option<(u32, IrType)> {
-        let ty = self.infer_expr_type(expr)?;
-        let value = self.lower_expr(expr)?;
-        Some((value, ty))
-    }
-
     fn infer_expr_type(&self, expr: &Expr) -> Option<IrType> {
         match expr {
-            Expr::Literal(Literal::Integer { .. }) => Some(IrType::I32),
+            Expr::Literal(Literal::Integer { .. }) => Some(IrType::I64),
             Expr::Literal(Literal::Bool { .. }) => Some(IrType::Bool),
-            Expr::Literal(Literal::String { .. }) => Some(IrType::Str),
+            Expr::Literal(Literal::String { .. }) | Expr::Interpolated { .. } => Some(IrType::Str),
             Expr::Identifier { name, .. } => match self.env.get(name) {
                 Some(Binding::Value { ty, .. }) => Some(ty.clone()),
+                None => self.globals.get(name).map(|global| global.ty.clone()),
+            },
+            Expr::Call { callee, .. } => match callee.as_ref() {
+                Expr::Identifier { name, .. } => self
+                    .functions
+                    .get(name)
+                    .map(|sig| sig.ret.clone())
+                    .filter(|ty| *ty != IrType::Void),
                 _ => None,
             },
-            Expr::Binary { op, .. } => match op {
+            Expr::Binary {
+                left, op, right, ..
+            } => match op {
                 BinaryOp::Equal
                 | BinaryOp::NotEqual
                 | BinaryOp::Less
@@ -953,18 +1786,126 @@ impl<'a> FnLower<'a> {
                 | BinaryOp::GreaterEqual
                 | BinaryOp::LogicalAnd
                 | BinaryOp::LogicalOr => Some(IrType::Bool),
-                _ => Some(IrType::I32),
+                BinaryOp::ShiftLeft | BinaryOp::ShiftRight => Some(self.int_type(left)),
+                _ => Some(self.operand_type(left, right)),
+            },
+            Expr::Unary { op, expr, .. } => match op {
+                UnaryOp::Not => Some(IrType::Bool),
+                UnaryOp::Negate | UnaryOp::BitNot => Some(self.int_type(expr)),
+                UnaryOp::Borrow => None,
             },
+            Expr::Assignment { value, .. } => self.infer_expr_type(value),
             _ => None,
         }
     }
 
+    /// The type `expr` computes in, `I64` (the type of integer literals)
+    /// when nothing narrower is known.
+    fn int_type(&self, expr: &Expr) -> IrType {
+        self.infer_expr_type(expr).unwrap_or(IrType::I64)
+    }
+
+    /// The type a binary operation on `left` and `right` computes in. An
+    /// integer literal takes the type of the other side, as in the
+    /// interpreter.
+    fn operand_type(&self, left: &Expr, right: &Expr) -> IrType {
+        if is_int_literal(left) {
+            self.int_type(right)
+        } else {
+            self.int_type(left)
+        }
+    }
+
+    /// Truncates `value` back to the width of `ty`, re-extending it by the
+    /// type's signedness. 64-bit types already wrap in the register.
+    fn wrap_int(&mut self, value: u32, ty: &IrType) -> u32 {
+        let bits = match ty {
+            IrType::I8 | IrType::U8 => 8,
+            IrType::I16 | IrType::U16 => 16,
+            IrType::I32 | IrType::U32 => 32,
+            _ => return value,
+        };
+        if is_unsigned(ty) {
+            let mask = self.emit_int((1i128 << bits) - 1);
+            let dst = self.builder.next_value(self.func_id);
+            return self.emit_bin_int(IrInstr::And {
+                dst,
+                a: value,
+                b: mask,
+                ty: ty.clone(),
+            });
+        }
+        let spare = self.emit_int(64 - bits);
+        let dst = self.builder.next_value(self.func_id);
+        let high = self.emit_bin_int(IrInstr::Shl {
+            dst,
+            a: value,
+            b: spare,
+            ty: IrType::I64,
+        });
+        let dst = self.builder.next_value(self.func_id);
+        self.emit_bin_int(IrInstr::AShr {
+            dst,
+            a: high,
+            b: spare,
+            ty: ty.clone(),
+        })
+    }
+
     fn read_binding(&mut self, name: &str) -> Option<u32> {
         match self.env.get(name)? {
             Binding::Value { value, .. } => Some(*value),
         }
     }
 
+    /// Loads a global that no local binding shadows.
+    fn read_global(&mut self, name: &str) -> Option<u32> {
+        let globals = self.globals;
+        let global = globals.get(name)?;
+        if global.mutable {
+            let dst = self.builder.next_value(self.func_id);
+            self.builder.emit(
+                self.func_id,
+                self.block_id,
+                IrInstr::LoadGlobal {
+                    dst,
+                    global: global.id,
+                    ty: global.ty.clone(),
+                },
+            );
+            return Some(dst);
+        }
+        match &global.value {
+            ConstValue::Int(value) => Some(self.emit_int(*value)),
+            ConstValue::Bool(value) => Some(self.emit_bool(*value)),
+            ConstValue::Str(text) => {
+                let sid = self.builder.intern_string(text.clone());
+                let dst = self.builder.next_value(self.func_id);
+                self.builder.emit(
+                    self.func_id,
+                    self.block_id,
+                    IrInstr::LoadConstStr { dst, sid },
+                );
+                Some(dst)
+            }
+        }
+    }
+
+    fn write_global(&mut self, name: &str, value: u32) {
+        let globals = self.globals;
+        if let Some(global) = globals.get(name).filter(|global| global.mutable) {
+            self.builder.emit(
+                self.func_id,
+                self.block_id,
+                IrInstr::StoreGlobal {
+                    global: global.id,
+                    value,
+                    ty: global.ty.clone(),
+                },
+            );
+        }
+    }
+
     fn assign_binding(&mut self, name: &str, value: u32) {
         if let Some(binding) = self.env.get_mut(name) {
             match binding {
@@ -976,24 +1917,28 @@ impl<'a> FnLower<'a> {
     }
 }
 
-fn extract_range(expr: &Expr) -> Option<(&Expr, &Expr)> {
-    if let Expr::Binary {
-        left,
-        op: BinaryOp::Range,
-        right,
-        ..
-    } = expr
-    {
-        Some((left, right))
-    } else {
-        None
+/// Patterns a `switch` over integers can be lowered with.
+fn int_pattern(pattern: &Pattern) -> bool {
+    match pattern {
+        Pattern::Wildcard { .. } | Pattern::Binding { .. } => true,
+        Pattern::Literal(literal) => pattern_int(literal).is_some(),
+        Pattern::Range { start, end, .. } => {
+            pattern_int(start).is_some() && pattern_int(end).is_some()
+        }
+        Pattern::Or { alternatives, .. } => alternatives.iter().all(int_pattern),
+        _ => false,
+    }
+}
+
+fn pattern_int(literal: &Literal) -> Option<i128> {
+    match literal {
+        Literal::Integer { value, .. } => value.replace('_', "").parse().ok(),
+        _ => None,
     }
 }
 
 fn build_else_block(stmt: &IfStmt) -> Option<Block> {
-    if let Some(block) = &stmt.else_branch {
-        Some(block.clone())
-    } else if let Some((cond, block)) = stmt.else_if.first() {
+    if let Some((cond, block)) = stmt.else_if.first() {
         let nested = IfStmt {
             condition: cond.clone(),
             then_branch: block.clone(),
@@ -1006,7 +1951,44 @@ fn build_else_block(stmt: &IfStmt) -> Option<Block> {
             span: block.span,
         })
     } else {
-        None
+        stmt.else_branch.clone()
+    }
+}
+
+fn is_int_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(Literal::Integer { .. }) => true,
+        Expr::Unary {
+            op: UnaryOp::Negate,
+            expr,
+            ..
+        } => is_int_literal(expr),
+        _ => false,
+    }
+}
+
+fn is_unsigned(ty: &IrType) -> bool {
+    matches!(ty, IrType::U8 | IrType::U16 | IrType::U32 | IrType::U64)
+}
+
+/// How `unsupported` names an expression `lower_expr` has no lowering for.
+fn unsupported_expr(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Literal(Literal::Float { .. }) => "Float literals",
+        Expr::Literal(Literal::Char { .. }) => "Char literals",
+        Expr::Access { .. } => "Field accesses",
+        Expr::Await { .. } => "`await` expressions",
+        Expr::StructLiteral { .. } => "Struct literals",
+        Expr::ArrayLiteral { .. } => "Array literals",
+        Expr::TupleLiteral { .. } => "Tuples",
+        Expr::Cast { .. } => "`as` casts",
+        Expr::Block(_) => "Block expressions",
+        Expr::Try { .. } => "`?` operators",
+        Expr::Lambda(_) => "Closures",
+        Expr::Index { .. } => "Index expressions",
+        Expr::Check(_) => "`check` expressions",
+        Expr::Let { .. } => "`if let` and `while let` conditions",
+        _ => "Expressions of this kind",
     }
 }
 
@@ -1018,28 +2000,29 @@ fn is_log_object(expr: &Expr) -> bool {
     }
 }
 
-fn ir_type_from_hint(_ty: &crate::ast::TypeExpr) -> IrType {
-    use crate::ast::TypeExpr;
-
+fn ir_type_from_hint(_ty: &TypeExpr, aliases: &AliasTable) -> IrType {
     match _ty {
         TypeExpr::Named(named) => {
-            let last = named.segments.last().map(|s| s.name.as_str());
-            match last {
+            let last = named.segments.last();
+            if let Some(segment) = last {
+                if let Some(alias) = aliases.get(segment.name.as_str()) {
+                    return ir_type_from_hint(&alias.instantiate(&segment.generics), aliases);
+                }
+            }
+            match last.map(|s| s.name.as_str()) {
                 Some("str") | Some("string") => IrType::Str,
                 Some("bool") => IrType::Bool,
-                Some("i8")
-                | Some("i16")
-                | Some("i32")
-                | Some("i64")
-                | Some("i128")
-                | Some("u8")
-                | Some("u16")
-                | Some("u32")
-                | Some("u64")
-                | Some("u128") => IrType::I32,
-                _ => IrType::I32,
+                Some("i8") => IrType::I8,
+                Some("i16") => IrType::I16,
+                Some("i32") => IrType::I32,
+                Some("u8") => IrType::U8,
+                Some("u16") => IrType::U16,
+                Some("u32") => IrType::U32,
+                // Native values are 64 bits wide; 128-bit ones are truncated.
+                Some("u64") | Some("u128") => IrType::U64,
+                _ => IrType::I64,
             }
         }
-        _ => IrType::I32,
+        _ => IrType::I64,
     }
 }
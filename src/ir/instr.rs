@@ -126,6 +126,18 @@ pub enum IrInstr {
         ptr: u32,
         ty: IrType,
     },
+    /// Reads `IrModule::globals[global]`.
+    LoadGlobal {
+        dst: u32,
+        global: u32,
+        ty: IrType,
+    },
+    /// Writes `value` to `IrModule::globals[global]`, which must be mutable.
+    StoreGlobal {
+        global: u32,
+        value: u32,
+        ty: IrType,
+    },
     Gep {
         dst: u32,
         base: u32,
@@ -412,6 +424,7 @@ impl IrInstr {
             | IrInstr::Undef { dst, .. }
             | IrInstr::Alloca { dst, .. }
             | IrInstr::Load { dst, .. }
+            | IrInstr::LoadGlobal { dst, .. }
             | IrInstr::Gep { dst, .. }
             | IrInstr::PtrCast { dst, .. }
             | IrInstr::Add { dst, .. }
@@ -456,6 +469,7 @@ impl IrInstr {
             | IrInstr::AddI32 { dst, .. }
             | IrInstr::CmpEq { dst, .. } => Some(*dst),
             IrInstr::Store { .. }
+            | IrInstr::StoreGlobal { .. }
             | IrInstr::ArraySet { .. }
             | IrInstr::Call { dst: None, .. }
             | IrInstr::CallIntrinsic { dst: None, .. }
@@ -467,6 +481,160 @@ impl IrInstr {
             | IrInstr::PrintValue { .. } => None,
         }
     }
+
+    /// Values read by this instruction, in operand order. Phi incomings are
+    /// included even though they are read on the incoming edges.
+    pub fn operands(&self) -> Vec<u32> {
+        match self {
+            IrInstr::LoadConstInt { .. }
+            | IrInstr::LoadConstFloat { .. }
+            | IrInstr::LoadConstBool { .. }
+            | IrInstr::LoadConstStr { .. }
+            | IrInstr::Undef { .. }
+            | IrInstr::Alloca { .. }
+            | IrInstr::LoadConstI32 { .. }
+            | IrInstr::LoadGlobal { .. }
+            | IrInstr::PrintStr { .. } => Vec::new(),
+            IrInstr::Load { ptr, .. } | IrInstr::HeapFree { ptr } => vec![*ptr],
+            IrInstr::Store { src, ptr, .. } => vec![*src, *ptr],
+            IrInstr::StoreGlobal { value, .. } => vec![*value],
+            IrInstr::Gep { base, indices, .. } => std::iter::once(*base)
+                .chain(indices.iter().filter_map(|idx| match idx {
+                    GepIndex::Value(v) => Some(*v),
+                    GepIndex::Const(_) => None,
+                }))
+                .collect(),
+            IrInstr::PtrCast { src, .. } => vec![*src],
+            IrInstr::Add { a, b, .. }
+            | IrInstr::Sub { a, b, .. }
+            | IrInstr::Mul { a, b, .. }
+            | IrInstr::Div { a, b, .. }
+            | IrInstr::Rem { a, b, .. }
+            | IrInstr::FAdd { a, b, .. }
+            | IrInstr::FSub { a, b, .. }
+            | IrInstr::FMul { a, b, .. }
+            | IrInstr::FDiv { a, b, .. }
+            | IrInstr::FRem { a, b, .. }
+            | IrInstr::And { a, b, .. }
+            | IrInstr::Or { a, b, .. }
+            | IrInstr::Xor { a, b, .. }
+            | IrInstr::Shl { a, b, .. }
+            | IrInstr::LShr { a, b, .. }
+            | IrInstr::AShr { a, b, .. }
+            | IrInstr::Cmp { a, b, .. }
+            | IrInstr::AddI32 { a, b, .. }
+            | IrInstr::CmpEq { a, b, .. } => vec![*a, *b],
+            IrInstr::Neg { val, .. } | IrInstr::FNeg { val, .. } | IrInstr::Not { val, .. } => {
+                vec![*val]
+            }
+            IrInstr::Select {
+                cond,
+                then_v,
+                else_v,
+                ..
+            } => vec![*cond, *then_v, *else_v],
+            IrInstr::StructInit { fields, .. } => fields.clone(),
+            IrInstr::StructExtract { base, .. } | IrInstr::TupleExtract { base, .. } => {
+                vec![*base]
+            }
+            IrInstr::StructInsert { base, value, .. } => vec![*base, *value],
+            IrInstr::TupleInit { items, .. } => items.clone(),
+            IrInstr::ArrayInit { elems, .. } => elems.clone(),
+            IrInstr::ArrayGet { base, index, .. } => vec![*base, *index],
+            IrInstr::ArraySet {
+                base, index, value, ..
+            } => vec![*base, *index, *value],
+            IrInstr::SliceFromArray { base, len, .. } => vec![*base, *len],
+            IrInstr::Call { args, .. }
+            | IrInstr::CallIntrinsic { args, .. }
+            | IrInstr::CallExtern { args, .. } => args.clone(),
+            IrInstr::MakeClosure { env, .. } | IrInstr::LoadCapture { env, .. } => vec![*env],
+            IrInstr::Await { fut, .. } => vec![*fut],
+            IrInstr::HeapAlloc { size, align, .. } => vec![*size, *align],
+            IrInstr::MemCopy { dst, src, len } => vec![*dst, *src, *len],
+            IrInstr::MemSet { dst, value, len } => vec![*dst, *value, *len],
+            IrInstr::MemZero { len, .. } => vec![*len],
+            IrInstr::PrintValue { value, .. } => vec![*value],
+            IrInstr::Phi { incomings, .. } => incomings.iter().map(|(_, v)| *v).collect(),
+        }
+    }
+
+    /// Mutable references to the values read by this instruction, in the same
+    /// order as [`IrInstr::operands`].
+    pub fn operands_mut(&mut self) -> Vec<&mut u32> {
+        match self {
+            IrInstr::LoadConstInt { .. }
+            | IrInstr::LoadConstFloat { .. }
+            | IrInstr::LoadConstBool { .. }
+            | IrInstr::LoadConstStr { .. }
+            | IrInstr::Undef { .. }
+            | IrInstr::Alloca { .. }
+            | IrInstr::LoadConstI32 { .. }
+            | IrInstr::LoadGlobal { .. }
+            | IrInstr::PrintStr { .. } => Vec::new(),
+            IrInstr::Load { ptr, .. } | IrInstr::HeapFree { ptr } => vec![ptr],
+            IrInstr::Store { src, ptr, .. } => vec![src, ptr],
+            IrInstr::StoreGlobal { value, .. } => vec![value],
+            IrInstr::Gep { base, indices, .. } => std::iter::once(base)
+                .chain(indices.iter_mut().filter_map(|idx| match idx {
+                    GepIndex::Value(v) => Some(v),
+                    GepIndex::Const(_) => None,
+                }))
+                .collect(),
+            IrInstr::PtrCast { src, .. } => vec![src],
+            IrInstr::Add { a, b, .. }
+            | IrInstr::Sub { a, b, .. }
+            | IrInstr::Mul { a, b, .. }
+            | IrInstr::Div { a, b, .. }
+            | IrInstr::Rem { a, b, .. }
+            | IrInstr::FAdd { a, b, .. }
+            | IrInstr::FSub { a, b, .. }
+            | IrInstr::FMul { a, b, .. }
+            | IrInstr::FDiv { a, b, .. }
+            | IrInstr::FRem { a, b, .. }
+            | IrInstr::And { a, b, .. }
+            | IrInstr::Or { a, b, .. }
+            | IrInstr::Xor { a, b, .. }
+            | IrInstr::Shl { a, b, .. }
+            | IrInstr::LShr { a, b, .. }
+            | IrInstr::AShr { a, b, .. }
+            | IrInstr::Cmp { a, b, .. }
+            | IrInstr::AddI32 { a, b, .. }
+            | IrInstr::CmpEq { a, b, .. } => vec![a, b],
+            IrInstr::Neg { val, .. } | IrInstr::FNeg { val, .. } | IrInstr::Not { val, .. } => {
+                vec![val]
+            }
+            IrInstr::Select {
+                cond,
+                then_v,
+                else_v,
+                ..
+            } => vec![cond, then_v, else_v],
+            IrInstr::StructInit { fields, .. } => fields.iter_mut().collect(),
+            IrInstr::StructExtract { base, .. } | IrInstr::TupleExtract { base, .. } => {
+                vec![base]
+            }
+            IrInstr::StructInsert { base, value, .. } => vec![base, value],
+            IrInstr::TupleInit { items, .. } => items.iter_mut().collect(),
+            IrInstr::ArrayInit { elems, .. } => elems.iter_mut().collect(),
+            IrInstr::ArrayGet { base, index, .. } => vec![base, index],
+            IrInstr::ArraySet {
+                base, index, value, ..
+            } => vec![base, index, value],
+            IrInstr::SliceFromArray { base, len, .. } => vec![base, len],
+            IrInstr::Call { args, .. }
+            | IrInstr::CallIntrinsic { args, .. }
+            | IrInstr::CallExtern { args, .. } => args.iter_mut().collect(),
+            IrInstr::MakeClosure { env, .. } | IrInstr::LoadCapture { env, .. } => vec![env],
+            IrInstr::Await { fut, .. } => vec![fut],
+            IrInstr::HeapAlloc { size, align, .. } => vec![size, align],
+            IrInstr::MemCopy { dst, src, len } => vec![dst, src, len],
+            IrInstr::MemSet { dst, value, len } => vec![dst, value, len],
+            IrInstr::MemZero { len, .. } => vec![len],
+            IrInstr::PrintValue { value, .. } => vec![value],
+            IrInstr::Phi { incomings, .. } => incomings.iter_mut().map(|(_, v)| v).collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -495,6 +663,45 @@ pub enum IrTerm {
     },
 }
 
+impl IrTerm {
+    /// Values read by this terminator.
+    pub fn operands(&self) -> Vec<u32> {
+        match self {
+            IrTerm::Ret { value } => value.iter().copied().collect(),
+            IrTerm::Br { .. } | IrTerm::Unreachable => Vec::new(),
+            IrTerm::CondBr { cond, .. } => vec![*cond],
+            IrTerm::SwitchInt { scrutinee, .. } => vec![*scrutinee],
+            IrTerm::Invoke { call, .. } => call.operands(),
+        }
+    }
+
+    /// Mutable references to the values read by this terminator.
+    pub fn operands_mut(&mut self) -> Vec<&mut u32> {
+        match self {
+            IrTerm::Ret { value } => value.iter_mut().collect(),
+            IrTerm::Br { .. } | IrTerm::Unreachable => Vec::new(),
+            IrTerm::CondBr { cond, .. } => vec![cond],
+            IrTerm::SwitchInt { scrutinee, .. } => vec![scrutinee],
+            IrTerm::Invoke { call, .. } => call.operands_mut(),
+        }
+    }
+
+    /// Blocks this terminator can transfer control to.
+    pub fn successors(&self) -> Vec<u32> {
+        match self {
+            IrTerm::Ret { .. } | IrTerm::Unreachable => Vec::new(),
+            IrTerm::Br { target } => vec![*target],
+            IrTerm::CondBr { then_b, else_b, .. } => vec![*then_b, *else_b],
+            IrTerm::SwitchInt { cases, default, .. } => cases
+                .iter()
+                .map(|(_, block)| *block)
+                .chain(std::iter::once(*default))
+                .collect(),
+            IrTerm::Invoke { normal, unwind, .. } => vec![*normal, *unwind],
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IrBlock {
     pub id: u32,
@@ -535,11 +742,16 @@ impl IrFunction {
     }
 
     pub fn new_block(&mut self) -> u32 {
-        let id = self.blocks.len() as u32;
+        // Passes may delete blocks, so ids can have gaps.
+        let id = self.blocks.iter().map(|b| b.id + 1).max().unwrap_or(0);
         self.blocks.push(IrBlock::new(id));
         id
     }
 
+    pub fn block(&self, id: u32) -> Option<&IrBlock> {
+        self.blocks.iter().find(|b| b.id == id)
+    }
+
     pub fn block_mut(&mut self, id: u32) -> &mut IrBlock {
         self.blocks
             .iter_mut()
@@ -552,6 +764,22 @@ impl IrFunction {
         self.next_value += 1;
         id
     }
+
+    /// Rewrites every use of `from` in the function to read `to` instead.
+    pub fn replace_uses(&mut self, from: u32, to: u32) {
+        for block in &mut self.blocks {
+            let operands = block
+                .body
+                .iter_mut()
+                .flat_map(|instr| instr.operands_mut())
+                .chain(block.term.iter_mut().flat_map(|term| term.operands_mut()));
+            for operand in operands {
+                if *operand == from {
+                    *operand = to;
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -581,10 +809,21 @@ pub struct IrModule {
 impl fmt::Display for IrModule {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for g in &self.globals {
-            writeln!(f, "@{} : {:?} = {:?}", g.name, g.ty, g.init)?;
+            let mutability = if g.mutable { "mut " } else { "" };
+            write!(f, "@{} : {}{:?} = ", g.name, mutability, g.ty)?;
+            match &g.init {
+                GlobalInit::Zeroed => writeln!(f, "zeroed")?,
+                GlobalInit::Bytes(bytes) => writeln!(f, "bytes {:?}", bytes)?,
+                GlobalInit::Const { value, ty } => writeln!(f, "const {:?} {}", ty, value)?,
+                GlobalInit::FromString(sid) => {
+                    let text = self.strings.get(*sid as usize).map(|s| s.as_str());
+                    writeln!(f, "string \"{}\"", text.unwrap_or("").escape_default())?
+                }
+            }
         }
         for func in &self.funcs {
-            writeln!(f, "fn {}(", func.name)?;
+            let prefix = if func.is_async { "async " } else { "" };
+            writeln!(f, "{}fn {}(", prefix, func.name)?;
             for (idx, p) in func.params.iter().enumerate() {
                 writeln!(f, "  %arg{}: {:?}", idx, p)?;
             }
@@ -606,6 +845,14 @@ impl fmt::Display for IrModule {
 
 struct InstrFmt<'a>(&'a IrInstr, &'a IrModule);
 
+/// Globals are referenced by name, or by index when the id is out of range.
+fn write_global_ref(f: &mut fmt::Formatter<'_>, module: &IrModule, global: u32) -> fmt::Result {
+    match module.globals.get(global as usize) {
+        Some(g) => write!(f, "@{}", g.name),
+        None => write!(f, "@{}", global),
+    }
+}
+
 impl<'a> fmt::Display for InstrFmt<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let module = self.1;
@@ -631,6 +878,15 @@ impl<'a> fmt::Display for InstrFmt<'a> {
             IrInstr::Alloca { dst, ty } => write!(f, "%{dst} = alloca {:?}", ty),
             IrInstr::Load { dst, ptr, ty } => write!(f, "%{dst} = load {:?}, %{ptr}", ty),
             IrInstr::Store { src, ptr, ty } => write!(f, "store {:?} %{src}, %{ptr}", ty),
+            IrInstr::LoadGlobal { dst, global, ty } => {
+                write!(f, "%{dst} = load_global {:?} ", ty)?;
+                write_global_ref(f, module, *global)
+            }
+            IrInstr::StoreGlobal { global, value, ty } => {
+                write!(f, "store_global {:?} ", ty)?;
+                write_global_ref(f, module, *global)?;
+                write!(f, ", %{value}")
+            }
             IrInstr::Gep { dst, base, indices } => {
                 let idxs: Vec<String> = indices
                     .iter()
@@ -720,14 +976,23 @@ impl<'a> fmt::Display for InstrFmt<'a> {
                 display_list(elems)
             ),
             IrInstr::ArrayGet {
-                dst, base, index, ..
-            } => write!(f, "%{dst} = array_get %{base}, %{index}"),
+                dst,
+                base,
+                index,
+                elem_ty,
+            } => write!(f, "%{dst} = array_get {:?} %{base}, %{index}", elem_ty),
             IrInstr::ArraySet {
-                base, index, value, ..
-            } => write!(f, "array_set %{base}, %{index}, %{value}"),
-            IrInstr::SliceFromArray { dst, base, len, .. } => {
-                write!(f, "%{dst} = slice_from_array %{base}, %{len}")
-            }
+                base,
+                index,
+                value,
+                elem_ty,
+            } => write!(f, "array_set {:?} %{base}, %{index}, %{value}", elem_ty),
+            IrInstr::SliceFromArray {
+                dst,
+                base,
+                len,
+                elem_ty,
+            } => write!(f, "%{dst} = slice_from_array {:?} %{base}, %{len}", elem_ty),
             IrInstr::Call { dst, func, args } => {
                 if let Some(d) = dst {
                     write!(f, "%{d} = call %{func}({})", display_list(args))
@@ -776,8 +1041,8 @@ impl<'a> fmt::Display for InstrFmt<'a> {
             IrInstr::MakeClosure { dst, func, env } => {
                 write!(f, "%{dst} = make_closure func#{func}, %{env}")
             }
-            IrInstr::LoadCapture { dst, env, idx, .. } => {
-                write!(f, "%{dst} = load_capture %{env}, {}", idx)
+            IrInstr::LoadCapture { dst, env, idx, ty } => {
+                write!(f, "%{dst} = load_capture {:?} %{env}, {}", ty, idx)
             }
             IrInstr::Await { dst, fut } => write!(f, "%{dst} = await %{fut}"),
             IrInstr::HeapAlloc { dst, size, align } => {
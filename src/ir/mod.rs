@@ -1,12 +1,18 @@
 pub mod builder;
 pub mod convert;
 pub mod instr;
+pub mod parse;
+pub mod passes;
 pub mod types;
+pub mod verify;
 
 pub use builder::IrBuilder;
-pub use convert::{build_ir, format_ir};
+pub use convert::{build_ir, format_ir, IrBuildError};
 pub use instr::{
     CmpOp, GepIndex, GlobalInit, IrBlock, IrFunction, IrGlobal, IrInstr, IrIntrinsic, IrModule,
     IrTerm,
 };
+pub use parse::{parse_ir, IrParseError};
+pub use passes::PassManager;
 pub use types::IrType;
+pub use verify::{verify_module, VerifyError};
@@ -0,0 +1,1080 @@
+//! Reads the textual IR printed by `format_ir` back into an `IrModule`.
+//!
+//! The syntax is line based: one global, function header line, block label,
+//! instruction or terminator per line. Lines starting with `;` are comments.
+//! Besides the printed forms, a few conveniences are accepted for hand-written
+//! files: function headers may list their parameters on one line separated by
+//! commas, parameters may be referenced as `%argN`, and calls may name their
+//! callee as `@name` instead of `%index`. Globals are referenced by name and
+//! must be declared before the functions that use them.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{
+    CmpOp, GepIndex, GlobalInit, IrBlock, IrFunction, IrGlobal, IrInstr, IrIntrinsic, IrModule,
+    IrTerm, IrType,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IrParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for IrParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for IrParseError {}
+
+type PResult<T> = Result<T, String>;
+
+pub fn parse_ir(text: &str) -> Result<IrModule, IrParseError> {
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with(';'))
+        .collect();
+    let functions = lines
+        .iter()
+        .filter_map(|(_, line)| header_name(line))
+        .enumerate()
+        .map(|(idx, name)| (name.to_string(), idx as u32))
+        .collect();
+    let mut parser = Parser {
+        module: IrModule::default(),
+        functions,
+    };
+    let mut idx = 0;
+    while idx < lines.len() {
+        let (number, line) = lines[idx];
+        let error = |message| IrParseError {
+            line: number,
+            message,
+        };
+        if line.starts_with('@') {
+            parser.global(line).map_err(error)?;
+            idx += 1;
+        } else if header_name(line).is_some() {
+            idx = parser.function(&lines, idx)?;
+        } else {
+            return Err(error(format!(
+                "expected a global or a function, found `{}`",
+                line
+            )));
+        }
+    }
+    Ok(parser.module)
+}
+
+/// The function name when `line` starts a function header.
+fn header_name(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("async ").unwrap_or(line);
+    let rest = rest.strip_prefix("fn ")?;
+    Some(rest.split('(').next().unwrap_or(rest).trim())
+}
+
+struct Parser {
+    module: IrModule,
+    functions: HashMap<String, u32>,
+}
+
+impl Parser {
+    fn intern(&mut self, text: String) -> u32 {
+        if let Some(idx) = self.module.strings.iter().position(|s| *s == text) {
+            idx as u32
+        } else {
+            self.module.strings.push(text);
+            self.module.strings.len() as u32 - 1
+        }
+    }
+
+    fn global(&mut self, line: &str) -> PResult<()> {
+        let mut cur = Cursor::new(line);
+        cur.expect("@")?;
+        let name = cur
+            .take_until(|c| c.is_whitespace() || c == ':')
+            .to_string();
+        cur.expect(":")?;
+        let mutable = cur.eat_word("mut");
+        let ty = cur.ty()?;
+        cur.expect("=")?;
+        let init = match cur.word().as_str() {
+            "zeroed" => GlobalInit::Zeroed,
+            "bytes" => {
+                let bytes = cur.list('[', ']', |cur| {
+                    let byte = cur.integer()?;
+                    u8::try_from(byte).map_err(|_| format!("byte {} is out of range", byte))
+                })?;
+                GlobalInit::Bytes(bytes)
+            }
+            "const" => {
+                let ty = cur.ty()?;
+                let value = cur.integer()?;
+                GlobalInit::Const { value, ty }
+            }
+            "string" => {
+                let text = cur.string()?;
+                GlobalInit::FromString(self.intern(text))
+            }
+            other => return Err(format!("unknown global initializer `{}`", other)),
+        };
+        cur.end()?;
+        self.module.globals.push(IrGlobal {
+            id: self.module.globals.len() as u32,
+            name,
+            ty,
+            mutable,
+            init,
+        });
+        Ok(())
+    }
+
+    /// A global reference: `@name` for a global declared above, or `@index`.
+    fn global_ref(&self, cur: &mut Cursor) -> PResult<u32> {
+        cur.expect("@")?;
+        let name = cur.word();
+        if let Ok(index) = name.parse::<u32>() {
+            return Ok(index);
+        }
+        self.module
+            .globals
+            .iter()
+            .position(|global| global.name == name)
+            .map(|index| index as u32)
+            .ok_or_else(|| format!("unknown global @{}", name))
+    }
+
+    /// Parses the function starting at `lines[start]` and returns the index
+    /// of the line after its closing brace.
+    fn function(&mut self, lines: &[(usize, &str)], start: usize) -> Result<usize, IrParseError> {
+        // The printer puts every parameter on its own line; join the header
+        // up to the opening brace.
+        let mut header = String::new();
+        let mut idx = start;
+        loop {
+            let Some((_, line)) = lines.get(idx) else {
+                return Err(IrParseError {
+                    line: lines[start].0,
+                    message: "unterminated function header".to_string(),
+                });
+            };
+            header.push_str(line);
+            header.push(' ');
+            idx += 1;
+            if line.ends_with('{') {
+                break;
+            }
+        }
+        let mut func = parse_header(&header).map_err(|message| IrParseError {
+            line: lines[start].0,
+            message,
+        })?;
+
+        let mut current: Option<IrBlock> = None;
+        loop {
+            let Some((number, line)) = lines.get(idx).copied() else {
+                return Err(IrParseError {
+                    line: lines[start].0,
+                    message: format!("function {} is missing its closing `}}`", func.name),
+                });
+            };
+            idx += 1;
+            let error = |message| IrParseError {
+                line: number,
+                message,
+            };
+            if line == "}" {
+                break;
+            }
+            if let Some(label) = line.strip_suffix(':') {
+                let id = Cursor::new(label).block().map_err(error)?;
+                if let Some(block) = current.replace(IrBlock::new(id)) {
+                    func.blocks.push(block);
+                }
+                continue;
+            }
+            let Some(block) = current.as_mut() else {
+                return Err(error("instruction outside of a block".to_string()));
+            };
+            if block.term.is_some() {
+                return Err(error(format!("block{} already has a terminator", block.id)));
+            }
+            let mut cur = Cursor::new(line);
+            match cur.peek_word().as_str() {
+                "ret" | "br" | "condbr" | "switch" | "unreachable" | "invoke" => {
+                    block.term = Some(self.term(&mut cur).map_err(error)?);
+                }
+                _ => block.body.push(self.instr(&mut cur).map_err(error)?),
+            }
+            cur.end().map_err(error)?;
+        }
+        func.blocks.extend(current);
+
+        let defined = func
+            .blocks
+            .iter()
+            .flat_map(|block| &block.body)
+            .filter_map(IrInstr::result)
+            .map(|dst| dst + 1)
+            .max()
+            .unwrap_or(0);
+        func.next_value = defined.max(func.params.len() as u32);
+        self.module.funcs.push(func);
+        Ok(idx)
+    }
+
+    fn term(&mut self, cur: &mut Cursor) -> PResult<IrTerm> {
+        let term = match cur.word().as_str() {
+            "ret" => {
+                let value = if cur.at_end() {
+                    None
+                } else {
+                    Some(cur.value()?)
+                };
+                IrTerm::Ret { value }
+            }
+            "br" => IrTerm::Br {
+                target: cur.block()?,
+            },
+            "condbr" => {
+                let cond = cur.value()?;
+                cur.expect(",")?;
+                let then_b = cur.block()?;
+                cur.expect(",")?;
+                let else_b = cur.block()?;
+                IrTerm::CondBr {
+                    cond,
+                    then_b,
+                    else_b,
+                }
+            }
+            "switch" => {
+                let scrutinee = cur.value()?;
+                cur.expect(",")?;
+                let cases = cur.list('[', ']', |cur| {
+                    let value = cur.integer()?;
+                    cur.expect("->")?;
+                    Ok((value, cur.block()?))
+                })?;
+                cur.expect(",")?;
+                cur.expect_word("default")?;
+                IrTerm::SwitchInt {
+                    scrutinee,
+                    cases,
+                    default: cur.block()?,
+                }
+            }
+            "unreachable" => IrTerm::Unreachable,
+            "invoke" => {
+                let call = self.instr(cur)?;
+                cur.expect_word("to")?;
+                let normal = cur.block()?;
+                cur.expect(",")?;
+                cur.expect_word("unwind")?;
+                IrTerm::Invoke {
+                    call: Box::new(call),
+                    normal,
+                    unwind: cur.block()?,
+                }
+            }
+            other => return Err(format!("unknown terminator `{}`", other)),
+        };
+        Ok(term)
+    }
+
+    fn instr(&mut self, cur: &mut Cursor) -> PResult<IrInstr> {
+        let dst = if cur.peek_char() == Some('%') {
+            let dst = cur.value()?;
+            cur.expect("=")?;
+            Some(dst)
+        } else {
+            None
+        };
+        let op = cur.word();
+        let needs_dst = || dst.ok_or_else(|| format!("`{}` needs a result value", op));
+        let instr = match op.as_str() {
+            "load_const" => {
+                let dst = needs_dst()?;
+                if cur.eat_word("bool") {
+                    let value = match cur.word().as_str() {
+                        "true" => true,
+                        "false" => false,
+                        other => return Err(format!("expected a bool, found `{}`", other)),
+                    };
+                    IrInstr::LoadConstBool { dst, value }
+                } else {
+                    let ty = cur.ty()?;
+                    if matches!(ty, IrType::F32 | IrType::F64) {
+                        let text = cur.take_until(char::is_whitespace);
+                        let value = text
+                            .parse()
+                            .map_err(|_| format!("invalid float `{}`", text))?;
+                        IrInstr::LoadConstFloat { dst, value, ty }
+                    } else {
+                        let value = cur.integer()?;
+                        IrInstr::LoadConstInt { dst, value, ty }
+                    }
+                }
+            }
+            "load_const_str" => {
+                let dst = needs_dst()?;
+                let text = cur.string()?;
+                IrInstr::LoadConstStr {
+                    dst,
+                    sid: self.intern(text),
+                }
+            }
+            "undef" => IrInstr::Undef {
+                dst: needs_dst()?,
+                ty: cur.ty()?,
+            },
+            "alloca" => IrInstr::Alloca {
+                dst: needs_dst()?,
+                ty: cur.ty()?,
+            },
+            "load" => {
+                let dst = needs_dst()?;
+                let ty = cur.ty()?;
+                cur.expect(",")?;
+                IrInstr::Load {
+                    dst,
+                    ty,
+                    ptr: cur.value()?,
+                }
+            }
+            "store" => {
+                let ty = cur.ty()?;
+                let src = cur.value()?;
+                cur.expect(",")?;
+                IrInstr::Store {
+                    src,
+                    ty,
+                    ptr: cur.value()?,
+                }
+            }
+            "load_global" => {
+                let dst = needs_dst()?;
+                let ty = cur.ty()?;
+                IrInstr::LoadGlobal {
+                    dst,
+                    ty,
+                    global: self.global_ref(cur)?,
+                }
+            }
+            "store_global" => {
+                let ty = cur.ty()?;
+                let global = self.global_ref(cur)?;
+                cur.expect(",")?;
+                IrInstr::StoreGlobal {
+                    global,
+                    ty,
+                    value: cur.value()?,
+                }
+            }
+            "gep" => {
+                let dst = needs_dst()?;
+                let base = cur.value()?;
+                cur.expect(",")?;
+                let indices = cur.list('[', ']', |cur| {
+                    if cur.peek_char() == Some('%') {
+                        Ok(GepIndex::Value(cur.value()?))
+                    } else {
+                        Ok(GepIndex::Const(cur.number()?))
+                    }
+                })?;
+                IrInstr::Gep { dst, base, indices }
+            }
+            "ptrcast" => {
+                let dst = needs_dst()?;
+                let src = cur.value()?;
+                cur.expect_word("to")?;
+                IrInstr::PtrCast {
+                    dst,
+                    src,
+                    ty: cur.ty()?,
+                }
+            }
+            "add" | "sub" | "mul" | "and" | "or" | "xor" | "shl" | "lshr" | "ashr" => {
+                let dst = needs_dst()?;
+                let ty = cur.ty()?;
+                let (a, b) = cur.value_pair()?;
+                match op.as_str() {
+                    "add" => IrInstr::Add { dst, a, b, ty },
+                    "sub" => IrInstr::Sub { dst, a, b, ty },
+                    "mul" => IrInstr::Mul { dst, a, b, ty },
+                    "and" => IrInstr::And { dst, a, b, ty },
+                    "or" => IrInstr::Or { dst, a, b, ty },
+                    "xor" => IrInstr::Xor { dst, a, b, ty },
+                    "shl" => IrInstr::Shl { dst, a, b, ty },
+                    "lshr" => IrInstr::LShr { dst, a, b, ty },
+                    _ => IrInstr::AShr { dst, a, b, ty },
+                }
+            }
+            "div" | "rem" => {
+                let dst = needs_dst()?;
+                let ty = cur.ty()?;
+                let (a, b) = cur.value_pair()?;
+                cur.expect("(")?;
+                cur.expect_word("signed")?;
+                cur.expect("=")?;
+                let signed = match cur.word().as_str() {
+                    "true" => true,
+                    "false" => false,
+                    other => return Err(format!("expected a bool, found `{}`", other)),
+                };
+                cur.expect(")")?;
+                if op == "div" {
+                    IrInstr::Div {
+                        dst,
+                        a,
+                        b,
+                        ty,
+                        signed,
+                    }
+                } else {
+                    IrInstr::Rem {
+                        dst,
+                        a,
+                        b,
+                        ty,
+                        signed,
+                    }
+                }
+            }
+            "neg" | "not" => {
+                let dst = needs_dst()?;
+                let ty = cur.ty()?;
+                let val = cur.value()?;
+                if op == "neg" {
+                    IrInstr::Neg { dst, val, ty }
+                } else {
+                    IrInstr::Not { dst, val, ty }
+                }
+            }
+            "fadd" | "fsub" | "fmul" | "fdiv" | "frem" => {
+                let dst = needs_dst()?;
+                let (a, b) = cur.value_pair()?;
+                match op.as_str() {
+                    "fadd" => IrInstr::FAdd { dst, a, b },
+                    "fsub" => IrInstr::FSub { dst, a, b },
+                    "fmul" => IrInstr::FMul { dst, a, b },
+                    "fdiv" => IrInstr::FDiv { dst, a, b },
+                    _ => IrInstr::FRem { dst, a, b },
+                }
+            }
+            "fneg" => IrInstr::FNeg {
+                dst: needs_dst()?,
+                val: cur.value()?,
+            },
+            "select" => {
+                let dst = needs_dst()?;
+                let cond = cur.value()?;
+                cur.expect(",")?;
+                let (then_v, else_v) = cur.value_pair()?;
+                IrInstr::Select {
+                    dst,
+                    cond,
+                    then_v,
+                    else_v,
+                }
+            }
+            "cmp" => {
+                let dst = needs_dst()?;
+                let cond = cmp_op(&cur.word())?;
+                let ty = cur.ty()?;
+                let (a, b) = cur.value_pair()?;
+                IrInstr::Cmp {
+                    dst,
+                    a,
+                    b,
+                    cond,
+                    ty,
+                }
+            }
+            "struct_init" => IrInstr::StructInit {
+                dst: needs_dst()?,
+                ty: cur.ty()?,
+                fields: cur.value_list()?,
+            },
+            "struct_extract" => {
+                let dst = needs_dst()?;
+                let base = cur.value()?;
+                cur.expect(",")?;
+                IrInstr::StructExtract {
+                    dst,
+                    base,
+                    field_idx: cur.number()?,
+                }
+            }
+            "struct_insert" => {
+                let dst = needs_dst()?;
+                let base = cur.value()?;
+                cur.expect(",")?;
+                let field_idx = cur.number()?;
+                cur.expect(",")?;
+                IrInstr::StructInsert {
+                    dst,
+                    base,
+                    field_idx,
+                    value: cur.value()?,
+                }
+            }
+            "tuple" => IrInstr::TupleInit {
+                dst: needs_dst()?,
+                items: cur.value_list()?,
+            },
+            "tuple_extract" => {
+                let dst = needs_dst()?;
+                let base = cur.value()?;
+                cur.expect(",")?;
+                IrInstr::TupleExtract {
+                    dst,
+                    base,
+                    idx: cur.number()?,
+                }
+            }
+            "array_init" => IrInstr::ArrayInit {
+                dst: needs_dst()?,
+                elem_ty: cur.ty()?,
+                elems: cur.value_list()?,
+            },
+            "array_get" => {
+                let dst = needs_dst()?;
+                let elem_ty = cur.ty()?;
+                let (base, index) = cur.value_pair()?;
+                IrInstr::ArrayGet {
+                    dst,
+                    base,
+                    index,
+                    elem_ty,
+                }
+            }
+            "array_set" => {
+                let elem_ty = cur.ty()?;
+                let (base, index) = cur.value_pair()?;
+                cur.expect(",")?;
+                IrInstr::ArraySet {
+                    base,
+                    index,
+                    value: cur.value()?,
+                    elem_ty,
+                }
+            }
+            "slice_from_array" => {
+                let dst = needs_dst()?;
+                let elem_ty = cur.ty()?;
+                let (base, len) = cur.value_pair()?;
+                IrInstr::SliceFromArray {
+                    dst,
+                    base,
+                    len,
+                    elem_ty,
+                }
+            }
+            "call" => {
+                let func = if cur.eat("@") {
+                    let name = cur.take_until(|c| c == '(').trim();
+                    *self
+                        .functions
+                        .get(name)
+                        .ok_or_else(|| format!("call to unknown function @{}", name))?
+                } else {
+                    cur.value()?
+                };
+                IrInstr::Call {
+                    dst,
+                    func,
+                    args: cur.args()?,
+                }
+            }
+            "call_intrinsic" => {
+                let name = cur.take_until(|c| c == '(').trim().to_string();
+                IrInstr::CallIntrinsic {
+                    dst,
+                    intrinsic: intrinsic(&name)?,
+                    args: cur.args()?,
+                }
+            }
+            "call_extern" => {
+                cur.expect("@")?;
+                let symbol = cur.number()?;
+                let sig = cur.ty()?;
+                IrInstr::CallExtern {
+                    dst,
+                    symbol,
+                    args: cur.args()?,
+                    sig,
+                }
+            }
+            "make_closure" => {
+                let dst = needs_dst()?;
+                cur.expect("func#")?;
+                let func = cur.number()? as usize;
+                cur.expect(",")?;
+                IrInstr::MakeClosure {
+                    dst,
+                    func,
+                    env: cur.value()?,
+                }
+            }
+            "load_capture" => {
+                let dst = needs_dst()?;
+                let ty = cur.ty()?;
+                let env = cur.value()?;
+                cur.expect(",")?;
+                IrInstr::LoadCapture {
+                    dst,
+                    env,
+                    idx: cur.number()?,
+                    ty,
+                }
+            }
+            "await" => IrInstr::Await {
+                dst: needs_dst()?,
+                fut: cur.value()?,
+            },
+            "heap_alloc" => {
+                let dst = needs_dst()?;
+                let size = cur.value()?;
+                cur.expect(",")?;
+                cur.expect_word("align")?;
+                IrInstr::HeapAlloc {
+                    dst,
+                    size,
+                    align: cur.number()?,
+                }
+            }
+            "heap_free" => IrInstr::HeapFree { ptr: cur.value()? },
+            "mem_copy" | "mem_set" => {
+                let target = cur.value()?;
+                cur.expect(",")?;
+                let (second, len) = cur.value_pair()?;
+                if op == "mem_copy" {
+                    IrInstr::MemCopy {
+                        dst: target,
+                        src: second,
+                        len,
+                    }
+                } else {
+                    IrInstr::MemSet {
+                        dst: target,
+                        value: second,
+                        len,
+                    }
+                }
+            }
+            "mem_zero" => IrInstr::MemZero {
+                dst: needs_dst()?,
+                len: cur.value()?,
+            },
+            "phi" => {
+                let dst = needs_dst()?;
+                let mut incomings = Vec::new();
+                loop {
+                    cur.expect("(")?;
+                    let block = cur.block()?;
+                    cur.expect(",")?;
+                    incomings.push((block, cur.value()?));
+                    cur.expect(")")?;
+                    if !cur.eat(",") {
+                        break;
+                    }
+                }
+                IrInstr::Phi { dst, incomings }
+            }
+            "load_const_i32" => {
+                let dst = needs_dst()?;
+                let value = cur.integer()?;
+                IrInstr::LoadConstI32 {
+                    dst,
+                    value: i32::try_from(value)
+                        .map_err(|_| format!("{} does not fit in i32", value))?,
+                }
+            }
+            "add_i32" | "cmp_eq" => {
+                let dst = needs_dst()?;
+                let (a, b) = cur.value_pair()?;
+                if op == "add_i32" {
+                    IrInstr::AddI32 { dst, a, b }
+                } else {
+                    IrInstr::CmpEq { dst, a, b }
+                }
+            }
+            "print_str" => {
+                let text = cur.string()?;
+                IrInstr::PrintStr {
+                    sid: self.intern(text),
+                }
+            }
+            "print_val" => IrInstr::PrintValue {
+                value: cur.value()?,
+                ty: cur.ty()?,
+            },
+            other => return Err(format!("unknown instruction `{}`", other)),
+        };
+        if dst.is_some() && instr.result().is_none() {
+            return Err(format!("`{}` does not produce a value", op));
+        }
+        Ok(instr)
+    }
+}
+
+fn parse_header(header: &str) -> PResult<IrFunction> {
+    let mut cur = Cursor::new(header);
+    let is_async = cur.eat_word("async");
+    cur.expect_word("fn")?;
+    let name = cur.take_until(|c| c == '(').trim().to_string();
+    cur.expect("(")?;
+    let mut params = Vec::new();
+    while !cur.eat(")") {
+        let index = cur.value()?;
+        if index as usize != params.len() {
+            return Err(format!(
+                "parameter %arg{} is out of order (expected %arg{})",
+                index,
+                params.len()
+            ));
+        }
+        cur.expect(":")?;
+        params.push(cur.ty()?);
+        cur.eat(",");
+    }
+    cur.expect("->")?;
+    let ret = cur.ty()?;
+    cur.expect("{")?;
+    cur.end()?;
+    let mut func = IrFunction::new(name, params, ret);
+    func.is_async = is_async;
+    Ok(func)
+}
+
+fn cmp_op(name: &str) -> PResult<CmpOp> {
+    let op = match name {
+        "Eq" => CmpOp::Eq,
+        "Ne" => CmpOp::Ne,
+        "Lt" => CmpOp::Lt,
+        "Le" => CmpOp::Le,
+        "Gt" => CmpOp::Gt,
+        "Ge" => CmpOp::Ge,
+        "Ult" => CmpOp::Ult,
+        "Ule" => CmpOp::Ule,
+        "Ugt" => CmpOp::Ugt,
+        "Uge" => CmpOp::Uge,
+        "Flt" => CmpOp::Flt,
+        "Fle" => CmpOp::Fle,
+        "Fgt" => CmpOp::Fgt,
+        "Fge" => CmpOp::Fge,
+        other => return Err(format!("unknown comparison `{}`", other)),
+    };
+    Ok(op)
+}
+
+fn intrinsic(name: &str) -> PResult<IrIntrinsic> {
+    let intrinsic = match name {
+        "LogInfo" => IrIntrinsic::LogInfo,
+        "Panic" => IrIntrinsic::Panic,
+        "MemAlloc" => IrIntrinsic::MemAlloc,
+        "MemFree" => IrIntrinsic::MemFree,
+        "MemCopy" => IrIntrinsic::MemCopy,
+        "MemSet" => IrIntrinsic::MemSet,
+        "MemZero" => IrIntrinsic::MemZero,
+        "StringLen" => IrIntrinsic::StringLen,
+        "StringConcat" => IrIntrinsic::StringConcat,
+        "VecNew" => IrIntrinsic::VecNew,
+        "VecLen" => IrIntrinsic::VecLen,
+        "VecPush" => IrIntrinsic::VecPush,
+        "VecPop" => IrIntrinsic::VecPop,
+        "RangeIterNext" => IrIntrinsic::RangeIterNext,
+        other => return Err(format!("unknown intrinsic `{}`", other)),
+    };
+    Ok(intrinsic)
+}
+
+struct Cursor<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_ws();
+        self.rest().is_empty()
+    }
+
+    fn end(&mut self) -> PResult<()> {
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(format!("unexpected `{}`", self.rest()))
+        }
+    }
+
+    fn peek_char(&mut self) -> Option<char> {
+        self.skip_ws();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> PResult<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`, found `{}`", token, self.rest()))
+        }
+    }
+
+    fn take_until(&mut self, stop: impl Fn(char) -> bool) -> &'a str {
+        self.skip_ws();
+        let rest = self.rest();
+        let len = rest.find(stop).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn peek_word(&mut self) -> String {
+        let pos = self.pos;
+        let word = self.word();
+        self.pos = pos;
+        word
+    }
+
+    fn word(&mut self) -> String {
+        self.take_until(|c| !(c.is_alphanumeric() || c == '_'))
+            .to_string()
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let pos = self.pos;
+        if self.word() == word {
+            true
+        } else {
+            self.pos = pos;
+            false
+        }
+    }
+
+    fn expect_word(&mut self, word: &str) -> PResult<()> {
+        if self.eat_word(word) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`, found `{}`", word, self.rest()))
+        }
+    }
+
+    fn integer(&mut self) -> PResult<i128> {
+        self.skip_ws();
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|&(idx, c)| !(c.is_ascii_digit() || (idx == 0 && c == '-')))
+            .map_or(rest.len(), |(idx, _)| idx);
+        let value = rest[..len]
+            .parse()
+            .map_err(|_| format!("expected an integer, found `{}`", rest))?;
+        self.pos += len;
+        Ok(value)
+    }
+
+    fn number(&mut self) -> PResult<u32> {
+        let value = self.integer()?;
+        u32::try_from(value).map_err(|_| format!("{} is not a valid index", value))
+    }
+
+    /// A value reference: `%N`, or `%argN` for parameter `N`.
+    fn value(&mut self) -> PResult<u32> {
+        self.expect("%")?;
+        self.eat("arg");
+        self.number()
+    }
+
+    fn value_pair(&mut self) -> PResult<(u32, u32)> {
+        let a = self.value()?;
+        self.expect(",")?;
+        Ok((a, self.value()?))
+    }
+
+    fn value_list(&mut self) -> PResult<Vec<u32>> {
+        self.list('[', ']', Cursor::value)
+    }
+
+    fn args(&mut self) -> PResult<Vec<u32>> {
+        self.list('(', ')', Cursor::value)
+    }
+
+    fn block(&mut self) -> PResult<u32> {
+        self.expect("block")?;
+        self.number()
+    }
+
+    fn list<T>(
+        &mut self,
+        open: char,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> PResult<T>,
+    ) -> PResult<Vec<T>> {
+        self.expect(open.encode_utf8(&mut [0; 4]))?;
+        let close = close.to_string();
+        let mut items = Vec::new();
+        if self.eat(&close) {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat(&close) {
+                return Ok(items);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    /// A double-quoted string using the escapes of `str::escape_default`.
+    fn string(&mut self) -> PResult<String> {
+        self.expect("\"")?;
+        let mut text = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += idx + 1;
+                    return Ok(text);
+                }
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some(c @ ('\\' | '\'' | '"')) => c,
+                        Some('u') => {
+                            let digits: String = chars
+                                .by_ref()
+                                .map(|(_, c)| c)
+                                .skip_while(|c| *c == '{')
+                                .take_while(|c| *c != '}')
+                                .collect();
+                            u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| format!("invalid escape `\\u{{{}}}`", digits))?
+                        }
+                        other => {
+                            return Err(format!("invalid escape `\\{}`", other.unwrap_or(' ')))
+                        }
+                    };
+                    text.push(escaped);
+                }
+                c => text.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    }
+
+    /// A type in the `Debug` form `format_ir` prints, e.g. `Ptr(I32)` or
+    /// `Array { elem: U8, len: 4 }`.
+    fn ty(&mut self) -> PResult<IrType> {
+        let name = self.word();
+        let ty = match name.as_str() {
+            "I1" => IrType::I1,
+            "I8" => IrType::I8,
+            "I16" => IrType::I16,
+            "I32" => IrType::I32,
+            "I64" => IrType::I64,
+            "U8" => IrType::U8,
+            "U16" => IrType::U16,
+            "U32" => IrType::U32,
+            "U64" => IrType::U64,
+            "Bool" => IrType::Bool,
+            "F32" => IrType::F32,
+            "F64" => IrType::F64,
+            "Void" => IrType::Void,
+            "Str" => IrType::Str,
+            "Ptr" => {
+                self.expect("(")?;
+                let inner = self.ty()?;
+                self.expect(")")?;
+                IrType::ptr(inner)
+            }
+            "Tuple" => {
+                self.expect("(")?;
+                let items = self.list('[', ']', Cursor::ty)?;
+                self.expect(")")?;
+                IrType::Tuple(items)
+            }
+            "Func" => {
+                self.expect("{")?;
+                self.expect_word("params")?;
+                self.expect(":")?;
+                let params = self.list('[', ']', Cursor::ty)?;
+                self.expect(",")?;
+                self.expect_word("ret")?;
+                self.expect(":")?;
+                let ret = Box::new(self.ty()?);
+                self.expect("}")?;
+                IrType::Func { params, ret }
+            }
+            "Array" => {
+                self.expect("{")?;
+                self.expect_word("elem")?;
+                self.expect(":")?;
+                let elem = Box::new(self.ty()?);
+                self.expect(",")?;
+                self.expect_word("len")?;
+                self.expect(":")?;
+                let len = self.number()?;
+                self.expect("}")?;
+                IrType::Array { elem, len }
+            }
+            "Slice" => {
+                self.expect("{")?;
+                self.expect_word("elem")?;
+                self.expect(":")?;
+                let elem = Box::new(self.ty()?);
+                self.expect("}")?;
+                IrType::Slice { elem }
+            }
+            "Struct" => {
+                self.expect("{")?;
+                self.expect_word("name")?;
+                self.expect(":")?;
+                let name = self.string()?;
+                self.expect(",")?;
+                self.expect_word("fields")?;
+                self.expect(":")?;
+                let fields = self.list('[', ']', Cursor::ty)?;
+                self.expect("}")?;
+                IrType::Struct { name, fields }
+            }
+            "Opaque" => return Err("opaque types cannot be read back".to_string()),
+            "" => return Err(format!("expected a type, found `{}`", self.rest())),
+            other => return Err(format!("unknown type `{}`", other)),
+        };
+        Ok(ty)
+    }
+}
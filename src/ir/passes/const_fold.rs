@@ -0,0 +1,242 @@
+//! Constant folding: arithmetic, logic and comparisons on constant operands
+//! become constant loads, and branches on constant conditions become jumps.
+//!
+//! Integers fold with wrapping 64-bit arithmetic, matching the registers the
+//! native backends compute in.
+
+use std::collections::HashMap;
+
+use crate::ir::{CmpOp, IrFunction, IrInstr, IrTerm, IrType};
+
+use super::Pass;
+
+pub struct ConstFold;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Const {
+    Int(i64),
+    Bool(bool),
+}
+
+impl Const {
+    fn as_int(self) -> i64 {
+        match self {
+            Const::Int(value) => value,
+            Const::Bool(value) => value as i64,
+        }
+    }
+}
+
+impl Pass for ConstFold {
+    fn name(&self) -> &'static str {
+        "const-fold"
+    }
+
+    fn run_on_function(&self, func: &mut IrFunction) -> bool {
+        let mut changed = false;
+        loop {
+            let consts = collect_consts(func);
+            let mut round = false;
+            for block in &mut func.blocks {
+                for instr in &mut block.body {
+                    if let Some(folded) = fold_instr(instr, &consts) {
+                        *instr = folded;
+                        round = true;
+                    }
+                }
+            }
+            round |= fold_branches(func, &consts);
+            if !round {
+                return changed;
+            }
+            changed = true;
+        }
+    }
+}
+
+fn collect_consts(func: &IrFunction) -> HashMap<u32, Const> {
+    let mut consts = HashMap::new();
+    for instr in func.blocks.iter().flat_map(|block| &block.body) {
+        match instr {
+            IrInstr::LoadConstInt { dst, value, .. } => {
+                consts.insert(*dst, Const::Int(*value as i64));
+            }
+            IrInstr::LoadConstI32 { dst, value } => {
+                consts.insert(*dst, Const::Int(*value as i64));
+            }
+            IrInstr::LoadConstBool { dst, value } => {
+                consts.insert(*dst, Const::Bool(*value));
+            }
+            _ => {}
+        }
+    }
+    consts
+}
+
+fn int_const(dst: u32, value: i64, ty: &IrType) -> IrInstr {
+    IrInstr::LoadConstInt {
+        dst,
+        value: value as i128,
+        ty: ty.clone(),
+    }
+}
+
+fn bool_const(dst: u32, value: bool) -> IrInstr {
+    IrInstr::LoadConstBool { dst, value }
+}
+
+fn fold_instr(instr: &IrInstr, consts: &HashMap<u32, Const>) -> Option<IrInstr> {
+    let get = |value: &u32| consts.get(value).copied();
+    let folded = match instr {
+        IrInstr::Add { dst, a, b, ty } => {
+            int_const(*dst, get(a)?.as_int().wrapping_add(get(b)?.as_int()), ty)
+        }
+        IrInstr::AddI32 { dst, a, b } => int_const(
+            *dst,
+            get(a)?.as_int().wrapping_add(get(b)?.as_int()),
+            &IrType::I32,
+        ),
+        IrInstr::Sub { dst, a, b, ty } => {
+            int_const(*dst, get(a)?.as_int().wrapping_sub(get(b)?.as_int()), ty)
+        }
+        IrInstr::Mul { dst, a, b, ty } => {
+            int_const(*dst, get(a)?.as_int().wrapping_mul(get(b)?.as_int()), ty)
+        }
+        IrInstr::Div {
+            dst,
+            a,
+            b,
+            ty,
+            signed,
+        } => {
+            let (a, b) = (get(a)?.as_int(), get(b)?.as_int());
+            let value = if *signed {
+                a.checked_div(b)?
+            } else {
+                (a as u64).checked_div(b as u64)? as i64
+            };
+            int_const(*dst, value, ty)
+        }
+        IrInstr::Rem {
+            dst,
+            a,
+            b,
+            ty,
+            signed,
+        } => {
+            let (a, b) = (get(a)?.as_int(), get(b)?.as_int());
+            let value = if *signed {
+                a.checked_rem(b)?
+            } else {
+                (a as u64).checked_rem(b as u64)? as i64
+            };
+            int_const(*dst, value, ty)
+        }
+        IrInstr::Neg { dst, val, ty } => int_const(*dst, get(val)?.as_int().wrapping_neg(), ty),
+        IrInstr::And { dst, a, b, ty } => match (get(a)?, get(b)?) {
+            (Const::Bool(a), Const::Bool(b)) => bool_const(*dst, a && b),
+            (a, b) => int_const(*dst, a.as_int() & b.as_int(), ty),
+        },
+        IrInstr::Or { dst, a, b, ty } => match (get(a)?, get(b)?) {
+            (Const::Bool(a), Const::Bool(b)) => bool_const(*dst, a || b),
+            (a, b) => int_const(*dst, a.as_int() | b.as_int(), ty),
+        },
+        IrInstr::Xor { dst, a, b, ty } => match (get(a)?, get(b)?) {
+            (Const::Bool(a), Const::Bool(b)) => bool_const(*dst, a != b),
+            (a, b) => int_const(*dst, a.as_int() ^ b.as_int(), ty),
+        },
+        IrInstr::Not { dst, val, ty } => match get(val)? {
+            Const::Bool(value) => bool_const(*dst, !value),
+            Const::Int(value) => int_const(*dst, !value, ty),
+        },
+        IrInstr::Shl { dst, a, b, ty } => {
+            let shift = (get(b)?.as_int() & 63) as u32;
+            int_const(*dst, get(a)?.as_int().wrapping_shl(shift), ty)
+        }
+        IrInstr::LShr { dst, a, b, ty } => {
+            let shift = (get(b)?.as_int() & 63) as u32;
+            int_const(*dst, ((get(a)?.as_int() as u64) >> shift) as i64, ty)
+        }
+        IrInstr::AShr { dst, a, b, ty } => {
+            let shift = (get(b)?.as_int() & 63) as u32;
+            int_const(*dst, get(a)?.as_int() >> shift, ty)
+        }
+        IrInstr::Cmp {
+            dst, a, b, cond, ..
+        } => bool_const(*dst, compare(*cond, get(a)?.as_int(), get(b)?.as_int())?),
+        IrInstr::CmpEq { dst, a, b } => bool_const(*dst, get(a)? == get(b)?),
+        _ => return None,
+    };
+    Some(folded)
+}
+
+fn compare(cond: CmpOp, a: i64, b: i64) -> Option<bool> {
+    let (ua, ub) = (a as u64, b as u64);
+    Some(match cond {
+        CmpOp::Eq => a == b,
+        CmpOp::Ne => a != b,
+        CmpOp::Lt => a < b,
+        CmpOp::Le => a <= b,
+        CmpOp::Gt => a > b,
+        CmpOp::Ge => a >= b,
+        CmpOp::Ult => ua < ub,
+        CmpOp::Ule => ua <= ub,
+        CmpOp::Ugt => ua > ub,
+        CmpOp::Uge => ua >= ub,
+        CmpOp::Flt | CmpOp::Fle | CmpOp::Fgt | CmpOp::Fge => return None,
+    })
+}
+
+/// Turns `CondBr`/`SwitchInt` on constants into `Br`, dropping the phi
+/// incomings of the edges that can no longer be taken.
+fn fold_branches(func: &mut IrFunction, consts: &HashMap<u32, Const>) -> bool {
+    let mut dropped_edges = Vec::new();
+    for block in &mut func.blocks {
+        let Some(term) = &block.term else {
+            continue;
+        };
+        let taken = match term {
+            IrTerm::CondBr {
+                cond,
+                then_b,
+                else_b,
+            } => match consts.get(cond) {
+                Some(Const::Bool(value)) => Some(if *value { *then_b } else { *else_b }),
+                _ => None,
+            },
+            IrTerm::SwitchInt {
+                scrutinee,
+                cases,
+                default,
+            } => consts.get(scrutinee).map(|value| {
+                let value = value.as_int() as i128;
+                cases
+                    .iter()
+                    .find(|(case, _)| *case == value)
+                    .map(|(_, block)| *block)
+                    .unwrap_or(*default)
+            }),
+            _ => None,
+        };
+        let Some(taken) = taken else {
+            continue;
+        };
+        for succ in term.successors() {
+            if succ != taken {
+                dropped_edges.push((block.id, succ));
+            }
+        }
+        block.term = Some(IrTerm::Br { target: taken });
+    }
+    let changed = !dropped_edges.is_empty();
+    for (from, to) in dropped_edges {
+        if let Some(block) = func.blocks.iter_mut().find(|b| b.id == to) {
+            for instr in &mut block.body {
+                if let IrInstr::Phi { incomings, .. } = instr {
+                    incomings.retain(|(pred, _)| *pred != from);
+                }
+            }
+        }
+    }
+    changed
+}
@@ -0,0 +1,131 @@
+//! Copy propagation. SSA has no move instruction, so a "copy" is anything
+//! that just forwards another value: a phi whose incomings all agree, a
+//! select with a constant condition or identical arms, and arithmetic with an
+//! identity operand (`x + 0`, `x * 1`, `b && true`, ...). Uses of the copy are
+//! rewritten to the original value and the copy is removed.
+
+use std::collections::HashMap;
+
+use crate::ir::{IrFunction, IrInstr};
+
+use super::Pass;
+
+pub struct CopyProp;
+
+impl Pass for CopyProp {
+    fn name(&self) -> &'static str {
+        "copy-prop"
+    }
+
+    fn run_on_function(&self, func: &mut IrFunction) -> bool {
+        let mut changed = false;
+        while let Some((dst, src)) = find_copy(func) {
+            for block in &mut func.blocks {
+                block.body.retain(|instr| instr.result() != Some(dst));
+            }
+            func.replace_uses(dst, src);
+            changed = true;
+        }
+        changed
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Const {
+    Int(i128),
+    Bool(bool),
+}
+
+fn find_copy(func: &IrFunction) -> Option<(u32, u32)> {
+    let mut consts = HashMap::new();
+    for instr in func.blocks.iter().flat_map(|block| &block.body) {
+        match instr {
+            IrInstr::LoadConstInt { dst, value, .. } => {
+                consts.insert(*dst, Const::Int(*value));
+            }
+            IrInstr::LoadConstI32 { dst, value } => {
+                consts.insert(*dst, Const::Int(*value as i128));
+            }
+            IrInstr::LoadConstBool { dst, value } => {
+                consts.insert(*dst, Const::Bool(*value));
+            }
+            _ => {}
+        }
+    }
+    let is = |value: &u32, expected: Const| consts.get(value) == Some(&expected);
+    let zero = Const::Int(0);
+    let one = Const::Int(1);
+    for instr in func.blocks.iter().flat_map(|block| &block.body) {
+        let copy = match instr {
+            IrInstr::Phi { dst, incomings } => {
+                // Self-references come from loop back edges that leave the
+                // value unchanged.
+                let mut sources = incomings
+                    .iter()
+                    .map(|(_, value)| *value)
+                    .filter(|value| value != dst);
+                match sources.next() {
+                    Some(first) if sources.all(|value| value == first) => Some((*dst, first)),
+                    _ => None,
+                }
+            }
+            IrInstr::Select {
+                dst,
+                cond,
+                then_v,
+                else_v,
+            } => {
+                if then_v == else_v || is(cond, Const::Bool(true)) {
+                    Some((*dst, *then_v))
+                } else if is(cond, Const::Bool(false)) {
+                    Some((*dst, *else_v))
+                } else {
+                    None
+                }
+            }
+            IrInstr::Add { dst, a, b, .. } | IrInstr::AddI32 { dst, a, b } => {
+                if is(b, zero) {
+                    Some((*dst, *a))
+                } else if is(a, zero) {
+                    Some((*dst, *b))
+                } else {
+                    None
+                }
+            }
+            IrInstr::Sub { dst, a, b, .. } if is(b, zero) => Some((*dst, *a)),
+            IrInstr::Mul { dst, a, b, .. } => {
+                if is(b, one) {
+                    Some((*dst, *a))
+                } else if is(a, one) {
+                    Some((*dst, *b))
+                } else {
+                    None
+                }
+            }
+            IrInstr::Div { dst, a, b, .. } if is(b, one) => Some((*dst, *a)),
+            IrInstr::And { dst, a, b, .. } => {
+                if is(b, Const::Bool(true)) {
+                    Some((*dst, *a))
+                } else if is(a, Const::Bool(true)) {
+                    Some((*dst, *b))
+                } else {
+                    None
+                }
+            }
+            IrInstr::Or { dst, a, b, .. } => {
+                if is(b, Const::Bool(false)) {
+                    Some((*dst, *a))
+                } else if is(a, Const::Bool(false)) {
+                    Some((*dst, *b))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        if copy.is_some() {
+            return copy;
+        }
+    }
+    None
+}
@@ -0,0 +1,55 @@
+//! Dead-code elimination. Values read by terminators and by instructions
+//! with side effects are live, as is everything they transitively read; pure
+//! instructions defining anything else are removed. Marking from the roots
+//! also clears dead cycles such as a loop counter nobody reads.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{IrFunction, IrInstr};
+
+use super::{is_pure, Pass};
+
+pub struct DeadCodeElim;
+
+impl Pass for DeadCodeElim {
+    fn name(&self) -> &'static str {
+        "dce"
+    }
+
+    fn run_on_function(&self, func: &mut IrFunction) -> bool {
+        let mut defs: HashMap<u32, &IrInstr> = HashMap::new();
+        let mut worklist = Vec::new();
+        for block in &func.blocks {
+            for instr in &block.body {
+                if let Some(dst) = instr.result() {
+                    defs.insert(dst, instr);
+                }
+                if !is_pure(instr) {
+                    worklist.extend(instr.operands());
+                }
+            }
+            if let Some(term) = &block.term {
+                worklist.extend(term.operands());
+            }
+        }
+
+        let mut live = HashSet::new();
+        while let Some(value) = worklist.pop() {
+            if live.insert(value) {
+                if let Some(instr) = defs.get(&value) {
+                    worklist.extend(instr.operands());
+                }
+            }
+        }
+
+        let mut changed = false;
+        for block in &mut func.blocks {
+            let before = block.body.len();
+            block.body.retain(|instr| {
+                !is_pure(instr) || instr.result().is_some_and(|dst| live.contains(&dst))
+            });
+            changed |= block.body.len() != before;
+        }
+        changed
+    }
+}
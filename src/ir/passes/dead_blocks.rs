@@ -0,0 +1,34 @@
+//! Dead-block elimination: removes blocks the entry block cannot reach and
+//! the phi incomings that named them.
+
+use crate::ir::{IrFunction, IrInstr};
+
+use super::{reachable_blocks, Pass};
+
+pub struct DeadBlockElim;
+
+impl Pass for DeadBlockElim {
+    fn name(&self) -> &'static str {
+        "dead-blocks"
+    }
+
+    fn run_on_function(&self, func: &mut IrFunction) -> bool {
+        remove_unreachable_blocks(func)
+    }
+}
+
+pub(crate) fn remove_unreachable_blocks(func: &mut IrFunction) -> bool {
+    let reachable = reachable_blocks(func);
+    if reachable.len() == func.blocks.len() {
+        return false;
+    }
+    func.blocks.retain(|block| reachable.contains(&block.id));
+    for block in &mut func.blocks {
+        for instr in &mut block.body {
+            if let IrInstr::Phi { incomings, .. } = instr {
+                incomings.retain(|(pred, _)| reachable.contains(pred));
+            }
+        }
+    }
+    true
+}
@@ -0,0 +1,150 @@
+//! Loop-invariant code motion. Natural loops are found from back edges (an
+//! edge whose target dominates its source); pure instructions whose operands
+//! are all defined outside the loop move to the end of the loop's preheader.
+//! A preheader is inserted when the header's single outside predecessor does
+//! not already jump straight to it. Inner loops are visited first, so code
+//! hoisted out of one can continue out of the loop around it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{IrBlock, IrFunction, IrInstr, IrTerm};
+
+use super::{dominators, is_pure, predecessors, rename_phi_pred, retarget, successors, Pass};
+
+pub struct Licm;
+
+struct Loop {
+    header: u32,
+    body: HashSet<u32>,
+}
+
+impl Pass for Licm {
+    fn name(&self) -> &'static str {
+        "licm"
+    }
+
+    fn run_on_function(&self, func: &mut IrFunction) -> bool {
+        let mut changed = false;
+        // Inserting a preheader changes the CFG, so the analysis is redone
+        // after each one.
+        'analyze: loop {
+            let preds = predecessors(func);
+            let mut loops = find_loops(func, &preds);
+            loops.sort_by_key(|lp| lp.body.len());
+            for lp in &loops {
+                let outside: Vec<u32> = preds[&lp.header]
+                    .iter()
+                    .copied()
+                    .filter(|pred| !lp.body.contains(pred))
+                    .collect();
+                let [pred] = outside.as_slice() else {
+                    continue;
+                };
+                let direct = matches!(
+                    func.block(*pred).and_then(|b| b.term.as_ref()),
+                    Some(IrTerm::Br { target }) if *target == lp.header
+                );
+                if !direct {
+                    insert_preheader(func, *pred, lp.header);
+                    changed = true;
+                    continue 'analyze;
+                }
+                changed |= hoist(func, lp, *pred);
+            }
+            return changed;
+        }
+    }
+}
+
+fn insert_preheader(func: &mut IrFunction, pred: u32, header: u32) {
+    let id = func.blocks.iter().map(|b| b.id + 1).max().unwrap_or(0);
+    let mut block = IrBlock::new(id);
+    block.term = Some(IrTerm::Br { target: header });
+    func.blocks.push(block);
+    if let Some(term) = func
+        .blocks
+        .iter_mut()
+        .find(|b| b.id == pred)
+        .and_then(|b| b.term.as_mut())
+    {
+        retarget(term, header, id);
+    }
+    rename_phi_pred(func, header, pred, id);
+}
+
+fn hoist(func: &mut IrFunction, lp: &Loop, preheader: u32) -> bool {
+    let mut defined_inside: HashSet<u32> = func
+        .blocks
+        .iter()
+        .filter(|block| lp.body.contains(&block.id))
+        .flat_map(|block| &block.body)
+        .filter_map(IrInstr::result)
+        .collect();
+    let mut hoisted = Vec::new();
+    loop {
+        let mut moved = false;
+        for block in func.blocks.iter_mut() {
+            if !lp.body.contains(&block.id) {
+                continue;
+            }
+            let mut idx = 0;
+            while idx < block.body.len() {
+                let instr = &block.body[idx];
+                let invariant = is_pure(instr)
+                    && !matches!(instr, IrInstr::Phi { .. })
+                    && instr
+                        .operands()
+                        .iter()
+                        .all(|operand| !defined_inside.contains(operand));
+                if invariant {
+                    let instr = block.body.remove(idx);
+                    if let Some(dst) = instr.result() {
+                        defined_inside.remove(&dst);
+                    }
+                    hoisted.push(instr);
+                    moved = true;
+                } else {
+                    idx += 1;
+                }
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+    if hoisted.is_empty() {
+        return false;
+    }
+    if let Some(block) = func.blocks.iter_mut().find(|b| b.id == preheader) {
+        block.body.extend(hoisted);
+    }
+    true
+}
+
+/// Natural loops, one per header; loops sharing a header are merged.
+fn find_loops(func: &IrFunction, preds: &HashMap<u32, Vec<u32>>) -> Vec<Loop> {
+    let dominators = dominators(func, preds);
+    let mut loops: Vec<Loop> = Vec::new();
+    for block in &func.blocks {
+        for header in successors(block.term.as_ref()) {
+            let dominated = dominators
+                .get(&block.id)
+                .is_some_and(|doms| doms.contains(&header));
+            if !dominated {
+                continue;
+            }
+            let mut body = HashSet::from([header]);
+            let mut worklist = vec![block.id];
+            while let Some(id) = worklist.pop() {
+                if body.insert(id) {
+                    worklist.extend(preds.get(&id).into_iter().flatten().copied());
+                }
+            }
+            match loops.iter_mut().find(|lp| lp.header == header) {
+                Some(lp) => lp.body.extend(body),
+                None => loops.push(Loop { header, body }),
+            }
+        }
+    }
+    loops
+}
@@ -0,0 +1,316 @@
+//! Optimization passes over `IrModule`.
+//!
+//! Every pass implements [`Pass`] and can be run on its own; [`PassManager`]
+//! strings them into a pipeline. `apexrc build --release` runs
+//! [`PassManager::release`].
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+
+use super::verify::verify_module;
+use super::{IrFunction, IrInstr, IrModule, IrTerm};
+
+pub mod const_fold;
+pub mod copy_prop;
+pub mod dce;
+pub mod dead_blocks;
+pub mod licm;
+pub mod simplify_cfg;
+
+pub use const_fold::ConstFold;
+pub use copy_prop::CopyProp;
+pub use dce::DeadCodeElim;
+pub use dead_blocks::DeadBlockElim;
+pub use licm::Licm;
+pub use simplify_cfg::SimplifyCfg;
+
+pub trait Pass {
+    /// Name used to select the pass and to label `--dump-ir` output.
+    fn name(&self) -> &'static str;
+
+    /// Runs the pass over every function, returning whether anything changed.
+    fn run(&self, module: &mut IrModule) -> bool {
+        let mut changed = false;
+        for func in &mut module.funcs {
+            changed |= self.run_on_function(func);
+        }
+        changed
+    }
+
+    fn run_on_function(&self, func: &mut IrFunction) -> bool;
+}
+
+/// Names accepted by [`pass_by_name`], in release pipeline order.
+pub const PASS_NAMES: [&str; 6] = [
+    "const-fold",
+    "copy-prop",
+    "dead-blocks",
+    "simplify-cfg",
+    "licm",
+    "dce",
+];
+
+pub fn pass_by_name(name: &str) -> Option<Box<dyn Pass>> {
+    let pass: Box<dyn Pass> = match name {
+        "const-fold" => Box::new(ConstFold),
+        "copy-prop" => Box::new(CopyProp),
+        "dead-blocks" => Box::new(DeadBlockElim),
+        "simplify-cfg" => Box::new(SimplifyCfg),
+        "licm" => Box::new(Licm),
+        "dce" => Box::new(DeadCodeElim),
+        _ => return None,
+    };
+    Some(pass)
+}
+
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pipeline used for release builds.
+    pub fn release() -> Self {
+        Self::from_names(&[
+            "const-fold",
+            "copy-prop",
+            "dead-blocks",
+            "simplify-cfg",
+            "licm",
+            "copy-prop",
+            "dce",
+        ])
+        .expect("release pipeline uses known passes")
+    }
+
+    /// Builds a pipeline from pass names, e.g. `["const-fold", "dce"]`.
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Result<Self> {
+        let mut manager = Self::new();
+        for name in names {
+            let name = name.as_ref();
+            let pass = pass_by_name(name).ok_or_else(|| {
+                anyhow!(
+                    "unknown IR pass `{}` (expected one of: {})",
+                    name,
+                    PASS_NAMES.join(", ")
+                )
+            })?;
+            manager.passes.push(pass);
+        }
+        Ok(manager)
+    }
+
+    pub fn add(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn pass_names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    pub fn run(&self, module: &mut IrModule) -> Result<bool> {
+        self.run_with(module, |_, _| {})
+    }
+
+    /// Runs the pipeline, calling `after_pass` with each pass name and the
+    /// module it produced. The module is verified after every pass, so a
+    /// pass that breaks the IR is reported by name.
+    pub fn run_with(
+        &self,
+        module: &mut IrModule,
+        mut after_pass: impl FnMut(&str, &IrModule),
+    ) -> Result<bool> {
+        let mut changed = false;
+        for pass in &self.passes {
+            changed |= pass.run(module);
+            after_pass(pass.name(), module);
+            check_module(module).map_err(|err| {
+                anyhow!("IR verification failed after `{}`: {}", pass.name(), err)
+            })?;
+        }
+        Ok(changed)
+    }
+}
+
+/// Runs [`verify_module`] and joins any problems into a single error.
+pub fn check_module(module: &IrModule) -> Result<()> {
+    let errors = verify_module(module);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    Err(anyhow!(messages.join("; ")))
+}
+
+/// Predecessor lists keyed by block id, in block order.
+pub(crate) fn predecessors(func: &IrFunction) -> HashMap<u32, Vec<u32>> {
+    let mut preds: HashMap<u32, Vec<u32>> = func
+        .blocks
+        .iter()
+        .map(|block| (block.id, Vec::new()))
+        .collect();
+    for block in &func.blocks {
+        for succ in successors(block.term.as_ref()) {
+            let entry = preds.entry(succ).or_default();
+            if !entry.contains(&block.id) {
+                entry.push(block.id);
+            }
+        }
+    }
+    preds
+}
+
+pub(crate) fn successors(term: Option<&IrTerm>) -> Vec<u32> {
+    term.map(IrTerm::successors).unwrap_or_default()
+}
+
+/// The set of blocks dominating each block (including itself). Only
+/// meaningful for blocks reachable from the entry; edges out of unreachable
+/// blocks are ignored.
+pub(crate) fn dominators(
+    func: &IrFunction,
+    preds: &HashMap<u32, Vec<u32>>,
+) -> HashMap<u32, HashSet<u32>> {
+    let Some(entry) = func.blocks.first().map(|block| block.id) else {
+        return HashMap::new();
+    };
+    let reachable = reachable_blocks(func);
+    let all: HashSet<u32> = func.blocks.iter().map(|block| block.id).collect();
+    let mut doms: HashMap<u32, HashSet<u32>> = func
+        .blocks
+        .iter()
+        .map(|block| (block.id, all.clone()))
+        .collect();
+    doms.insert(entry, HashSet::from([entry]));
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in func.blocks.iter().skip(1) {
+            let mut new: Option<HashSet<u32>> = None;
+            let live_preds = preds.get(&block.id).into_iter().flatten();
+            for pred in live_preds.filter(|pred| reachable.contains(pred)) {
+                let pred_doms = &doms[pred];
+                new = Some(match new {
+                    Some(acc) => acc.intersection(pred_doms).copied().collect(),
+                    None => pred_doms.clone(),
+                });
+            }
+            let mut new = new.unwrap_or_default();
+            new.insert(block.id);
+            if new != doms[&block.id] {
+                doms.insert(block.id, new);
+                changed = true;
+            }
+        }
+    }
+    doms
+}
+
+/// Blocks reachable from the entry block.
+pub(crate) fn reachable_blocks(func: &IrFunction) -> HashSet<u32> {
+    let mut reachable = HashSet::new();
+    let mut worklist: Vec<u32> = func
+        .blocks
+        .first()
+        .map(|block| block.id)
+        .into_iter()
+        .collect();
+    while let Some(id) = worklist.pop() {
+        if reachable.insert(id) {
+            if let Some(block) = func.block(id) {
+                worklist.extend(successors(block.term.as_ref()));
+            }
+        }
+    }
+    reachable
+}
+
+/// Points every edge of `term` that goes to `from` at `to` instead.
+pub(crate) fn retarget(term: &mut IrTerm, from: u32, to: u32) {
+    let swap = |block: &mut u32| {
+        if *block == from {
+            *block = to;
+        }
+    };
+    match term {
+        IrTerm::Br { target } => swap(target),
+        IrTerm::CondBr { then_b, else_b, .. } => {
+            swap(then_b);
+            swap(else_b);
+        }
+        IrTerm::SwitchInt { cases, default, .. } => {
+            cases.iter_mut().for_each(|(_, block)| swap(block));
+            swap(default);
+        }
+        IrTerm::Invoke { normal, unwind, .. } => {
+            swap(normal);
+            swap(unwind);
+        }
+        IrTerm::Ret { .. } | IrTerm::Unreachable => {}
+    }
+}
+
+/// Renames the predecessor `from` to `to` in the phis of `block`.
+pub(crate) fn rename_phi_pred(func: &mut IrFunction, block: u32, from: u32, to: u32) {
+    if let Some(block) = func.blocks.iter_mut().find(|b| b.id == block) {
+        for instr in &mut block.body {
+            if let IrInstr::Phi { incomings, .. } = instr {
+                for (pred, _) in incomings.iter_mut() {
+                    if *pred == from {
+                        *pred = to;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Instructions without side effects that cannot trap, so they may be
+/// deleted when unused or executed speculatively. Division is excluded
+/// because it faults on a zero divisor.
+pub(crate) fn is_pure(instr: &IrInstr) -> bool {
+    matches!(
+        instr,
+        IrInstr::LoadConstInt { .. }
+            | IrInstr::LoadConstFloat { .. }
+            | IrInstr::LoadConstBool { .. }
+            | IrInstr::LoadConstStr { .. }
+            | IrInstr::LoadConstI32 { .. }
+            | IrInstr::Undef { .. }
+            | IrInstr::Gep { .. }
+            | IrInstr::PtrCast { .. }
+            | IrInstr::Add { .. }
+            | IrInstr::Sub { .. }
+            | IrInstr::Mul { .. }
+            | IrInstr::Neg { .. }
+            | IrInstr::FAdd { .. }
+            | IrInstr::FSub { .. }
+            | IrInstr::FMul { .. }
+            | IrInstr::FDiv { .. }
+            | IrInstr::FRem { .. }
+            | IrInstr::FNeg { .. }
+            | IrInstr::And { .. }
+            | IrInstr::Or { .. }
+            | IrInstr::Xor { .. }
+            | IrInstr::Shl { .. }
+            | IrInstr::LShr { .. }
+            | IrInstr::AShr { .. }
+            | IrInstr::Not { .. }
+            | IrInstr::Select { .. }
+            | IrInstr::Cmp { .. }
+            | IrInstr::StructInit { .. }
+            | IrInstr::StructExtract { .. }
+            | IrInstr::StructInsert { .. }
+            | IrInstr::TupleInit { .. }
+            | IrInstr::TupleExtract { .. }
+            | IrInstr::Phi { .. }
+            | IrInstr::AddI32 { .. }
+            | IrInstr::CmpEq { .. }
+    )
+}
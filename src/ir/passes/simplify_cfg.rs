@@ -0,0 +1,199 @@
+//! CFG simplification, repeated until nothing changes:
+//! - a conditional branch whose arms agree becomes a jump;
+//! - edges into an empty block that only jumps on are sent to its target;
+//! - a block is merged into its predecessor when that predecessor jumps
+//!   straight to it and nothing else does.
+//!
+//! Blocks left unreachable are removed.
+
+use crate::ir::{IrFunction, IrInstr, IrTerm};
+
+use super::dead_blocks::remove_unreachable_blocks;
+use super::{predecessors, rename_phi_pred, retarget, successors, Pass};
+
+pub struct SimplifyCfg;
+
+impl Pass for SimplifyCfg {
+    fn name(&self) -> &'static str {
+        "simplify-cfg"
+    }
+
+    fn run_on_function(&self, func: &mut IrFunction) -> bool {
+        let mut changed = false;
+        loop {
+            let round = fold_same_target_branches(func)
+                || forward_empty_block(func)
+                || merge_into_predecessor(func)
+                || remove_unreachable_blocks(func);
+            if !round {
+                return changed;
+            }
+            changed = true;
+        }
+    }
+}
+
+fn phi_incomings_from(func: &IrFunction, block: u32, pred: u32) -> Vec<Vec<u32>> {
+    func.block(block)
+        .map(|block| {
+            block
+                .body
+                .iter()
+                .filter_map(|instr| match instr {
+                    IrInstr::Phi { incomings, .. } => Some(
+                        incomings
+                            .iter()
+                            .filter(|(from, _)| *from == pred)
+                            .map(|(_, value)| *value)
+                            .collect(),
+                    ),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn has_phis(func: &IrFunction, block: u32) -> bool {
+    func.block(block).is_some_and(|block| {
+        block
+            .body
+            .iter()
+            .any(|instr| matches!(instr, IrInstr::Phi { .. }))
+    })
+}
+
+/// `condbr %c, B, B` becomes `br B`, provided every phi in `B` receives the
+/// same value along both edges.
+fn fold_same_target_branches(func: &mut IrFunction) -> bool {
+    for idx in 0..func.blocks.len() {
+        let id = func.blocks[idx].id;
+        let Some(IrTerm::CondBr { then_b, else_b, .. }) = func.blocks[idx].term else {
+            continue;
+        };
+        if then_b != else_b {
+            continue;
+        }
+        let agree = phi_incomings_from(func, then_b, id)
+            .iter()
+            .all(|values| values.windows(2).all(|pair| pair[0] == pair[1]));
+        if !agree {
+            continue;
+        }
+        func.blocks[idx].term = Some(IrTerm::Br { target: then_b });
+        if let Some(target) = func.blocks.iter_mut().find(|b| b.id == then_b) {
+            for instr in &mut target.body {
+                if let IrInstr::Phi { incomings, .. } = instr {
+                    let mut seen = false;
+                    incomings.retain(|(pred, _)| {
+                        if *pred != id {
+                            return true;
+                        }
+                        let keep = !seen;
+                        seen = true;
+                        keep
+                    });
+                }
+            }
+        }
+        return true;
+    }
+    false
+}
+
+/// Redirects the predecessors of an empty `br T` block straight to `T`. When
+/// `T` has phis this is only done for a single predecessor that is not
+/// already a predecessor of `T`, so the phi incoming can simply be renamed.
+fn forward_empty_block(func: &mut IrFunction) -> bool {
+    let entry = func.blocks.first().map(|block| block.id);
+    let preds = predecessors(func);
+    for block in &func.blocks {
+        if Some(block.id) == entry || !block.body.is_empty() {
+            continue;
+        }
+        let Some(IrTerm::Br { target }) = block.term else {
+            continue;
+        };
+        if target == block.id {
+            continue;
+        }
+        let id = block.id;
+        let block_preds = preds.get(&id).cloned().unwrap_or_default();
+        if block_preds.is_empty() {
+            continue;
+        }
+        if has_phis(func, target) {
+            let target_preds = preds.get(&target).cloned().unwrap_or_default();
+            if block_preds.len() != 1 || target_preds.contains(&block_preds[0]) {
+                continue;
+            }
+            rename_phi_pred(func, target, id, block_preds[0]);
+        }
+        for pred in block_preds {
+            if let Some(term) = func
+                .blocks
+                .iter_mut()
+                .find(|b| b.id == pred)
+                .and_then(|b| b.term.as_mut())
+            {
+                retarget(term, id, target);
+            }
+        }
+        return true;
+    }
+    false
+}
+
+/// Appends a block to its only predecessor when that predecessor ends in an
+/// unconditional jump to it.
+fn merge_into_predecessor(func: &mut IrFunction) -> bool {
+    let entry = func.blocks.first().map(|block| block.id);
+    let preds = predecessors(func);
+    for block in &func.blocks {
+        let id = block.id;
+        if Some(id) == entry {
+            continue;
+        }
+        let [pred] = preds.get(&id).map(Vec::as_slice).unwrap_or_default() else {
+            continue;
+        };
+        let pred = *pred;
+        if pred == id {
+            continue;
+        }
+        let jumps_here = matches!(
+            func.block(pred).and_then(|b| b.term.as_ref()),
+            Some(IrTerm::Br { target }) if *target == id
+        );
+        if !jumps_here {
+            continue;
+        }
+
+        let idx = func.blocks.iter().position(|b| b.id == id).unwrap();
+        let merged = func.blocks.remove(idx);
+        let mut body = Vec::with_capacity(merged.body.len());
+        let mut forwarded = Vec::new();
+        for instr in merged.body {
+            match instr {
+                // With a single predecessor every phi has one incoming value.
+                IrInstr::Phi { dst, incomings } => {
+                    if let Some((_, value)) = incomings.first() {
+                        forwarded.push((dst, *value));
+                    }
+                }
+                other => body.push(other),
+            }
+        }
+        for succ in successors(merged.term.as_ref()) {
+            rename_phi_pred(func, succ, id, pred);
+        }
+        let target = func.blocks.iter_mut().find(|b| b.id == pred).unwrap();
+        target.body.extend(body);
+        target.term = merged.term;
+        for (dst, value) in forwarded {
+            func.replace_uses(dst, value);
+        }
+        return true;
+    }
+    false
+}
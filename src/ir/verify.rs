@@ -0,0 +1,300 @@
+//! Structural checks for `IrModule`s: every block ends in a terminator that
+//! names existing blocks, every value is defined once and dominates its uses,
+//! phis list exactly the predecessors of their block, and calls, global and
+//! string references point at things that exist.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::passes::{dominators, predecessors, reachable_blocks};
+use super::{IrFunction, IrInstr, IrModule, IrTerm, IrType};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub func: String,
+    pub block: Option<u32>,
+    pub message: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.block {
+            Some(block) => write!(f, "fn {}, block{}: {}", self.func, block, self.message),
+            None => write!(f, "fn {}: {}", self.func, self.message),
+        }
+    }
+}
+
+/// Verifies every function of `module`, returning all problems found.
+pub fn verify_module(module: &IrModule) -> Vec<VerifyError> {
+    let mut errors = Vec::new();
+    for func in &module.funcs {
+        FunctionVerifier::new(module, func, &mut errors).verify();
+    }
+    errors
+}
+
+/// Where a value is defined: a parameter, or instruction `index` of `block`.
+#[derive(Clone, Copy)]
+enum Def {
+    Param,
+    Instr { block: u32, index: usize },
+}
+
+struct FunctionVerifier<'a> {
+    module: &'a IrModule,
+    func: &'a IrFunction,
+    errors: &'a mut Vec<VerifyError>,
+    defs: HashMap<u32, Def>,
+    reachable: HashSet<u32>,
+    dominators: HashMap<u32, HashSet<u32>>,
+}
+
+impl<'a> FunctionVerifier<'a> {
+    fn new(module: &'a IrModule, func: &'a IrFunction, errors: &'a mut Vec<VerifyError>) -> Self {
+        let preds = predecessors(func);
+        Self {
+            module,
+            func,
+            errors,
+            defs: HashMap::new(),
+            reachable: reachable_blocks(func),
+            dominators: dominators(func, &preds),
+        }
+    }
+
+    fn error(&mut self, block: Option<u32>, message: String) {
+        self.errors.push(VerifyError {
+            func: self.func.name.clone(),
+            block,
+            message,
+        });
+    }
+
+    fn verify(mut self) {
+        if self.func.blocks.is_empty() {
+            self.error(None, "function has no blocks".to_string());
+            return;
+        }
+        let mut ids = HashSet::new();
+        for block in &self.func.blocks {
+            if !ids.insert(block.id) {
+                self.error(Some(block.id), "duplicate block id".to_string());
+            }
+        }
+        self.collect_defs();
+        let preds = predecessors(self.func);
+        for block in &self.func.blocks {
+            self.verify_block(block.id, &ids, &preds);
+        }
+    }
+
+    fn collect_defs(&mut self) {
+        for param in 0..self.func.params.len() as u32 {
+            self.defs.insert(param, Def::Param);
+        }
+        for block in &self.func.blocks {
+            for (index, instr) in block.body.iter().enumerate() {
+                let Some(dst) = instr.result() else {
+                    continue;
+                };
+                if self.defs.contains_key(&dst) {
+                    self.error(
+                        Some(block.id),
+                        format!("value %{} is defined more than once", dst),
+                    );
+                    continue;
+                }
+                if dst >= self.func.next_value {
+                    self.error(
+                        Some(block.id),
+                        format!(
+                            "value %{} is not below next_value {}",
+                            dst, self.func.next_value
+                        ),
+                    );
+                }
+                self.defs.insert(
+                    dst,
+                    Def::Instr {
+                        block: block.id,
+                        index,
+                    },
+                );
+            }
+        }
+    }
+
+    fn verify_block(&mut self, id: u32, ids: &HashSet<u32>, preds: &HashMap<u32, Vec<u32>>) {
+        let block = self.func.block(id).expect("block ids were collected");
+        let mut seen_non_phi = false;
+        for (index, instr) in block.body.iter().enumerate() {
+            match instr {
+                IrInstr::Phi { dst, incomings } => {
+                    if seen_non_phi {
+                        self.error(
+                            Some(id),
+                            format!("phi %{} follows a non-phi instruction", dst),
+                        );
+                    }
+                    self.verify_phi(id, *dst, incomings, &preds[&id]);
+                }
+                _ => {
+                    seen_non_phi = true;
+                    for operand in instr.operands() {
+                        self.verify_use(id, index, operand);
+                    }
+                    self.verify_instr(id, instr);
+                }
+            }
+        }
+
+        let Some(term) = &block.term else {
+            self.error(Some(id), "block has no terminator".to_string());
+            return;
+        };
+        for operand in term.operands() {
+            self.verify_use(id, block.body.len(), operand);
+        }
+        for succ in term.successors() {
+            if !ids.contains(&succ) {
+                self.error(Some(id), format!("branch to unknown block{}", succ));
+            }
+        }
+        match term {
+            IrTerm::Ret { value: Some(_) } if self.func.ret == IrType::Void => {
+                self.error(Some(id), "void function returns a value".to_string());
+            }
+            IrTerm::Ret { value: None } if self.func.ret != IrType::Void => {
+                self.error(Some(id), "non-void function returns no value".to_string());
+            }
+            IrTerm::Invoke { call, .. } => self.verify_instr(id, call),
+            _ => {}
+        }
+    }
+
+    fn verify_phi(&mut self, block: u32, dst: u32, incomings: &[(u32, u32)], preds: &[u32]) {
+        let mut seen = HashSet::new();
+        for (pred, value) in incomings {
+            if !seen.insert(*pred) {
+                self.error(
+                    Some(block),
+                    format!("phi %{} has two incomings from block{}", dst, pred),
+                );
+            }
+            if !preds.contains(pred) {
+                self.error(
+                    Some(block),
+                    format!(
+                        "phi %{} has an incoming from block{}, which is not a predecessor",
+                        dst, pred
+                    ),
+                );
+                continue;
+            }
+            // The incoming value is read at the end of the predecessor.
+            let end = self.func.block(*pred).map_or(0, |b| b.body.len());
+            self.verify_use(*pred, end, *value);
+        }
+        for pred in preds {
+            if !seen.contains(pred) {
+                self.error(
+                    Some(block),
+                    format!("phi %{} has no incoming value for block{}", dst, pred),
+                );
+            }
+        }
+    }
+
+    /// Checks that `value` is available before instruction `index` of `block`
+    /// (`index == body.len()` for the terminator).
+    fn verify_use(&mut self, block: u32, index: usize, value: u32) {
+        let Some(def) = self.defs.get(&value).copied() else {
+            self.error(Some(block), format!("use of undefined value %{}", value));
+            return;
+        };
+        let Def::Instr {
+            block: def_block,
+            index: def_index,
+        } = def
+        else {
+            return;
+        };
+        if def_block == block {
+            if def_index >= index {
+                self.error(
+                    Some(block),
+                    format!("value %{} is used before its definition", value),
+                );
+            }
+            return;
+        }
+        if !self.reachable.contains(&block) {
+            return;
+        }
+        let dominated = self
+            .dominators
+            .get(&block)
+            .is_some_and(|doms| doms.contains(&def_block));
+        if !dominated {
+            self.error(
+                Some(block),
+                format!(
+                    "value %{} (defined in block{}) does not dominate this use",
+                    value, def_block
+                ),
+            );
+        }
+    }
+
+    fn verify_instr(&mut self, block: u32, instr: &IrInstr) {
+        match instr {
+            IrInstr::Call { dst, func, args } => {
+                let Some(callee) = self.module.funcs.get(*func as usize) else {
+                    self.error(Some(block), format!("call to unknown function %{}", func));
+                    return;
+                };
+                if callee.params.len() != args.len() {
+                    self.error(
+                        Some(block),
+                        format!(
+                            "call to {} passes {} arguments, expected {}",
+                            callee.name,
+                            args.len(),
+                            callee.params.len()
+                        ),
+                    );
+                }
+                if dst.is_some() && callee.ret == IrType::Void {
+                    self.error(
+                        Some(block),
+                        format!("call to void function {} has a result", callee.name),
+                    );
+                }
+            }
+            IrInstr::LoadGlobal { global, .. } => {
+                if self.module.globals.get(*global as usize).is_none() {
+                    self.error(Some(block), format!("unknown global @{}", global));
+                }
+            }
+            IrInstr::StoreGlobal { global, .. } => {
+                match self.module.globals.get(*global as usize) {
+                    None => self.error(Some(block), format!("unknown global @{}", global)),
+                    Some(g) if !g.mutable => {
+                        self.error(
+                            Some(block),
+                            format!("store to immutable global @{}", g.name),
+                        );
+                    }
+                    Some(_) => {}
+                }
+            }
+            IrInstr::LoadConstStr { sid, .. } | IrInstr::PrintStr { sid } => {
+                if *sid as usize >= self.module.strings.len() {
+                    self.error(Some(block), format!("unknown string id {}", sid));
+                }
+            }
+            _ => {}
+        }
+    }
+}
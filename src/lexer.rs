@@ -1,6 +1,6 @@
 use crate::diagnostics::LexError;
 use crate::span::Span;
-use crate::token::{Keyword, Token, TokenKind};
+use crate::token::{Keyword, StringPart, Token, TokenKind};
 
 pub fn lex(source: &str) -> Result<Vec<Token>, LexError> {
     Lexer::new(source).lex()
@@ -32,117 +32,126 @@ impl<'a> Lexer<'a> {
                 Some(ch) => ch,
                 None => break,
             };
-            let token = if is_ident_start(ch) {
-                self.lex_identifier()?
-            } else if ch.is_alphabetic() && !ch.is_ascii() {
-                let span = Span::new(
-                    self.index,
-                    self.index + ch.len_utf8(),
-                    self.line,
-                    self.column,
-                );
-                return Err(LexError::NonAsciiIdentifierChar { ch, span });
-            } else if ch.is_ascii_digit() {
-                self.lex_number()?
-            } else {
-                match ch {
-                    '"' => self.lex_string()?,
-                    '\'' => self.lex_char_literal()?,
-                    '{' => self.simple_token(TokenKind::LeftBrace),
-                    '}' => self.simple_token(TokenKind::RightBrace),
-                    '(' => self.simple_token(TokenKind::LeftParen),
-                    ')' => self.simple_token(TokenKind::RightParen),
-                    '[' => self.simple_token(TokenKind::LeftBracket),
-                    ']' => self.simple_token(TokenKind::RightBracket),
-                    ',' => self.simple_token(TokenKind::Comma),
-                    '.' => {
-                        if self.peek_second_char() == Some('.') {
-                            self.multi_char_token(2, TokenKind::DotDot)
-                        } else {
-                            self.simple_token(TokenKind::Dot)
-                        }
-                    }
-                    ';' => self.simple_token(TokenKind::Semicolon),
-                    ':' => {
-                        if self.peek_second_char() == Some(':') {
-                            self.multi_char_token(2, TokenKind::ColonColon)
-                        } else {
-                            self.simple_token(TokenKind::Colon)
-                        }
-                    }
-                    '-' => {
-                        if self.peek_second_char() == Some('>') {
-                            self.multi_char_token(2, TokenKind::ThinArrow)
-                        } else {
-                            self.simple_token(TokenKind::Minus)
-                        }
-                    }
-                    '=' => {
-                        if self.peek_second_char() == Some('=') {
-                            self.multi_char_token(2, TokenKind::EqualEqual)
-                        } else {
-                            self.simple_token(TokenKind::Equals)
-                        }
+            tokens.push(self.lex_token(ch)?);
+        }
+        tokens.push(Token::new(
+            TokenKind::Eof,
+            Span::new(self.index, self.index, self.line, self.column),
+        ));
+        Ok(tokens)
+    }
+
+    fn lex_token(&mut self, ch: char) -> Result<Token, LexError> {
+        let token = if is_ident_start(ch) {
+            self.lex_identifier()?
+        } else if ch.is_alphabetic() && !ch.is_ascii() {
+            let span = Span::new(
+                self.index,
+                self.index + ch.len_utf8(),
+                self.line,
+                self.column,
+            );
+            return Err(LexError::NonAsciiIdentifierChar { ch, span });
+        } else if ch.is_ascii_digit() {
+            self.lex_number()?
+        } else {
+            match ch {
+                '"' => self.lex_string()?,
+                '\'' => {
+                    let label = self.peek_second_char().is_some_and(is_ident_start)
+                        && self.peek_nth_char(2) != Some('\'');
+                    if label {
+                        self.lex_label()
+                    } else {
+                        self.lex_char_literal()?
                     }
-                    '&' => {
-                        if self.peek_second_char() == Some('&') {
-                            self.multi_char_token(2, TokenKind::AmpersandAmpersand)
-                        } else {
-                            self.simple_token(TokenKind::Ampersand)
-                        }
+                }
+                '{' => self.simple_token(TokenKind::LeftBrace),
+                '}' => self.simple_token(TokenKind::RightBrace),
+                '(' => self.simple_token(TokenKind::LeftParen),
+                ')' => self.simple_token(TokenKind::RightParen),
+                '[' => self.simple_token(TokenKind::LeftBracket),
+                ']' => self.simple_token(TokenKind::RightBracket),
+                ',' => self.simple_token(TokenKind::Comma),
+                '.' => {
+                    if self.peek_second_char() == Some('.') {
+                        self.multi_char_token(2, TokenKind::DotDot)
+                    } else {
+                        self.simple_token(TokenKind::Dot)
                     }
-                    '|' => {
-                        if self.peek_second_char() == Some('|') {
-                            self.multi_char_token(2, TokenKind::PipePipe)
-                        } else {
-                            self.simple_token(TokenKind::Pipe)
-                        }
+                }
+                ';' => self.simple_token(TokenKind::Semicolon),
+                ':' => {
+                    if self.peek_second_char() == Some(':') {
+                        self.multi_char_token(2, TokenKind::ColonColon)
+                    } else {
+                        self.simple_token(TokenKind::Colon)
                     }
-                    '+' => self.simple_token(TokenKind::Plus),
-                    '*' => self.simple_token(TokenKind::Star),
-                    '/' => self.simple_token(TokenKind::Slash),
-                    '%' => self.simple_token(TokenKind::Percent),
-                    '?' => self.simple_token(TokenKind::Question),
-                    '!' => {
-                        if self.peek_second_char() == Some('=') {
-                            self.multi_char_token(2, TokenKind::BangEqual)
-                        } else {
-                            self.simple_token(TokenKind::Bang)
-                        }
+                }
+                '-' => match self.peek_second_char() {
+                    Some('>') => self.multi_char_token(2, TokenKind::ThinArrow),
+                    Some('=') => self.multi_char_token(2, TokenKind::MinusEqual),
+                    _ => self.simple_token(TokenKind::Minus),
+                },
+                '=' => {
+                    if self.peek_second_char() == Some('=') {
+                        self.multi_char_token(2, TokenKind::EqualEqual)
+                    } else {
+                        self.simple_token(TokenKind::Equals)
                     }
-                    '<' => {
-                        if self.peek_second_char() == Some('=') {
-                            self.multi_char_token(2, TokenKind::LessEqual)
-                        } else {
-                            self.simple_token(TokenKind::Less)
-                        }
+                }
+                '&' => match self.peek_second_char() {
+                    Some('&') => self.multi_char_token(2, TokenKind::AmpersandAmpersand),
+                    Some('=') => self.multi_char_token(2, TokenKind::AmpersandEqual),
+                    _ => self.simple_token(TokenKind::Ampersand),
+                },
+                '|' => match self.peek_second_char() {
+                    Some('|') => self.multi_char_token(2, TokenKind::PipePipe),
+                    Some('=') => self.multi_char_token(2, TokenKind::PipeEqual),
+                    _ => self.simple_token(TokenKind::Pipe),
+                },
+                '+' => self.with_equals(TokenKind::Plus, TokenKind::PlusEqual),
+                '*' => self.with_equals(TokenKind::Star, TokenKind::StarEqual),
+                '/' => self.with_equals(TokenKind::Slash, TokenKind::SlashEqual),
+                '%' => self.with_equals(TokenKind::Percent, TokenKind::PercentEqual),
+                '^' => self.with_equals(TokenKind::Caret, TokenKind::CaretEqual),
+                '~' => self.simple_token(TokenKind::Tilde),
+                '?' => self.simple_token(TokenKind::Question),
+                '!' => {
+                    if self.peek_second_char() == Some('=') {
+                        self.multi_char_token(2, TokenKind::BangEqual)
+                    } else {
+                        self.simple_token(TokenKind::Bang)
                     }
-                    '>' => {
-                        if self.peek_second_char() == Some('=') {
-                            self.multi_char_token(2, TokenKind::GreaterEqual)
-                        } else {
-                            self.simple_token(TokenKind::Greater)
-                        }
+                }
+                '<' => match self.peek_second_char() {
+                    Some('=') => self.multi_char_token(2, TokenKind::LessEqual),
+                    Some('<') if self.peek_nth_char(2) == Some('=') => {
+                        self.multi_char_token(3, TokenKind::LessLessEqual)
                     }
-                    '@' => self.simple_token(TokenKind::At),
-                    other => {
-                        let span = Span::new(
-                            self.index,
-                            self.index + other.len_utf8(),
-                            self.line,
-                            self.column,
-                        );
-                        return Err(LexError::UnexpectedChar { ch: other, span });
+                    Some('<') => self.multi_char_token(2, TokenKind::LessLess),
+                    _ => self.simple_token(TokenKind::Less),
+                },
+                '>' => {
+                    if self.peek_second_char() == Some('=') {
+                        self.multi_char_token(2, TokenKind::GreaterEqual)
+                    } else {
+                        self.simple_token(TokenKind::Greater)
                     }
                 }
-            };
-            tokens.push(token);
-        }
-        tokens.push(Token::new(
-            TokenKind::Eof,
-            Span::new(self.index, self.index, self.line, self.column),
-        ));
-        Ok(tokens)
+                '@' => self.simple_token(TokenKind::At),
+                other => {
+                    let span = Span::new(
+                        self.index,
+                        self.index + other.len_utf8(),
+                        self.line,
+                        self.column,
+                    );
+                    return Err(LexError::UnexpectedChar { ch: other, span });
+                }
+            }
+        };
+        Ok(token)
     }
 
     fn lex_identifier(&mut self) -> Result<Token, LexError> {
@@ -211,12 +220,28 @@ impl<'a> Lexer<'a> {
         let start_line = self.line;
         let start_col = self.column;
         self.advance_char(); // opening "
+        let mut parts = Vec::new();
         let mut value = String::new();
         loop {
             match self.advance_char() {
                 Some((_, '"')) => {
                     let span = Span::new(start_index, self.index, start_line, start_col);
-                    return Ok(Token::new(TokenKind::StringLiteral(value), span));
+                    if parts.is_empty() {
+                        return Ok(Token::new(TokenKind::StringLiteral(value), span));
+                    }
+                    if !value.is_empty() {
+                        parts.push(StringPart::Text(value));
+                    }
+                    return Ok(Token::new(TokenKind::InterpolatedString(parts), span));
+                }
+                Some((dollar, '$')) if self.peek_char() == Some('{') => {
+                    let (line, column) = (self.line, self.column - 1);
+                    self.advance_char();
+                    if !value.is_empty() {
+                        parts.push(StringPart::Text(std::mem::take(&mut value)));
+                    }
+                    let open = Span::new(dollar, self.index, line, column);
+                    parts.push(StringPart::Expr(self.lex_interpolation(open)?));
                 }
                 Some((_, '\\')) => {
                     if let Some((_, esc)) = self.advance_char() {
@@ -245,6 +270,58 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Lexes the expression of a `${...}` segment up to its closing `}`,
+    /// which is consumed. Tokens keep their positions in the source, so
+    /// diagnostics for the expression point inside the literal. `open` is the
+    /// span of the `${`; a missing `}` is reported there, including when the
+    /// literal's closing quote is taken as the start of a nested string.
+    fn lex_interpolation(&mut self, open: Span) -> Result<Vec<Token>, LexError> {
+        let mut tokens = Vec::new();
+        let mut depth = 0usize;
+        loop {
+            self.skip_trivia()?;
+            let Some(ch) = self.peek_char() else {
+                return Err(LexError::UnterminatedInterpolation { span: open });
+            };
+            match ch {
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    tokens.push(Token::new(
+                        TokenKind::Eof,
+                        Span::new(self.index, self.index, self.line, self.column),
+                    ));
+                    self.advance_char();
+                    return Ok(tokens);
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+            let token = self.lex_token(ch).map_err(|err| match err {
+                LexError::UnterminatedString { .. } => {
+                    LexError::UnterminatedInterpolation { span: open }
+                }
+                other => other,
+            })?;
+            tokens.push(token);
+        }
+    }
+
+    /// `'name`: a loop label, told apart from a character literal by the
+    /// missing closing quote.
+    fn lex_label(&mut self) -> Token {
+        let start_index = self.index;
+        let start_line = self.line;
+        let start_col = self.column;
+        self.advance_char(); // '
+        let mut name = String::new();
+        while let Some(ch) = self.peek_char().filter(|ch| is_ident_char(*ch)) {
+            name.push(ch);
+            self.advance_char();
+        }
+        let span = Span::new(start_index, self.index, start_line, start_col);
+        Token::new(TokenKind::Label(name), span)
+    }
+
     fn lex_char_literal(&mut self) -> Result<Token, LexError> {
         let start_index = self.index;
         let start_line = self.line;
@@ -334,6 +411,15 @@ impl<'a> Lexer<'a> {
         Ok(())
     }
 
+    /// `kind`, or `with_equals` when the next character is `=`.
+    fn with_equals(&mut self, kind: TokenKind, with_equals: TokenKind) -> Token {
+        if self.peek_second_char() == Some('=') {
+            self.multi_char_token(2, with_equals)
+        } else {
+            self.simple_token(kind)
+        }
+    }
+
     fn simple_token(&mut self, kind: TokenKind) -> Token {
         let start_index = self.index;
         let start_line = self.line;
@@ -446,6 +532,7 @@ fn keyword(ident: &str) -> Option<Keyword> {
         "let" => Some(Keyword::Let),
         "var" => Some(Keyword::Var),
         "const" => Some(Keyword::Const),
+        "static" => Some(Keyword::Static),
         "struct" => Some(Keyword::Struct),
         "enum" => Some(Keyword::Enum),
         "trait" => Some(Keyword::Trait),
@@ -474,6 +561,8 @@ fn keyword(ident: &str) -> Option<Keyword> {
         "type" => Some(Keyword::Type),
         "pub" => Some(Keyword::Pub),
         "check" => Some(Keyword::Check),
+        "where" => Some(Keyword::Where),
+        "loop" => Some(Keyword::Loop),
         _ => None,
     }
 }
@@ -26,6 +26,7 @@ pub mod parser;
 pub mod runtime;
 pub mod span;
 pub mod token;
+pub mod type_checker;
 pub mod ui;
 pub mod validation;
 
@@ -1,5 +1,6 @@
 #![allow(warnings)]
 mod ast;
+mod bytecode;
 mod diagnostics;
 mod lexer;
 mod module_loader;
@@ -8,6 +9,7 @@ mod parser;
 mod runtime;
 mod span;
 mod token;
+mod type_checker;
 mod validation;
 
 // Include Android library when building for Android
@@ -39,11 +41,17 @@ struct Cli {
     /// Execute apex() with the prototype interpreter.
     #[arg(long)]
     run: bool,
+    /// Treat FILE as a compiled .afbc module and execute apex() on the bytecode VM.
+    #[arg(long)]
+    vm: bool,
 }
 
 fn main() -> anyhow::Result<()> {
     eprintln!("[main] start");
     let cli = Cli::parse();
+    if cli.vm {
+        return run_bytecode(cli.input.as_deref());
+    }
     let input = read_source(cli.input.as_deref())?;
     let source = &input.source;
     eprintln!("[main] source loaded ({} bytes)", source.len());
@@ -84,6 +92,14 @@ fn main() -> anyhow::Result<()> {
             }
             return Ok(());
         }
+        let type_errors = type_checker::check_file(&report.file);
+        if !type_errors.is_empty() {
+            for err in type_errors {
+                let msg = diagnostics::format_diagnostic(source, Some(err.span), &err.message);
+                eprintln!("{msg}");
+            }
+            return Ok(());
+        }
         let ast = report.file;
         eprintln!("[main] parse complete");
         if cli.ast {
@@ -99,9 +115,13 @@ fn main() -> anyhow::Result<()> {
 
         if cli.run {
             let module_loader = module_loader::ModuleLoader::with_root(input.root.clone());
-            let mut interpreter = runtime::Interpreter::with_module_loader(module_loader);
+            let interpreter = runtime::Interpreter::new(module_loader);
             eprintln!("[main] interpreter created, running apex");
-            if let Err(err) = interpreter.run(&ast) {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .context("failed to start async runtime")?;
+            if let Err(err) = runtime.block_on(interpreter.run(&ast)) {
                 let message = format!("runtime error: {}", err.message());
                 let formatted = diagnostics::format_diagnostic(source, err.span(), &message);
                 eprintln!("{formatted}");
@@ -113,6 +133,19 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn run_bytecode(path: Option<&Path>) -> anyhow::Result<()> {
+    let path = path.context("--vm requires an .afbc file")?;
+    let bytes =
+        fs::read(path).with_context(|| format!("failed to read bytecode {}", path.display()))?;
+    let module = bytecode::AfbcModule::read(&mut bytes.as_slice())
+        .with_context(|| format!("invalid AFBC module {}", path.display()))?;
+    let mut vm = bytecode::vm::Vm::new(&module).map_err(|err| anyhow::anyhow!(err.message()))?;
+    if let Err(err) = vm.run() {
+        eprintln!("runtime error: {}", err.message());
+    }
+    Ok(())
+}
+
 struct SourceInput {
     source: String,
     root: PathBuf,
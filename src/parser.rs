@@ -2,7 +2,7 @@ use crate::ast::*;
 use crate::diagnostics::{AfnsError, ParseError};
 use crate::lexer;
 use crate::span::Span;
-use crate::token::{Keyword, Token, TokenKind};
+use crate::token::{Keyword, StringPart, Token, TokenKind};
 
 #[allow(dead_code)]
 pub fn parse(source: &str) -> Result<File, AfnsError> {
@@ -40,6 +40,9 @@ struct Parser<'a> {
     tokens: Vec<Token>,
     index: usize,
     errors: Vec<ParseError>,
+    /// Set while parsing a `switch` scrutinee, where `{` opens the arms
+    /// rather than a struct literal.
+    no_struct_literals: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -49,6 +52,7 @@ impl<'a> Parser<'a> {
             tokens,
             index: 0,
             errors: Vec::new(),
+            no_struct_literals: false,
         }
     }
 
@@ -181,33 +185,54 @@ impl<'a> Parser<'a> {
         if self.is_at_end() {
             return Ok(None);
         }
-        if self.check_keyword(Keyword::Struct) {
-            return Ok(Some(Item::Struct(self.parse_struct(attributes)?)));
-        }
-        if self.check_keyword(Keyword::Enum) {
-            return Ok(Some(Item::Enum(self.parse_enum(attributes)?)));
-        }
-        if self.check_keyword(Keyword::Trait) {
-            return Ok(Some(Item::Trait(self.parse_trait(attributes)?)));
-        }
-        if self.check_keyword(Keyword::Impl) {
-            return Ok(Some(Item::Impl(self.parse_impl(attributes)?)));
-        }
-        if self.check_keyword(Keyword::Extern) {
-            return Ok(Some(Item::ExternFunction(self.parse_extern(attributes)?)));
-        }
-        if self.check_keyword(Keyword::Async) || self.check_keyword(Keyword::Fun) {
-            return Ok(Some(Item::Function(self.parse_function(attributes)?)));
-        }
-        if self.check(|k| matches!(k, TokenKind::Eof)) {
+        let is_pub = self.match_keyword(Keyword::Pub);
+        let mut item = if self.check_keyword(Keyword::Struct) {
+            Item::Struct(self.parse_struct(attributes)?)
+        } else if self.check_keyword(Keyword::Enum) {
+            Item::Enum(self.parse_enum(attributes)?)
+        } else if self.check_keyword(Keyword::Trait) {
+            Item::Trait(self.parse_trait(attributes)?)
+        } else if self.check_keyword(Keyword::Impl) && !is_pub {
+            Item::Impl(self.parse_impl(attributes)?)
+        } else if self.check_keyword(Keyword::Extern) {
+            Item::ExternFunction(self.parse_extern(attributes)?)
+        } else if self.check_keyword(Keyword::Async) || self.check_keyword(Keyword::Fun) {
+            Item::Function(self.parse_function(attributes)?)
+        } else if self.check_keyword(Keyword::Const)
+            || self.check_keyword(Keyword::Static)
+            || self.check_keyword(Keyword::Var)
+        {
+            Item::Global(self.parse_global(attributes)?)
+        } else if self.check_keyword(Keyword::Type) {
+            Item::TypeAlias(self.parse_type_alias(attributes)?)
+        } else if self.check(|k| matches!(k, TokenKind::Eof)) && !is_pub {
             return Ok(None);
-        }
-        let token = self.peek().clone();
-        Err(ParseError::UnexpectedToken {
-            expected: "top level declaration",
-            found: token.kind,
-            span: token.span,
-        })
+        } else {
+            let token = self.peek().clone();
+            return Err(ParseError::UnexpectedToken {
+                expected: if is_pub {
+                    "item after `pub`"
+                } else {
+                    "top level declaration"
+                },
+                found: token.kind,
+                span: token.span,
+            });
+        };
+        if is_pub {
+            match &mut item {
+                Item::Function(func) => func.signature.is_pub = true,
+                Item::ExternFunction(func) => func.signature.is_pub = true,
+                Item::Struct(def) => def.is_pub = true,
+                Item::Enum(def) => def.is_pub = true,
+                Item::Trait(def) => def.is_pub = true,
+                Item::Global(def) => def.is_pub = true,
+                Item::TypeAlias(def) => def.is_pub = true,
+                // `pub impl` is rejected above; methods carry their own `pub`.
+                Item::Impl(_) => {}
+            }
+        }
+        Ok(Some(item))
     }
 
     fn parse_attributes(&mut self) -> Result<Vec<Attribute>, ParseError> {
@@ -252,13 +277,66 @@ impl<'a> Parser<'a> {
         Ok(attrs)
     }
 
+    fn parse_global(&mut self, attributes: Vec<Attribute>) -> Result<GlobalDef, ParseError> {
+        let start = self.peek().span;
+        let kind = if self.match_keyword(Keyword::Const) {
+            GlobalKind::Const
+        } else if self.match_keyword(Keyword::Static) {
+            GlobalKind::Static
+        } else {
+            self.expect_keyword(Keyword::Var)?;
+            GlobalKind::Var
+        };
+        let (name, _) = self.expect_identifier("global name")?;
+        let ty = if self.match_type_separator() {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.expect_with("'='", |k| matches!(k, TokenKind::Equals))?;
+        let value = self.parse_expression()?;
+        let end = self
+            .expect_with("';'", |k| matches!(k, TokenKind::Semicolon))?
+            .span;
+        Ok(GlobalDef {
+            attributes,
+            is_pub: false,
+            kind,
+            name,
+            ty,
+            value,
+            span: start.merge(end),
+        })
+    }
+
+    fn parse_type_alias(&mut self, attributes: Vec<Attribute>) -> Result<TypeAliasDef, ParseError> {
+        let start = self.expect_keyword(Keyword::Type)?.span;
+        let (name, _) = self.expect_identifier("type alias name")?;
+        let type_params = self.parse_type_params()?;
+        self.expect_with("'='", |k| matches!(k, TokenKind::Equals))?;
+        let target = self.parse_type()?;
+        let end = self
+            .expect_with("';'", |k| matches!(k, TokenKind::Semicolon))?
+            .span;
+        Ok(TypeAliasDef {
+            attributes,
+            is_pub: false,
+            name,
+            type_params,
+            target,
+            span: start.merge(end),
+        })
+    }
+
     fn parse_struct(&mut self, attributes: Vec<Attribute>) -> Result<StructDef, ParseError> {
         let start = self.expect_keyword(Keyword::Struct)?.span;
         let (name, _) = self.expect_identifier("struct name")?;
-        let type_params = self.parse_type_params()?;
+        let mut type_params = self.parse_type_params()?;
+        self.parse_where_clause(&mut type_params)?;
         self.expect_with("'{'", |k| matches!(k, TokenKind::LeftBrace))?;
         let mut fields = Vec::new();
         while !self.check(|k| matches!(k, TokenKind::RightBrace)) {
+            let is_pub = self.match_keyword(Keyword::Pub);
             let (field_name, field_span) = self.expect_identifier("struct field name")?;
             self.expect_type_separator()?;
             let ty = self.parse_type()?;
@@ -267,6 +345,7 @@ impl<'a> Parser<'a> {
                 span = span.merge(self.prev().span);
             }
             fields.push(StructField {
+                is_pub,
                 name: field_name,
                 ty,
                 span,
@@ -277,6 +356,7 @@ impl<'a> Parser<'a> {
             .span;
         Ok(StructDef {
             attributes,
+            is_pub: false,
             name,
             type_params,
             fields,
@@ -323,6 +403,7 @@ impl<'a> Parser<'a> {
             .span;
         Ok(EnumDef {
             attributes,
+            is_pub: false,
             name,
             type_params,
             variants,
@@ -337,15 +418,21 @@ impl<'a> Parser<'a> {
         self.expect_with("'{'", |k| matches!(k, TokenKind::LeftBrace))?;
         let mut methods = Vec::new();
         while !self.check(|k| matches!(k, TokenKind::RightBrace)) {
-            let sig = self.parse_function_signature()?;
-            self.expect_with("';'", |k| matches!(k, TokenKind::Semicolon))?;
-            methods.push(sig);
+            let signature = self.parse_function_signature()?;
+            let default = if self.check(|k| matches!(k, TokenKind::LeftBrace)) {
+                Some(self.parse_block()?)
+            } else {
+                self.expect_with("';' or method body", |k| matches!(k, TokenKind::Semicolon))?;
+                None
+            };
+            methods.push(TraitMethod { signature, default });
         }
         let end = self
             .expect_with("'}'", |k| matches!(k, TokenKind::RightBrace))?
             .span;
         Ok(TraitDef {
             attributes,
+            is_pub: false,
             name,
             type_params,
             methods,
@@ -355,7 +442,7 @@ impl<'a> Parser<'a> {
 
     fn parse_impl(&mut self, attributes: Vec<Attribute>) -> Result<ImplBlock, ParseError> {
         let start = self.expect_keyword(Keyword::Impl)?.span;
-        let type_params = self.parse_type_params()?;
+        let mut type_params = self.parse_type_params()?;
         let target_or_trait = self.parse_type()?;
         let (trait_type, target) = if self.match_keyword(Keyword::For) {
             let target = self.parse_type()?;
@@ -363,11 +450,15 @@ impl<'a> Parser<'a> {
         } else {
             (None, target_or_trait)
         };
+        self.parse_where_clause(&mut type_params)?;
         self.expect_with("'{'", |k| matches!(k, TokenKind::LeftBrace))?;
         let mut methods = Vec::new();
         while !self.check(|k| matches!(k, TokenKind::RightBrace)) {
             let attrs = self.parse_attributes()?;
-            methods.push(self.parse_function(attrs)?);
+            let is_pub = self.match_keyword(Keyword::Pub);
+            let mut method = self.parse_function(attrs)?;
+            method.signature.is_pub = is_pub;
+            methods.push(method);
         }
         let end = self
             .expect_with("'}'", |k| matches!(k, TokenKind::RightBrace))?
@@ -425,7 +516,7 @@ impl<'a> Parser<'a> {
             start_span = fun_token.span;
         }
         let (name, _) = self.expect_identifier("function name")?;
-        let type_params = self.parse_type_params()?;
+        let mut type_params = self.parse_type_params()?;
         self.expect_with("'('", |k| matches!(k, TokenKind::LeftParen))?;
         let mut params = Vec::new();
         if !self.check(|k| matches!(k, TokenKind::RightParen)) {
@@ -453,6 +544,7 @@ impl<'a> Parser<'a> {
             }
             return_type = Some(self.parse_type()?);
         }
+        self.parse_where_clause(&mut type_params)?;
         let end_span = return_type
             .as_ref()
             .map(|ty| ty.span())
@@ -460,6 +552,7 @@ impl<'a> Parser<'a> {
         let span = start_span.merge(end_span);
         Ok(FunctionSignature {
             name,
+            is_pub: false,
             is_async,
             returns_async,
             params,
@@ -519,42 +612,54 @@ impl<'a> Parser<'a> {
             let start = self.prev().span;
             Ok(Stmt::If(self.parse_if(start)?))
         } else if self.match_keyword(Keyword::While) {
-            let start = self.prev().span;
-            let condition = self.parse_expression()?;
-            let body = self.parse_block()?;
-            let span = start.merge(body.span);
-            Ok(Stmt::While {
-                condition,
-                body,
-                span,
-            })
+            self.parse_while(self.prev().span, None)
         } else if self.match_keyword(Keyword::For) {
-            let start = self.prev().span;
-            let (name, name_span) = self.expect_identifier("for binding")?;
-            self.expect_keyword(Keyword::In)?;
-            let iterable = self.parse_expression()?;
-            let body = self.parse_block()?;
-            let span = start.merge(body.span).merge(name_span);
-            Ok(Stmt::For {
-                var: name,
-                iterable,
-                body,
-                span,
-            })
+            self.parse_for(self.prev().span, None)
+        } else if self.match_keyword(Keyword::Loop) {
+            let loop_expr = self.parse_loop(self.prev().span, None)?;
+            self.match_with(|k| matches!(k, TokenKind::Semicolon));
+            Ok(Stmt::Expr(loop_expr))
+        } else if let TokenKind::Label(label) = self.peek().kind.clone() {
+            let start = self.advance().span;
+            self.expect_with("':'", |k| matches!(k, TokenKind::Colon))?;
+            if self.match_keyword(Keyword::While) {
+                self.parse_while(start, Some(label))
+            } else if self.match_keyword(Keyword::For) {
+                self.parse_for(start, Some(label))
+            } else {
+                self.expect_keyword(Keyword::Loop)?;
+                let loop_expr = self.parse_loop(start, Some(label))?;
+                self.match_with(|k| matches!(k, TokenKind::Semicolon));
+                Ok(Stmt::Expr(loop_expr))
+            }
         } else if self.match_keyword(Keyword::Switch) {
             Ok(Stmt::Switch(self.parse_switch(self.prev().span)?))
         } else if self.match_keyword(Keyword::Break) {
             let start = self.prev().span;
+            let label = self.match_label();
+            let value = if !self.check(|k| matches!(k, TokenKind::Semicolon)) {
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
             let end = self
                 .expect_with("';'", |k| matches!(k, TokenKind::Semicolon))?
                 .span;
-            Ok(Stmt::Break(start.merge(end)))
+            Ok(Stmt::Break {
+                label,
+                value,
+                span: start.merge(end),
+            })
         } else if self.match_keyword(Keyword::Continue) {
             let start = self.prev().span;
+            let label = self.match_label();
             let end = self
                 .expect_with("';'", |k| matches!(k, TokenKind::Semicolon))?
                 .span;
-            Ok(Stmt::Continue(start.merge(end)))
+            Ok(Stmt::Continue {
+                label,
+                span: start.merge(end),
+            })
         } else if self.match_keyword(Keyword::Try) {
             Ok(Stmt::Try(self.parse_try(self.prev().span)?))
         } else if self.match_keyword(Keyword::Unsafe) {
@@ -583,8 +688,8 @@ impl<'a> Parser<'a> {
             self.expect_keyword(Keyword::Const)?;
             VarKind::Const
         };
-        let (name, name_span) = self.expect_identifier("variable name")?;
-        let mut span = name_span;
+        let pattern = self.parse_binding_pattern("variable name")?;
+        let mut span = pattern.span();
         let ty = if self.match_type_separator() {
             let ty = self.parse_type()?;
             span = span.merge(ty.span());
@@ -601,21 +706,81 @@ impl<'a> Parser<'a> {
         span = span.merge(end);
         Ok(Stmt::VarDecl(VarDecl {
             kind,
-            name,
+            pattern,
             ty,
             value,
             span,
         }))
     }
 
+    fn parse_while(&mut self, start: Span, label: Option<String>) -> Result<Stmt, ParseError> {
+        let condition = self.parse_condition()?;
+        let body = self.parse_block()?;
+        let span = start.merge(body.span);
+        Ok(Stmt::While {
+            label,
+            condition,
+            body,
+            span,
+        })
+    }
+
+    fn parse_for(&mut self, start: Span, label: Option<String>) -> Result<Stmt, ParseError> {
+        let pattern = self.parse_binding_pattern("for binding")?;
+        self.expect_keyword(Keyword::In)?;
+        let iterable = self.parse_expression()?;
+        let body = self.parse_block()?;
+        let span = start.merge(body.span).merge(pattern.span());
+        Ok(Stmt::For {
+            label,
+            pattern,
+            iterable,
+            body,
+            span,
+        })
+    }
+
+    /// The body of a `loop`, whose keyword (and label) are already consumed.
+    fn parse_loop(&mut self, start: Span, label: Option<String>) -> Result<Expr, ParseError> {
+        let body = self.parse_block()?;
+        let span = start.merge(body.span);
+        Ok(Expr::Loop { label, body, span })
+    }
+
+    fn match_label(&mut self) -> Option<String> {
+        if let TokenKind::Label(label) = self.peek().kind.clone() {
+            self.advance();
+            Some(label)
+        } else {
+            None
+        }
+    }
+
+    /// An `if` or `while` condition: an expression, or `let pattern = value`.
+    fn parse_condition(&mut self) -> Result<Expr, ParseError> {
+        if !self.match_keyword(Keyword::Let) {
+            return self.parse_expression();
+        }
+        let start = self.prev().span;
+        let pattern = self.parse_pattern()?;
+        self.expect_with("'='", |k| matches!(k, TokenKind::Equals))?;
+        let value = self.parse_expression()?;
+        let span = start.merge(value.span());
+        Ok(Expr::Let {
+            pattern,
+            value: Box::new(value),
+            span,
+        })
+    }
+
     fn parse_if(&mut self, start: Span) -> Result<IfStmt, ParseError> {
-        let condition = self.parse_expression()?;
+        let condition = self.parse_condition()?;
         let then_branch = self.parse_block()?;
         let mut else_if = Vec::new();
         let mut else_branch = None;
         while self.match_keyword(Keyword::Else) {
             if self.match_keyword(Keyword::If) {
-                let cond = self.parse_expression()?;
+                let cond = self.parse_condition()?;
                 let block = self.parse_block()?;
                 else_if.push((cond, block));
             } else {
@@ -640,16 +805,25 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_switch(&mut self, start: Span) -> Result<SwitchStmt, ParseError> {
-        let expr = self.parse_expression()?;
+        let saved = std::mem::replace(&mut self.no_struct_literals, true);
+        let expr = self.parse_expression();
+        self.no_struct_literals = saved;
+        let expr = expr?;
         self.expect_with("'{'", |k| matches!(k, TokenKind::LeftBrace))?;
         let mut arms = Vec::new();
         while !self.check(|k| matches!(k, TokenKind::RightBrace)) {
             let pattern = self.parse_pattern()?;
+            let guard = if self.match_keyword(Keyword::If) {
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
             self.expect_with("'->'", |k| matches!(k, TokenKind::ThinArrow))?;
             let value = self.parse_expression()?;
             let span = pattern.span().merge(value.span());
             arms.push(SwitchArm {
                 pattern,
+                guard,
                 expr: value,
                 span,
             });
@@ -800,73 +974,184 @@ impl<'a> Parser<'a> {
         }))
     }
 
+    /// Parses a pattern, including `|` alternatives.
     fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let first = self.parse_single_pattern()?;
+        if !self.check(|k| matches!(k, TokenKind::Pipe)) {
+            return Ok(first);
+        }
+        let mut span = first.span();
+        let mut alternatives = vec![first];
+        while self.match_with(|k| matches!(k, TokenKind::Pipe)) {
+            let alternative = self.parse_single_pattern()?;
+            span = span.merge(alternative.span());
+            alternatives.push(alternative);
+        }
+        Ok(Pattern::Or { alternatives, span })
+    }
+
+    /// The left side of `let`, `var`, `const` and `for`: a plain name, which
+    /// may be followed by a `::` type annotation, or a destructuring pattern.
+    fn parse_binding_pattern(&mut self, expected: &'static str) -> Result<Pattern, ParseError> {
+        let destructures = match (&self.peek().kind, self.peek_kind_at(1)) {
+            (TokenKind::Identifier(_), Some(TokenKind::LeftParen | TokenKind::LeftBrace)) => true,
+            (TokenKind::Identifier(_), _) => false,
+            _ => true,
+        };
+        if destructures {
+            return self.parse_pattern();
+        }
+        let (name, span) = self.expect_identifier(expected)?;
+        Ok(if name == "_" {
+            Pattern::Wildcard { span }
+        } else {
+            Pattern::Binding { name, span }
+        })
+    }
+
+    fn parse_single_pattern(&mut self) -> Result<Pattern, ParseError> {
         let token = self.advance();
         match token.kind.clone() {
             TokenKind::Identifier(name) => {
                 if name == "_" {
-                    Ok(Pattern::Wildcard { span: token.span })
-                } else {
-                    let mut segments = vec![name.clone()];
-                    let mut span = token.span;
-                    while self.match_with(|k| matches!(k, TokenKind::ColonColon)) {
-                        let (seg, seg_span) = self.expect_identifier("pattern path segment")?;
-                        span = span.merge(seg_span);
-                        segments.push(seg);
-                    }
-                    if self.match_with(|k| matches!(k, TokenKind::LeftParen)) {
-                        let mut bindings = Vec::new();
-                        if !self.check(|k| matches!(k, TokenKind::RightParen)) {
-                            loop {
-                                let (binding, _) = self.expect_identifier("pattern binding")?;
-                                bindings.push(binding);
-                                if self.match_with(|k| matches!(k, TokenKind::Comma)) {
-                                    continue;
-                                }
-                                break;
+                    return Ok(Pattern::Wildcard { span: token.span });
+                }
+                let mut segments = vec![name.clone()];
+                let mut span = token.span;
+                while self.match_with(|k| matches!(k, TokenKind::ColonColon)) {
+                    let (seg, seg_span) = self.expect_identifier("pattern path segment")?;
+                    span = span.merge(seg_span);
+                    segments.push(seg);
+                }
+                if self.match_with(|k| matches!(k, TokenKind::LeftParen)) {
+                    let (args, _) = self.parse_pattern_list()?;
+                    Ok(Pattern::Enum {
+                        path: segments,
+                        args,
+                        span: span.merge(self.prev().span),
+                    })
+                } else if self.match_with(|k| matches!(k, TokenKind::LeftBrace)) {
+                    let mut fields = Vec::new();
+                    while !self.check(|k| matches!(k, TokenKind::RightBrace)) {
+                        let (field, field_span) = self.expect_identifier("field pattern")?;
+                        let pattern = if self.match_type_separator() {
+                            self.parse_pattern()?
+                        } else {
+                            Pattern::Binding {
+                                name: field.clone(),
+                                span: field_span,
                             }
+                        };
+                        fields.push(FieldPattern {
+                            name: field,
+                            span: field_span.merge(pattern.span()),
+                            pattern,
+                        });
+                        if !self.match_with(|k| matches!(k, TokenKind::Comma)) {
+                            break;
                         }
-                        let close = self
-                            .expect_with("')'", |k| matches!(k, TokenKind::RightParen))?
-                            .span;
-                        span = span.merge(close);
-                        Ok(Pattern::Enum {
-                            path: segments,
-                            bindings,
-                            span,
-                        })
-                    } else if segments.len() == 1 {
-                        Ok(Pattern::Binding {
-                            name,
-                            span: token.span,
-                        })
-                    } else {
-                        Ok(Pattern::Path { segments, span })
                     }
+                    let close = self
+                        .expect_with("'}'", |k| matches!(k, TokenKind::RightBrace))?
+                        .span;
+                    Ok(Pattern::Struct {
+                        path: segments,
+                        fields,
+                        span: span.merge(close),
+                    })
+                } else if segments.len() == 1 {
+                    Ok(Pattern::Binding {
+                        name,
+                        span: token.span,
+                    })
+                } else {
+                    Ok(Pattern::Path { segments, span })
                 }
             }
-            TokenKind::IntegerLiteral(value) => Ok(Pattern::Literal(Literal::Integer {
-                value,
-                span: token.span,
-            })),
-            TokenKind::FloatLiteral(value) => Ok(Pattern::Literal(Literal::Float {
-                value,
-                span: token.span,
-            })),
-            TokenKind::StringLiteral(value) => Ok(Pattern::Literal(Literal::String {
-                value,
-                span: token.span,
-            })),
-            TokenKind::CharLiteral(value) => Ok(Pattern::Literal(Literal::Char {
-                value,
-                span: token.span,
-            })),
-            _ => Err(ParseError::UnexpectedToken {
-                expected: "pattern",
-                found: token.kind,
-                span: token.span,
-            }),
+            TokenKind::LeftParen => {
+                let (mut elements, trailing_comma) = self.parse_pattern_list()?;
+                if elements.len() == 1 && !trailing_comma {
+                    return Ok(elements.remove(0));
+                }
+                Ok(Pattern::Tuple {
+                    elements,
+                    span: token.span.merge(self.prev().span),
+                })
+            }
+            _ => {
+                let start = self.pattern_literal(token)?;
+                if !self.match_with(|k| matches!(k, TokenKind::DotDot)) {
+                    return Ok(Pattern::Literal(start));
+                }
+                let inclusive = self.match_with(|k| matches!(k, TokenKind::Equals));
+                let end_token = self.advance();
+                let end = self.pattern_literal(end_token)?;
+                Ok(Pattern::Range {
+                    span: start.span().merge(end.span()),
+                    start,
+                    end,
+                    inclusive,
+                })
+            }
+        }
+    }
+
+    /// Patterns separated by commas up to the closing `)`, and whether the
+    /// last one was followed by a comma.
+    fn parse_pattern_list(&mut self) -> Result<(Vec<Pattern>, bool), ParseError> {
+        let mut patterns = Vec::new();
+        let mut trailing_comma = false;
+        while !self.check(|k| matches!(k, TokenKind::RightParen)) {
+            patterns.push(self.parse_pattern()?);
+            trailing_comma = self.match_with(|k| matches!(k, TokenKind::Comma));
+            if !trailing_comma {
+                break;
+            }
         }
+        self.expect_with("')'", |k| matches!(k, TokenKind::RightParen))?;
+        Ok((patterns, trailing_comma))
+    }
+
+    /// A literal in pattern position, where numbers may carry a leading `-`.
+    fn pattern_literal(&mut self, token: Token) -> Result<Literal, ParseError> {
+        let span = token.span;
+        let literal = match token.kind {
+            TokenKind::Minus => {
+                let number = self.advance();
+                let span = span.merge(number.span);
+                match number.kind {
+                    TokenKind::IntegerLiteral(value) => Literal::Integer {
+                        value: format!("-{value}"),
+                        span,
+                    },
+                    TokenKind::FloatLiteral(value) => Literal::Float {
+                        value: format!("-{value}"),
+                        span,
+                    },
+                    found => {
+                        return Err(ParseError::UnexpectedToken {
+                            expected: "number",
+                            found,
+                            span: number.span,
+                        })
+                    }
+                }
+            }
+            TokenKind::IntegerLiteral(value) => Literal::Integer { value, span },
+            TokenKind::FloatLiteral(value) => Literal::Float { value, span },
+            TokenKind::StringLiteral(value) => Literal::String { value, span },
+            TokenKind::CharLiteral(value) => Literal::Char { value, span },
+            TokenKind::Keyword(Keyword::True) => Literal::Bool { value: true, span },
+            TokenKind::Keyword(Keyword::False) => Literal::Bool { value: false, span },
+            found => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "pattern",
+                    found,
+                    span,
+                })
+            }
+        };
+        Ok(literal)
     }
 
     fn parse_expression(&mut self) -> Result<Expr, ParseError> {
@@ -883,23 +1168,83 @@ impl<'a> Parser<'a> {
                 value: Box::new(value),
                 span,
             };
+        } else if let Some(op) = self.match_compound_assignment() {
+            // `a += b` is sugar for `a = a + b`, so the target is evaluated
+            // twice; side effects in an index expression happen twice too.
+            let value = self.parse_assignment()?;
+            let span = expr.span().merge(value.span());
+            let value = Expr::Binary {
+                left: Box::new(expr.clone()),
+                op,
+                right: Box::new(value),
+                span,
+            };
+            expr = Expr::Assignment {
+                target: Box::new(expr),
+                value: Box::new(value),
+                span,
+            };
         }
         Ok(expr)
     }
 
+    fn match_compound_assignment(&mut self) -> Option<BinaryOp> {
+        let op = match self.peek().kind {
+            TokenKind::PlusEqual => BinaryOp::Add,
+            TokenKind::MinusEqual => BinaryOp::Subtract,
+            TokenKind::StarEqual => BinaryOp::Multiply,
+            TokenKind::SlashEqual => BinaryOp::Divide,
+            TokenKind::PercentEqual => BinaryOp::Modulo,
+            TokenKind::AmpersandEqual => BinaryOp::BitAnd,
+            TokenKind::PipeEqual => BinaryOp::BitOr,
+            TokenKind::CaretEqual => BinaryOp::BitXor,
+            TokenKind::LessLessEqual => BinaryOp::ShiftLeft,
+            TokenKind::Greater
+                if self.adjacent_follows(|k| matches!(k, TokenKind::GreaterEqual)) =>
+            {
+                self.advance();
+                BinaryOp::ShiftRight
+            }
+            _ => return None,
+        };
+        self.advance();
+        Some(op)
+    }
+
+    /// Whether the token after the current one matches `predicate` and starts
+    /// right where the current one ends, as the second `>` of `>>` does.
+    fn adjacent_follows<F>(&self, predicate: F) -> bool
+    where
+        F: Fn(&TokenKind) -> bool,
+    {
+        match self.tokens.get(self.index + 1) {
+            Some(next) => predicate(&next.kind) && next.span.start == self.peek().span.end,
+            None => false,
+        }
+    }
+
     fn parse_range(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_logical_or()?;
-        while self.match_with(|k| matches!(k, TokenKind::DotDot)) {
-            let right = self.parse_logical_or()?;
-            let span = expr.span().merge(right.span());
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                op: BinaryOp::Range,
-                right: Box::new(right),
-                span,
+        let start = self.parse_logical_or()?;
+        if !self.match_with(|k| matches!(k, TokenKind::DotDot)) {
+            return Ok(start);
+        }
+        let inclusive = self.match_with(|k| matches!(k, TokenKind::Equals));
+        let end = self.parse_logical_or()?;
+        // `step` is only a keyword right after a range.
+        let step =
+            if self.match_with(|k| matches!(k, TokenKind::Identifier(name) if name == "step")) {
+                Some(Box::new(self.parse_logical_or()?))
+            } else {
+                None
             };
-        }
-        Ok(expr)
+        let last = step.as_deref().unwrap_or(&end);
+        Ok(Expr::Range {
+            span: start.span().merge(last.span()),
+            start: Box::new(start),
+            end: Box::new(end),
+            inclusive,
+            step,
+        })
     }
 
     fn parse_logical_or(&mut self) -> Result<Expr, ParseError> {
@@ -961,13 +1306,16 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_comparison(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.parse_term()?;
+        let mut expr = self.parse_bit_or()?;
         loop {
             let op = if self.match_with(|k| matches!(k, TokenKind::Less)) {
                 Some(BinaryOp::Less)
             } else if self.match_with(|k| matches!(k, TokenKind::LessEqual)) {
                 Some(BinaryOp::LessEqual)
-            } else if self.match_with(|k| matches!(k, TokenKind::Greater)) {
+            } else if self.check(|k| matches!(k, TokenKind::Greater))
+                && !self.adjacent_follows(|k| matches!(k, TokenKind::GreaterEqual))
+            {
+                self.advance();
                 Some(BinaryOp::Greater)
             } else if self.match_with(|k| matches!(k, TokenKind::GreaterEqual)) {
                 Some(BinaryOp::GreaterEqual)
@@ -975,7 +1323,7 @@ impl<'a> Parser<'a> {
                 None
             };
             if let Some(op) = op {
-                let right = self.parse_term()?;
+                let right = self.parse_bit_or()?;
                 let span = expr.span().merge(right.span());
                 expr = Expr::Binary {
                     left: Box::new(expr),
@@ -990,6 +1338,108 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    /// Parses each `${...}` segment with its own parser over the segment's
+    /// tokens, which the lexer ends with an `Eof` at the closing `}`.
+    fn parse_interpolation(
+        &mut self,
+        parts: Vec<StringPart>,
+        span: Span,
+    ) -> Result<Expr, ParseError> {
+        let mut out = Vec::with_capacity(parts.len());
+        for part in parts {
+            match part {
+                StringPart::Text(text) => out.push(InterpolationPart::Text(text)),
+                StringPart::Expr(tokens) => {
+                    let mut parser = Parser::new(self.source, tokens);
+                    let expr = parser.parse_expression();
+                    self.errors.append(&mut parser.errors);
+                    let expr = expr?;
+                    if !parser.is_at_end() {
+                        let token = parser.peek().clone();
+                        return Err(ParseError::UnexpectedToken {
+                            expected: "`}` to close the interpolated expression",
+                            found: token.kind,
+                            span: token.span,
+                        });
+                    }
+                    out.push(InterpolationPart::Expr(expr));
+                }
+            }
+        }
+        Ok(Expr::Interpolated { parts: out, span })
+    }
+
+    fn parse_bit_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_bit_xor()?;
+        while self.match_with(|k| matches!(k, TokenKind::Pipe)) {
+            let right = self.parse_bit_xor()?;
+            let span = expr.span().merge(right.span());
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                op: BinaryOp::BitOr,
+                right: Box::new(right),
+                span,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_bit_xor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_bit_and()?;
+        while self.match_with(|k| matches!(k, TokenKind::Caret)) {
+            let right = self.parse_bit_and()?;
+            let span = expr.span().merge(right.span());
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                op: BinaryOp::BitXor,
+                right: Box::new(right),
+                span,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_bit_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_shift()?;
+        while self.match_with(|k| matches!(k, TokenKind::Ampersand)) {
+            let right = self.parse_shift()?;
+            let span = expr.span().merge(right.span());
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                op: BinaryOp::BitAnd,
+                right: Box::new(right),
+                span,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_shift(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_term()?;
+        loop {
+            let op = if self.match_with(|k| matches!(k, TokenKind::LessLess)) {
+                BinaryOp::ShiftLeft
+            } else if self.check(|k| matches!(k, TokenKind::Greater))
+                && self.adjacent_follows(|k| matches!(k, TokenKind::Greater))
+            {
+                self.advance();
+                self.advance();
+                BinaryOp::ShiftRight
+            } else {
+                break;
+            };
+            let right = self.parse_term()?;
+            let span = expr.span().merge(right.span());
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+                span,
+            };
+        }
+        Ok(expr)
+    }
+
     fn parse_term(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.parse_factor()?;
         loop {
@@ -1074,6 +1524,16 @@ impl<'a> Parser<'a> {
                 span,
             });
         }
+        if self.match_with(|k| matches!(k, TokenKind::Tilde)) {
+            let op_span = self.prev().span;
+            let inner = self.parse_unary()?;
+            let span = op_span.merge(inner.span());
+            return Ok(Expr::Unary {
+                op: UnaryOp::BitNot,
+                expr: Box::new(inner),
+                span,
+            });
+        }
         if self.match_with(|k| matches!(k, TokenKind::Ampersand)) {
             let op_span = self.prev().span;
             let inner = self.parse_unary()?;
@@ -1189,7 +1649,8 @@ impl<'a> Parser<'a> {
                 };
                 continue;
             }
-            if self.check(|k| matches!(k, TokenKind::LeftBrace))
+            if !self.no_struct_literals
+                && self.check(|k| matches!(k, TokenKind::LeftBrace))
                 && expr.is_path_like()
                 && self.struct_literal_follows()
             {
@@ -1237,6 +1698,14 @@ impl<'a> Parser<'a> {
                     unreachable!()
                 }
             }
+            TokenKind::InterpolatedString(_) => {
+                let token = self.advance();
+                if let TokenKind::InterpolatedString(parts) = token.kind.clone() {
+                    self.parse_interpolation(parts, token.span)
+                } else {
+                    unreachable!()
+                }
+            }
             TokenKind::CharLiteral(_) => {
                 let token = self.advance();
                 if let TokenKind::CharLiteral(value) = token.kind.clone() {
@@ -1271,6 +1740,16 @@ impl<'a> Parser<'a> {
                 let if_stmt = self.parse_if(start)?;
                 Ok(Expr::If(Box::new(if_stmt)))
             }
+            TokenKind::Keyword(Keyword::Loop) => {
+                let start = self.advance().span;
+                self.parse_loop(start, None)
+            }
+            TokenKind::Label(label) => {
+                let start = self.advance().span;
+                self.expect_with("':'", |k| matches!(k, TokenKind::Colon))?;
+                self.expect_keyword(Keyword::Loop)?;
+                self.parse_loop(start, Some(label))
+            }
             TokenKind::Keyword(Keyword::Fun) => self.parse_lambda(),
             TokenKind::Keyword(Keyword::Async) => {
                 if matches!(self.peek_kind_at(1), Some(TokenKind::Keyword(Keyword::Fun))) {
@@ -1577,7 +2056,12 @@ impl<'a> Parser<'a> {
         let mut params = Vec::new();
         loop {
             let (name, span) = self.expect_identifier("type parameter")?;
-            params.push(TypeParam { name, span });
+            let bounds = if self.match_with(|k| matches!(k, TokenKind::Colon)) {
+                self.parse_trait_bounds()?
+            } else {
+                Vec::new()
+            };
+            params.push(TypeParam { name, bounds, span });
             if self.match_with(|k| matches!(k, TokenKind::Comma)) {
                 continue;
             }
@@ -1587,6 +2071,44 @@ impl<'a> Parser<'a> {
         Ok(params)
     }
 
+    /// `Display + Eq`, after the `:` of a type parameter or `where` predicate.
+    fn parse_trait_bounds(&mut self) -> Result<Vec<TypeExpr>, ParseError> {
+        let mut bounds = vec![self.parse_type()?];
+        while self.match_with(|k| matches!(k, TokenKind::Plus)) {
+            bounds.push(self.parse_type()?);
+        }
+        Ok(bounds)
+    }
+
+    /// `where T: Display, U: Eq + Hash`; each predicate adds to the bounds of
+    /// a parameter declared in `params`.
+    fn parse_where_clause(&mut self, params: &mut [TypeParam]) -> Result<(), ParseError> {
+        if !self.match_keyword(Keyword::Where) {
+            return Ok(());
+        }
+        loop {
+            let token = self.peek().clone();
+            let (name, _) = self.expect_identifier("type parameter in `where` clause")?;
+            let Some(param) = params.iter_mut().find(|p| p.name == name) else {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "declared type parameter",
+                    found: token.kind,
+                    span: token.span,
+                });
+            };
+            self.expect_with("':'", |k| matches!(k, TokenKind::Colon))?;
+            let bounds = self.parse_trait_bounds()?;
+            param.bounds.extend(bounds);
+            if self.match_with(|k| matches!(k, TokenKind::Comma))
+                && self.check(|k| matches!(k, TokenKind::Identifier(_)))
+            {
+                continue;
+            }
+            break;
+        }
+        Ok(())
+    }
+
     fn check_keyword(&self, keyword: Keyword) -> bool {
         matches!(self.peek().kind, TokenKind::Keyword(k) if k == keyword)
     }
@@ -1747,6 +2269,10 @@ impl<'a> Parser<'a> {
                 | TokenKind::Keyword(Keyword::Extern)
                 | TokenKind::Keyword(Keyword::Fun)
                 | TokenKind::Keyword(Keyword::Async)
+                | TokenKind::Keyword(Keyword::Const)
+                | TokenKind::Keyword(Keyword::Static)
+                | TokenKind::Keyword(Keyword::Var)
+                | TokenKind::Keyword(Keyword::Type)
                 | TokenKind::Keyword(Keyword::Import) => return,
                 _ => {
                     self.advance();
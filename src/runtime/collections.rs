@@ -0,0 +1,463 @@
+//! Higher-order builtins shared by the `vec`, `set` and `map` modules, and so
+//! callable as methods on all three (`v.filter(f)` is `vec.filter(v, f)`).
+//! Arrays have no module of their own but take the same methods.
+//!
+//! Each one walks the collection's items: a vec's or array's elements, a
+//! set's elements or a map's `(key, value)` tuples. Adapters are eager:
+//! `map`, `filter` and the like return a new `vec`, so each step of a chain
+//! builds one. `any`, `all` and `find` stop at the first item that decides
+//! the result.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use futures::future::LocalBoxFuture;
+
+use super::{
+    ensure_arity, expect_bool_value, expect_string, int_fits_type, make_map_value, make_vec_value,
+    map_key_from_value, map_key_to_value, option_none_value, option_some_value, value_type_tag,
+    BuiltinFn, IntType, Interpreter, PrimitiveType, RuntimeError, RuntimeResult, TypeTag, Value,
+};
+
+const HIGHER_ORDER: [(&str, BuiltinFn); 15] = [
+    ("map", builtin_map),
+    ("filter", builtin_filter),
+    ("fold", builtin_fold),
+    ("any", builtin_any),
+    ("all", builtin_all),
+    ("find", builtin_find),
+    ("enumerate", builtin_enumerate),
+    ("zip", builtin_zip),
+    ("sum", builtin_sum),
+    ("min_by", builtin_min_by),
+    ("max_by", builtin_max_by),
+    ("sort_by", builtin_sort_by),
+    ("group_by", builtin_group_by),
+    ("join", builtin_join),
+    ("count", builtin_count),
+];
+
+/// The builtins every collection module gets, by name.
+pub(super) fn higher_order_builtins() -> impl Iterator<Item = (String, Value)> {
+    HIGHER_ORDER
+        .into_iter()
+        .map(|(name, func)| (name.to_string(), Value::Builtin(func)))
+}
+
+/// The builtin behind `method` on an array.
+pub(super) fn array_method(method: &str) -> Option<BuiltinFn> {
+    HIGHER_ORDER
+        .iter()
+        .find(|(name, _)| *name == method)
+        .map(|(_, func)| *func)
+}
+
+/// `module.method` for error messages, after the collection in `args`.
+fn qualified(args: &[Value], method: &str) -> String {
+    let module = match args.first() {
+        Some(Value::Array(_)) => "array",
+        Some(Value::Set(_)) => "set",
+        Some(Value::Map(_)) => "map",
+        _ => "vec",
+    };
+    format!("{module}.{method}")
+}
+
+/// The items of `value` and their element type.
+fn collection_items(value: &Value, name: &str) -> RuntimeResult<(Vec<Value>, Option<TypeTag>)> {
+    match value {
+        Value::Vec(vec_rc) => {
+            let vec_ref = vec_rc.borrow();
+            Ok((vec_ref.items.clone(), vec_ref.elem_type.clone()))
+        }
+        Value::Array(arr_rc) => {
+            let arr_ref = arr_rc.borrow();
+            Ok((arr_ref.items.clone(), arr_ref.elem_type.clone()))
+        }
+        Value::Set(set_rc) => {
+            let set_ref = set_rc.borrow();
+            let items = set_ref
+                .items
+                .iter()
+                .map(|key| map_key_to_value(key, set_ref.elem_type.as_ref()))
+                .collect();
+            Ok((items, set_ref.elem_type.clone()))
+        }
+        Value::Map(map_rc) => {
+            let map_ref = map_rc.borrow();
+            let items = map_ref
+                .entries
+                .iter()
+                .map(|(key, value)| {
+                    Value::Tuple(vec![
+                        map_key_to_value(key, map_ref.key_type.as_ref()),
+                        value.clone(),
+                    ])
+                })
+                .collect();
+            let tag = TypeTag::Tuple(vec![
+                map_ref.key_type.clone().unwrap_or(TypeTag::Unknown),
+                map_ref.value_type.clone().unwrap_or(TypeTag::Unknown),
+            ]);
+            Ok((items, Some(tag)))
+        }
+        other => Err(RuntimeError::new(format!(
+            "{name} expects a vec, array, set or map, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Calls `predicate` on `item`, which must answer with a `bool`.
+async fn test_item(
+    interp: &Interpreter,
+    predicate: &Value,
+    item: Value,
+    name: &str,
+) -> RuntimeResult<bool> {
+    let answer = interp.call_value(predicate.clone(), vec![item]).await?;
+    expect_bool_value(answer, &format!("{name} predicate"))
+}
+
+/// Orders two keys returned by a key function.
+fn compare_keys(a: &Value, b: &Value, name: &str) -> RuntimeResult<Ordering> {
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => Ok(x.cmp(y)),
+        (Value::Float(x), Value::Float(y)) => Ok(x.partial_cmp(y).unwrap_or(Ordering::Equal)),
+        (Value::String(x), Value::String(y)) => Ok(x.cmp(y)),
+        (Value::Char(x), Value::Char(y)) => Ok(x.cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Ok(x.cmp(y)),
+        _ => Err(RuntimeError::new(format!(
+            "{name} keys must be numbers, strings, chars or bools of one type, got {} and {}",
+            a.type_name(),
+            b.type_name()
+        ))),
+    }
+}
+
+/// Each item paired with the key `key_fn` gives it.
+async fn keyed_items(
+    interp: &Interpreter,
+    items: Vec<Value>,
+    key_fn: &Value,
+) -> RuntimeResult<Vec<(Value, Value)>> {
+    let mut keyed = Vec::with_capacity(items.len());
+    for item in items {
+        let key = interp
+            .call_value(key_fn.clone(), vec![item.clone()])
+            .await?;
+        keyed.push((key, item));
+    }
+    Ok(keyed)
+}
+
+fn builtin_map<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "map");
+        ensure_arity(&args, 2, &name)?;
+        let (items, _) = collection_items(&args[0], &name)?;
+        let mut mapped = Vec::with_capacity(items.len());
+        for item in items {
+            mapped.push(interp.call_value(args[1].clone(), vec![item]).await?);
+        }
+        let elem_type = mapped.first().map(value_type_tag);
+        Ok(make_vec_value(mapped, elem_type))
+    })
+}
+
+fn builtin_filter<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "filter");
+        ensure_arity(&args, 2, &name)?;
+        let (items, elem_type) = collection_items(&args[0], &name)?;
+        let mut kept = Vec::new();
+        for item in items {
+            if test_item(interp, &args[1], item.clone(), &name).await? {
+                kept.push(item);
+            }
+        }
+        Ok(make_vec_value(kept, elem_type))
+    })
+}
+
+fn builtin_fold<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "fold");
+        ensure_arity(&args, 3, &name)?;
+        let (items, _) = collection_items(&args[0], &name)?;
+        let mut acc = args[1].clone();
+        for item in items {
+            acc = interp.call_value(args[2].clone(), vec![acc, item]).await?;
+        }
+        Ok(acc)
+    })
+}
+
+fn builtin_any<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "any");
+        ensure_arity(&args, 2, &name)?;
+        let (items, _) = collection_items(&args[0], &name)?;
+        for item in items {
+            if test_item(interp, &args[1], item, &name).await? {
+                return Ok(Value::Bool(true));
+            }
+        }
+        Ok(Value::Bool(false))
+    })
+}
+
+fn builtin_all<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "all");
+        ensure_arity(&args, 2, &name)?;
+        let (items, _) = collection_items(&args[0], &name)?;
+        for item in items {
+            if !test_item(interp, &args[1], item, &name).await? {
+                return Ok(Value::Bool(false));
+            }
+        }
+        Ok(Value::Bool(true))
+    })
+}
+
+fn builtin_count<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "count");
+        ensure_arity(&args, 2, &name)?;
+        let (items, _) = collection_items(&args[0], &name)?;
+        let mut count = 0;
+        for item in items {
+            if test_item(interp, &args[1], item, &name).await? {
+                count += 1;
+            }
+        }
+        Ok(Value::Int(count))
+    })
+}
+
+fn builtin_find<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "find");
+        ensure_arity(&args, 2, &name)?;
+        let (items, elem_type) = collection_items(&args[0], &name)?;
+        for item in items {
+            if test_item(interp, &args[1], item.clone(), &name).await? {
+                return Ok(option_some_value(item, elem_type));
+            }
+        }
+        Ok(option_none_value(elem_type))
+    })
+}
+
+fn builtin_enumerate<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "enumerate");
+        ensure_arity(&args, 1, &name)?;
+        let (items, elem_type) = collection_items(&args[0], &name)?;
+        let pairs = items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| Value::Tuple(vec![Value::Int(index as i128), item]))
+            .collect();
+        let tag = TypeTag::Tuple(vec![
+            TypeTag::Primitive(PrimitiveType::Int(IntType::I64)),
+            elem_type.unwrap_or(TypeTag::Unknown),
+        ]);
+        Ok(make_vec_value(pairs, Some(tag)))
+    })
+}
+
+fn builtin_zip<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "zip");
+        ensure_arity(&args, 2, &name)?;
+        let (left, left_type) = collection_items(&args[0], &name)?;
+        let (right, right_type) = collection_items(&args[1], &name)?;
+        let pairs = left
+            .into_iter()
+            .zip(right)
+            .map(|(a, b)| Value::Tuple(vec![a, b]))
+            .collect();
+        let tag = TypeTag::Tuple(vec![
+            left_type.unwrap_or(TypeTag::Unknown),
+            right_type.unwrap_or(TypeTag::Unknown),
+        ]);
+        Ok(make_vec_value(pairs, Some(tag)))
+    })
+}
+
+fn builtin_sum<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "sum");
+        ensure_arity(&args, 1, &name)?;
+        let (items, elem_type) = collection_items(&args[0], &name)?;
+        if matches!(items.first(), Some(Value::Float(_))) {
+            let mut total = 0.0;
+            for item in &items {
+                match item {
+                    Value::Float(value) => total += value,
+                    other => return Err(sum_operand_error(&name, other)),
+                }
+            }
+            return Ok(Value::Float(total));
+        }
+        let kind = match elem_type {
+            Some(TypeTag::Primitive(PrimitiveType::Int(kind))) => kind,
+            _ => IntType::I64,
+        };
+        let mut total: i128 = 0;
+        for item in &items {
+            let Value::Int(value) = item else {
+                return Err(sum_operand_error(&name, item));
+            };
+            total = total
+                .checked_add(*value)
+                .filter(|total| int_fits_type(*total, &kind))
+                .ok_or_else(|| {
+                    let ty = PrimitiveType::Int(kind.clone());
+                    RuntimeError::new(format!("{name} overflowed {ty}"))
+                })?;
+        }
+        Ok(Value::Int(total))
+    })
+}
+
+fn sum_operand_error(name: &str, item: &Value) -> RuntimeError {
+    RuntimeError::new(format!(
+        "{name} expects integers or floats, got {}",
+        item.type_name()
+    ))
+}
+
+/// The item whose key is `wanted` relative to all others; the first one on ties.
+async fn extreme_by(
+    args: Vec<Value>,
+    interp: &Interpreter,
+    method: &str,
+    wanted: Ordering,
+) -> RuntimeResult<Value> {
+    let name = qualified(&args, method);
+    ensure_arity(&args, 2, &name)?;
+    let (items, elem_type) = collection_items(&args[0], &name)?;
+    let mut best: Option<(Value, Value)> = None;
+    for (key, item) in keyed_items(interp, items, &args[1]).await? {
+        let better = match &best {
+            Some((best_key, _)) => compare_keys(&key, best_key, &name)? == wanted,
+            None => true,
+        };
+        if better {
+            best = Some((key, item));
+        }
+    }
+    Ok(match best {
+        Some((_, item)) => option_some_value(item, elem_type),
+        None => option_none_value(elem_type),
+    })
+}
+
+fn builtin_min_by<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(extreme_by(args, interp, "min_by", Ordering::Less))
+}
+
+fn builtin_max_by<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(extreme_by(args, interp, "max_by", Ordering::Greater))
+}
+
+fn builtin_sort_by<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "sort_by");
+        ensure_arity(&args, 2, &name)?;
+        let (items, elem_type) = collection_items(&args[0], &name)?;
+        let mut keyed = keyed_items(interp, items, &args[1]).await?;
+        let mut failure = None;
+        keyed.sort_by(|(a, _), (b, _)| {
+            compare_keys(a, b, &name).unwrap_or_else(|err| {
+                failure.get_or_insert(err);
+                Ordering::Equal
+            })
+        });
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        let sorted = keyed.into_iter().map(|(_, item)| item).collect();
+        Ok(make_vec_value(sorted, elem_type))
+    })
+}
+
+fn builtin_group_by<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "group_by");
+        ensure_arity(&args, 2, &name)?;
+        let (items, elem_type) = collection_items(&args[0], &name)?;
+        let mut key_type = None;
+        let mut groups: HashMap<_, Vec<Value>> = HashMap::new();
+        for (key, item) in keyed_items(interp, items, &args[1]).await? {
+            key_type.get_or_insert_with(|| value_type_tag(&key));
+            let key = map_key_from_value(&key, &name)?;
+            groups.entry(key).or_default().push(item);
+        }
+        let entries = groups
+            .into_iter()
+            .map(|(key, items)| (key, make_vec_value(items, elem_type.clone())))
+            .collect();
+        let value_type = TypeTag::Vec(Box::new(elem_type.unwrap_or(TypeTag::Unknown)));
+        Ok(make_map_value(entries, key_type, Some(value_type)))
+    })
+}
+
+fn builtin_join<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "join");
+        ensure_arity(&args, 2, &name)?;
+        let (items, _) = collection_items(&args[0], &name)?;
+        let separator = expect_string(&args[1])?;
+        let parts: Vec<String> = items.iter().map(Value::to_string_value).collect();
+        Ok(Value::String(parts.join(&separator)))
+    })
+}
@@ -0,0 +1,475 @@
+//! Combinator methods on `option` and `result` values (`o.map(f)`,
+//! `r.unwrap_or(0)`, `r.ok()`).
+//!
+//! These are reachable through method syntax only: `result.ok(x)` already
+//! builds a result, so `r.ok()` cannot live in the `result` module too.
+//! Whatever a combinator returns keeps the receiver's `elem_type`, `ok_type`
+//! and `err_type` tags where the payload is carried over. A payload produced
+//! by a callback is tagged from the value the callback returned.
+
+use futures::future::LocalBoxFuture;
+
+use super::{
+    ensure_arity, expect_string, option_none_value, option_some_value, result_err_value,
+    result_ok_value, value_type_tag, BuiltinFn, Interpreter, OptionValue, ResultValue,
+    RuntimeError, RuntimeResult, TypeTag, Value,
+};
+
+/// The combinator `name` on `receiver`, when it is an option or a result that
+/// has one.
+pub(super) fn method(receiver: &Value, name: &str) -> Option<BuiltinFn> {
+    let is_option = match receiver {
+        Value::Option(_) => true,
+        Value::Result(_) => false,
+        _ => return None,
+    };
+    let func: BuiltinFn = match (is_option, name) {
+        (_, "unwrap") => builtin_unwrap,
+        (_, "expect") => builtin_expect,
+        (_, "unwrap_or") => builtin_unwrap_or,
+        (_, "unwrap_or_else") => builtin_unwrap_or_else,
+        (_, "map") => builtin_map,
+        (_, "and_then") => builtin_and_then,
+        (_, "or_else") => builtin_or_else,
+        (_, "flatten") => builtin_flatten,
+        (true, "is_some") => builtin_is_some,
+        (true, "is_none") => builtin_is_none,
+        (true, "ok_or") => builtin_ok_or,
+        (false, "is_ok") => builtin_is_ok,
+        (false, "is_err") => builtin_is_err,
+        (false, "map_err") => builtin_map_err,
+        (false, "ok") => builtin_ok,
+        (false, "err") => builtin_err,
+        _ => return None,
+    };
+    Some(func)
+}
+
+/// `option.method` or `result.method` for error messages, after the receiver
+/// in `args`.
+fn qualified(args: &[Value], method: &str) -> String {
+    let module = match args.first() {
+        Some(Value::Result(_)) => "result",
+        _ => "option",
+    };
+    format!("{module}.{method}")
+}
+
+/// What an option or result holds.
+enum Payload {
+    /// The value of a `some` or an `ok`.
+    Value(Value),
+    /// The error of an `err`.
+    Error(Value),
+    /// A `none`.
+    Empty,
+}
+
+fn payload(receiver: &Value, name: &str) -> RuntimeResult<Payload> {
+    match receiver {
+        Value::Option(OptionValue::Some { value, .. })
+        | Value::Result(ResultValue::Ok { value, .. }) => Ok(Payload::Value((**value).clone())),
+        Value::Result(ResultValue::Err { value, .. }) => Ok(Payload::Error((**value).clone())),
+        Value::Option(OptionValue::None { .. }) => Ok(Payload::Empty),
+        other => Err(receiver_error(name, other)),
+    }
+}
+
+fn receiver_error(name: &str, receiver: &Value) -> RuntimeError {
+    RuntimeError::new(format!(
+        "{name} expects an option or result, got {}",
+        receiver.type_name()
+    ))
+}
+
+/// Checks that a callback of `name` answered with the same kind of value as
+/// `receiver`, as `and_then` and `or_else` callbacks must.
+fn expect_same_kind(answer: Value, receiver: &Value, name: &str) -> RuntimeResult<Value> {
+    match (receiver, &answer) {
+        (Value::Option(_), Value::Option(_)) | (Value::Result(_), Value::Result(_)) => Ok(answer),
+        _ => Err(RuntimeError::new(format!(
+            "{name} callback must return {}, got {}",
+            if matches!(receiver, Value::Result(_)) {
+                "a result"
+            } else {
+                "an option"
+            },
+            answer.type_name()
+        ))),
+    }
+}
+
+/// Fills `slot` with `tag` unless the value already knows its type.
+fn inherit_tag(slot: &mut Option<TypeTag>, tag: &Option<TypeTag>) {
+    if slot.is_none() {
+        slot.clone_from(tag);
+    }
+}
+
+fn builtin_is_some<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        ensure_arity(&args, 1, "option.is_some")?;
+        Ok(Value::Bool(matches!(
+            args[0],
+            Value::Option(OptionValue::Some { .. })
+        )))
+    })
+}
+
+fn builtin_is_none<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        ensure_arity(&args, 1, "option.is_none")?;
+        Ok(Value::Bool(matches!(
+            args[0],
+            Value::Option(OptionValue::None { .. })
+        )))
+    })
+}
+
+fn builtin_is_ok<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        ensure_arity(&args, 1, "result.is_ok")?;
+        Ok(Value::Bool(matches!(
+            args[0],
+            Value::Result(ResultValue::Ok { .. })
+        )))
+    })
+}
+
+fn builtin_is_err<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        ensure_arity(&args, 1, "result.is_err")?;
+        Ok(Value::Bool(matches!(
+            args[0],
+            Value::Result(ResultValue::Err { .. })
+        )))
+    })
+}
+
+fn builtin_unwrap<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "unwrap");
+        ensure_arity(&args, 1, &name)?;
+        match payload(&args[0], &name)? {
+            Payload::Value(value) => Ok(value),
+            Payload::Error(error) => Err(RuntimeError::new(format!(
+                "{name} called on err: {}",
+                error.to_string_value()
+            ))),
+            Payload::Empty => Err(RuntimeError::new(format!("{name} called on none"))),
+        }
+    })
+}
+
+fn builtin_expect<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "expect");
+        ensure_arity(&args, 2, &name)?;
+        let message = expect_string(&args[1])?;
+        match payload(&args[0], &name)? {
+            Payload::Value(value) => Ok(value),
+            Payload::Error(error) => Err(RuntimeError::new(format!(
+                "{message}: {}",
+                error.to_string_value()
+            ))),
+            Payload::Empty => Err(RuntimeError::new(message)),
+        }
+    })
+}
+
+fn builtin_unwrap_or<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "unwrap_or");
+        ensure_arity(&args, 2, &name)?;
+        match payload(&args[0], &name)? {
+            Payload::Value(value) => Ok(value),
+            Payload::Error(_) | Payload::Empty => Ok(args[1].clone()),
+        }
+    })
+}
+
+fn builtin_unwrap_or_else<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "unwrap_or_else");
+        ensure_arity(&args, 2, &name)?;
+        match payload(&args[0], &name)? {
+            Payload::Value(value) => Ok(value),
+            Payload::Error(error) => interp.call_value(args[1].clone(), vec![error]).await,
+            Payload::Empty => interp.call_value(args[1].clone(), Vec::new()).await,
+        }
+    })
+}
+
+fn builtin_map<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "map");
+        ensure_arity(&args, 2, &name)?;
+        match &args[0] {
+            Value::Option(OptionValue::Some { value, .. }) => {
+                let mapped = interp
+                    .call_value(args[1].clone(), vec![(**value).clone()])
+                    .await?;
+                let elem_type = Some(value_type_tag(&mapped));
+                Ok(option_some_value(mapped, elem_type))
+            }
+            Value::Option(OptionValue::None { .. }) => Ok(option_none_value(None)),
+            Value::Result(ResultValue::Ok {
+                value, err_type, ..
+            }) => {
+                let mapped = interp
+                    .call_value(args[1].clone(), vec![(**value).clone()])
+                    .await?;
+                let ok_type = Some(value_type_tag(&mapped));
+                Ok(result_ok_value(mapped, ok_type, err_type.clone()))
+            }
+            Value::Result(ResultValue::Err {
+                value, err_type, ..
+            }) => Ok(result_err_value((**value).clone(), None, err_type.clone())),
+            other => Err(receiver_error(&name, other)),
+        }
+    })
+}
+
+fn builtin_map_err<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        ensure_arity(&args, 2, "result.map_err")?;
+        match &args[0] {
+            Value::Result(ResultValue::Ok { value, ok_type, .. }) => {
+                Ok(result_ok_value((**value).clone(), ok_type.clone(), None))
+            }
+            Value::Result(ResultValue::Err { value, ok_type, .. }) => {
+                let mapped = interp
+                    .call_value(args[1].clone(), vec![(**value).clone()])
+                    .await?;
+                let err_type = Some(value_type_tag(&mapped));
+                Ok(result_err_value(mapped, ok_type.clone(), err_type))
+            }
+            other => Err(receiver_error("result.map_err", other)),
+        }
+    })
+}
+
+fn builtin_and_then<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "and_then");
+        ensure_arity(&args, 2, &name)?;
+        match &args[0] {
+            Value::Option(OptionValue::Some { value, .. }) => {
+                let answer = interp
+                    .call_value(args[1].clone(), vec![(**value).clone()])
+                    .await?;
+                expect_same_kind(answer, &args[0], &name)
+            }
+            Value::Option(OptionValue::None { .. }) => Ok(option_none_value(None)),
+            Value::Result(ResultValue::Ok {
+                value, err_type, ..
+            }) => {
+                let answer = interp
+                    .call_value(args[1].clone(), vec![(**value).clone()])
+                    .await?;
+                let mut answer = expect_same_kind(answer, &args[0], &name)?;
+                if let Value::Result(
+                    ResultValue::Ok { err_type: slot, .. }
+                    | ResultValue::Err { err_type: slot, .. },
+                ) = &mut answer
+                {
+                    inherit_tag(slot, err_type);
+                }
+                Ok(answer)
+            }
+            Value::Result(ResultValue::Err {
+                value, err_type, ..
+            }) => Ok(result_err_value((**value).clone(), None, err_type.clone())),
+            other => Err(receiver_error(&name, other)),
+        }
+    })
+}
+
+fn builtin_or_else<'a>(
+    interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "or_else");
+        ensure_arity(&args, 2, &name)?;
+        match &args[0] {
+            Value::Option(OptionValue::Some { .. }) => Ok(args[0].clone()),
+            Value::Option(OptionValue::None { elem_type }) => {
+                let answer = interp.call_value(args[1].clone(), Vec::new()).await?;
+                let mut answer = expect_same_kind(answer, &args[0], &name)?;
+                if let Value::Option(
+                    OptionValue::Some {
+                        elem_type: slot, ..
+                    }
+                    | OptionValue::None { elem_type: slot },
+                ) = &mut answer
+                {
+                    inherit_tag(slot, elem_type);
+                }
+                Ok(answer)
+            }
+            Value::Result(ResultValue::Ok { value, ok_type, .. }) => {
+                Ok(result_ok_value((**value).clone(), ok_type.clone(), None))
+            }
+            Value::Result(ResultValue::Err { value, ok_type, .. }) => {
+                let answer = interp
+                    .call_value(args[1].clone(), vec![(**value).clone()])
+                    .await?;
+                let mut answer = expect_same_kind(answer, &args[0], &name)?;
+                if let Value::Result(
+                    ResultValue::Ok { ok_type: slot, .. } | ResultValue::Err { ok_type: slot, .. },
+                ) = &mut answer
+                {
+                    inherit_tag(slot, ok_type);
+                }
+                Ok(answer)
+            }
+            other => Err(receiver_error(&name, other)),
+        }
+    })
+}
+
+fn builtin_ok_or<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        ensure_arity(&args, 2, "option.ok_or")?;
+        let error = args[1].clone();
+        let err_type = Some(value_type_tag(&error));
+        match &args[0] {
+            Value::Option(OptionValue::Some { value, elem_type }) => Ok(result_ok_value(
+                (**value).clone(),
+                elem_type.clone(),
+                err_type,
+            )),
+            Value::Option(OptionValue::None { elem_type }) => {
+                Ok(result_err_value(error, elem_type.clone(), err_type))
+            }
+            other => Err(receiver_error("option.ok_or", other)),
+        }
+    })
+}
+
+fn builtin_ok<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        ensure_arity(&args, 1, "result.ok")?;
+        match &args[0] {
+            Value::Result(ResultValue::Ok { value, ok_type, .. }) => {
+                Ok(option_some_value((**value).clone(), ok_type.clone()))
+            }
+            Value::Result(ResultValue::Err { ok_type, .. }) => {
+                Ok(option_none_value(ok_type.clone()))
+            }
+            other => Err(receiver_error("result.ok", other)),
+        }
+    })
+}
+
+fn builtin_err<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        ensure_arity(&args, 1, "result.err")?;
+        match &args[0] {
+            Value::Result(ResultValue::Err {
+                value, err_type, ..
+            }) => Ok(option_some_value((**value).clone(), err_type.clone())),
+            Value::Result(ResultValue::Ok { err_type, .. }) => {
+                Ok(option_none_value(err_type.clone()))
+            }
+            other => Err(receiver_error("result.err", other)),
+        }
+    })
+}
+
+fn builtin_flatten<'a>(
+    _interp: &'a Interpreter,
+    args: Vec<Value>,
+) -> LocalBoxFuture<'a, RuntimeResult<Value>> {
+    Box::pin(async move {
+        let name = qualified(&args, "flatten");
+        ensure_arity(&args, 1, &name)?;
+        let nested_error = |inner: &Value| {
+            RuntimeError::new(format!(
+                "{name} expects a nested {}, got one holding {}",
+                args[0].type_name(),
+                inner.type_name()
+            ))
+        };
+        match &args[0] {
+            Value::Option(OptionValue::Some { value, .. }) => match &**value {
+                inner @ Value::Option(_) => Ok(inner.clone()),
+                other => Err(nested_error(other)),
+            },
+            Value::Option(OptionValue::None { elem_type }) => {
+                let inner = match elem_type {
+                    Some(TypeTag::Option(inner)) => Some((**inner).clone()),
+                    _ => None,
+                };
+                Ok(option_none_value(inner))
+            }
+            Value::Result(ResultValue::Ok {
+                value, err_type, ..
+            }) => match &**value {
+                Value::Result(inner) => {
+                    let mut inner = inner.clone();
+                    let (ResultValue::Ok { err_type: slot, .. }
+                    | ResultValue::Err { err_type: slot, .. }) = &mut inner;
+                    inherit_tag(slot, err_type);
+                    Ok(Value::Result(inner))
+                }
+                other => Err(nested_error(other)),
+            },
+            Value::Result(ResultValue::Err {
+                value,
+                ok_type,
+                err_type,
+            }) => {
+                let inner = match ok_type {
+                    Some(TypeTag::Result(inner, _)) => Some((**inner).clone()),
+                    _ => None,
+                };
+                Ok(result_err_value((**value).clone(), inner, err_type.clone()))
+            }
+            other => Err(receiver_error(&name, other)),
+        }
+    })
+}
@@ -19,8 +19,9 @@ use tokio::net::{TcpListener, TcpStream, UdpSocket};
 use tokio::sync::Mutex; // For read/write traits
 
 use crate::ast::{
-    Block, CheckPattern, Expr, File, FunctionSignature, IfStmt, Import, Item, Literal, NamedType,
-    Param, Pattern, Stmt, SwitchStmt, TraitDef, TryCatch, TypeExpr, VarKind,
+    Block, CheckPattern, Expr, File, FunctionSignature, GlobalDef, GlobalKind, IfStmt, Import,
+    InterpolationPart, Item, Literal, NamedType, Param, Pattern, Stmt, SwitchStmt, TraitDef,
+    TryCatch, TypeAliasDef, TypeExpr, TypeParam, VarKind,
 };
 use crate::module_loader::{ExportMeta, ExportSchema, ModuleLoader};
 use crate::span::Span;
@@ -39,6 +40,8 @@ pub mod android {
         Value::Null
     }
 }
+mod collections;
+mod combinators;
 mod forge;
 mod java_runtime;
 pub mod web;
@@ -66,7 +69,7 @@ pub enum RuntimeError {
 }
 
 impl RuntimeError {
-    fn new<S: Into<String>>(msg: S) -> Self {
+    pub(crate) fn new<S: Into<String>>(msg: S) -> Self {
         RuntimeError::Message {
             message: msg.into(),
             span: None,
@@ -440,6 +443,10 @@ struct Binding {
 struct EnvData {
     values: HashMap<String, Binding>,
     parent: Option<Env>,
+    /// Imported module whose code runs in this scope; `None` for the entry file.
+    module: Option<Rc<str>>,
+    /// Trait whose default method body runs in this scope.
+    in_trait: Option<Rc<str>>,
 }
 
 impl Env {
@@ -447,6 +454,8 @@ impl Env {
         Env(Rc::new(RefCell::new(EnvData {
             values: HashMap::new(),
             parent: None,
+            module: None,
+            in_trait: None,
         })))
     }
 
@@ -454,9 +463,27 @@ impl Env {
         Env(Rc::new(RefCell::new(EnvData {
             values: HashMap::new(),
             parent: Some(self.clone()),
+            module: self.module(),
+            in_trait: self.0.borrow().in_trait.clone(),
         })))
     }
 
+    fn trait_child(&self, trait_name: &str) -> Self {
+        let env = self.child();
+        env.0.borrow_mut().in_trait = Some(Rc::from(trait_name));
+        env
+    }
+
+    fn module_child(&self, module: &str) -> Self {
+        let env = self.child();
+        env.0.borrow_mut().module = Some(Rc::from(module));
+        env
+    }
+
+    fn module(&self) -> Option<Rc<str>> {
+        self.0.borrow().module.clone()
+    }
+
     fn define(&self, name: impl Into<String>, value: Value) {
         self.define_binding_internal(name, value, VarKind::Let, None);
     }
@@ -582,6 +609,22 @@ impl Env {
     }
 }
 
+/// Fails when code running in `env` uses a non-`pub` member owned by another
+/// module. `what` names the member for the error message.
+fn ensure_visible(
+    env: &Env,
+    owner: &Option<Rc<str>>,
+    is_pub: bool,
+    what: impl FnOnce() -> String,
+) -> RuntimeResult<()> {
+    match owner {
+        Some(module) if !is_pub && env.module().as_ref() != Some(module) => Err(RuntimeError::new(
+            format!("{} is private to module `{module}`", what()),
+        )),
+        _ => Ok(()),
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum IntType {
     I8,
@@ -604,6 +647,36 @@ impl IntType {
         )
     }
 
+    fn bits(&self) -> u32 {
+        match self {
+            IntType::I8 | IntType::U8 => 8,
+            IntType::I16 | IntType::U16 => 16,
+            IntType::I32 | IntType::U32 => 32,
+            IntType::I64 | IntType::U64 => 64,
+            IntType::I128 | IntType::U128 => 128,
+        }
+    }
+
+    /// Truncates the two's complement bit pattern `raw` to this type's width
+    /// and reads it back as a value of this type.
+    fn wrap(&self, raw: u128) -> RuntimeResult<i128> {
+        let bits = self.bits();
+        if bits == 128 {
+            return if self.is_signed() {
+                Ok(raw as i128)
+            } else {
+                i128::try_from(raw)
+                    .map_err(|_| RuntimeError::new("u128 result does not fit in a runtime integer"))
+            };
+        }
+        let value = (raw & ((1u128 << bits) - 1)) as i128;
+        if self.is_signed() && value >> (bits - 1) == 1 {
+            Ok(value - (1i128 << bits))
+        } else {
+            Ok(value)
+        }
+    }
+
     fn name(&self) -> &'static str {
         match self {
             IntType::I8 => "i8",
@@ -718,6 +791,10 @@ struct StructSchema {
     name: String,
     type_params: Vec<String>,
     fields: HashMap<String, TypeExpr>,
+    module: Option<Rc<str>>,
+    is_pub: bool,
+    public_fields: HashSet<String>,
+    bounds: Vec<(String, String)>,
 }
 
 #[derive(Clone, Debug)]
@@ -749,31 +826,64 @@ fn primitive_from_name(name: &str) -> Option<PrimitiveType> {
     }
 }
 
-fn type_tag_from_type_expr(expr: &TypeExpr) -> TypeTag {
+/// `type` items by name; expanded whenever a `TypeExpr` becomes a `TypeTag`.
+type TypeAliases = HashMap<String, TypeAliasDef>;
+
+fn type_tag_from_type_expr(expr: &TypeExpr, aliases: &TypeAliases) -> TypeTag {
     let bindings = HashMap::new();
-    type_tag_from_type_expr_with_bindings(expr, &bindings)
+    type_tag_from_type_expr_with_bindings(expr, &bindings, aliases)
+}
+
+/// The built-in module whose functions serve as methods on `value`.
+fn builtin_method_module(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::Vec(_) => Some("vec"),
+        Value::String(_) => Some("str"),
+        Value::Map(_) => Some("map"),
+        Value::Set(_) => Some("set"),
+        _ => None,
+    }
+}
+
+/// `(parameter, trait)` pairs for every bound declared on `params`, with the
+/// trait named the way `impl` blocks register it.
+fn type_param_bounds(params: &[TypeParam], aliases: &TypeAliases) -> Vec<(String, String)> {
+    params
+        .iter()
+        .flat_map(|param| {
+            param.bounds.iter().map(|bound| {
+                (
+                    param.name.clone(),
+                    type_tag_from_type_expr(bound, aliases).describe(),
+                )
+            })
+        })
+        .collect()
 }
 
 fn type_tag_from_type_expr_with_bindings(
     expr: &TypeExpr,
     bindings: &HashMap<String, TypeTag>,
+    aliases: &TypeAliases,
 ) -> TypeTag {
     match expr {
-        TypeExpr::Named(named) => type_tag_from_named(named, bindings),
+        TypeExpr::Named(named) => type_tag_from_named(named, bindings, aliases),
         TypeExpr::Slice { element, .. } => TypeTag::Slice(Box::new(
-            type_tag_from_type_expr_with_bindings(element, bindings),
+            type_tag_from_type_expr_with_bindings(element, bindings, aliases),
         )),
         TypeExpr::Reference { inner: element, .. } => TypeTag::Slice(Box::new(
-            type_tag_from_type_expr_with_bindings(element, bindings),
+            type_tag_from_type_expr_with_bindings(element, bindings, aliases),
         )),
         TypeExpr::Array { element, size, .. } => TypeTag::Array(
-            Box::new(type_tag_from_type_expr_with_bindings(element, bindings)),
+            Box::new(type_tag_from_type_expr_with_bindings(
+                element, bindings, aliases,
+            )),
             *size,
         ),
         TypeExpr::Tuple { elements, .. } => TypeTag::Tuple(
             elements
                 .iter()
-                .map(|e| type_tag_from_type_expr_with_bindings(e, bindings))
+                .map(|e| type_tag_from_type_expr_with_bindings(e, bindings, aliases))
                 .collect(),
         ),
     }
@@ -803,6 +913,7 @@ fn resolve_struct_field_tag(
     schema: &StructSchema,
     bindings: &HashMap<String, TypeTag>,
     field: &str,
+    aliases: &TypeAliases,
 ) -> RuntimeResult<TypeTag> {
     let expr = schema.fields.get(field).ok_or_else(|| {
         RuntimeError::new(format!(
@@ -810,13 +921,16 @@ fn resolve_struct_field_tag(
             field, schema.name
         ))
     })?;
-    Ok(type_tag_from_type_expr_with_bindings(expr, bindings))
+    Ok(type_tag_from_type_expr_with_bindings(
+        expr, bindings, aliases,
+    ))
 }
 
 fn resolve_enum_variant_tags(
     schema: &EnumSchema,
     bindings: &HashMap<String, TypeTag>,
     variant: &str,
+    aliases: &TypeAliases,
 ) -> RuntimeResult<Vec<TypeTag>> {
     let payload = schema.variants.get(variant).ok_or_else(|| {
         RuntimeError::new(format!(
@@ -826,11 +940,15 @@ fn resolve_enum_variant_tags(
     })?;
     Ok(payload
         .iter()
-        .map(|expr| type_tag_from_type_expr_with_bindings(expr, bindings))
+        .map(|expr| type_tag_from_type_expr_with_bindings(expr, bindings, aliases))
         .collect())
 }
 
-fn type_tag_from_named(named: &NamedType, bindings: &HashMap<String, TypeTag>) -> TypeTag {
+fn type_tag_from_named(
+    named: &NamedType,
+    bindings: &HashMap<String, TypeTag>,
+    aliases: &TypeAliases,
+) -> TypeTag {
     if named.segments.len() == 1 {
         let seg = &named.segments[0];
         if let Some(bound) = bindings.get(&seg.name) {
@@ -841,12 +959,16 @@ fn type_tag_from_named(named: &NamedType, bindings: &HashMap<String, TypeTag>) -
         .segments
         .last()
         .expect("named type has at least one segment");
+    if let Some(alias) = aliases.get(&last.name) {
+        let expanded = alias.instantiate(&last.generics);
+        return type_tag_from_type_expr_with_bindings(&expanded, bindings, aliases);
+    }
     match last.name.as_str() {
         "vec" => {
             let elem = last
                 .generics
                 .get(0)
-                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings))
+                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings, aliases))
                 .unwrap_or(TypeTag::Unknown);
             TypeTag::Vec(Box::new(elem))
         }
@@ -854,7 +976,7 @@ fn type_tag_from_named(named: &NamedType, bindings: &HashMap<String, TypeTag>) -
             let elem = last
                 .generics
                 .get(0)
-                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings))
+                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings, aliases))
                 .unwrap_or(TypeTag::Unknown);
             TypeTag::Set(Box::new(elem))
         }
@@ -862,12 +984,12 @@ fn type_tag_from_named(named: &NamedType, bindings: &HashMap<String, TypeTag>) -
             let key = last
                 .generics
                 .get(0)
-                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings))
+                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings, aliases))
                 .unwrap_or(TypeTag::Unknown);
             let value = last
                 .generics
                 .get(1)
-                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings))
+                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings, aliases))
                 .unwrap_or(TypeTag::Unknown);
             TypeTag::Map(Box::new(key), Box::new(value))
         }
@@ -875,7 +997,7 @@ fn type_tag_from_named(named: &NamedType, bindings: &HashMap<String, TypeTag>) -
             let inner = last
                 .generics
                 .get(0)
-                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings))
+                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings, aliases))
                 .unwrap_or(TypeTag::Unknown);
             TypeTag::Option(Box::new(inner))
         }
@@ -883,12 +1005,12 @@ fn type_tag_from_named(named: &NamedType, bindings: &HashMap<String, TypeTag>) -
             let ok = last
                 .generics
                 .get(0)
-                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings))
+                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings, aliases))
                 .unwrap_or(TypeTag::Unknown);
             let err = last
                 .generics
                 .get(1)
-                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings))
+                .map(|g| type_tag_from_type_expr_with_bindings(g, bindings, aliases))
                 .unwrap_or(TypeTag::Unknown);
             TypeTag::Result(Box::new(ok), Box::new(err))
         }
@@ -899,7 +1021,7 @@ fn type_tag_from_named(named: &NamedType, bindings: &HashMap<String, TypeTag>) -
                 let params = last
                     .generics
                     .iter()
-                    .map(|g| type_tag_from_type_expr_with_bindings(g, bindings))
+                    .map(|g| type_tag_from_type_expr_with_bindings(g, bindings, aliases))
                     .collect::<Vec<_>>();
                 let path = named
                     .segments
@@ -952,6 +1074,14 @@ fn type_tag_matches_value(tag: &TypeTag, value: &Value) -> bool {
                     instance.type_params == *params
                 }
             }
+            // Annotations name enums and structs alike, and both resolve to `Struct`.
+            Value::Enum(_) => type_tag_matches_value(
+                &TypeTag::Enum {
+                    name: name.clone(),
+                    params: params.clone(),
+                },
+                value,
+            ),
             _ => false,
         },
         TypeTag::Enum { name, params } => match value {
@@ -1035,6 +1165,7 @@ fn bind_type_params_from_type_expr(
     actual: &TypeTag,
     type_params: &[String],
     bindings: &mut HashMap<String, TypeTag>,
+    aliases: &TypeAliases,
 ) {
     match ty {
         TypeExpr::Named(named) => {
@@ -1049,6 +1180,11 @@ fn bind_type_params_from_type_expr(
                 .segments
                 .last()
                 .expect("named type has at least one segment");
+            if let Some(alias) = aliases.get(&last.name) {
+                let expanded = alias.instantiate(&last.generics);
+                bind_type_params_from_type_expr(&expanded, actual, type_params, bindings, aliases);
+                return;
+            }
             match last.name.as_str() {
                 "vec" => {
                     if let Some(inner_ty) = last.generics.get(0) {
@@ -1058,6 +1194,7 @@ fn bind_type_params_from_type_expr(
                                 inner_tag,
                                 type_params,
                                 bindings,
+                                aliases,
                             );
                         }
                     }
@@ -1070,6 +1207,7 @@ fn bind_type_params_from_type_expr(
                                 inner_tag,
                                 type_params,
                                 bindings,
+                                aliases,
                             );
                         }
                     }
@@ -1082,6 +1220,7 @@ fn bind_type_params_from_type_expr(
                                 inner_tag,
                                 type_params,
                                 bindings,
+                                aliases,
                             );
                         }
                     }
@@ -1091,8 +1230,20 @@ fn bind_type_params_from_type_expr(
                         (last.generics.get(0), last.generics.get(1))
                     {
                         if let TypeTag::Result(ok_tag, err_tag) = actual {
-                            bind_type_params_from_type_expr(ok_ty, ok_tag, type_params, bindings);
-                            bind_type_params_from_type_expr(err_ty, err_tag, type_params, bindings);
+                            bind_type_params_from_type_expr(
+                                ok_ty,
+                                ok_tag,
+                                type_params,
+                                bindings,
+                                aliases,
+                            );
+                            bind_type_params_from_type_expr(
+                                err_ty,
+                                err_tag,
+                                type_params,
+                                bindings,
+                                aliases,
+                            );
                         }
                     }
                 }
@@ -1101,12 +1252,19 @@ fn bind_type_params_from_type_expr(
                         (last.generics.get(0), last.generics.get(1))
                     {
                         if let TypeTag::Map(key_tag, value_tag) = actual {
-                            bind_type_params_from_type_expr(key_ty, key_tag, type_params, bindings);
+                            bind_type_params_from_type_expr(
+                                key_ty,
+                                key_tag,
+                                type_params,
+                                bindings,
+                                aliases,
+                            );
                             bind_type_params_from_type_expr(
                                 value_ty,
                                 value_tag,
                                 type_params,
                                 bindings,
+                                aliases,
                             );
                         }
                     }
@@ -1122,6 +1280,7 @@ fn bind_type_params_from_type_expr(
                             actual_tag,
                             type_params,
                             bindings,
+                            aliases,
                         );
                     }
                 }
@@ -1130,18 +1289,18 @@ fn bind_type_params_from_type_expr(
         TypeExpr::Tuple { elements, .. } => {
             if let TypeTag::Tuple(actual_items) = actual {
                 for (expr, tag) in elements.iter().zip(actual_items.iter()) {
-                    bind_type_params_from_type_expr(expr, tag, type_params, bindings);
+                    bind_type_params_from_type_expr(expr, tag, type_params, bindings, aliases);
                 }
             }
         }
         TypeExpr::Array { element, .. } => {
             if let TypeTag::Array(tag, _) = actual {
-                bind_type_params_from_type_expr(element, tag, type_params, bindings);
+                bind_type_params_from_type_expr(element, tag, type_params, bindings, aliases);
             }
         }
         TypeExpr::Slice { element, .. } | TypeExpr::Reference { inner: element, .. } => {
             if let TypeTag::Slice(tag) = actual {
-                bind_type_params_from_type_expr(element, tag, type_params, bindings);
+                bind_type_params_from_type_expr(element, tag, type_params, bindings, aliases);
             }
         }
     }
@@ -1227,6 +1386,39 @@ fn value_type_tag(value: &Value) -> TypeTag {
     }
 }
 
+/// Impl targets a method call on `value` may resolve against: its declared
+/// type first (integers are stored as `i64` whatever their annotation), then
+/// the type of the value itself.
+fn method_type_keys(value: &TypedValue) -> Vec<String> {
+    let mut keys = vec![value_type_tag(&value.value).describe()];
+    if let Some(tag) = &value.tag {
+        let declared = tag.describe();
+        if !keys.contains(&declared) {
+            keys.insert(0, declared);
+        }
+    }
+    keys
+}
+
+/// The enum name, variant and payload of values patterns can destructure by
+/// variant; `option` and `result` values count as enums of those names.
+fn enum_parts(value: &Value) -> Option<(Option<&str>, &str, Vec<&Value>)> {
+    match value {
+        Value::Enum(e) => Some((e.name.as_deref(), &e.variant, e.payload.iter().collect())),
+        Value::Option(OptionValue::Some { value, .. }) => {
+            Some((Some("option"), "Some", vec![value]))
+        }
+        Value::Option(OptionValue::None { .. }) => Some((Some("option"), "None", Vec::new())),
+        Value::Result(ResultValue::Ok { value, .. }) => Some((Some("result"), "Ok", vec![value])),
+        Value::Result(ResultValue::Err { value, .. }) => Some((Some("result"), "Err", vec![value])),
+        _ => None,
+    }
+}
+
+fn builtin_variant(name: &str) -> bool {
+    matches!(name, "Some" | "None" | "Ok" | "Err")
+}
+
 fn resolved_tag(value: &TypedValue) -> TypeTag {
     value
         .tag
@@ -1598,7 +1790,7 @@ impl DerefMut for MapValue {
     }
 }
 
-fn map_key_from_value(value: &Value, context: &str) -> RuntimeResult<MapKey> {
+pub(crate) fn map_key_from_value(value: &Value, context: &str) -> RuntimeResult<MapKey> {
     match value {
         Value::String(s) => Ok(MapKey::Str(s.clone())),
         Value::Int(i) => Ok(MapKey::Int(*i)),
@@ -1702,6 +1894,9 @@ pub struct ClosureValue {
     pub params: Vec<String>,
     pub body: crate::ast::Block,
     pub is_async: bool,
+    /// Lexical scope the closure was created in. Shared by reference, so writes through
+    /// captured `var` bindings are visible to the enclosing frame and to other closures.
+    pub env: Env,
 }
 
 // ---------------------------
@@ -1784,7 +1979,7 @@ impl FutureValue {
     pub fn new_spawn(interp: Interpreter, func: Value, args: Vec<Value>) -> Self {
         let func_clone = func.clone();
         let args_clone = args.clone();
-        let fut = async move { interp.invoke(func_clone, args_clone, None).await };
+        let fut = async move { interp.run_spawned(func_clone, args_clone).await };
         Self::new(Box::pin(fut), FutureKind::Spawn { func, args })
     }
 
@@ -1992,7 +2187,7 @@ impl Value {
         }
     }
 
-    fn to_string_value(&self) -> String {
+    pub(crate) fn to_string_value(&self) -> String {
         match self {
             Value::Null => "null".to_string(),
             Value::Bool(b) => b.to_string(),
@@ -2106,6 +2301,8 @@ pub struct ModuleValue {
 pub struct TraitMethodValue {
     pub trait_name: String,
     pub signature: FunctionSignature,
+    /// The trait's own body, used for implementors that do not override it.
+    pub default: Option<Rc<UserFunction>>,
 }
 
 #[derive(Clone, Debug)]
@@ -2118,6 +2315,9 @@ pub struct UserFunction {
     pub type_params: Vec<String>,
     pub return_type: Option<TypeExpr>,
     pub forced_type_args: Option<Vec<TypeTag>>,
+    /// `(parameter, trait)` pairs checked once the type parameters are bound.
+    pub type_bounds: Vec<(String, String)>,
+    pub is_pub: bool,
 }
 
 type BuiltinFn =
@@ -2131,8 +2331,11 @@ pub struct Interpreter {
     type_bindings: Rc<RefCell<Vec<HashMap<String, TypeTag>>>>,
     struct_defs: Rc<RefCell<HashMap<String, StructSchema>>>,
     enum_defs: Rc<RefCell<HashMap<String, EnumSchema>>>,
+    type_aliases: Rc<RefCell<TypeAliases>>,
     trait_defs: Rc<RefCell<HashMap<String, TraitDef>>>,
     inherent_impls: Rc<RefCell<HashMap<String, HashMap<String, UserFunction>>>>,
+    /// Non-`pub` item names of each loaded module, for error messages.
+    private_members: Rc<RefCell<HashMap<String, HashSet<String>>>>,
     trait_impls: Rc<RefCell<HashMap<String, HashMap<String, HashMap<String, UserFunction>>>>>,
     return_type_stack: Rc<RefCell<Vec<Option<TypeTag>>>>,
 }
@@ -2140,8 +2343,91 @@ pub struct Interpreter {
 enum ExecSignal {
     None,
     Return(Value),
-    Break,
-    Continue,
+    /// `break`, with its label and value (`null` without one).
+    Break(Option<String>, Value),
+    Continue(Option<String>),
+}
+
+/// What a loop does after its body ends with a signal.
+enum LoopStep {
+    Next,
+    Exit(Value),
+    /// A `return`, or a `break`/`continue` naming an enclosing loop.
+    Leave(ExecSignal),
+}
+
+/// The step for a loop labeled `label` whose body ended with `signal`.
+fn loop_step(signal: ExecSignal, label: Option<&str>) -> LoopStep {
+    let ours = |target: &Option<String>| target.is_none() || target.as_deref() == label;
+    match signal {
+        ExecSignal::None => LoopStep::Next,
+        ExecSignal::Continue(target) if ours(&target) => LoopStep::Next,
+        ExecSignal::Break(target, value) if ours(&target) => LoopStep::Exit(value),
+        other => LoopStep::Leave(other),
+    }
+}
+
+/// The integers of a range, produced on demand.
+#[derive(Clone, Debug)]
+struct IntRange {
+    /// `None` once the range is exhausted or the next value would overflow.
+    next: Option<i128>,
+    end: i128,
+    inclusive: bool,
+    step: i128,
+}
+
+impl Iterator for IntRange {
+    type Item = i128;
+
+    fn next(&mut self) -> Option<i128> {
+        let current = self.next?;
+        let more = if self.inclusive {
+            current <= self.end
+        } else {
+            current < self.end
+        };
+        if !more {
+            self.next = None;
+            return None;
+        }
+        self.next = current.checked_add(self.step);
+        Some(current)
+    }
+}
+
+/// What a `for` loop walks, one item at a time. Vecs, arrays and strings are
+/// read by position, so the loop sees items replaced while it runs; maps and
+/// sets are walked over the keys they held when the loop started.
+enum ForIter {
+    Range(IntRange),
+    /// Stops at the length the vec had when the loop started, so pushing to
+    /// it inside the loop cannot keep the loop going.
+    Vec {
+        items: Rc<RefCell<VecValue>>,
+        index: usize,
+        len: usize,
+    },
+    Array {
+        items: Rc<RefCell<ArrayValue>>,
+        index: usize,
+    },
+    /// `(key, value)` tuples; keys removed during the loop are skipped.
+    Map {
+        map: Rc<RefCell<MapValue>>,
+        keys: std::vec::IntoIter<MapKey>,
+    },
+    Set(std::vec::IntoIter<Value>),
+    Chars {
+        text: String,
+        offset: usize,
+    },
+    /// A value whose type implements the `Iterator` trait in scope. A
+    /// `self_mut` receiver of `next` is stored back after each call.
+    User {
+        target: Value,
+        next: UserFunction,
+    },
 }
 
 impl Interpreter {
@@ -2159,8 +2445,10 @@ impl Interpreter {
             type_bindings: Rc::new(RefCell::new(Vec::new())),
             struct_defs: Rc::new(RefCell::new(HashMap::new())),
             enum_defs: Rc::new(RefCell::new(HashMap::new())),
+            type_aliases: Rc::new(RefCell::new(HashMap::new())),
             trait_defs: Rc::new(RefCell::new(HashMap::new())),
             inherent_impls: Rc::new(RefCell::new(HashMap::new())),
+            private_members: Rc::new(RefCell::new(HashMap::new())),
             trait_impls: Rc::new(RefCell::new(HashMap::new())),
             return_type_stack: Rc::new(RefCell::new(Vec::new())),
         }
@@ -2187,7 +2475,24 @@ impl Interpreter {
         self.invoke(value, args, None).await
     }
 
+    /// Global binding by name (builtin modules such as `log`, or registered items).
+    pub(crate) fn global(&self, name: &str) -> Option<Value> {
+        self.globals.get(name).ok()
+    }
+
+    pub(crate) async fn call_value(&self, callee: Value, args: Vec<Value>) -> RuntimeResult<Value> {
+        self.invoke(callee, args, None).await
+    }
+
     fn register_item_definitions(&self, env: &Env, items: &[Item]) -> RuntimeResult<()> {
+        // Aliases first: impl targets below are resolved eagerly.
+        for item in items {
+            if let Item::TypeAlias(alias) = item {
+                self.type_aliases
+                    .borrow_mut()
+                    .insert(alias.name.clone(), alias.clone());
+            }
+        }
         for item in items {
             match item {
                 Item::Struct(def) => {
@@ -2201,6 +2506,18 @@ impl Interpreter {
                             name: def.name.clone(),
                             type_params: def.type_params.iter().map(|p| p.name.clone()).collect(),
                             fields,
+                            module: env.module(),
+                            is_pub: def.is_pub,
+                            public_fields: def
+                                .fields
+                                .iter()
+                                .filter(|f| f.is_pub)
+                                .map(|f| f.name.clone())
+                                .collect(),
+                            bounds: type_param_bounds(
+                                &def.type_params,
+                                &self.type_aliases.borrow(),
+                            ),
                         },
                     );
                 }
@@ -2222,13 +2539,36 @@ impl Interpreter {
                     self.trait_defs
                         .borrow_mut()
                         .insert(def.name.clone(), def.clone());
+                    let default_env = env.trait_child(&def.name);
                     let mut fields = HashMap::new();
                     for method in &def.methods {
+                        let signature = &method.signature;
+                        let default = method.default.as_ref().map(|body| {
+                            Rc::new(UserFunction {
+                                name: signature.name.clone(),
+                                params: signature.params.clone(),
+                                body: body.clone(),
+                                is_async: signature.is_async,
+                                env: default_env.clone(),
+                                // `Self` binds to the implementor at each call.
+                                type_params: std::iter::once("Self".to_string())
+                                    .chain(signature.type_params.iter().map(|p| p.name.clone()))
+                                    .collect(),
+                                return_type: signature.return_type.clone(),
+                                forced_type_args: None,
+                                type_bounds: type_param_bounds(
+                                    &signature.type_params,
+                                    &self.type_aliases.borrow(),
+                                ),
+                                is_pub: true,
+                            })
+                        });
                         fields.insert(
-                            method.name.clone(),
+                            signature.name.clone(),
                             Value::TraitMethod(TraitMethodValue {
                                 trait_name: def.name.clone(),
-                                signature: method.clone(),
+                                signature: signature.clone(),
+                                default,
                             }),
                         );
                     }
@@ -2241,12 +2581,21 @@ impl Interpreter {
                     );
                 }
                 Item::Impl(imp) => {
-                    let target_tag = type_tag_from_type_expr(&imp.target);
+                    let target_tag =
+                        type_tag_from_type_expr(&imp.target, &self.type_aliases.borrow());
                     let type_key = target_tag.describe();
-                    let trait_key = imp
-                        .trait_type
-                        .as_ref()
-                        .map(|t| type_tag_from_type_expr(t).describe());
+                    let trait_key = imp.trait_type.as_ref().map(|t| {
+                        type_tag_from_type_expr(t, &self.type_aliases.borrow()).describe()
+                    });
+                    if let Some(trait_name) = &trait_key {
+                        // Recorded even without methods: the impl may rely on defaults.
+                        self.trait_impls
+                            .borrow_mut()
+                            .entry(trait_name.clone())
+                            .or_default()
+                            .entry(type_key.clone())
+                            .or_default();
+                    }
                     for method in &imp.methods {
                         if method.signature.params.is_empty() {
                             return Err(RuntimeError::new(format!(
@@ -2268,6 +2617,12 @@ impl Interpreter {
                                 .collect(),
                             return_type: method.signature.return_type.clone(),
                             forced_type_args: None,
+                            type_bounds: type_param_bounds(
+                                &method.signature.type_params,
+                                &self.type_aliases.borrow(),
+                            ),
+                            // Trait methods are as visible as the trait.
+                            is_pub: method.signature.is_pub || trait_key.is_some(),
                         };
                         if let Some(trait_name) = &trait_key {
                             self.trait_impls
@@ -2286,6 +2641,7 @@ impl Interpreter {
                         }
                     }
                 }
+                Item::Global(global) => self.define_global(env, global)?,
                 _ => {}
             }
         }
@@ -2318,6 +2674,8 @@ impl Interpreter {
         }
     }
 
+    /// Binds the functions, enums and globals of `ast` in `env` and returns the
+    /// `pub` ones, which is what importers of the module see.
     fn load_items_into_env(&self, env: &Env, ast: &File) -> RuntimeResult<HashMap<String, Value>> {
         let mut defined = HashMap::new();
         for item in &ast.items {
@@ -2337,9 +2695,16 @@ impl Interpreter {
                             .collect(),
                         return_type: func.signature.return_type.clone(),
                         forced_type_args: None,
+                        type_bounds: type_param_bounds(
+                            &func.signature.type_params,
+                            &self.type_aliases.borrow(),
+                        ),
+                        is_pub: func.signature.is_pub,
                     });
                     env.define(func.signature.name.clone(), value.clone());
-                    defined.insert(func.signature.name.clone(), value);
+                    if func.signature.is_pub {
+                        defined.insert(func.signature.name.clone(), value);
+                    }
                 }
                 Item::Enum(enum_def) => {
                     let mut fields = HashMap::new();
@@ -2367,7 +2732,16 @@ impl Interpreter {
                         fields,
                     });
                     env.define(enum_def.name.clone(), module.clone());
-                    defined.insert(enum_def.name.clone(), module);
+                    if enum_def.is_pub {
+                        defined.insert(enum_def.name.clone(), module);
+                    }
+                }
+                // Bound by `register_item_definitions`; exported by value.
+                Item::Global(global) if global.is_pub => {
+                    defined.insert(global.name.clone(), env.get(&global.name)?);
+                }
+                Item::Trait(def) if def.is_pub => {
+                    defined.insert(def.name.clone(), env.get(&def.name)?);
                 }
                 _ => {}
             }
@@ -2410,13 +2784,27 @@ impl Interpreter {
                 }
                 continue;
             }
-            let module = self.load_module_value(&module_name)?;
-            if let Some(member) = &import.member {
+            // `import geo::origin;` parses as a two-segment path; when that is
+            // not a module itself, read it as member `origin` of module `geo`.
+            let (module, member) = match self.load_module_value(&module_name) {
+                Ok(module) => (module, import.member.as_ref()),
+                Err(err) if import.member.is_none() && import.path.len() > 1 => {
+                    let (last, parent) = import.path.split_last().expect("path has segments");
+                    match self.load_module_value(&parent.join(".")) {
+                        Ok(module) => (module, Some(last)),
+                        Err(_) => return Err(err),
+                    }
+                }
+                Err(err) => return Err(err),
+            };
+            if let Some(member) = member {
                 let field = module.fields.get(member).cloned().ok_or_else(|| {
-                    RuntimeError::new(format!(
-                        "unknown member `{}` in module `{}`",
-                        member, module.name
-                    ))
+                    self.missing_member(
+                        &module,
+                        member,
+                        format!("unknown member `{}` in module `{}`", member, module.name),
+                    )
+                    .with_span(import.span)
                 })?;
                 let binding = import
                     .alias
@@ -2436,15 +2824,185 @@ impl Interpreter {
         Ok(())
     }
 
+    /// The error for a `member` that `module` does not export: a dedicated
+    /// message when the item exists but is not `pub`, `fallback` otherwise.
+    fn missing_member(&self, module: &ModuleValue, member: &str, fallback: String) -> RuntimeError {
+        let private = self
+            .private_members
+            .borrow()
+            .get(&module.name)
+            .is_some_and(|names| names.contains(member));
+        if private {
+            RuntimeError::new(format!("`{member}` is private to module `{}`", module.name))
+        } else {
+            RuntimeError::new(fallback)
+        }
+    }
+
+    /// The body `type_key` runs for `tm`: its own impl, else the trait default.
+    fn trait_impl_function(
+        &self,
+        tm: &TraitMethodValue,
+        type_key: &str,
+    ) -> RuntimeResult<UserFunction> {
+        self.trait_impls
+            .borrow()
+            .get(&tm.trait_name)
+            .and_then(|impls| impls.get(type_key))
+            .and_then(|methods| {
+                methods
+                    .get(&tm.signature.name)
+                    .cloned()
+                    .or_else(|| tm.default.as_deref().cloned())
+            })
+            .ok_or_else(|| {
+                RuntimeError::new(format!(
+                    "trait bound not satisfied: missing `impl {} for {}`",
+                    tm.trait_name, type_key
+                ))
+            })
+    }
+
+    /// The body of `trait_name`'s `method` for `value`: the impl for its type
+    /// of the trait bound to that name in `env`, if there is one.
+    fn scoped_trait_function(
+        &self,
+        env: &Env,
+        trait_name: &str,
+        method: &str,
+        value: &TypedValue,
+    ) -> RuntimeResult<Option<UserFunction>> {
+        let Ok(Value::Module(module)) = env.get(trait_name) else {
+            return Ok(None);
+        };
+        let Some(Value::TraitMethod(tm)) = module.fields.get(method) else {
+            return Ok(None);
+        };
+        for type_key in method_type_keys(value) {
+            let implemented = self
+                .trait_impls
+                .borrow()
+                .get(&tm.trait_name)
+                .is_some_and(|impls| impls.contains_key(&type_key));
+            if implemented {
+                return self.trait_impl_function(tm, &type_key).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The trait method `value.method()` refers to: a method of a trait that
+    /// is bound by name in `env` and implemented for `type_key`. Several such
+    /// traits make the call ambiguous, unless `env` is inside a default body of
+    /// one of them.
+    fn trait_method_in_scope(
+        &self,
+        env: &Env,
+        type_key: &str,
+        method: &str,
+    ) -> RuntimeResult<Option<TraitMethodValue>> {
+        let mut found: Vec<TraitMethodValue> = Vec::new();
+        for (trait_name, impls) in self.trait_impls.borrow().iter() {
+            if !impls.contains_key(type_key) {
+                continue;
+            }
+            let Ok(Value::Module(module)) = env.get(trait_name) else {
+                continue;
+            };
+            if let Some(Value::TraitMethod(tm)) = module.fields.get(method) {
+                if &tm.trait_name == trait_name {
+                    found.push(tm.clone());
+                }
+            }
+        }
+        // A default body calling a sibling method means its own trait.
+        let in_trait = env.0.borrow().in_trait.clone();
+        if let Some(own) = in_trait {
+            if let Some(index) = found.iter().position(|tm| *tm.trait_name == *own) {
+                return Ok(Some(found.swap_remove(index)));
+            }
+        }
+        if found.len() > 1 {
+            let mut traits: Vec<&str> = found.iter().map(|tm| tm.trait_name.as_str()).collect();
+            traits.sort_unstable();
+            return Err(RuntimeError::new(format!(
+                "Ambiguous method `{method}` on `{type_key}`: it is provided by traits `{}`; call it as `{}::{method}(...)`",
+                traits.join("`, `"),
+                traits[0]
+            )));
+        }
+        Ok(found.pop())
+    }
+
+    /// Fails unless every bound parameter's type has an `impl` of each trait
+    /// it is bounded by.
+    fn check_type_bounds(
+        &self,
+        bounds: &[(String, String)],
+        bindings: &HashMap<String, TypeTag>,
+    ) -> RuntimeResult<()> {
+        for (param, trait_name) in bounds {
+            let Some(tag) = bindings.get(param) else {
+                continue;
+            };
+            let type_key = tag.describe();
+            let implemented = self
+                .trait_impls
+                .borrow()
+                .get(trait_name)
+                .is_some_and(|impls| impls.contains_key(&type_key));
+            if !implemented {
+                return Err(RuntimeError::new(format!(
+                    "trait bound `{param}: {trait_name}` is not satisfied: missing `impl {trait_name} for {type_key}`"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn ensure_field_visible(
+        &self,
+        env: &Env,
+        instance: &StructInstance,
+        field: &str,
+    ) -> RuntimeResult<()> {
+        let Some(name) = &instance.name else {
+            return Ok(());
+        };
+        let defs = self.struct_defs.borrow();
+        let Some(schema) = defs.get(name) else {
+            return Ok(());
+        };
+        ensure_visible(
+            env,
+            &schema.module,
+            schema.public_fields.contains(field),
+            || format!("Field `{field}` of struct `{name}`"),
+        )
+    }
+
     fn load_module_value(&self, name: &str) -> RuntimeResult<ModuleValue> {
         if let Some(module) = self.modules.borrow().get(name) {
             return Ok(module.clone());
         }
-        let module_value = match self.module_loader.borrow_mut().load_module(name) {
+        let loaded = self.module_loader.borrow_mut().load_module(name);
+        let module_value = match loaded {
             Ok(loaded) => {
-                let module_env = self.globals.child();
+                let module_env = self.globals.module_child(&loaded.name);
                 self.bind_imports(&loaded.ast.imports, &module_env)?;
+                self.register_item_definitions(&module_env, &loaded.ast.items)?;
                 let fields = self.load_items_into_env(&module_env, &loaded.ast)?;
+                let private = loaded
+                    .ast
+                    .items
+                    .iter()
+                    .filter(|item| !item.is_pub())
+                    .filter_map(Item::name)
+                    .map(str::to_string)
+                    .collect();
+                self.private_members
+                    .borrow_mut()
+                    .insert(loaded.name.clone(), private);
                 ModuleValue {
                     name: loaded.name.clone(),
                     fields,
@@ -2661,15 +3219,19 @@ impl Interpreter {
         let result = match stmt {
             Stmt::VarDecl(var) => {
                 let typed = if matches!(var.kind, VarKind::Const) {
-                    self.eval_const_expr_typed(&var.value)?
+                    self.eval_const_expr_typed(&var.value, env)?
                 } else {
                     self.eval_expr_typed(&var.value, env).await?
                 };
                 let declared_tag = var.ty.as_ref().map(|ty| {
                     if let Some(bindings) = self.type_bindings.borrow().last() {
-                        type_tag_from_type_expr_with_bindings(ty, bindings)
+                        type_tag_from_type_expr_with_bindings(
+                            ty,
+                            bindings,
+                            &self.type_aliases.borrow(),
+                        )
                     } else {
-                        type_tag_from_type_expr(ty)
+                        type_tag_from_type_expr(ty, &self.type_aliases.borrow())
                     }
                 });
                 let final_value = if let Some(tag) = &declared_tag {
@@ -2683,9 +3245,27 @@ impl Interpreter {
                         .clone()
                         .or_else(|| Some(value_type_tag(&final_value.value)))
                 });
-                env.define_var(var.name.clone(), var.kind, final_value.value, inferred_tag)?;
+                if let Some(name) = var.pattern.binding_name() {
+                    env.define_var(name.to_string(), var.kind, final_value.value, inferred_tag)?;
+                } else {
+                    let bindings =
+                        self.destructure(&final_value.value, &var.pattern, "declaration")?;
+                    for (name, value) in bindings {
+                        let tag = value_type_tag(&value);
+                        env.define_var(name, var.kind, value, Some(tag))?;
+                    }
+                }
                 Ok(ExecSignal::None)
             }
+            Stmt::Expr(Expr::Loop { label, body, .. }) => {
+                match self
+                    .run_loop(label.as_deref(), body, env, loop_depth)
+                    .await?
+                {
+                    LoopStep::Leave(signal) => Ok(signal),
+                    LoopStep::Next | LoopStep::Exit(_) => Ok(ExecSignal::None),
+                }
+            }
             Stmt::Expr(expr) => {
                 self.eval_expr(expr, env).await?;
                 Ok(ExecSignal::None)
@@ -2698,92 +3278,73 @@ impl Interpreter {
                 };
                 Ok(ExecSignal::Return(result))
             }
-            Stmt::If(if_stmt) => {
-                let cond = self.eval_expr_typed(&if_stmt.condition, env).await?;
-                if expect_bool_value(cond.value, "if condition")? {
-                    let signal = self
-                        .execute_block(&if_stmt.then_branch, env.clone(), loop_depth)
-                        .await?;
-                    if !matches!(signal, ExecSignal::None) {
-                        return Ok(signal);
-                    }
-                } else {
-                    let mut executed = false;
-                    for (cond, block) in &if_stmt.else_if {
-                        let cond_val = self.eval_expr_typed(cond, env).await?;
-                        if expect_bool_value(cond_val.value, "else-if condition")? {
-                            let signal = self.execute_block(block, env.clone(), loop_depth).await?;
-                            if !matches!(signal, ExecSignal::None) {
-                                return Ok(signal);
-                            }
-                            executed = true;
-                            break;
-                        }
-                    }
-                    if !executed {
-                        if let Some(block) = &if_stmt.else_branch {
-                            let signal = self.execute_block(block, env.clone(), loop_depth).await?;
-                            if !matches!(signal, ExecSignal::None) {
-                                return Ok(signal);
-                            }
-                        }
-                    }
+            Stmt::If(if_stmt) => match self.select_branch(if_stmt, env, "if condition").await? {
+                Some((block, branch_env)) => {
+                    self.execute_block(block, branch_env, loop_depth).await
                 }
-                Ok(ExecSignal::None)
-            }
+                None => Ok(ExecSignal::None),
+            },
             Stmt::While {
-                condition, body, ..
+                label,
+                condition,
+                body,
+                ..
             } => {
-                loop {
-                    let cond = self.eval_expr_typed(condition, env).await?;
-                    if !expect_bool_value(cond.value, "while condition")? {
-                        break;
-                    }
-                    let signal = self
-                        .execute_block(body, env.clone(), loop_depth + 1)
-                        .await?;
-                    match signal {
-                        ExecSignal::Return(_) => return Ok(signal),
-                        ExecSignal::Break => break,
-                        ExecSignal::Continue => continue,
-                        ExecSignal::None => {}
+                while let Some(body_env) = self
+                    .eval_condition(condition, env, "while condition")
+                    .await?
+                {
+                    let signal = self.execute_block(body, body_env, loop_depth + 1).await?;
+                    match loop_step(signal, label.as_deref()) {
+                        LoopStep::Next => {}
+                        LoopStep::Exit(_) => break,
+                        LoopStep::Leave(signal) => return Ok(signal),
                     }
                 }
                 Ok(ExecSignal::None)
             }
             Stmt::For {
-                var,
+                label,
+                pattern,
                 iterable,
                 body,
                 ..
             } => {
-                let iterable_value = self.eval_expr(iterable, env).await?;
-                let items = self.collect_iterable(iterable_value)?;
-                for item in items {
+                let mut items = self.for_iter(iterable, env).await?;
+                while let Some(item) = self.next_item(&mut items).await? {
                     let loop_env = env.child();
-                    loop_env.define(var.clone(), item);
+                    if let Some(name) = pattern.binding_name() {
+                        loop_env.define(name, item);
+                    } else {
+                        for (name, value) in self.destructure(&item, pattern, "for-loop")? {
+                            loop_env.define(name, value);
+                        }
+                    }
                     let signal = self.execute_block(body, loop_env, loop_depth + 1).await?;
-                    match signal {
-                        ExecSignal::Return(_) => return Ok(signal),
-                        ExecSignal::Break => break,
-                        ExecSignal::Continue => continue,
-                        ExecSignal::None => {}
+                    match loop_step(signal, label.as_deref()) {
+                        LoopStep::Next => {}
+                        LoopStep::Exit(_) => break,
+                        LoopStep::Leave(signal) => return Ok(signal),
                     }
                 }
                 Ok(ExecSignal::None)
             }
-            Stmt::Break(span) => {
+            Stmt::Break { label, value, span } => {
                 if loop_depth == 0 {
                     Err(RuntimeError::new("break used outside of a loop").with_span(*span))
                 } else {
-                    Ok(ExecSignal::Break)
+                    let value = match value {
+                        Some(expr) => self.eval_expr(expr, env).await?,
+                        None => Value::Null,
+                    };
+                    Ok(ExecSignal::Break(label.clone(), value))
                 }
             }
-            Stmt::Continue(span) => {
+            Stmt::Continue { label, span } => {
                 if loop_depth == 0 {
                     Err(RuntimeError::new("continue used outside of a loop").with_span(*span))
                 } else {
-                    Ok(ExecSignal::Continue)
+                    Ok(ExecSignal::Continue(label.clone()))
                 }
             }
             Stmt::Switch(switch_stmt) => self.execute_switch(switch_stmt, env).await,
@@ -2801,56 +3362,303 @@ impl Interpreter {
         })
     }
 
-    fn collect_iterable(&self, value: Value) -> RuntimeResult<Vec<Value>> {
-        match value {
-            Value::Vec(vec_rc) => Ok(clone_vec_items(&vec_rc)),
-            Value::Array(arr_rc) => Ok(arr_rc.borrow().iter().cloned().collect()),
-            other => Err(RuntimeError::new(format!(
-                "for-loop expects vec or array iterable, got {}",
-                other.type_name()
-            ))),
+    /// Runs a `loop` until a `break` for it, giving that break's value, or
+    /// until its body hands back a signal for an enclosing construct.
+    async fn run_loop(
+        &self,
+        label: Option<&str>,
+        body: &Block,
+        env: &Env,
+        loop_depth: usize,
+    ) -> RuntimeResult<LoopStep> {
+        loop {
+            let signal = self
+                .execute_block(body, env.clone(), loop_depth + 1)
+                .await?;
+            match loop_step(signal, label) {
+                LoopStep::Next => {}
+                done => return Ok(done),
+            }
         }
     }
 
-    async fn execute_switch(&self, switch: &SwitchStmt, env: &Env) -> RuntimeResult<ExecSignal> {
-        let value = self.eval_expr(&switch.expr, env).await?;
-        for arm in &switch.arms {
-            if let Some(bindings) = self.pattern_matches(&value, &arm.pattern)? {
-                let arm_env = env.child();
-                for (name, val) in bindings {
-                    arm_env.define(name, val);
-                }
-                let _ = self.eval_expr(&arm.expr, &arm_env).await?;
-                return Ok(ExecSignal::None);
+    /// The environment the body of an `if` or `while` runs in when
+    /// `condition` holds: `env` itself, or a child binding the names of a
+    /// `let` pattern that matched.
+    async fn eval_condition(
+        &self,
+        condition: &Expr,
+        env: &Env,
+        what: &str,
+    ) -> RuntimeResult<Option<Env>> {
+        if let Expr::Let { pattern, value, .. } = condition {
+            let value = self.eval_expr(value, env).await?;
+            let Some(bindings) = self.pattern_matches(&value, pattern)? else {
+                return Ok(None);
+            };
+            let branch_env = env.child();
+            for (name, value) in bindings {
+                branch_env.define(name, value);
             }
+            return Ok(Some(branch_env));
         }
-        Ok(ExecSignal::None)
+        let cond = self.eval_expr_typed(condition, env).await?;
+        Ok(expect_bool_value(cond.value, what)?.then(|| env.clone()))
     }
 
-    async fn execute_try(
+    /// The branch of `if_stmt` that runs, with the environment it runs in.
+    async fn select_branch<'s>(
         &self,
-        try_stmt: &TryCatch,
+        if_stmt: &'s IfStmt,
         env: &Env,
-        loop_depth: usize,
-    ) -> RuntimeResult<ExecSignal> {
-        match self
-            .execute_block(&try_stmt.try_block, env.clone(), loop_depth)
-            .await
+        what: &str,
+    ) -> RuntimeResult<Option<(&'s Block, Env)>> {
+        if let Some(branch_env) = self.eval_condition(&if_stmt.condition, env, what).await? {
+            return Ok(Some((&if_stmt.then_branch, branch_env)));
+        }
+        for (cond, block) in &if_stmt.else_if {
+            if let Some(branch_env) = self.eval_condition(cond, env, "else-if condition").await? {
+                return Ok(Some((block, branch_env)));
+            }
+        }
+        Ok(if_stmt
+            .else_branch
+            .as_ref()
+            .map(|block| (block, env.clone())))
+    }
+
+    /// Starts walking `iterable` for a `for` loop. A range expression is
+    /// walked without building its `vec`.
+    async fn for_iter(&self, iterable: &Expr, env: &Env) -> RuntimeResult<ForIter> {
+        if let Expr::Range {
+            start,
+            end,
+            inclusive,
+            step,
+            ..
+        } = iterable
         {
-            Ok(signal) => Ok(signal),
-            Err(err) => {
-                let mut catch_value = Value::String(err.to_string());
-                if let Some(value) = err.propagated_value() {
-                    catch_value = value;
-                }
-                let catch_env = env.child();
-                if let Some(binding) = &try_stmt.catch_binding {
-                    catch_env.define(binding.clone(), catch_value);
+            let (range, _) = self
+                .eval_range(start, end, *inclusive, step.as_deref(), env)
+                .await?;
+            return Ok(ForIter::Range(range));
+        }
+        let value = self.eval_expr_typed(iterable, env).await?;
+        let iter = match &value.value {
+            Value::Vec(items) => ForIter::Vec {
+                items: items.clone(),
+                index: 0,
+                len: items.borrow().items.len(),
+            },
+            Value::Array(items) => ForIter::Array {
+                items: items.clone(),
+                index: 0,
+            },
+            Value::Map(map) => {
+                let keys: Vec<MapKey> = map.borrow().entries.keys().cloned().collect();
+                ForIter::Map {
+                    map: map.clone(),
+                    keys: keys.into_iter(),
                 }
-                self.execute_block(&try_stmt.catch_block, catch_env, loop_depth)
-                    .await
             }
-        }
+            Value::Set(set) => {
+                let set_ref = set.borrow();
+                let items: Vec<Value> = set_ref
+                    .items
+                    .iter()
+                    .map(|key| map_key_to_value(key, set_ref.elem_type.as_ref()))
+                    .collect();
+                ForIter::Set(items.into_iter())
+            }
+            Value::String(text) => ForIter::Chars {
+                text: text.clone(),
+                offset: 0,
+            },
+            other => match self.scoped_trait_function(env, "Iterator", "next", &value)? {
+                Some(next) => ForIter::User {
+                    target: other.clone(),
+                    next,
+                },
+                None => {
+                    return Err(RuntimeError::new(format!(
+                        "for-loop cannot iterate over {}",
+                        other.type_name()
+                    )))
+                }
+            },
+        };
+        Ok(iter)
+    }
+
+    /// The next item of a `for` loop, or `None` once it is done.
+    async fn next_item(&self, iter: &mut ForIter) -> RuntimeResult<Option<Value>> {
+        let item = match iter {
+            ForIter::Range(range) => range.next().map(Value::Int),
+            ForIter::Vec { items, index, len } => {
+                let item = if *index < *len {
+                    items.borrow().items.get(*index).cloned()
+                } else {
+                    None
+                };
+                *index += 1;
+                item
+            }
+            ForIter::Array { items, index } => {
+                let item = items.borrow().items.get(*index).cloned();
+                *index += 1;
+                item
+            }
+            ForIter::Map { map, keys } => {
+                let map_ref = map.borrow();
+                keys.find_map(|key| {
+                    let value = map_ref.entries.get(&key)?.clone();
+                    let key = map_key_to_value(&key, map_ref.key_type.as_ref());
+                    Some(Value::Tuple(vec![key, value]))
+                })
+            }
+            ForIter::Set(items) => items.next(),
+            ForIter::Chars { text, offset } => {
+                let ch = text[*offset..].chars().next();
+                *offset += ch.map_or(0, char::len_utf8);
+                ch.map(Value::Char)
+            }
+            ForIter::User { target, next } => {
+                let (item, frame) = self
+                    .execute_user_function_frame(next, vec![target.clone()])
+                    .await?;
+                if next.params.first().is_some_and(|p| p.name == "self_mut") {
+                    *target = frame.get("self_mut")?;
+                }
+                match item {
+                    Value::Option(OptionValue::Some { value, .. }) => Some(*value),
+                    Value::Option(OptionValue::None { .. }) => None,
+                    other => {
+                        return Err(RuntimeError::new(format!(
+                            "`Iterator::next` must return an option, got {}",
+                            other.type_name()
+                        )))
+                    }
+                }
+            }
+        };
+        Ok(item)
+    }
+
+    /// The integers `start..end` (or `..=`) covers, every `step`th one.
+    async fn eval_range(
+        &self,
+        start: &Expr,
+        end: &Expr,
+        inclusive: bool,
+        step: Option<&Expr>,
+        env: &Env,
+    ) -> RuntimeResult<(IntRange, IntType)> {
+        let left = self.eval_expr_typed(start, env).await?;
+        let right = self.eval_expr_typed(end, env).await?;
+        let kind = match (resolved_tag(&left), resolved_tag(&right)) {
+            (
+                TypeTag::Primitive(PrimitiveType::Int(kind_l)),
+                TypeTag::Primitive(PrimitiveType::Int(kind_r)),
+            ) if kind_l == kind_r => kind_l,
+            (left_tag, right_tag) => {
+                return Err(RuntimeError::new(format!(
+                    "Range expects matching integer types, got {} and {}",
+                    left_tag.describe(),
+                    right_tag.describe()
+                )))
+            }
+        };
+        let (Value::Int(first), Value::Int(last)) = (left.value, right.value) else {
+            return Err(RuntimeError::new("Range expects integer bounds"));
+        };
+        if !kind.is_signed() && (first < 0 || last < 0) {
+            return Err(RuntimeError::new(
+                "Range bounds must be non-negative for unsigned integers",
+            ));
+        }
+        let step = match step {
+            None => 1,
+            Some(expr) => match self.eval_expr(expr, env).await? {
+                Value::Int(step) if step > 0 => step,
+                Value::Int(step) => {
+                    return Err(RuntimeError::new(format!(
+                        "Range step must be positive, got {step}"
+                    ))
+                    .with_span(expr.span()))
+                }
+                other => {
+                    return Err(RuntimeError::new(format!(
+                        "Range step must be an integer, got {}",
+                        other.type_name()
+                    ))
+                    .with_span(expr.span()))
+                }
+            },
+        };
+        let range = IntRange {
+            next: Some(first),
+            end: last,
+            inclusive,
+            step,
+        };
+        Ok((range, kind))
+    }
+
+    async fn execute_switch(&self, switch: &SwitchStmt, env: &Env) -> RuntimeResult<ExecSignal> {
+        let value = self.eval_expr(&switch.expr, env).await?;
+        for arm in &switch.arms {
+            if let Some(bindings) = self.pattern_matches(&value, &arm.pattern)? {
+                let arm_env = env.child();
+                for (name, val) in bindings {
+                    arm_env.define(name, val);
+                }
+                if let Some(guard) = &arm.guard {
+                    let passed = self.eval_expr(guard, &arm_env).await?;
+                    if !expect_bool_value(passed, "switch guard")? {
+                        continue;
+                    }
+                }
+                let _ = self.eval_expr(&arm.expr, &arm_env).await?;
+                return Ok(ExecSignal::None);
+            }
+        }
+        let matched = match &value {
+            Value::Enum(e) => format!(
+                " for `{}::{}`",
+                e.name.as_deref().unwrap_or("enum"),
+                e.variant
+            ),
+            _ => String::new(),
+        };
+        Err(RuntimeError::new(format!(
+            "switch: non-exhaustive (no arm matched{matched})"
+        )))
+    }
+
+    async fn execute_try(
+        &self,
+        try_stmt: &TryCatch,
+        env: &Env,
+        loop_depth: usize,
+    ) -> RuntimeResult<ExecSignal> {
+        match self
+            .execute_block(&try_stmt.try_block, env.clone(), loop_depth)
+            .await
+        {
+            Ok(signal) => Ok(signal),
+            Err(err) => {
+                let mut catch_value = Value::String(err.to_string());
+                if let Some(value) = err.propagated_value() {
+                    catch_value = value;
+                }
+                let catch_env = env.child();
+                if let Some(binding) = &try_stmt.catch_binding {
+                    catch_env.define(binding.clone(), catch_value);
+                }
+                self.execute_block(&try_stmt.catch_block, catch_env, loop_depth)
+                    .await
+            }
+        }
     }
 
     fn pattern_matches(
@@ -2858,61 +3666,154 @@ impl Interpreter {
         value: &Value,
         pattern: &Pattern,
     ) -> RuntimeResult<Option<HashMap<String, Value>>> {
+        let mut bindings = HashMap::new();
+        Ok(self
+            .bind_pattern(value, pattern, &mut bindings)?
+            .then_some(bindings))
+    }
+
+    /// The bindings of a `let` or `for` pattern, which must match `value`.
+    fn destructure(
+        &self,
+        value: &Value,
+        pattern: &Pattern,
+        context: &str,
+    ) -> RuntimeResult<HashMap<String, Value>> {
+        self.pattern_matches(value, pattern)?.ok_or_else(|| {
+            RuntimeError::new(format!(
+                "{context} pattern does not match value of type `{}`",
+                value.type_name()
+            ))
+        })
+    }
+
+    /// Matches `value` against `pattern`, adding what it binds to `bindings`.
+    fn bind_pattern(
+        &self,
+        value: &Value,
+        pattern: &Pattern,
+        bindings: &mut HashMap<String, Value>,
+    ) -> RuntimeResult<bool> {
         match pattern {
-            Pattern::Wildcard { .. } => Ok(Some(HashMap::new())),
+            Pattern::Wildcard { .. } => Ok(true),
             Pattern::Binding { name, .. } => {
-                let mut map = HashMap::new();
-                map.insert(name.clone(), value.clone());
-                Ok(Some(map))
+                // A bare variant name of the value's own enum matches that variant.
+                if let Some((enum_name, variant, _)) = enum_parts(value) {
+                    let is_variant = match enum_name {
+                        Some("option") | Some("result") => builtin_variant(name),
+                        Some(enum_name) => self
+                            .enum_defs
+                            .borrow()
+                            .get(enum_name)
+                            .is_some_and(|schema| schema.variants.contains_key(name)),
+                        None => false,
+                    };
+                    if is_variant {
+                        return Ok(variant == name);
+                    }
+                }
+                bindings.insert(name.clone(), value.clone());
+                Ok(true)
             }
             Pattern::Literal(lit) => {
                 let lit_value = self.eval_literal(lit)?;
-                if self.values_equal(value, &lit_value) {
-                    Ok(Some(HashMap::new()))
-                } else {
-                    Ok(None)
-                }
+                Ok(self.values_equal(value, &lit_value))
             }
             Pattern::Path { segments, .. } => {
-                if let Value::Enum(e) = value {
-                    let variant = segments.last().unwrap().as_str();
-                    if e.variant == variant {
-                        if segments.len() == 1
-                            || e.name.as_deref() == segments.first().map(|s| s.as_str())
-                        {
-                            return Ok(Some(HashMap::new()));
-                        }
+                let Some((enum_name, variant, _)) = enum_parts(value) else {
+                    return Ok(false);
+                };
+                Ok(variant == segments.last().unwrap()
+                    && (segments.len() == 1 || enum_name == segments.first().map(|s| s.as_str())))
+            }
+            Pattern::Enum { path, args, .. } => {
+                let Some((enum_name, variant, payload)) = enum_parts(value) else {
+                    return Ok(false);
+                };
+                if variant != path.last().unwrap() {
+                    return Ok(false);
+                }
+                if path.len() > 1 && enum_name.is_some_and(|name| name != path[0]) {
+                    return Ok(false);
+                }
+                if args.len() != payload.len() {
+                    return Err(RuntimeError::new(format!(
+                        "enum pattern arity mismatch: expected {} patterns, got {}",
+                        payload.len(),
+                        args.len()
+                    )));
+                }
+                for (arg, item) in args.iter().zip(payload) {
+                    if !self.bind_pattern(item, arg, bindings)? {
+                        return Ok(false);
                     }
                 }
-                Ok(None)
+                Ok(true)
             }
-            Pattern::Enum { path, bindings, .. } => {
-                if let Value::Enum(e) = value {
-                    let variant = path.last().unwrap().as_str();
-                    if e.variant != variant {
-                        return Ok(None);
-                    }
-                    if path.len() > 1 {
-                        if let Some(name) = &e.name {
-                            if name != path.first().unwrap() {
-                                return Ok(None);
-                            }
-                        }
+            Pattern::Tuple { elements, .. } => {
+                let Value::Tuple(items) = value else {
+                    return Ok(false);
+                };
+                if items.len() != elements.len() {
+                    return Ok(false);
+                }
+                for (element, item) in elements.iter().zip(items) {
+                    if !self.bind_pattern(item, element, bindings)? {
+                        return Ok(false);
                     }
-                    if bindings.len() != e.payload.len() {
+                }
+                Ok(true)
+            }
+            Pattern::Struct { path, fields, .. } => {
+                let Value::Struct(instance) = value else {
+                    return Ok(false);
+                };
+                let name = instance.name.as_deref().unwrap_or("anonymous");
+                if path.last().is_some_and(|last| last != name) {
+                    return Ok(false);
+                }
+                for field in fields {
+                    let Some(item) = instance.fields.get(&field.name) else {
                         return Err(RuntimeError::new(format!(
-                            "enum pattern arity mismatch: expected {} bindings, got {}",
-                            e.payload.len(),
-                            bindings.len()
+                            "struct `{name}` has no field `{}`",
+                            field.name
                         )));
+                    };
+                    if !self.bind_pattern(item, &field.pattern, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+                ..
+            } => {
+                let start = self.eval_literal(start)?;
+                let end = self.eval_literal(end)?;
+                let in_range = |low: bool, high: std::cmp::Ordering| {
+                    low && (high.is_lt() || (*inclusive && high.is_eq()))
+                };
+                Ok(match (value, &start, &end) {
+                    (Value::Int(v), Value::Int(a), Value::Int(b)) => in_range(v >= a, v.cmp(b)),
+                    (Value::Char(v), Value::Char(a), Value::Char(b)) => in_range(v >= a, v.cmp(b)),
+                    (Value::Float(v), Value::Float(a), Value::Float(b)) => {
+                        v.partial_cmp(b).is_some_and(|high| in_range(v >= a, high))
                     }
-                    let mut map = HashMap::new();
-                    for (binding, value) in bindings.iter().zip(e.payload.iter()) {
-                        map.insert(binding.clone(), value.clone());
+                    _ => false,
+                })
+            }
+            Pattern::Or { alternatives, .. } => {
+                for alternative in alternatives {
+                    let mut scratch = HashMap::new();
+                    if self.bind_pattern(value, alternative, &mut scratch)? {
+                        bindings.extend(scratch);
+                        return Ok(true);
                     }
-                    return Ok(Some(map));
                 }
-                Ok(None)
+                Ok(false)
             }
         }
     }
@@ -2924,6 +3825,217 @@ impl Interpreter {
             .map(|typed| typed.value)
     }
 
+    /// `object.method(args)`: module members, inherent and trait methods, and
+    /// the built-in methods of collections, strings, options and results.
+    #[async_recursion(?Send)]
+    async fn eval_method_call(
+        &self,
+        object: &Expr,
+        method: &str,
+        args: &[Expr],
+        env: &Env,
+    ) -> RuntimeResult<TypedValue> {
+        let object_mutable = match object {
+            Expr::Identifier { name, .. } => {
+                matches!(env.binding_kind(name), Some(VarKind::Var))
+            }
+            _ => false,
+        };
+        let object_typed = self.eval_expr_typed(object, env).await?;
+        let object_val = object_typed.value.clone();
+
+        if let Value::Module(m) = &object_val {
+            let mut evaluated_args = Vec::new();
+            for arg in args {
+                evaluated_args.push(self.eval_expr_typed(arg, env).await?.value);
+            }
+            if let Some(member) = m.fields.get(method) {
+                let member_value = member.clone();
+                let result = match member_value {
+                    Value::Builtin(func) => func(self, evaluated_args).await?,
+                    Value::TraitMethod(tm) => {
+                        if evaluated_args.is_empty() {
+                            return Err(RuntimeError::new(format!(
+                                "Trait method `{}` requires a target",
+                                tm.signature.name
+                            )));
+                        }
+                        let target = &evaluated_args[0];
+                        let type_key = value_type_tag(target).describe();
+                        if let Some(first) = tm.signature.params.first() {
+                            if first.name == "self_mut" && !object_mutable {
+                                return Err(RuntimeError::new(
+                                    "cannot borrow immutable value as mutable (method requires self_mut)",
+                                ));
+                            }
+                        }
+                        let func = self.trait_impl_function(&tm, &type_key)?;
+                        self.call_user_function(func, evaluated_args).await?
+                    }
+                    other => self.invoke(other, evaluated_args, None).await?,
+                };
+                return Ok(TypedValue {
+                    tag: Some(value_type_tag(&result)),
+                    value: result,
+                    is_literal: false,
+                });
+            } else {
+                return Err(self.missing_member(
+                    &m,
+                    method,
+                    format!("Unknown method `{method}` on module {}", m.name),
+                ));
+            }
+        }
+
+        let mut evaluated_args = vec![object_val.clone()];
+        for arg in args {
+            evaluated_args.push(self.eval_expr_typed(arg, env).await?.value);
+        }
+
+        let type_keys = method_type_keys(&object_typed);
+        let inherent = type_keys.iter().find_map(|key| {
+            self.inherent_impls
+                .borrow()
+                .get(key)
+                .and_then(|methods| methods.get(method))
+                .map(|func| (key, func.clone()))
+        });
+        if let Some((type_key, func)) = inherent {
+            ensure_visible(env, &func.env.module(), func.is_pub, || {
+                format!("Method `{method}` of `{type_key}`")
+            })?;
+            if let Some(first) = func.params.first() {
+                if first.name == "self_mut" && !object_mutable {
+                    return Err(RuntimeError::new(
+                        "cannot borrow immutable value as mutable (method requires self_mut)",
+                    ));
+                }
+            }
+            let result = self.call_user_function(func, evaluated_args).await?;
+            return Ok(TypedValue {
+                tag: Some(value_type_tag(&result)),
+                value: result,
+                is_literal: false,
+            });
+        }
+
+        // Built-in methods win over trait methods, as inherent ones do.
+        let builtin = match &object_val {
+            Value::Struct(_) | Value::Enum(_) => false,
+            Value::Array(_) => method == "len" || collections::array_method(method).is_some(),
+            Value::Option(_) | Value::Result(_) => {
+                combinators::method(&object_val, method).is_some()
+            }
+            other => builtin_method_module(other).is_some_and(|name| {
+                matches!(env.get(name), Ok(Value::Module(m)) if m.fields.contains_key(method))
+            }),
+        };
+        if !builtin {
+            for type_key in &type_keys {
+                let Some(tm) = self.trait_method_in_scope(env, type_key, method)? else {
+                    continue;
+                };
+                if let Some(first) = tm.signature.params.first() {
+                    if first.name == "self_mut" && !object_mutable {
+                        return Err(RuntimeError::new(
+                            "cannot borrow immutable value as mutable (method requires self_mut)",
+                        ));
+                    }
+                }
+                let func = self.trait_impl_function(&tm, type_key)?;
+                let result = self.call_user_function(func, evaluated_args).await?;
+                return Ok(TypedValue {
+                    tag: Some(value_type_tag(&result)),
+                    value: result,
+                    is_literal: false,
+                });
+            }
+        }
+
+        match &object_val {
+            Value::Struct(instance) => {
+                return Err(RuntimeError::new(format!(
+                    "Unknown method `{}` on struct `{}`",
+                    method,
+                    instance
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| "anonymous".to_string())
+                )));
+            }
+            Value::Enum(instance) => {
+                return Err(RuntimeError::new(format!(
+                    "Unknown method `{}` on enum `{}`",
+                    method,
+                    instance.name.as_deref().unwrap_or("anonymous")
+                )));
+            }
+            _ => {}
+        }
+
+        // Allow direct methods on core collection types without requiring explicit import.
+        if let Value::Array(arr_rc) = &object_val {
+            if method == "len" {
+                let len = arr_rc.borrow().len() as i128;
+                return Ok(TypedValue {
+                    tag: Some(TypeTag::Primitive(PrimitiveType::Int(IntType::I128))),
+                    value: Value::Int(len),
+                    is_literal: false,
+                });
+            }
+            let Some(func) = collections::array_method(method) else {
+                return Err(RuntimeError::new(format!(
+                    "Unknown method `{method}` on array"
+                )));
+            };
+            let result = func(self, evaluated_args).await?;
+            return Ok(TypedValue {
+                tag: Some(value_type_tag(&result)),
+                value: result,
+                is_literal: false,
+            });
+        }
+
+        if let Value::Option(_) | Value::Result(_) = &object_val {
+            let Some(func) = combinators::method(&object_val, method) else {
+                return Err(RuntimeError::new(format!(
+                    "Unknown method `{method}` on {}",
+                    object_val.type_name()
+                )));
+            };
+            let result = func(self, evaluated_args).await?;
+            return Ok(TypedValue {
+                tag: Some(value_type_tag(&result)),
+                value: result,
+                is_literal: false,
+            });
+        }
+
+        let Some(module_name) = builtin_method_module(&object_val) else {
+            return Err(RuntimeError::new(format!(
+                "Method `{method}` not supported on this type"
+            )));
+        };
+        let module = env.get(module_name)?;
+        if let Value::Module(m) = module {
+            if let Some(Value::Builtin(func)) = m.fields.get(method) {
+                let result = func(self, evaluated_args).await?;
+                Ok(TypedValue {
+                    tag: Some(value_type_tag(&result)),
+                    value: result,
+                    is_literal: false,
+                })
+            } else {
+                Err(RuntimeError::new(format!(
+                    "Unknown method `{method}` on {module_name}"
+                )))
+            }
+        } else {
+            Err(RuntimeError::new(format!("{module_name} is not a module")))
+        }
+    }
+
     #[async_recursion(?Send)]
     async fn eval_block_expr_typed(&self, block: &Block, env: &Env) -> RuntimeResult<TypedValue> {
         let local_env = env.child();
@@ -2948,7 +4060,7 @@ impl Interpreter {
                                 is_literal: false,
                             });
                         }
-                        ExecSignal::Break | ExecSignal::Continue => {
+                        ExecSignal::Break(..) | ExecSignal::Continue(_) => {
                             return Err(RuntimeError::new("Control flow signal in expression"));
                         }
                     }
@@ -3042,32 +4154,55 @@ impl Interpreter {
                     self.eval_binary_typed(*op, l, r)
                 }
             },
+            Expr::Range {
+                start,
+                end,
+                inclusive,
+                step,
+                ..
+            } => {
+                let (range, kind) = self
+                    .eval_range(start, end, *inclusive, step.as_deref(), env)
+                    .await?;
+                let elem_tag = TypeTag::Primitive(PrimitiveType::Int(kind));
+                let vec = VecValue {
+                    elem_type: Some(elem_tag.clone()),
+                    items: range.map(Value::Int).collect(),
+                };
+                Ok(TypedValue {
+                    value: Value::Vec(Rc::new(RefCell::new(vec))),
+                    tag: Some(TypeTag::Vec(Box::new(elem_tag))),
+                    is_literal: false,
+                })
+            }
             Expr::If(if_stmt) => {
-                let cond = self.eval_expr_typed(&if_stmt.condition, env).await?;
-                if expect_bool_value(cond.value, "if expression condition")? {
-                    self.eval_block_expr_typed(&if_stmt.then_branch, env).await
-                } else {
-                    let mut matched_block = None;
-                    for (cond, block) in &if_stmt.else_if {
-                        let cond_val = self.eval_expr_typed(cond, env).await?;
-                        if expect_bool_value(cond_val.value, "else-if condition")? {
-                            matched_block = Some(block);
-                            break;
-                        }
-                    }
-                    if let Some(block) = matched_block {
-                        self.eval_block_expr_typed(block, env).await
-                    } else if let Some(block) = &if_stmt.else_branch {
-                        self.eval_block_expr_typed(block, env).await
-                    } else {
-                        Ok(TypedValue {
-                            value: Value::Null,
-                            tag: Some(TypeTag::Primitive(PrimitiveType::Unit)),
-                            is_literal: false,
-                        })
+                match self
+                    .select_branch(if_stmt, env, "if expression condition")
+                    .await?
+                {
+                    Some((block, branch_env)) => {
+                        self.eval_block_expr_typed(block, &branch_env).await
                     }
+                    None => Ok(TypedValue {
+                        value: Value::Null,
+                        tag: Some(TypeTag::Primitive(PrimitiveType::Unit)),
+                        is_literal: false,
+                    }),
                 }
             }
+            Expr::Loop { label, body, .. } => {
+                match self.run_loop(label.as_deref(), body, env, 0).await? {
+                    LoopStep::Exit(value) => Ok(TypedValue {
+                        tag: Some(value_type_tag(&value)),
+                        value,
+                        is_literal: false,
+                    }),
+                    _ => Err(RuntimeError::new("Control flow signal in expression")),
+                }
+            }
+            Expr::Let { .. } => Err(RuntimeError::new(
+                "`let` is only allowed as an `if` or `while` condition",
+            )),
             Expr::Unary { op, expr, .. } => {
                 let value = self.eval_expr_typed(expr, env).await?;
                 self.eval_unary_typed(*op, value)
@@ -3093,9 +4228,13 @@ impl Interpreter {
                             .iter()
                             .map(|ty| {
                                 if let Some(map) = last {
-                                    type_tag_from_type_expr_with_bindings(ty, map)
+                                    type_tag_from_type_expr_with_bindings(
+                                        ty,
+                                        map,
+                                        &self.type_aliases.borrow(),
+                                    )
                                 } else {
-                                    type_tag_from_type_expr(ty)
+                                    type_tag_from_type_expr(ty, &self.type_aliases.borrow())
                                 }
                             })
                             .collect::<Vec<_>>(),
@@ -3210,6 +4349,7 @@ impl Interpreter {
                                     )))
                                 }
                             };
+                            self.ensure_field_visible(env, &struct_val, member)?;
                             let mut new_field = val.value.clone();
                             if let Some(struct_name) = &struct_val.name {
                                 if let Some(schema) = self.struct_defs.borrow().get(struct_name) {
@@ -3219,8 +4359,12 @@ impl Interpreter {
                                         "Struct",
                                         struct_name,
                                     )?;
-                                    let expected =
-                                        resolve_struct_field_tag(schema, &bindings, member)?;
+                                    let expected = resolve_struct_field_tag(
+                                        schema,
+                                        &bindings,
+                                        member,
+                                        &self.type_aliases.borrow(),
+                                    )?;
                                     ensure_tag_match(
                                         &Some(expected.clone()),
                                         &new_field,
@@ -3276,6 +4420,9 @@ impl Interpreter {
                         .get(name)
                         .cloned()
                         .ok_or_else(|| RuntimeError::new(format!("Unknown struct `{name}`")))?;
+                    ensure_visible(env, &schema.module, schema.is_pub, || {
+                        format!("Struct `{name}`")
+                    })?;
                     if schema.type_params.is_empty() {
                         if !type_args.is_empty() {
                             return Err(RuntimeError::new(format!(
@@ -3306,6 +4453,7 @@ impl Interpreter {
                             "Struct",
                             name,
                         )?;
+                        self.check_type_bounds(&schema.bounds, &schema_bindings)?;
                     }
                     Some(schema)
                 } else {
@@ -3318,10 +4466,20 @@ impl Interpreter {
                             field.name
                         )));
                     }
+                    if let Some(schema) = &struct_schema {
+                        let is_pub = schema.public_fields.contains(&field.name);
+                        ensure_visible(env, &schema.module, is_pub, || {
+                            format!("Field `{}` of struct `{}`", field.name, schema.name)
+                        })?;
+                    }
                     let mut value = self.eval_expr_typed(&field.expr, env).await?.value;
                     if let Some(schema) = &struct_schema {
-                        let expected =
-                            resolve_struct_field_tag(schema, &schema_bindings, &field.name)?;
+                        let expected = resolve_struct_field_tag(
+                            schema,
+                            &schema_bindings,
+                            &field.name,
+                            &self.type_aliases.borrow(),
+                        )?;
                         ensure_tag_match(&Some(expected.clone()), &value, "struct literal")?;
                         apply_type_tag_to_value(&mut value, &expected);
                     }
@@ -3435,31 +4593,37 @@ impl Interpreter {
             Expr::Cast { expr, ty, .. } => {
                 let inner = self.eval_expr_typed(expr, env).await?;
                 let target_tag = if let Some(bindings) = self.type_bindings.borrow().last() {
-                    type_tag_from_type_expr_with_bindings(ty, bindings)
+                    type_tag_from_type_expr_with_bindings(ty, bindings, &self.type_aliases.borrow())
                 } else {
-                    type_tag_from_type_expr(ty)
+                    type_tag_from_type_expr(ty, &self.type_aliases.borrow())
                 };
                 cast_typed_to_tag(inner, &target_tag)
             }
             Expr::Access { base, member, .. } => {
                 let base_val = self.eval_expr_typed(base, env).await?.value;
-                let value = match base_val {
-                    Value::Module(module) => {
-                        module.fields.get(member).cloned().ok_or_else(|| {
-                            RuntimeError::new(format!("Unknown member `{member}`"))
-                        })?
-                    }
-                    Value::Struct(instance) => instance
-                        .fields
-                        .get(member)
-                        .cloned()
-                        .ok_or_else(|| RuntimeError::new(format!("Unknown field `{member}`")))?,
-                    _ => {
-                        return Err(RuntimeError::new(
-                            "Member access supported only on modules or structs",
-                        ))
-                    }
-                };
+                let value =
+                    match base_val {
+                        Value::Module(module) => {
+                            module.fields.get(member).cloned().ok_or_else(|| {
+                                self.missing_member(
+                                    &module,
+                                    member,
+                                    format!("Unknown member `{member}`"),
+                                )
+                            })?
+                        }
+                        Value::Struct(instance) => {
+                            self.ensure_field_visible(env, &instance, member)?;
+                            instance.fields.get(member).cloned().ok_or_else(|| {
+                                RuntimeError::new(format!("Unknown field `{member}`"))
+                            })?
+                        }
+                        _ => {
+                            return Err(RuntimeError::new(
+                                "Member access supported only on modules or structs",
+                            ))
+                        }
+                    };
                 Ok(TypedValue {
                     tag: Some(value_type_tag(&value)),
                     value,
@@ -3559,151 +4723,7 @@ impl Interpreter {
                 method,
                 args,
                 ..
-            } => {
-                let (object_val, object_mutable) = match object.as_ref() {
-                    Expr::Identifier { name, .. } => {
-                        let is_mut = matches!(env.binding_kind(name), Some(VarKind::Var));
-                        (self.eval_expr_typed(object, env).await?.value, is_mut)
-                    }
-                    _ => (self.eval_expr_typed(object, env).await?.value, false),
-                };
-
-                if let Value::Module(m) = &object_val {
-                    let mut evaluated_args = Vec::new();
-                    for arg in args {
-                        evaluated_args.push(self.eval_expr_typed(arg, env).await?.value);
-                    }
-                    if let Some(member) = m.fields.get(method) {
-                        let member_value = member.clone();
-                        let result = match member_value {
-                            Value::Builtin(func) => func(self, evaluated_args).await?,
-                            Value::TraitMethod(tm) => {
-                                if evaluated_args.is_empty() {
-                                    return Err(RuntimeError::new(format!(
-                                        "Trait method `{}` requires a target",
-                                        tm.signature.name
-                                    )));
-                                }
-                                let target = &evaluated_args[0];
-                                let type_key = value_type_tag(target).describe();
-                                if let Some(first) = tm.signature.params.first() {
-                                    if first.name == "self_mut" && !object_mutable {
-                                        return Err(RuntimeError::new(
-                                            "cannot borrow immutable value as mutable (method requires self_mut)",
-                                        ));
-                                    }
-                                }
-                                let func = {
-                                    let impls = self.trait_impls.borrow();
-                                    impls
-                                        .get(&tm.trait_name)
-                                        .and_then(|m| m.get(&type_key))
-                                        .and_then(|m| m.get(&tm.signature.name))
-                                        .cloned()
-                                        .ok_or_else(|| {
-                                            RuntimeError::new(format!(
-                                                "trait bound not satisfied: {} for {}",
-                                                tm.trait_name, type_key
-                                            ))
-                                        })?
-                                };
-                                self.call_user_function(func, evaluated_args).await?
-                            }
-                            other => self.invoke(other, evaluated_args, None).await?,
-                        };
-                        return Ok(TypedValue {
-                            tag: Some(value_type_tag(&result)),
-                            value: result,
-                            is_literal: false,
-                        });
-                    } else {
-                        return Err(RuntimeError::new(format!(
-                            "Unknown method `{method}` on module {}",
-                            m.name
-                        )));
-                    }
-                }
-
-                let mut evaluated_args = vec![object_val.clone()];
-                for arg in args {
-                    evaluated_args.push(self.eval_expr_typed(arg, env).await?.value);
-                }
-
-                if let Value::Struct(instance) = &object_val {
-                    let type_key = value_type_tag(&object_val).describe();
-                    if let Some(methods) = self.inherent_impls.borrow().get(&type_key) {
-                        if let Some(func) = methods.get(method) {
-                            if let Some(first) = func.params.first() {
-                                if first.name == "self_mut" && !object_mutable {
-                                    return Err(RuntimeError::new(
-                                        "cannot borrow immutable value as mutable (method requires self_mut)",
-                                    ));
-                                }
-                            }
-                            let result = self
-                                .call_user_function(func.clone(), evaluated_args.clone())
-                                .await?;
-                            return Ok(TypedValue {
-                                tag: Some(value_type_tag(&result)),
-                                value: result,
-                                is_literal: false,
-                            });
-                        }
-                    }
-                    return Err(RuntimeError::new(format!(
-                        "Unknown method `{}` on struct `{}`",
-                        method,
-                        instance
-                            .name
-                            .clone()
-                            .unwrap_or_else(|| "anonymous".to_string())
-                    )));
-                }
-
-                // Allow direct methods on core collection types without requiring explicit import.
-                if let Value::Array(arr_rc) = &object_val {
-                    if method == "len" {
-                        let len = arr_rc.borrow().len() as i128;
-                        return Ok(TypedValue {
-                            tag: Some(TypeTag::Primitive(PrimitiveType::Int(IntType::I128))),
-                            value: Value::Int(len),
-                            is_literal: false,
-                        });
-                    }
-                    return Err(RuntimeError::new(format!(
-                        "Unknown method `{method}` on array"
-                    )));
-                }
-
-                let module_name = match &object_val {
-                    Value::Vec(_) => "vec",
-                    Value::String(_) => "str",
-                    Value::Map(_) => "map",
-                    Value::Set(_) => "set",
-                    _ => {
-                        return Err(RuntimeError::new(format!(
-                            "Method `{method}` not supported on this type"
-                        )))
-                    }
-                };
-                let module = env.get(module_name)?;
-                if let Value::Module(m) = module {
-                    if let Some(Value::Builtin(func)) = m.fields.get(method) {
-                        let result = func(self, evaluated_args).await?;
-                        Ok(TypedValue {
-                            tag: Some(value_type_tag(&result)),
-                            value: result,
-                            is_literal: false,
-                        })
-                    } else {
-                        Err(RuntimeError::new(format!(
-                            "Unknown method `{method}` on {module_name}"
-                        )))
-                    }
-                } else {
-                    Err(RuntimeError::new(format!("{module_name} is not a module")))
-                }
-            }
+            } => self.eval_method_call(object, method, args, env).await,
             Expr::Check(check_expr) => {
                 let target_value = if let Some(target) = &check_expr.target {
                     Some(self.eval_expr_typed(target, env).await?)
@@ -3745,11 +4765,40 @@ impl Interpreter {
                 }
                 Err(RuntimeError::new("check: non-exhaustive (no arm matched)"))
             }
+            Expr::Interpolated { parts, .. } => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        InterpolationPart::Text(chunk) => text.push_str(chunk),
+                        InterpolationPart::Expr(expr) => {
+                            let value = self.eval_expr_typed(expr, env).await?;
+                            let shown = match self.scoped_trait_function(
+                                env,
+                                "Display",
+                                "to_string",
+                                &value,
+                            )? {
+                                Some(func) => {
+                                    self.call_user_function(func, vec![value.value]).await?
+                                }
+                                None => value.value,
+                            };
+                            text.push_str(&shown.to_string_value());
+                        }
+                    }
+                }
+                Ok(TypedValue {
+                    value: Value::String(text),
+                    tag: Some(TypeTag::Primitive(PrimitiveType::String)),
+                    is_literal: false,
+                })
+            }
             Expr::Lambda(lambda_expr) => Ok(TypedValue {
                 value: Value::Closure(ClosureValue {
                     params: lambda_expr.params.iter().map(|p| p.name.clone()).collect(),
                     body: lambda_expr.body.clone(),
                     is_async: lambda_expr.is_async,
+                    env: env.clone(),
                 }),
                 tag: None,
                 is_literal: false,
@@ -3797,15 +4846,56 @@ impl Interpreter {
         }
     }
 
-    fn eval_const_expr_typed(&self, expr: &Expr) -> RuntimeResult<TypedValue> {
+    /// Evaluates a `const` initializer: literals, unary and binary operators,
+    /// and names bound by other `const` declarations.
+    fn eval_const_expr_typed(&self, expr: &Expr, env: &Env) -> RuntimeResult<TypedValue> {
         match expr {
             Expr::Literal(lit) => self.eval_literal_typed(lit),
-            _ => Err(RuntimeError::new("const values must be literals")),
+            Expr::Identifier { name, .. } => match env.binding_kind(name) {
+                Some(VarKind::Const) => env.get_typed(name),
+                _ => Err(RuntimeError::new(format!(
+                    "const values can only refer to other constants, found `{name}`"
+                ))),
+            },
+            Expr::Unary { op, expr, .. } if !matches!(op, crate::ast::UnaryOp::Borrow) => {
+                let value = self.eval_const_expr_typed(expr, env)?;
+                self.eval_unary_typed(*op, value)
+            }
+            Expr::Binary {
+                left, op, right, ..
+            } => {
+                let left = self.eval_const_expr_typed(left, env)?;
+                let right = self.eval_const_expr_typed(right, env)?;
+                self.eval_binary_typed(*op, left, right)
+            }
+            _ => Err(RuntimeError::new(
+                "const values must be literals, operators or other constants",
+            )),
         }
     }
 
-    fn eval_const_expr(&self, expr: &Expr) -> RuntimeResult<Value> {
-        self.eval_const_expr_typed(expr).map(|typed| typed.value)
+    /// Binds a module-level `const`, `static` or `var` item in `env`.
+    fn define_global(&self, env: &Env, global: &GlobalDef) -> RuntimeResult<()> {
+        let typed = self.eval_const_expr_typed(&global.value, env)?;
+        let declared_tag = global
+            .ty
+            .as_ref()
+            .map(|ty| type_tag_from_type_expr(ty, &self.type_aliases.borrow()));
+        let final_value = match &declared_tag {
+            Some(tag) => coerce_typed_to_tag(typed, tag)?,
+            None => typed,
+        };
+        let tag = declared_tag.or_else(|| {
+            final_value
+                .tag
+                .clone()
+                .or_else(|| Some(value_type_tag(&final_value.value)))
+        });
+        let kind = match global.kind {
+            GlobalKind::Const | GlobalKind::Static => VarKind::Const,
+            GlobalKind::Var => VarKind::Var,
+        };
+        env.define_var(global.name.clone(), kind, final_value.value, tag)
     }
 
     fn eval_binary_typed(
@@ -3967,39 +5057,85 @@ impl Interpreter {
                     right_tag.describe()
                 ))),
             },
-            Range => match (left_tag, right_tag) {
+            BitAnd | BitOr | BitXor => match (left_tag, right_tag) {
                 (
                     TypeTag::Primitive(PrimitiveType::Int(kind_l)),
                     TypeTag::Primitive(PrimitiveType::Int(kind_r)),
                 ) if kind_l == kind_r => {
-                    let (start, end) = match (left.value, right.value) {
+                    let (a, b) = match (left.value, right.value) {
                         (Value::Int(a), Value::Int(b)) => (a, b),
-                        _ => return Err(RuntimeError::new("Range expects integer bounds")),
+                        _ => return Err(RuntimeError::new("Bitwise ops expect integers")),
                     };
-                    if !kind_l.is_signed() && (start < 0 || end < 0) {
-                        return Err(RuntimeError::new(
-                            "Range bounds must be non-negative for unsigned integers",
-                        ));
-                    }
-                    let mut items = Vec::new();
-                    let mut current = start;
-                    while current < end {
-                        items.push(Value::Int(current));
-                        current += 1;
+                    // Values are sign-extended, so the i128 result is already in range.
+                    let result = match op {
+                        BitAnd => a & b,
+                        BitOr => a | b,
+                        BitXor => a ^ b,
+                        _ => unreachable!(),
+                    };
+                    Ok(TypedValue {
+                        value: Value::Int(result),
+                        tag: Some(TypeTag::Primitive(PrimitiveType::Int(kind_l))),
+                        is_literal: false,
+                    })
+                }
+                (
+                    TypeTag::Primitive(PrimitiveType::Bool),
+                    TypeTag::Primitive(PrimitiveType::Bool),
+                ) => {
+                    let (a, b) = match (left.value, right.value) {
+                        (Value::Bool(a), Value::Bool(b)) => (a, b),
+                        _ => return Err(RuntimeError::new("Bitwise ops expect bools")),
+                    };
+                    let result = match op {
+                        BitAnd => a & b,
+                        BitOr => a | b,
+                        BitXor => a ^ b,
+                        _ => unreachable!(),
+                    };
+                    Ok(TypedValue {
+                        value: Value::Bool(result),
+                        tag: Some(TypeTag::Primitive(PrimitiveType::Bool)),
+                        is_literal: false,
+                    })
+                }
+                (left_tag, right_tag) => Err(RuntimeError::new(format!(
+                    "Bitwise operators expect matching integer or bool types, got {} and {}",
+                    left_tag.describe(),
+                    right_tag.describe()
+                ))),
+            },
+            // The shift amount may be any integer type; the result has the
+            // type of the left operand.
+            ShiftLeft | ShiftRight => match (left_tag, right_tag) {
+                (
+                    TypeTag::Primitive(PrimitiveType::Int(kind)),
+                    TypeTag::Primitive(PrimitiveType::Int(_)),
+                ) => {
+                    let (a, amount) = match (left.value, right.value) {
+                        (Value::Int(a), Value::Int(b)) => (a, b),
+                        _ => return Err(RuntimeError::new("Shifts expect integers")),
+                    };
+                    if amount < 0 || amount >= kind.bits() as i128 {
+                        return Err(RuntimeError::new(format!(
+                            "Shift amount {amount} is out of range for {}",
+                            kind.name()
+                        )));
                     }
-                    let elem_tag = TypeTag::Primitive(PrimitiveType::Int(kind_l));
-                    let vec = VecValue {
-                        elem_type: Some(elem_tag.clone()),
-                        items,
+                    // Unsigned values are never negative, so `>>` is logical
+                    // for them and arithmetic for signed ones.
+                    let result = match op {
+                        ShiftLeft => kind.wrap((a as u128) << amount)?,
+                        _ => a >> amount,
                     };
                     Ok(TypedValue {
-                        value: Value::Vec(Rc::new(RefCell::new(vec))),
-                        tag: Some(TypeTag::Vec(Box::new(elem_tag))),
+                        value: Value::Int(result),
+                        tag: Some(TypeTag::Primitive(PrimitiveType::Int(kind))),
                         is_literal: false,
                     })
                 }
                 (left_tag, right_tag) => Err(RuntimeError::new(format!(
-                    "Range expects matching integer types, got {} and {}",
+                    "Shifts expect integer operands, got {} and {}",
                     left_tag.describe(),
                     right_tag.describe()
                 ))),
@@ -4060,6 +5196,14 @@ impl Interpreter {
             Borrow => Err(RuntimeError::new(
                 "borrow operator not supported in runtime yet",
             )),
+            BitNot => match (tag, value.value) {
+                (TypeTag::Primitive(PrimitiveType::Int(kind)), Value::Int(v)) => Ok(TypedValue {
+                    value: Value::Int(kind.wrap(!(v as u128))?),
+                    tag: Some(TypeTag::Primitive(PrimitiveType::Int(kind))),
+                    is_literal: false,
+                }),
+                _ => Err(RuntimeError::new("Unary ~ expects integer")),
+            },
         }
     }
 
@@ -4223,7 +5367,12 @@ impl Interpreter {
                         )));
                     }
                     let empty: HashMap<String, TypeTag> = HashMap::new();
-                    resolve_enum_variant_tags(schema, &empty, &variant_name)?
+                    resolve_enum_variant_tags(
+                        schema,
+                        &empty,
+                        &variant_name,
+                        &self.type_aliases.borrow(),
+                    )?
                 } else {
                     if type_params.is_empty() {
                         return Err(RuntimeError::new(format!(
@@ -4237,7 +5386,12 @@ impl Interpreter {
                         "Enum",
                         &enum_name,
                     )?;
-                    resolve_enum_variant_tags(schema, &bindings, &variant_name)?
+                    resolve_enum_variant_tags(
+                        schema,
+                        &bindings,
+                        &variant_name,
+                        &self.type_aliases.borrow(),
+                    )?
                 };
                 if expected_tags.len() != args.len() {
                     return Err(RuntimeError::new(format!(
@@ -4286,6 +5440,18 @@ impl Interpreter {
         func: &UserFunction,
         args: Vec<Value>,
     ) -> RuntimeResult<Value> {
+        self.execute_user_function_frame(func, args)
+            .await
+            .map(|(result, _)| result)
+    }
+
+    /// Runs `func` and also returns the frame its parameters live in, where
+    /// the final value of a `self_mut` receiver can be read back.
+    async fn execute_user_function_frame(
+        &self,
+        func: &UserFunction,
+        args: Vec<Value>,
+    ) -> RuntimeResult<(Value, Env)> {
         if func.params.len() != args.len() {
             return Err(RuntimeError::new(format!(
                 "Function `{}` expects {} arguments, got {}",
@@ -4317,20 +5483,34 @@ impl Interpreter {
                     &arg_tag,
                     &func.type_params,
                     &mut type_bindings,
+                    &self.type_aliases.borrow(),
                 );
             }
-            let tag = type_tag_from_type_expr_with_bindings(&param.ty, &type_bindings);
+            let tag = type_tag_from_type_expr_with_bindings(
+                &param.ty,
+                &type_bindings,
+                &self.type_aliases.borrow(),
+            );
             ensure_tag_match(&Some(tag.clone()), &value, "function argument")?;
             apply_type_tag_to_value(&mut value, &tag);
-            frame.define(param.name.clone(), value);
+            if param.name == "self_mut" {
+                frame.define_mutable(param.name.clone(), value);
+            } else {
+                frame.define(param.name.clone(), value);
+            }
         }
+        self.check_type_bounds(&func.type_bounds, &type_bindings)?;
         self.type_bindings.borrow_mut().push(type_bindings.clone());
-        self.return_type_stack.borrow_mut().push(
-            func.return_type
-                .as_ref()
-                .map(|ret_ty| type_tag_from_type_expr_with_bindings(ret_ty, &type_bindings)),
-        );
-        let block_result = self.execute_block(&func.body, frame, 0).await;
+        self.return_type_stack
+            .borrow_mut()
+            .push(func.return_type.as_ref().map(|ret_ty| {
+                type_tag_from_type_expr_with_bindings(
+                    ret_ty,
+                    &type_bindings,
+                    &self.type_aliases.borrow(),
+                )
+            }));
+        let block_result = self.execute_block(&func.body, frame.clone(), 0).await;
         self.type_bindings.borrow_mut().pop();
         self.return_type_stack.borrow_mut().pop();
         let mut result = match block_result? {
@@ -4343,11 +5523,15 @@ impl Interpreter {
             }
         };
         if let Some(ret_ty) = &func.return_type {
-            let return_tag = type_tag_from_type_expr_with_bindings(ret_ty, &type_bindings);
+            let return_tag = type_tag_from_type_expr_with_bindings(
+                ret_ty,
+                &type_bindings,
+                &self.type_aliases.borrow(),
+            );
             ensure_tag_match(&Some(return_tag.clone()), &result, "return value")?;
             apply_type_tag_to_value(&mut result, &return_tag);
         }
-        Ok(result)
+        Ok((result, frame))
     }
 
     async fn await_value(&self, value: Value) -> RuntimeResult<Value> {
@@ -4359,13 +5543,28 @@ impl Interpreter {
 
     fn resolve_type_expr(&self, ty: &TypeExpr) -> TypeTag {
         if let Some(bindings) = self.type_bindings.borrow().last() {
-            type_tag_from_type_expr_with_bindings(ty, bindings)
+            type_tag_from_type_expr_with_bindings(ty, bindings, &self.type_aliases.borrow())
         } else {
-            type_tag_from_type_expr(ty)
+            type_tag_from_type_expr(ty, &self.type_aliases.borrow())
         }
     }
 
     async fn call_closure(&self, closure: ClosureValue, args: Vec<Value>) -> RuntimeResult<Value> {
+        if closure.is_async {
+            return Ok(make_future(FutureValue::new_spawn(
+                self.clone(),
+                Value::Closure(closure),
+                args,
+            )));
+        }
+        self.execute_closure(&closure, args).await
+    }
+
+    async fn execute_closure(
+        &self,
+        closure: &ClosureValue,
+        args: Vec<Value>,
+    ) -> RuntimeResult<Value> {
         if closure.params.len() != args.len() {
             return Err(RuntimeError::new(format!(
                 "Closure expects {} arguments, got {}",
@@ -4373,7 +5572,7 @@ impl Interpreter {
                 args.len()
             )));
         }
-        let frame = self.globals.child();
+        let frame = closure.env.child();
         for (param, value) in closure.params.iter().zip(args.into_iter()) {
             frame.define(param.clone(), value);
         }
@@ -4385,6 +5584,18 @@ impl Interpreter {
             )),
         }
     }
+
+    /// Body of a spawned task: async functions and closures run their body directly
+    /// (calling them again would only produce another future), and whatever the task
+    /// returns is awaited so callers never observe a future-of-a-future.
+    async fn run_spawned(&self, callee: Value, args: Vec<Value>) -> RuntimeResult<Value> {
+        let result = match callee {
+            Value::Function(func) => self.execute_user_function(&func, args).await?,
+            Value::Closure(closure) => self.execute_closure(&closure, args).await?,
+            other => self.invoke(other, args, None).await?,
+        };
+        self.await_value(result).await
+    }
 }
 
 fn stmt_span(stmt: &Stmt) -> Span {
@@ -4400,7 +5611,7 @@ fn stmt_span(stmt: &Stmt) -> Span {
         Stmt::Block(block) => block.span,
         Stmt::Unsafe { span, .. } => *span,
         Stmt::Assembly(block) => block.span,
-        Stmt::Break(span) | Stmt::Continue(span) => *span,
+        Stmt::Break { span, .. } | Stmt::Continue { span, .. } => *span,
     }
 }
 
@@ -4460,6 +5671,7 @@ fn register_builtins(env: &Env) {
             map.insert("insert".to_string(), Value::Builtin(builtin_vec_insert));
             map.insert("remove".to_string(), Value::Builtin(builtin_vec_remove));
             map.insert("extend".to_string(), Value::Builtin(builtin_vec_extend));
+            map.extend(collections::higher_order_builtins());
             map
         },
     });
@@ -4554,6 +5766,7 @@ fn register_builtins(env: &Env) {
             map.insert("values".to_string(), Value::Builtin(builtin_map_values));
             map.insert("items".to_string(), Value::Builtin(builtin_map_items));
             map.insert("len".to_string(), Value::Builtin(builtin_map_len));
+            map.extend(collections::higher_order_builtins());
             map
         },
     });
@@ -4576,6 +5789,7 @@ fn register_builtins(env: &Env) {
                 "difference".to_string(),
                 Value::Builtin(builtin_set_difference),
             );
+            map.extend(collections::higher_order_builtins());
             map
         },
     });
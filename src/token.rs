@@ -23,6 +23,7 @@ impl Keyword {
             Keyword::Let => "let",
             Keyword::Var => "var",
             Keyword::Const => "const",
+            Keyword::Static => "static",
             Keyword::Struct => "struct",
             Keyword::Enum => "enum",
             Keyword::Trait => "trait",
@@ -51,6 +52,8 @@ impl Keyword {
             Keyword::Type => "type",
             Keyword::Pub => "pub",
             Keyword::Check => "check",
+            Keyword::Where => "where",
+            Keyword::Loop => "loop",
         }
     }
 }
@@ -65,6 +68,7 @@ pub enum Keyword {
     Let,
     Var,
     Const,
+    Static,
     Struct,
     Enum,
     Trait,
@@ -93,6 +97,8 @@ pub enum Keyword {
     Type,
     Pub,
     Check,
+    Where,
+    Loop,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -101,7 +107,11 @@ pub enum TokenKind {
     IntegerLiteral(String),
     FloatLiteral(String),
     StringLiteral(String),
+    /// A string literal containing at least one `${...}` segment.
+    InterpolatedString(Vec<StringPart>),
     CharLiteral(char),
+    /// A loop label such as `'outer`, stored without the quote.
+    Label(String),
     Keyword(Keyword),
     LeftBrace,
     RightBrace,
@@ -132,8 +142,30 @@ pub enum TokenKind {
     Question,
     Bang,
     Pipe,
+    Caret,
+    Tilde,
+    /// `<<`. There is no `>>` token: `>` `>` closes nested generics, and the
+    /// expression parser reads two adjacent `>` as a right shift.
+    LessLess,
     AmpersandAmpersand,
     PipePipe,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    AmpersandEqual,
+    PipeEqual,
+    CaretEqual,
+    LessLessEqual,
     At,
     Eof,
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    /// Tokens of an embedded expression, terminated by an `Eof` token placed
+    /// at the closing `}`.
+    Expr(Vec<Token>),
+}
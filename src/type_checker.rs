@@ -0,0 +1,2011 @@
+use crate::ast::*;
+use crate::span::Span;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct TypeError {
+    pub message: String,
+    pub span: Span,
+}
+
+/// Static types as seen by the checker. `Unknown` is used wherever the checker cannot
+/// prove anything (module members, closures, builtin collection methods) and is
+/// compatible with every other type, so only provable mismatches are reported.
+#[derive(Debug, Clone, PartialEq)]
+enum Ty {
+    Unknown,
+    Unit,
+    Bool,
+    /// Integer; `None` for unsuffixed literals, which adapt to any width.
+    Int(Option<String>),
+    Float(Option<String>),
+    Str,
+    Char,
+    Vec(Box<Ty>),
+    Array(Box<Ty>, usize),
+    Slice(Box<Ty>),
+    Set(Box<Ty>),
+    Map(Box<Ty>, Box<Ty>),
+    Option(Box<Ty>),
+    Result(Box<Ty>, Box<Ty>),
+    Tuple(Vec<Ty>),
+    Future(Box<Ty>),
+    Named {
+        name: String,
+        args: Vec<Ty>,
+    },
+    Param(String),
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Unknown => write!(f, "_"),
+            Ty::Unit => write!(f, "unit"),
+            Ty::Bool => write!(f, "bool"),
+            Ty::Int(Some(name)) | Ty::Float(Some(name)) => write!(f, "{name}"),
+            Ty::Int(None) => write!(f, "integer"),
+            Ty::Float(None) => write!(f, "float"),
+            Ty::Str => write!(f, "str"),
+            Ty::Char => write!(f, "char"),
+            Ty::Vec(inner) => write!(f, "vec<{inner}>"),
+            Ty::Array(inner, size) => write!(f, "[{inner}; {size}]"),
+            Ty::Slice(inner) => write!(f, "[{inner}]"),
+            Ty::Set(inner) => write!(f, "set<{inner}>"),
+            Ty::Map(key, value) => write!(f, "map<{key}, {value}>"),
+            Ty::Option(inner) => write!(f, "option<{inner}>"),
+            Ty::Result(ok, err) => write!(f, "result<{ok}, {err}>"),
+            Ty::Tuple(elements) => {
+                let parts = elements.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+                write!(f, "({})", parts.join(", "))
+            }
+            Ty::Future(inner) => write!(f, "future<{inner}>"),
+            Ty::Named { name, args } if args.is_empty() => write!(f, "{name}"),
+            Ty::Named { name, args } => {
+                let parts = args.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+                write!(f, "{name}<{}>", parts.join(", "))
+            }
+            Ty::Param(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl Ty {
+    fn is_unknown(&self) -> bool {
+        matches!(self, Ty::Unknown | Ty::Param(_))
+    }
+}
+
+fn compatible(expected: &Ty, actual: &Ty) -> bool {
+    match (expected, actual) {
+        (a, b) if a.is_unknown() || b.is_unknown() => true,
+        (Ty::Int(_), Ty::Int(_)) | (Ty::Float(_), Ty::Float(_)) => true,
+        (Ty::Vec(a), Ty::Vec(b))
+        | (Ty::Set(a), Ty::Set(b))
+        | (Ty::Slice(a), Ty::Slice(b))
+        | (Ty::Option(a), Ty::Option(b))
+        | (Ty::Future(a), Ty::Future(b)) => compatible(a, b),
+        (Ty::Slice(a), Ty::Vec(b)) | (Ty::Slice(a), Ty::Array(b, _)) => compatible(a, b),
+        // Array literals are typed as vectors; their length is only known at runtime.
+        (Ty::Array(a, _), Ty::Vec(b)) => compatible(a, b),
+        (Ty::Array(a, n), Ty::Array(b, m)) => n == m && compatible(a, b),
+        (Ty::Map(ka, va), Ty::Map(kb, vb)) | (Ty::Result(ka, va), Ty::Result(kb, vb)) => {
+            compatible(ka, kb) && compatible(va, vb)
+        }
+        (Ty::Tuple(a), Ty::Tuple(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| compatible(x, y))
+        }
+        (Ty::Named { name: a, args: aa }, Ty::Named { name: b, args: ba }) => {
+            a == b
+                && (aa.is_empty()
+                    || ba.is_empty()
+                    || (aa.len() == ba.len()
+                        && aa.iter().zip(ba.iter()).all(|(x, y)| compatible(x, y))))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Replace generic parameters bound in `bindings`; parameters listed in `generics` but
+/// left unbound become `Unknown`.
+fn substitute(ty: &Ty, generics: &[String], bindings: &HashMap<String, Ty>) -> Ty {
+    let sub = |t: &Ty| Box::new(substitute(t, generics, bindings));
+    match ty {
+        Ty::Param(name) => match bindings.get(name) {
+            Some(bound) => bound.clone(),
+            None if generics.contains(name) => Ty::Unknown,
+            None => ty.clone(),
+        },
+        Ty::Vec(inner) => Ty::Vec(sub(inner)),
+        Ty::Array(inner, size) => Ty::Array(sub(inner), *size),
+        Ty::Slice(inner) => Ty::Slice(sub(inner)),
+        Ty::Set(inner) => Ty::Set(sub(inner)),
+        Ty::Map(key, value) => Ty::Map(sub(key), sub(value)),
+        Ty::Option(inner) => Ty::Option(sub(inner)),
+        Ty::Result(ok, err) => Ty::Result(sub(ok), sub(err)),
+        Ty::Future(inner) => Ty::Future(sub(inner)),
+        Ty::Tuple(elements) => Ty::Tuple(
+            elements
+                .iter()
+                .map(|t| substitute(t, generics, bindings))
+                .collect(),
+        ),
+        Ty::Named { name, args } => Ty::Named {
+            name: name.clone(),
+            args: args
+                .iter()
+                .map(|t| substitute(t, generics, bindings))
+                .collect(),
+        },
+        other => other.clone(),
+    }
+}
+
+/// Match `actual` against `expected`, binding the generic parameters in `generics`.
+/// Returns the conflicting parameter and its earlier binding on failure.
+fn unify(
+    expected: &Ty,
+    actual: &Ty,
+    generics: &[String],
+    bindings: &mut HashMap<String, Ty>,
+) -> Result<(), Option<(String, Ty)>> {
+    match (expected, actual) {
+        (Ty::Param(name), _) if generics.contains(name) => {
+            match bindings.get(name) {
+                Some(bound) if !compatible(bound, actual) => {
+                    return Err(Some((name.clone(), bound.clone())))
+                }
+                Some(bound) if !matches!(bound, Ty::Unknown | Ty::Int(None) | Ty::Float(None)) => {}
+                _ => {
+                    if !matches!(actual, Ty::Unknown) {
+                        bindings.insert(name.clone(), actual.clone());
+                    }
+                }
+            }
+            Ok(())
+        }
+        (Ty::Vec(a), Ty::Vec(b))
+        | (Ty::Set(a), Ty::Set(b))
+        | (Ty::Slice(a), Ty::Slice(b))
+        | (Ty::Slice(a), Ty::Vec(b))
+        | (Ty::Slice(a), Ty::Array(b, _))
+        | (Ty::Array(a, _), Ty::Vec(b))
+        | (Ty::Option(a), Ty::Option(b))
+        | (Ty::Future(a), Ty::Future(b)) => unify(a, b, generics, bindings),
+        (Ty::Array(a, n), Ty::Array(b, m)) if n == m => unify(a, b, generics, bindings),
+        (Ty::Map(ka, va), Ty::Map(kb, vb)) | (Ty::Result(ka, va), Ty::Result(kb, vb)) => {
+            unify(ka, kb, generics, bindings)?;
+            unify(va, vb, generics, bindings)
+        }
+        (Ty::Tuple(a), Ty::Tuple(b)) if a.len() == b.len() => {
+            for (x, y) in a.iter().zip(b.iter()) {
+                unify(x, y, generics, bindings)?;
+            }
+            Ok(())
+        }
+        (Ty::Named { name: a, args: aa }, Ty::Named { name: b, args: ba })
+            if a == b && aa.len() == ba.len() =>
+        {
+            for (x, y) in aa.iter().zip(ba.iter()) {
+                unify(x, y, generics, bindings)?;
+            }
+            Ok(())
+        }
+        _ => {
+            let resolved = substitute(expected, generics, bindings);
+            if compatible(&resolved, actual) {
+                Ok(())
+            } else {
+                Err(None)
+            }
+        }
+    }
+}
+
+fn int_or_float(name: &str) -> Option<Ty> {
+    match name {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "u8" | "u16" | "u32" | "u64" | "u128" => {
+            Some(Ty::Int(Some(name.to_string())))
+        }
+        "f32" | "f64" => Some(Ty::Float(Some(name.to_string()))),
+        _ => None,
+    }
+}
+
+fn primitive_ty(name: &str) -> Option<Ty> {
+    match name {
+        "bool" => Some(Ty::Bool),
+        "str" | "string" => Some(Ty::Str),
+        "char" => Some(Ty::Char),
+        "unit" => Some(Ty::Unit),
+        other => int_or_float(other),
+    }
+}
+
+fn builtin_generic_arity(name: &str) -> Option<usize> {
+    match name {
+        "vec" | "set" | "option" => Some(1),
+        "map" | "result" => Some(2),
+        _ => None,
+    }
+}
+
+fn path_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Identifier { name, .. } => Some(name.clone()),
+        Expr::Access {
+            base,
+            member,
+            op: AccessOperator::Path,
+            ..
+        } => path_name(base).map(|base| format!("{base}::{member}")),
+        _ => None,
+    }
+}
+
+fn plural(count: usize, word: &str) -> String {
+    if count == 1 {
+        format!("{count} {word}")
+    } else {
+        format!("{count} {word}s")
+    }
+}
+
+struct MethodInfo<'a> {
+    sig: &'a FunctionSignature,
+    has_self: bool,
+    generics: Vec<String>,
+    /// The trait providing the method; `None` for inherent methods.
+    trait_name: Option<String>,
+}
+
+/// What a `return` (or `?`) inside the current body has to produce.
+struct ReturnContext {
+    declared: Option<Ty>,
+}
+
+struct Checker<'a> {
+    functions: HashMap<&'a str, &'a FunctionSignature>,
+    structs: HashMap<&'a str, &'a StructDef>,
+    enums: HashMap<&'a str, &'a EnumDef>,
+    traits: HashMap<&'a str, &'a TraitDef>,
+    aliases: HashMap<&'a str, &'a TypeAliasDef>,
+    /// Alias targets resolved against the alias's own type parameters.
+    alias_tys: HashMap<String, Ty>,
+    methods: HashMap<String, HashMap<String, MethodInfo<'a>>>,
+    /// `(trait, type)` for every `impl Trait for Type` in the file.
+    trait_impls: HashSet<(String, String)>,
+    /// `(type, method)` pairs provided by more than one trait, with those traits.
+    ambiguous_methods: HashMap<(String, String), Vec<String>>,
+    imported: HashSet<String>,
+    scopes: Vec<HashMap<String, Ty>>,
+    generics: Vec<String>,
+    self_ty: Option<Ty>,
+    returns: Vec<ReturnContext>,
+    errors: Vec<TypeError>,
+}
+
+/// Type-check a parsed file. The checker is deliberately conservative: anything it
+/// cannot resolve statically is treated as `_` and accepted, so every reported error is
+/// a mismatch the interpreter would also reject.
+pub fn check_file(file: &File) -> Vec<TypeError> {
+    let mut checker = Checker::new(file);
+    // Globals first, so every function body sees their types.
+    for item in &file.items {
+        if let Item::Global(global) = item {
+            checker.check_global(global);
+        }
+    }
+    for item in &file.items {
+        checker.check_item(item);
+    }
+    checker.errors
+}
+
+impl<'a> Checker<'a> {
+    fn new(file: &'a File) -> Self {
+        let mut checker = Checker {
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            traits: HashMap::new(),
+            aliases: HashMap::new(),
+            alias_tys: HashMap::new(),
+            methods: HashMap::new(),
+            trait_impls: HashSet::new(),
+            ambiguous_methods: HashMap::new(),
+            imported: HashSet::new(),
+            scopes: vec![HashMap::new()],
+            generics: Vec::new(),
+            self_ty: None,
+            returns: Vec::new(),
+            errors: Vec::new(),
+        };
+        for import in &file.imports {
+            let bound = import
+                .alias
+                .clone()
+                .or_else(|| import.member.clone())
+                .or_else(|| import.path.last().cloned());
+            if let Some(name) = bound {
+                checker.imported.insert(name);
+            }
+        }
+        for item in &file.items {
+            match item {
+                Item::Function(func) => {
+                    checker
+                        .functions
+                        .insert(func.signature.name.as_str(), &func.signature);
+                }
+                Item::ExternFunction(func) => {
+                    checker
+                        .functions
+                        .insert(func.signature.name.as_str(), &func.signature);
+                }
+                Item::Struct(def) => {
+                    checker.structs.insert(def.name.as_str(), def);
+                }
+                Item::Enum(def) => {
+                    checker.enums.insert(def.name.as_str(), def);
+                }
+                Item::Trait(def) => {
+                    checker.traits.insert(def.name.as_str(), def);
+                }
+                Item::TypeAlias(def) => {
+                    checker.aliases.insert(def.name.as_str(), def);
+                }
+                Item::Impl(_) | Item::Global(_) => {}
+            }
+        }
+        for item in &file.items {
+            if let Item::Impl(imp) = item {
+                let Some(key) = type_key(&imp.target) else {
+                    continue;
+                };
+                let trait_name = imp.trait_type.as_ref().and_then(type_key);
+                if let Some(trait_name) = &trait_name {
+                    checker
+                        .trait_impls
+                        .insert((trait_name.clone(), key.clone()));
+                }
+                let generics = imp
+                    .type_params
+                    .iter()
+                    .map(|p| p.name.clone())
+                    .collect::<Vec<_>>();
+                // Defaults the impl leaves out become methods of the target too.
+                let defaults = trait_name
+                    .as_deref()
+                    .and_then(|name| checker.traits.get(name))
+                    .into_iter()
+                    .flat_map(|def| &def.methods)
+                    .filter(|m| {
+                        m.default.is_some()
+                            && !imp
+                                .methods
+                                .iter()
+                                .any(|f| f.signature.name == m.signature.name)
+                    })
+                    .map(|m| &m.signature);
+                let sigs = imp
+                    .methods
+                    .iter()
+                    .map(|m| &m.signature)
+                    .chain(defaults)
+                    .collect::<Vec<_>>();
+                for sig in sigs {
+                    checker.add_method(&key, sig, &generics, trait_name.as_deref());
+                }
+            }
+        }
+        checker
+    }
+
+    /// Registers `sig` as a method of `key`. Inherent methods shadow trait
+    /// methods; two traits providing the same name make it ambiguous.
+    fn add_method(
+        &mut self,
+        key: &str,
+        sig: &'a FunctionSignature,
+        generics: &[String],
+        trait_name: Option<&str>,
+    ) {
+        let entry = self.methods.entry(key.to_string()).or_default();
+        let slot = (key.to_string(), sig.name.clone());
+        match (
+            entry.get(&sig.name).map(|m| m.trait_name.as_deref()),
+            trait_name,
+        ) {
+            (Some(None), Some(_)) => return,
+            (Some(Some(existing)), Some(new)) if existing != new => {
+                let traits = self.ambiguous_methods.entry(slot).or_default();
+                for name in [existing, new] {
+                    if !traits.iter().any(|t| t == name) {
+                        traits.push(name.to_string());
+                    }
+                }
+            }
+            (_, None) => {
+                self.ambiguous_methods.remove(&slot);
+            }
+            _ => {}
+        }
+        let has_self = sig
+            .params
+            .first()
+            .map(|p| p.name == "self" || p.name == "self_mut")
+            .unwrap_or(false);
+        entry.insert(
+            sig.name.clone(),
+            MethodInfo {
+                sig,
+                has_self,
+                generics: generics.to_vec(),
+                trait_name: trait_name.map(str::to_string),
+            },
+        );
+    }
+
+    fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.errors.push(TypeError {
+            message: message.into(),
+            span,
+        });
+    }
+
+    fn mismatch(&mut self, span: Span, expected: &Ty, found: &Ty) {
+        self.error(
+            span,
+            format!("Mismatched types: expected `{expected}`, found `{found}`"),
+        );
+    }
+
+    // ---------------------------------------------------------------
+    // Items
+    // ---------------------------------------------------------------
+
+    fn check_item(&mut self, item: &'a Item) {
+        match item {
+            Item::Function(func) => self.check_function(func, &[]),
+            Item::ExternFunction(func) => {
+                self.generics = func
+                    .signature
+                    .type_params
+                    .iter()
+                    .map(|p| p.name.clone())
+                    .collect();
+                self.signature_types(&func.signature);
+                self.generics.clear();
+            }
+            Item::Struct(def) => {
+                self.check_bound_refs(&def.type_params);
+                self.generics = def.type_params.iter().map(|p| p.name.clone()).collect();
+                for field in &def.fields {
+                    self.resolve(&field.ty);
+                }
+                self.generics.clear();
+            }
+            Item::Enum(def) => {
+                self.generics = def.type_params.iter().map(|p| p.name.clone()).collect();
+                for variant in &def.variants {
+                    for ty in &variant.payload {
+                        self.resolve(ty);
+                    }
+                }
+                self.generics.clear();
+            }
+            Item::Trait(def) => {
+                let trait_generics = def
+                    .type_params
+                    .iter()
+                    .map(|p| p.name.clone())
+                    .collect::<Vec<_>>();
+                for method in &def.methods {
+                    match &method.default {
+                        // `Self` stays unknown: the body runs for every implementor.
+                        Some(body) => self.check_body(&method.signature, body, &trait_generics),
+                        None => {
+                            self.check_bound_refs(&method.signature.type_params);
+                            self.generics = trait_generics
+                                .iter()
+                                .cloned()
+                                .chain(method.signature.type_params.iter().map(|p| p.name.clone()))
+                                .collect();
+                            self.signature_types(&method.signature);
+                        }
+                    }
+                }
+                self.generics.clear();
+            }
+            Item::Impl(imp) => {
+                let impl_generics = imp
+                    .type_params
+                    .iter()
+                    .map(|p| p.name.clone())
+                    .collect::<Vec<_>>();
+                self.check_bound_refs(&imp.type_params);
+                self.generics = impl_generics.clone();
+                if let Some(trait_ty) = &imp.trait_type {
+                    self.check_trait_ref(trait_ty);
+                }
+                let target = self.resolve(&imp.target);
+                self.self_ty = Some(target);
+                for method in &imp.methods {
+                    self.check_function(method, &impl_generics);
+                }
+                self.self_ty = None;
+                self.generics.clear();
+            }
+            Item::TypeAlias(def) => {
+                self.alias_ty(def);
+            }
+            // Checked up front by `check_file`.
+            Item::Global(_) => {}
+        }
+    }
+
+    fn check_global(&mut self, global: &'a GlobalDef) {
+        let declared = global.ty.as_ref().map(|ty| self.resolve(ty));
+        let actual = self.check_expr(&global.value);
+        let ty = match declared {
+            Some(expected) => {
+                if !compatible(&expected, &actual) {
+                    self.mismatch(global.value.span(), &expected, &actual);
+                }
+                expected
+            }
+            None => actual,
+        };
+        self.define(&global.name, ty);
+    }
+
+    fn check_trait_ref(&mut self, ty: &TypeExpr) {
+        if let TypeExpr::Named(named) = ty {
+            if named.segments.len() == 1 {
+                let name = &named.segments[0].name;
+                if !self.traits.contains_key(name.as_str()) && !self.imported.contains(name) {
+                    self.error(named.span, format!("Unknown trait `{name}`"));
+                }
+            }
+        }
+    }
+
+    fn check_bound_refs(&mut self, params: &[TypeParam]) {
+        for bound in params.iter().flat_map(|p| &p.bounds) {
+            self.check_trait_ref(bound);
+        }
+    }
+
+    /// Reports type arguments in `bindings` that lack an `impl` of a bound
+    /// declared on `params`. Only traits declared in this file are checked,
+    /// since impls of imported traits may live elsewhere.
+    fn check_bounds(
+        &mut self,
+        what: &str,
+        params: &[TypeParam],
+        bindings: &HashMap<String, Ty>,
+        span: Span,
+    ) {
+        for param in params {
+            let Some(key) = bindings.get(&param.name).and_then(impl_key) else {
+                continue;
+            };
+            for trait_name in param.bounds.iter().filter_map(type_key) {
+                if !self.traits.contains_key(trait_name.as_str())
+                    || self
+                        .trait_impls
+                        .contains(&(trait_name.clone(), key.clone()))
+                {
+                    continue;
+                }
+                self.error(
+                    span,
+                    format!(
+                        "{what}: trait bound `{}: {trait_name}` is not satisfied; missing `impl {trait_name} for {key}`",
+                        param.name
+                    ),
+                );
+            }
+        }
+    }
+
+    fn signature_types(&mut self, sig: &FunctionSignature) -> (Vec<Ty>, Option<Ty>) {
+        let params = sig.params.iter().map(|p| self.resolve(&p.ty)).collect();
+        let ret = sig.return_type.as_ref().map(|ty| self.resolve(ty));
+        (params, ret)
+    }
+
+    fn check_function(&mut self, func: &'a Function, outer_generics: &[String]) {
+        self.check_body(&func.signature, &func.body, outer_generics);
+    }
+
+    fn check_body(
+        &mut self,
+        sig: &'a FunctionSignature,
+        body: &'a Block,
+        outer_generics: &[String],
+    ) {
+        self.check_bound_refs(&sig.type_params);
+        self.generics = outer_generics
+            .iter()
+            .cloned()
+            .chain(sig.type_params.iter().map(|p| p.name.clone()))
+            .collect();
+        let (params, ret) = self.signature_types(sig);
+        self.scopes.push(HashMap::new());
+        for (param, ty) in sig.params.iter().zip(params) {
+            self.define(&param.name, ty);
+        }
+        self.returns.push(ReturnContext { declared: ret });
+        self.check_block(body);
+        self.returns.pop();
+        self.scopes.pop();
+        self.generics = outer_generics.to_vec();
+    }
+
+    // ---------------------------------------------------------------
+    // Types
+    // ---------------------------------------------------------------
+
+    fn resolve(&mut self, ty: &TypeExpr) -> Ty {
+        match ty {
+            TypeExpr::Named(named) => self.resolve_named(named),
+            TypeExpr::Array { element, size, .. } => {
+                Ty::Array(Box::new(self.resolve(element)), *size)
+            }
+            TypeExpr::Slice { element, .. } => Ty::Slice(Box::new(self.resolve(element))),
+            TypeExpr::Tuple { elements, .. } => {
+                Ty::Tuple(elements.iter().map(|e| self.resolve(e)).collect())
+            }
+            TypeExpr::Reference { inner, .. } => {
+                self.resolve(inner);
+                Ty::Unknown
+            }
+        }
+    }
+
+    fn resolve_named(&mut self, named: &NamedType) -> Ty {
+        let last = named
+            .segments
+            .last()
+            .expect("named type has at least one segment");
+        let args = last
+            .generics
+            .iter()
+            .map(|g| self.resolve(g))
+            .collect::<Vec<_>>();
+        if named.segments.len() > 1 {
+            // Module-qualified types come from other files; nothing to check here.
+            return Ty::Unknown;
+        }
+        let name = last.name.as_str();
+        if self.generics.iter().any(|g| g == name) {
+            return Ty::Param(name.to_string());
+        }
+        if name == "Self" {
+            return self.self_ty.clone().unwrap_or(Ty::Unknown);
+        }
+        if let Some(prim) = primitive_ty(name) {
+            if !args.is_empty() {
+                self.error(
+                    named.span,
+                    format!("Type `{name}` does not take type arguments"),
+                );
+            }
+            return prim;
+        }
+        if let Some(arity) = builtin_generic_arity(name) {
+            if !args.is_empty() && args.len() != arity {
+                self.error(
+                    named.span,
+                    format!(
+                        "Type `{name}` expects {}, got {}",
+                        plural(arity, "type argument"),
+                        args.len()
+                    ),
+                );
+                return Ty::Unknown;
+            }
+            let arg = |i: usize| Box::new(args.get(i).cloned().unwrap_or(Ty::Unknown));
+            return match name {
+                "vec" => Ty::Vec(arg(0)),
+                "set" => Ty::Set(arg(0)),
+                "option" => Ty::Option(arg(0)),
+                "map" => Ty::Map(arg(0), arg(1)),
+                _ => Ty::Result(arg(0), arg(1)),
+            };
+        }
+        let declared = if let Some(def) = self.structs.get(name) {
+            Some(("Struct", def.type_params.len()))
+        } else {
+            self.enums
+                .get(name)
+                .map(|def| ("Enum", def.type_params.len()))
+        };
+        if let Some((kind, expected)) = declared {
+            if !args.is_empty() && args.len() != expected {
+                self.error(
+                    named.span,
+                    format!(
+                        "{kind} `{name}` expects {}, got {}",
+                        plural(expected, "type argument"),
+                        args.len()
+                    ),
+                );
+                return Ty::Unknown;
+            }
+            return Ty::Named {
+                name: name.to_string(),
+                args,
+            };
+        }
+        if let Some(alias) = self.aliases.get(name).copied() {
+            let params = alias
+                .type_params
+                .iter()
+                .map(|p| p.name.clone())
+                .collect::<Vec<_>>();
+            if !args.is_empty() && args.len() != params.len() {
+                self.error(
+                    named.span,
+                    format!(
+                        "Type alias `{name}` expects {}, got {}",
+                        plural(params.len(), "type argument"),
+                        args.len()
+                    ),
+                );
+                return Ty::Unknown;
+            }
+            let target = self.alias_ty(alias);
+            let bindings = params.iter().cloned().zip(args).collect();
+            return substitute(&target, &params, &bindings);
+        }
+        if self.traits.contains_key(name) || self.imported.contains(name) {
+            return Ty::Unknown;
+        }
+        self.error(named.span, format!("Unknown type `{name}`"));
+        Ty::Unknown
+    }
+
+    /// The target of `alias`, in terms of its own type parameters. Resolved
+    /// once, so problems inside the target are reported a single time.
+    fn alias_ty(&mut self, alias: &'a TypeAliasDef) -> Ty {
+        if let Some(ty) = self.alias_tys.get(&alias.name) {
+            return ty.clone();
+        }
+        // Self-referential aliases are rejected by validation; stop here
+        // rather than recursing forever.
+        self.alias_tys.insert(alias.name.clone(), Ty::Unknown);
+        let outer = std::mem::replace(
+            &mut self.generics,
+            alias.type_params.iter().map(|p| p.name.clone()).collect(),
+        );
+        let ty = self.resolve(&alias.target);
+        self.generics = outer;
+        self.alias_tys.insert(alias.name.clone(), ty.clone());
+        ty
+    }
+
+    // ---------------------------------------------------------------
+    // Scopes
+    // ---------------------------------------------------------------
+
+    fn define(&mut self, name: &str, ty: Ty) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Ty> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn is_local(&self, name: &str) -> bool {
+        self.lookup(name).is_some()
+    }
+
+    // ---------------------------------------------------------------
+    // Statements
+    // ---------------------------------------------------------------
+
+    fn check_block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        for stmt in &block.statements {
+            self.check_stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_condition(&mut self, expr: &Expr) {
+        let ty = self.check_expr(expr);
+        if !compatible(&Ty::Bool, &ty) {
+            self.error(
+                expr.span(),
+                format!("Condition must be `bool`, found `{ty}`"),
+            );
+        }
+    }
+
+    /// Checks an `if`/`while` condition and the block it guards; a `let`
+    /// condition binds its pattern for that block only.
+    fn check_conditional(&mut self, condition: &Expr, body: &Block) {
+        let Expr::Let { pattern, value, .. } = condition else {
+            self.check_condition(condition);
+            self.check_block(body);
+            return;
+        };
+        let ty = self.check_expr(value);
+        self.scopes.push(HashMap::new());
+        self.bind_typed_pattern(pattern, ty);
+        self.check_block(body);
+        self.scopes.pop();
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl(decl) => {
+                let declared = decl.ty.as_ref().map(|ty| self.resolve(ty));
+                let actual = self.check_expr(&decl.value);
+                let ty = match declared {
+                    Some(expected) => {
+                        if !compatible(&expected, &actual) {
+                            self.mismatch(decl.value.span(), &expected, &actual);
+                        }
+                        expected
+                    }
+                    None => actual,
+                };
+                self.bind_typed_pattern(&decl.pattern, ty);
+            }
+            Stmt::Expr(expr) => {
+                self.check_expr(expr);
+            }
+            Stmt::Return { value, span } => {
+                let actual = value
+                    .as_ref()
+                    .map(|v| self.check_expr(v))
+                    .unwrap_or(Ty::Unit);
+                let declared = self.returns.last().and_then(|r| r.declared.clone());
+                if let Some(expected) = declared {
+                    if !compatible(&expected, &actual) {
+                        let at = value.as_ref().map(|v| v.span()).unwrap_or(*span);
+                        self.mismatch(at, &expected, &actual);
+                    }
+                }
+            }
+            Stmt::If(if_stmt) => self.check_if(if_stmt),
+            Stmt::While {
+                condition, body, ..
+            } => self.check_conditional(condition, body),
+            Stmt::For {
+                pattern,
+                iterable,
+                body,
+                ..
+            } => {
+                let iter_ty = self.check_expr(iterable);
+                let elem = match iter_ty {
+                    Ty::Vec(inner) | Ty::Array(inner, _) | Ty::Slice(inner) | Ty::Set(inner) => {
+                        *inner
+                    }
+                    Ty::Map(key, value) => Ty::Tuple(vec![*key, *value]),
+                    Ty::Str => Ty::Char,
+                    Ty::Unit | Ty::Bool | Ty::Int(_) | Ty::Float(_) | Ty::Char => {
+                        self.error(
+                            iterable.span(),
+                            format!("Cannot iterate over a value of type `{iter_ty}`"),
+                        );
+                        Ty::Unknown
+                    }
+                    _ => Ty::Unknown,
+                };
+                self.scopes.push(HashMap::new());
+                self.bind_typed_pattern(pattern, elem);
+                self.check_block(body);
+                self.scopes.pop();
+            }
+            Stmt::Switch(switch) => {
+                let scrutinee = self.check_expr(&switch.expr);
+                for arm in &switch.arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind_typed_pattern(&arm.pattern, scrutinee.clone());
+                    if let Some(guard) = &arm.guard {
+                        self.check_condition(guard);
+                    }
+                    self.check_expr(&arm.expr);
+                    self.scopes.pop();
+                }
+            }
+            Stmt::Try(try_catch) => {
+                self.check_block(&try_catch.try_block);
+                self.scopes.push(HashMap::new());
+                if let Some(binding) = &try_catch.catch_binding {
+                    self.define(binding, Ty::Unknown);
+                }
+                self.check_block(&try_catch.catch_block);
+                self.scopes.pop();
+            }
+            Stmt::Block(block) | Stmt::Unsafe { body: block, .. } => self.check_block(block),
+            Stmt::Break { value, .. } => {
+                if let Some(value) = value {
+                    self.check_expr(value);
+                }
+            }
+            Stmt::Assembly(_) | Stmt::Continue { .. } => {}
+        }
+    }
+
+    fn check_if(&mut self, if_stmt: &IfStmt) {
+        self.check_conditional(&if_stmt.condition, &if_stmt.then_branch);
+        for (cond, block) in &if_stmt.else_if {
+            self.check_conditional(cond, block);
+        }
+        if let Some(block) = &if_stmt.else_branch {
+            self.check_block(block);
+        }
+    }
+
+    /// Defines the names `pattern` binds, typing the parts of tuples and
+    /// structs from `ty`; whatever else a pattern binds is left unknown.
+    fn bind_typed_pattern(&mut self, pattern: &Pattern, ty: Ty) {
+        match pattern {
+            Pattern::Binding { name, .. } => self.define(name, ty),
+            Pattern::Tuple { elements, span } => {
+                let items = match ty {
+                    Ty::Tuple(items) if items.len() == elements.len() => items,
+                    Ty::Unknown | Ty::Param(_) => Vec::new(),
+                    other => {
+                        self.error(
+                            *span,
+                            format!(
+                                "Mismatched types: pattern expects a tuple of {} elements, found `{other}`",
+                                elements.len()
+                            ),
+                        );
+                        Vec::new()
+                    }
+                };
+                let mut items = items.into_iter();
+                for element in elements {
+                    let item = items.next().unwrap_or(Ty::Unknown);
+                    self.bind_typed_pattern(element, item);
+                }
+            }
+            Pattern::Struct { path, fields, span } => {
+                let name = path.last().map(String::as_str).unwrap_or_default();
+                let def = self.structs.get(name).copied();
+                let args = match &ty {
+                    Ty::Named { name: found, args } if found == name => args.clone(),
+                    Ty::Named { .. } if def.is_some() => {
+                        self.error(
+                            *span,
+                            format!("Mismatched types: expected `{name}`, found `{ty}`"),
+                        );
+                        Vec::new()
+                    }
+                    _ => Vec::new(),
+                };
+                for field in fields {
+                    let field_ty = match def {
+                        Some(def) => match def.fields.iter().find(|f| f.name == field.name) {
+                            Some(decl) => self.field_type(def, decl, &args),
+                            None => {
+                                self.error(
+                                    field.span,
+                                    format!("Struct `{name}` has no field `{}`", field.name),
+                                );
+                                Ty::Unknown
+                            }
+                        },
+                        None => Ty::Unknown,
+                    };
+                    self.bind_typed_pattern(&field.pattern, field_ty);
+                }
+            }
+            other => {
+                for (name, _) in other.bindings() {
+                    self.define(name, Ty::Unknown);
+                }
+            }
+        }
+    }
+
+    // ---------------------------------------------------------------
+    // Expressions
+    // ---------------------------------------------------------------
+
+    fn check_expr(&mut self, expr: &Expr) -> Ty {
+        match expr {
+            Expr::Literal(lit) => match lit {
+                Literal::Integer { .. } => Ty::Int(None),
+                Literal::Float { .. } => Ty::Float(None),
+                Literal::String { .. } => Ty::Str,
+                Literal::Char { .. } => Ty::Char,
+                Literal::Bool { .. } => Ty::Bool,
+            },
+            Expr::Identifier { name, .. } => self.lookup(name).cloned().unwrap_or(Ty::Unknown),
+            Expr::Access {
+                base,
+                member,
+                op,
+                span,
+            } => self.check_access(base, member, *op, *span),
+            Expr::Call {
+                callee,
+                args,
+                type_args,
+                span,
+            } => self.check_call(callee, args, type_args, *span),
+            Expr::Await { expr, .. } => match self.check_expr(expr) {
+                Ty::Future(inner) => *inner,
+                other => other,
+            },
+            Expr::Unary { op, expr, span } => {
+                let ty = self.check_expr(expr);
+                match op {
+                    UnaryOp::Not => {
+                        if !compatible(&Ty::Bool, &ty) {
+                            self.error(*span, format!("Cannot apply `!` to `{ty}`"));
+                        }
+                        Ty::Bool
+                    }
+                    UnaryOp::Negate => {
+                        if !ty.is_unknown() && !matches!(ty, Ty::Int(_) | Ty::Float(_)) {
+                            self.error(*span, format!("Cannot negate `{ty}`"));
+                        }
+                        ty
+                    }
+                    UnaryOp::Borrow => ty,
+                    UnaryOp::BitNot => {
+                        if !ty.is_unknown() && !matches!(ty, Ty::Int(_)) {
+                            self.error(*span, format!("Cannot apply `~` to `{ty}`"));
+                        }
+                        ty
+                    }
+                }
+            }
+            Expr::Binary {
+                left,
+                op,
+                right,
+                span,
+            } => self.check_binary(left, *op, right, *span),
+            Expr::Range {
+                start, end, step, ..
+            } => self.check_range(start, end, step.as_deref()),
+            Expr::Assignment { target, value, .. } => {
+                let expected = self.check_expr(target);
+                let actual = self.check_expr(value);
+                if !compatible(&expected, &actual) {
+                    self.mismatch(value.span(), &expected, &actual);
+                }
+                Ty::Unit
+            }
+            Expr::StructLiteral {
+                path,
+                type_args,
+                fields,
+                span,
+            } => self.check_struct_literal(path, type_args, fields, *span),
+            Expr::ArrayLiteral { elements, .. } => {
+                let mut elem = Ty::Unknown;
+                for element in elements {
+                    let ty = self.check_expr(element);
+                    if matches!(elem, Ty::Unknown | Ty::Int(None) | Ty::Float(None)) {
+                        elem = ty;
+                    } else if !compatible(&elem, &ty) {
+                        self.error(
+                            element.span(),
+                            format!(
+                                "Mismatched types in array literal: expected `{elem}`, found `{ty}`"
+                            ),
+                        );
+                    }
+                }
+                Ty::Vec(Box::new(elem))
+            }
+            Expr::TupleLiteral { elements, .. } => {
+                Ty::Tuple(elements.iter().map(|e| self.check_expr(e)).collect())
+            }
+            Expr::Cast { expr, ty, .. } => {
+                self.check_expr(expr);
+                self.resolve(ty)
+            }
+            Expr::Block(block) => {
+                self.check_block(block);
+                Ty::Unknown
+            }
+            Expr::If(if_stmt) => {
+                self.check_if(if_stmt);
+                Ty::Unknown
+            }
+            Expr::Loop { body, .. } => {
+                self.check_block(body);
+                Ty::Unknown
+            }
+            Expr::Let { value, .. } => {
+                self.check_expr(value);
+                Ty::Bool
+            }
+            Expr::Try { expr, span } => self.check_try(expr, *span),
+            Expr::Lambda(lambda) => {
+                self.scopes.push(HashMap::new());
+                for param in &lambda.params {
+                    let ty = param
+                        .ty
+                        .as_ref()
+                        .map(|ty| self.resolve(ty))
+                        .unwrap_or(Ty::Unknown);
+                    self.define(&param.name, ty);
+                }
+                let declared = lambda.return_type.as_ref().map(|ty| self.resolve(ty));
+                self.returns.push(ReturnContext { declared });
+                self.check_block(&lambda.body);
+                self.returns.pop();
+                self.scopes.pop();
+                Ty::Unknown
+            }
+            Expr::Index { base, index, span } => {
+                let base_ty = self.check_expr(base);
+                let index_ty = self.check_expr(index);
+                match base_ty {
+                    Ty::Vec(inner) | Ty::Array(inner, _) | Ty::Slice(inner) => {
+                        if !compatible(&Ty::Int(None), &index_ty) {
+                            self.error(
+                                index.span(),
+                                format!("Index must be an integer, found `{index_ty}`"),
+                            );
+                        }
+                        *inner
+                    }
+                    Ty::Map(key, value) => {
+                        if !compatible(&key, &index_ty) {
+                            self.mismatch(index.span(), &key, &index_ty);
+                        }
+                        *value
+                    }
+                    Ty::Tuple(elements) => match &**index {
+                        Expr::Literal(Literal::Integer { value, .. }) => {
+                            match value.parse::<usize>() {
+                                Ok(i) if i < elements.len() => elements[i].clone(),
+                                _ => {
+                                    self.error(
+                                        index.span(),
+                                        format!(
+                                            "Tuple index {value} out of bounds for `{}`",
+                                            Ty::Tuple(elements.clone())
+                                        ),
+                                    );
+                                    Ty::Unknown
+                                }
+                            }
+                        }
+                        _ => Ty::Unknown,
+                    },
+                    Ty::Str => Ty::Unknown,
+                    Ty::Unknown | Ty::Param(_) => Ty::Unknown,
+                    other => {
+                        self.error(
+                            *span,
+                            format!("Cannot index into a value of type `{other}`"),
+                        );
+                        Ty::Unknown
+                    }
+                }
+            }
+            Expr::MethodCall {
+                object,
+                method,
+                args,
+                span,
+            } => self.check_method_call(object, method, args, *span),
+            Expr::Check(check) => {
+                if let Some(target) = &check.target {
+                    self.check_expr(target);
+                }
+                for arm in &check.arms {
+                    if let CheckPattern::Guard(guard) = &arm.pattern {
+                        self.check_expr(guard);
+                    }
+                    self.check_expr(&arm.expr);
+                }
+                Ty::Unknown
+            }
+            Expr::Interpolated { parts, .. } => {
+                for part in parts {
+                    if let InterpolationPart::Expr(expr) = part {
+                        if self.check_expr(expr) == Ty::Unit {
+                            self.error(expr.span(), "Cannot interpolate a value of type `unit`");
+                        }
+                    }
+                }
+                Ty::Str
+            }
+        }
+    }
+
+    /// Ranges evaluate to a `vec` of their integers.
+    fn check_range(&mut self, start: &Expr, end: &Expr, step: Option<&Expr>) -> Ty {
+        let l = self.check_expr(start);
+        let r = self.check_expr(end);
+        for (ty, side) in [(&l, start), (&r, end)] {
+            if !compatible(&Ty::Int(None), ty) {
+                self.error(
+                    side.span(),
+                    format!("Range bounds must be integers, found `{ty}`"),
+                );
+            }
+        }
+        if let Some(step) = step {
+            let ty = self.check_expr(step);
+            if !compatible(&Ty::Int(None), &ty) {
+                self.error(
+                    step.span(),
+                    format!("Range step must be an integer, found `{ty}`"),
+                );
+            }
+        }
+        Ty::Vec(Box::new(if matches!(l, Ty::Int(Some(_))) { l } else { r }))
+    }
+
+    fn check_binary(&mut self, left: &Expr, op: BinaryOp, right: &Expr, span: Span) -> Ty {
+        use BinaryOp::*;
+        let l = self.check_expr(left);
+        let r = self.check_expr(right);
+        let numeric = |t: &Ty| t.is_unknown() || matches!(t, Ty::Int(_) | Ty::Float(_));
+        match op {
+            LogicalAnd | LogicalOr => {
+                for (ty, side) in [(&l, left), (&r, right)] {
+                    if !compatible(&Ty::Bool, ty) {
+                        self.error(
+                            side.span(),
+                            format!("Logical operators expect `bool` operands, found `{ty}`"),
+                        );
+                    }
+                }
+                Ty::Bool
+            }
+            Equal | NotEqual => {
+                if !compatible(&l, &r) {
+                    self.error(span, format!("Cannot compare `{l}` with `{r}`"));
+                }
+                Ty::Bool
+            }
+            Less | LessEqual | Greater | GreaterEqual => {
+                if !numeric(&l) || !numeric(&r) || !compatible(&l, &r) {
+                    self.error(span, format!("Cannot order `{l}` and `{r}`"));
+                }
+                Ty::Bool
+            }
+            Add | Subtract | Multiply | Divide | Modulo => {
+                if matches!(op, Add) && (l == Ty::Str || r == Ty::Str) && compatible(&l, &r) {
+                    return Ty::Str;
+                }
+                if !numeric(&l) || !numeric(&r) || !compatible(&l, &r) {
+                    self.error(
+                        span,
+                        format!("Numeric operators expect matching types, got `{l}` and `{r}`"),
+                    );
+                    return Ty::Unknown;
+                }
+                if matches!(l, Ty::Int(None) | Ty::Float(None)) || l.is_unknown() {
+                    r
+                } else {
+                    l
+                }
+            }
+            BitAnd | BitOr | BitXor => {
+                let bits = |t: &Ty| t.is_unknown() || matches!(t, Ty::Int(_) | Ty::Bool);
+                if !bits(&l) || !bits(&r) || !compatible(&l, &r) {
+                    self.error(
+                        span,
+                        format!("Bitwise operators expect matching integer or `bool` types, got `{l}` and `{r}`"),
+                    );
+                    return Ty::Unknown;
+                }
+                if matches!(l, Ty::Int(None)) || l.is_unknown() {
+                    r
+                } else {
+                    l
+                }
+            }
+            ShiftLeft | ShiftRight => {
+                // The shift amount may be any integer type.
+                for (ty, side) in [(&l, left), (&r, right)] {
+                    if !compatible(&Ty::Int(None), ty) {
+                        self.error(
+                            side.span(),
+                            format!("Shift operands must be integers, found `{ty}`"),
+                        );
+                    }
+                }
+                l
+            }
+        }
+    }
+
+    fn check_try(&mut self, expr: &Expr, span: Span) -> Ty {
+        let ty = self.check_expr(expr);
+        let declared = self.returns.last().and_then(|r| r.declared.clone());
+        let (inner, kind) = match ty {
+            Ty::Result(ok, _) => (*ok, "result"),
+            Ty::Option(inner) => (*inner, "option"),
+            Ty::Unknown | Ty::Param(_) => return Ty::Unknown,
+            other => {
+                self.error(
+                    span,
+                    format!("The `?` operator can only be applied to `result` or `option`, found `{other}`"),
+                );
+                return Ty::Unknown;
+            }
+        };
+        match (kind, declared) {
+            (_, None) => {}
+            (_, Some(Ty::Unknown | Ty::Param(_))) => {}
+            ("result", Some(Ty::Result(..))) | ("option", Some(Ty::Option(_))) => {}
+            (kind, Some(other)) => self.error(
+                span,
+                format!("The `?` operator on `{kind}` requires the function to return `{kind}`, but it returns `{other}`"),
+            ),
+        }
+        inner
+    }
+
+    fn check_args(&mut self, args: &[Expr]) -> Vec<Ty> {
+        args.iter().map(|a| self.check_expr(a)).collect()
+    }
+
+    /// Check argument count and types against `params`, binding `generics` along the way.
+    /// Returns the generic bindings so the caller can instantiate the return type.
+    fn check_signature_args(
+        &mut self,
+        what: &str,
+        params: &[Ty],
+        args: &[Expr],
+        arg_tys: &[Ty],
+        generics: &[String],
+        mut bindings: HashMap<String, Ty>,
+        span: Span,
+    ) -> HashMap<String, Ty> {
+        if params.len() != args.len() {
+            self.error(
+                span,
+                format!(
+                    "{what} expects {}, got {}",
+                    plural(params.len(), "argument"),
+                    args.len()
+                ),
+            );
+            return bindings;
+        }
+        for (index, ((param, arg), actual)) in params
+            .iter()
+            .zip(args.iter())
+            .zip(arg_tys.iter())
+            .enumerate()
+        {
+            match unify(param, actual, generics, &mut bindings) {
+                Ok(()) => {}
+                Err(Some((name, bound))) => self.error(
+                    arg.span(),
+                    format!(
+                        "Argument {} of {what}: type parameter `{name}` is `{bound}` here, found `{actual}`",
+                        index + 1
+                    ),
+                ),
+                Err(None) => {
+                    let expected = substitute(param, generics, &bindings);
+                    self.error(
+                        arg.span(),
+                        format!(
+                            "Argument {} of {what}: expected `{expected}`, found `{actual}`",
+                            index + 1
+                        ),
+                    );
+                }
+            }
+        }
+        bindings
+    }
+
+    fn explicit_type_args(
+        &mut self,
+        what: &str,
+        generics: &[String],
+        type_args: &[TypeExpr],
+        span: Span,
+    ) -> HashMap<String, Ty> {
+        let mut bindings = HashMap::new();
+        if type_args.is_empty() {
+            return bindings;
+        }
+        let resolved = type_args
+            .iter()
+            .map(|t| self.resolve(t))
+            .collect::<Vec<_>>();
+        if resolved.len() != generics.len() {
+            self.error(
+                span,
+                format!(
+                    "{what} expects {}, got {}",
+                    plural(generics.len(), "type argument"),
+                    resolved.len()
+                ),
+            );
+            return bindings;
+        }
+        for (name, ty) in generics.iter().zip(resolved) {
+            bindings.insert(name.clone(), ty);
+        }
+        bindings
+    }
+
+    fn instantiate_signature(
+        &mut self,
+        sig: &FunctionSignature,
+        outer_generics: &[String],
+    ) -> (Vec<String>, Vec<Ty>, Option<Ty>) {
+        let saved = std::mem::replace(
+            &mut self.generics,
+            outer_generics
+                .iter()
+                .cloned()
+                .chain(sig.type_params.iter().map(|p| p.name.clone()))
+                .collect(),
+        );
+        // Errors in the signature itself are reported when the item is checked.
+        let error_count = self.errors.len();
+        let (params, ret) = self.signature_types(sig);
+        self.errors.truncate(error_count);
+        let generics = std::mem::replace(&mut self.generics, saved);
+        (generics, params, ret)
+    }
+
+    fn call_result(
+        sig: &FunctionSignature,
+        ret: Option<Ty>,
+        generics: &[String],
+        bindings: &HashMap<String, Ty>,
+    ) -> Ty {
+        let ret = ret
+            .map(|ty| substitute(&ty, generics, bindings))
+            .unwrap_or(Ty::Unknown);
+        if sig.is_async {
+            Ty::Future(Box::new(ret))
+        } else {
+            ret
+        }
+    }
+
+    fn check_call(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        type_args: &[TypeExpr],
+        span: Span,
+    ) -> Ty {
+        let arg_tys = self.check_args(args);
+        if let Expr::Identifier { name, .. } = callee {
+            if !self.is_local(name) {
+                if let Some(sig) = self.functions.get(name.as_str()).copied() {
+                    let what = format!("function `{name}`");
+                    let (generics, params, ret) = self.instantiate_signature(sig, &[]);
+                    let bindings = self.explicit_type_args(
+                        &format!("Function `{name}`"),
+                        &generics,
+                        type_args,
+                        span,
+                    );
+                    let bindings = self.check_signature_args(
+                        &what, &params, args, &arg_tys, &generics, bindings, span,
+                    );
+                    self.check_bounds(&what, &sig.type_params, &bindings, span);
+                    return Self::call_result(sig, ret, &generics, &bindings);
+                }
+            }
+            return Ty::Unknown;
+        }
+        if let Expr::Access {
+            base,
+            member,
+            op: AccessOperator::Path,
+            ..
+        } = callee
+        {
+            if let Some(owner) = self.type_path(base) {
+                return self.check_path_call(&owner, member, args, &arg_tys, type_args, span);
+            }
+            return Ty::Unknown;
+        }
+        if let Expr::Access {
+            base,
+            member,
+            op: AccessOperator::Dot,
+            ..
+        } = callee
+        {
+            if let Expr::Identifier { name, .. } = &**base {
+                if !self.is_local(name) {
+                    return self.builtin_module_call(name, member, &arg_tys);
+                }
+            }
+        }
+        self.check_expr(callee);
+        Ty::Unknown
+    }
+
+    fn check_path_call(
+        &mut self,
+        owner: &str,
+        member: &str,
+        args: &[Expr],
+        arg_tys: &[Ty],
+        type_args: &[TypeExpr],
+        span: Span,
+    ) -> Ty {
+        if let Some(def) = self.enums.get(owner).copied() {
+            return self.check_variant_call(def, member, args, arg_tys, type_args, span);
+        }
+        if let Some(info) = self
+            .methods
+            .get(owner)
+            .and_then(|methods| methods.get(member))
+        {
+            let sig = info.sig;
+            let impl_generics = info.generics.clone();
+            let what = format!("`{owner}::{member}`");
+            let (generics, params, ret) = self.instantiate_signature(sig, &impl_generics);
+            let bindings = self.check_signature_args(
+                &what,
+                &params,
+                args,
+                arg_tys,
+                &generics,
+                HashMap::new(),
+                span,
+            );
+            self.check_bounds(&what, &sig.type_params, &bindings, span);
+            return Self::call_result(sig, ret, &generics, &bindings);
+        }
+        if let Some(def) = self.traits.get(owner).copied() {
+            let Some(sig) = def
+                .methods
+                .iter()
+                .map(|m| &m.signature)
+                .find(|sig| &sig.name == member)
+            else {
+                self.error(span, format!("Trait `{owner}` has no method `{member}`"));
+                return Ty::Unknown;
+            };
+            let trait_generics = def
+                .type_params
+                .iter()
+                .map(|p| p.name.clone())
+                .collect::<Vec<_>>();
+            let what = format!("`{owner}::{member}`");
+            let (generics, params, ret) = self.instantiate_signature(sig, &trait_generics);
+            let bindings = self.check_signature_args(
+                &what,
+                &params,
+                args,
+                arg_tys,
+                &generics,
+                HashMap::new(),
+                span,
+            );
+            return Self::call_result(sig, ret, &generics, &bindings);
+        }
+        if self.structs.contains_key(owner) {
+            self.error(
+                span,
+                format!("No function or associated item named `{member}` found for `{owner}`"),
+            );
+        }
+        Ty::Unknown
+    }
+
+    /// `result.ok(x)`, `option.some(x)` and friends, so `?` and return checks see through them.
+    fn builtin_module_call(&self, module: &str, member: &str, arg_tys: &[Ty]) -> Ty {
+        let first = || Box::new(arg_tys.first().cloned().unwrap_or(Ty::Unknown));
+        match (module, member) {
+            ("result", "ok") => Ty::Result(first(), Box::new(Ty::Unknown)),
+            ("result", "err") => Ty::Result(Box::new(Ty::Unknown), first()),
+            ("option", "some") => Ty::Option(first()),
+            ("option", "none") => Ty::Option(Box::new(Ty::Unknown)),
+            ("vec" | "set" | "map", _) => collection_call(member, arg_tys),
+            _ => Ty::Unknown,
+        }
+    }
+
+    /// Resolve an expression used as a type path (`Shape` in `Shape::Circle`).
+    fn type_path(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Identifier { name, .. } if !self.is_local(name) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    fn check_variant_call(
+        &mut self,
+        def: &'a EnumDef,
+        variant: &str,
+        args: &[Expr],
+        arg_tys: &[Ty],
+        type_args: &[TypeExpr],
+        span: Span,
+    ) -> Ty {
+        let Some(var) = def.variants.iter().find(|v| v.name == variant) else {
+            self.error(
+                span,
+                format!("Enum `{}` has no variant `{variant}`", def.name),
+            );
+            return Ty::Unknown;
+        };
+        let generics = def
+            .type_params
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+        let saved = std::mem::replace(&mut self.generics, generics.clone());
+        let error_count = self.errors.len();
+        let params = var
+            .payload
+            .iter()
+            .map(|t| self.resolve(t))
+            .collect::<Vec<_>>();
+        self.errors.truncate(error_count);
+        self.generics = saved;
+        let what = format!("Enum `{}`", def.name);
+        let bindings = self.explicit_type_args(&what, &generics, type_args, span);
+        let bindings = self.check_signature_args(
+            &format!("enum constructor `{}::{variant}`", def.name),
+            &params,
+            args,
+            arg_tys,
+            &generics,
+            bindings,
+            span,
+        );
+        Ty::Named {
+            name: def.name.clone(),
+            args: generics
+                .iter()
+                .map(|g| bindings.get(g).cloned().unwrap_or(Ty::Unknown))
+                .collect(),
+        }
+    }
+
+    fn check_access(&mut self, base: &Expr, member: &str, op: AccessOperator, span: Span) -> Ty {
+        if op == AccessOperator::Path {
+            if let Some(owner) = self.type_path(base) {
+                if let Some(def) = self.enums.get(owner.as_str()).copied() {
+                    if !def.variants.iter().any(|v| v.name == member) {
+                        self.error(span, format!("Enum `{owner}` has no variant `{member}`"));
+                        return Ty::Unknown;
+                    }
+                    return Ty::Named {
+                        name: owner,
+                        args: Vec::new(),
+                    };
+                }
+            }
+            return Ty::Unknown;
+        }
+        let base_ty = self.check_expr(base);
+        match &base_ty {
+            Ty::Named { name, args } => {
+                let Some(def) = self.structs.get(name.as_str()).copied() else {
+                    return Ty::Unknown;
+                };
+                if let Some(field) = def.fields.iter().find(|f| f.name == member) {
+                    return self.field_type(def, field, args);
+                }
+                if self.lookup_method(name, member).is_none() {
+                    self.error(span, format!("Struct `{name}` has no field `{member}`"));
+                }
+                Ty::Unknown
+            }
+            Ty::Tuple(elements) => match member.parse::<usize>() {
+                Ok(index) if index < elements.len() => elements[index].clone(),
+                Ok(_) => {
+                    self.error(
+                        span,
+                        format!("Tuple index `{member}` out of bounds for `{base_ty}`"),
+                    );
+                    Ty::Unknown
+                }
+                Err(_) => Ty::Unknown,
+            },
+            _ => Ty::Unknown,
+        }
+    }
+
+    fn field_type(&mut self, def: &StructDef, field: &StructField, args: &[Ty]) -> Ty {
+        let generics = def
+            .type_params
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+        let saved = std::mem::replace(&mut self.generics, generics.clone());
+        let error_count = self.errors.len();
+        let ty = self.resolve(&field.ty);
+        self.errors.truncate(error_count);
+        self.generics = saved;
+        let bindings = generics
+            .iter()
+            .cloned()
+            .zip(args.iter().cloned())
+            .collect::<HashMap<_, _>>();
+        substitute(&ty, &generics, &bindings)
+    }
+
+    fn lookup_method(&self, type_name: &str, method: &str) -> Option<&MethodInfo<'a>> {
+        self.methods.get(type_name).and_then(|m| m.get(method))
+    }
+
+    fn check_method_call(&mut self, object: &Expr, method: &str, args: &[Expr], span: Span) -> Ty {
+        if let Expr::Identifier { name, .. } = object {
+            if !self.is_local(name) {
+                // `Type::member(...)` and `module.member(...)` parse to the same node.
+                let arg_tys = self.check_args(args);
+                let is_type = self.structs.contains_key(name.as_str())
+                    || self.enums.contains_key(name.as_str())
+                    || self.traits.contains_key(name.as_str())
+                    || self.methods.contains_key(name);
+                if is_type {
+                    return self.check_path_call(name, method, args, &arg_tys, &[], span);
+                }
+                return self.builtin_module_call(name, method, &arg_tys);
+            }
+        }
+        let object_ty = self.check_expr(object);
+        let arg_tys = self.check_args(args);
+        if let Ty::Array(..) = &object_ty {
+            // Arrays have no module; their methods are the collection builtins.
+            return collection_call(method, &[vec![object_ty.clone()], arg_tys].concat());
+        }
+        let Some(name) = &impl_key(&object_ty) else {
+            return Ty::Unknown;
+        };
+        let is_struct = self.structs.contains_key(name.as_str());
+        // Other types may still have built-in methods the checker does not model.
+        let is_user_type = is_struct || self.enums.contains_key(name.as_str());
+        if !is_user_type && self.lookup_method(name, method).is_none() {
+            if let Ty::Option(_) | Ty::Result(..) = &object_ty {
+                // `r.ok()` is not `result.ok(r)`: combinators are methods only.
+                return combinator_call(&object_ty, method, &arg_tys);
+            }
+            let receiver_and_args = [vec![object_ty.clone()], arg_tys].concat();
+            return self.builtin_module_call(name, method, &receiver_and_args);
+        }
+        let Some(info) = self.lookup_method(name, method) else {
+            // A struct field holding a closure can still be called with method syntax.
+            let is_field = is_struct
+                && self.structs[name.as_str()]
+                    .fields
+                    .iter()
+                    .any(|f| f.name == method);
+            if !is_field {
+                self.error(
+                    span,
+                    format!("No method named `{method}` found for type `{object_ty}`"),
+                );
+            }
+            return Ty::Unknown;
+        };
+        if let Some(traits) = self
+            .ambiguous_methods
+            .get(&(name.clone(), method.to_string()))
+        {
+            let mut traits = traits.clone();
+            traits.sort_unstable();
+            self.error(
+                span,
+                format!(
+                    "Ambiguous method `{method}` on `{name}`: it is provided by traits `{}`; call it as `{}::{method}(...)`",
+                    traits.join("`, `"),
+                    traits[0]
+                ),
+            );
+            return Ty::Unknown;
+        }
+        let sig = info.sig;
+        let has_self = info.has_self;
+        let impl_generics = info.generics.clone();
+        let what = format!("method `{method}`");
+        if !has_self {
+            self.error(
+                span,
+                format!("`{name}::{method}` is an associated function, not a method; call it as `{name}::{method}(...)`"),
+            );
+            return Ty::Unknown;
+        }
+        let (generics, params, ret) = self.instantiate_signature(sig, &impl_generics);
+        let bindings = self.check_signature_args(
+            &what,
+            &params[1..],
+            args,
+            &arg_tys,
+            &generics,
+            HashMap::new(),
+            span,
+        );
+        self.check_bounds(&what, &sig.type_params, &bindings, span);
+        Self::call_result(sig, ret, &generics, &bindings)
+    }
+
+    fn check_struct_literal(
+        &mut self,
+        path: &Expr,
+        type_args: &[TypeExpr],
+        fields: &[StructLiteralField],
+        span: Span,
+    ) -> Ty {
+        let field_tys = fields
+            .iter()
+            .map(|f| self.check_expr(&f.expr))
+            .collect::<Vec<_>>();
+        let Some(name) = path_name(path) else {
+            return Ty::Unknown;
+        };
+        let Some(def) = self.structs.get(name.as_str()).copied() else {
+            if !name.contains("::") && !self.imported.contains(&name) {
+                self.error(path.span(), format!("Unknown struct `{name}`"));
+            }
+            return Ty::Unknown;
+        };
+        let generics = def
+            .type_params
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+        if generics.is_empty() && !type_args.is_empty() {
+            self.error(
+                span,
+                format!("Struct `{name}` does not take type arguments"),
+            );
+        }
+        let mut bindings =
+            self.explicit_type_args(&format!("Struct `{name}`"), &generics, type_args, span);
+        let mut seen = HashSet::new();
+        for (field, actual) in fields.iter().zip(field_tys.iter()) {
+            if !seen.insert(field.name.as_str()) {
+                self.error(
+                    field.span,
+                    format!("Duplicate field `{}` in struct literal", field.name),
+                );
+                continue;
+            }
+            let Some(decl) = def.fields.iter().find(|f| f.name == field.name) else {
+                self.error(
+                    field.span,
+                    format!("Struct `{name}` has no field `{}`", field.name),
+                );
+                continue;
+            };
+            let saved = std::mem::replace(&mut self.generics, generics.clone());
+            let error_count = self.errors.len();
+            let expected = self.resolve(&decl.ty);
+            self.errors.truncate(error_count);
+            self.generics = saved;
+            if let Err(conflict) = unify(&expected, actual, &generics, &mut bindings) {
+                let expected = match conflict {
+                    Some((_, bound)) => bound,
+                    None => substitute(&expected, &generics, &bindings),
+                };
+                self.mismatch(field.expr.span(), &expected, actual);
+            }
+        }
+        for decl in &def.fields {
+            if !seen.contains(decl.name.as_str()) {
+                self.error(
+                    span,
+                    format!("Missing field `{}` for struct `{name}`", decl.name),
+                );
+            }
+        }
+        self.check_bounds(
+            &format!("struct `{name}`"),
+            &def.type_params,
+            &bindings,
+            span,
+        );
+        Ty::Named {
+            name: name.clone(),
+            args: generics
+                .iter()
+                .map(|g| bindings.get(g).cloned().unwrap_or(Ty::Unknown))
+                .collect(),
+        }
+    }
+}
+
+/// Result types of the higher-order builtins of `vec`, `set` and `map`;
+/// `arg_tys` starts with the collection. Anything else is left unknown.
+fn collection_call(member: &str, arg_tys: &[Ty]) -> Ty {
+    let item = |index: usize| match arg_tys.get(index) {
+        Some(Ty::Vec(item) | Ty::Set(item) | Ty::Array(item, _) | Ty::Slice(item)) => {
+            (**item).clone()
+        }
+        Some(Ty::Map(key, value)) => Ty::Tuple(vec![(**key).clone(), (**value).clone()]),
+        _ => Ty::Unknown,
+    };
+    let index = || Ty::Int(Some("i64".to_string()));
+    match member {
+        "filter" | "sort_by" => Ty::Vec(Box::new(item(0))),
+        "find" | "min_by" | "max_by" => Ty::Option(Box::new(item(0))),
+        "any" | "all" => Ty::Bool,
+        "count" => index(),
+        "join" => Ty::Str,
+        "enumerate" => Ty::Vec(Box::new(Ty::Tuple(vec![index(), item(0)]))),
+        "zip" => Ty::Vec(Box::new(Ty::Tuple(vec![item(0), item(1)]))),
+        "group_by" => Ty::Map(Box::new(Ty::Unknown), Box::new(Ty::Vec(Box::new(item(0))))),
+        "fold" => arg_tys.get(1).cloned().unwrap_or(Ty::Unknown),
+        "sum" => match item(0) {
+            ty @ (Ty::Int(_) | Ty::Float(_)) => ty,
+            _ => Ty::Unknown,
+        },
+        "map" => Ty::Vec(Box::new(Ty::Unknown)),
+        _ => Ty::Unknown,
+    }
+}
+
+/// Result types of the combinator methods on an `option` or `result`
+/// receiver. Callbacks are not typed, so what `map` and friends produce is
+/// left unknown.
+fn combinator_call(receiver: &Ty, member: &str, arg_tys: &[Ty]) -> Ty {
+    let unknown = || Box::new(Ty::Unknown);
+    match (receiver, member) {
+        (Ty::Option(_), "is_some" | "is_none") | (Ty::Result(..), "is_ok" | "is_err") => Ty::Bool,
+        (
+            Ty::Option(value) | Ty::Result(value, _),
+            "unwrap" | "expect" | "unwrap_or" | "unwrap_or_else",
+        ) => (**value).clone(),
+        (Ty::Option(_), "map" | "and_then") => Ty::Option(unknown()),
+        (Ty::Option(_), "or_else") => receiver.clone(),
+        (Ty::Option(value), "ok_or") => Ty::Result(
+            value.clone(),
+            Box::new(arg_tys.first().cloned().unwrap_or(Ty::Unknown)),
+        ),
+        (Ty::Option(value), "flatten") => match &**value {
+            inner @ Ty::Option(_) => inner.clone(),
+            _ => Ty::Option(unknown()),
+        },
+        (Ty::Result(_, error), "map" | "and_then") => Ty::Result(unknown(), error.clone()),
+        (Ty::Result(value, _), "map_err" | "or_else") => Ty::Result(value.clone(), unknown()),
+        (Ty::Result(value, _), "ok") => Ty::Option(value.clone()),
+        (Ty::Result(_, error), "err") => Ty::Option(error.clone()),
+        (Ty::Result(value, error), "flatten") => match &**value {
+            Ty::Result(inner, _) => Ty::Result(inner.clone(), error.clone()),
+            _ => Ty::Result(unknown(), error.clone()),
+        },
+        _ => Ty::Unknown,
+    }
+}
+
+fn type_key(ty: &TypeExpr) -> Option<String> {
+    match ty {
+        TypeExpr::Named(named) => named.segments.last().map(|s| s.name.clone()),
+        _ => None,
+    }
+}
+
+/// The name an `impl` for `ty` is written against, when `ty` is concrete.
+fn impl_key(ty: &Ty) -> Option<String> {
+    let name = match ty {
+        Ty::Int(Some(name)) | Ty::Float(Some(name)) | Ty::Named { name, .. } => name.as_str(),
+        Ty::Unit => "unit",
+        Ty::Bool => "bool",
+        Ty::Str => "str",
+        Ty::Char => "char",
+        Ty::Vec(_) => "vec",
+        Ty::Set(_) => "set",
+        Ty::Map(..) => "map",
+        Ty::Option(_) => "option",
+        Ty::Result(..) => "result",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
@@ -1,6 +1,6 @@
 use crate::ast::*;
 use crate::span::Span;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
 pub struct ValidationError {
@@ -11,6 +11,21 @@ pub struct ValidationError {
 #[derive(Default)]
 struct Scope {
     names: HashMap<String, Span>,
+    /// Names bound by `const` declarations and `static` items.
+    readonly: HashSet<String>,
+    /// Variants of the enums declared in the file, plus `option` and `result`;
+    /// only the global scope has these.
+    enums: HashMap<String, Vec<String>>,
+}
+
+impl Scope {
+    fn with_params<'a>(params: impl IntoIterator<Item = (&'a String, Span)>) -> Self {
+        let mut scope = Scope::default();
+        for (name, span) in params {
+            scope.names.insert(name.clone(), span);
+        }
+        scope
+    }
 }
 
 pub fn validate_file(file: &File) -> Vec<ValidationError> {
@@ -28,39 +43,279 @@ pub fn validate_file(file: &File) -> Vec<ValidationError> {
             }
         }
     }
-    let mut scopes: Vec<Scope> = vec![Scope::default()];
+    validate_type_aliases(&file.items, &mut errors);
+    validate_trait_impls(&file.items, &mut errors);
+    let mut scopes: Vec<Scope> = vec![declare_globals(&file.items, &mut errors)];
     for item in &file.items {
         validate_item(item, &mut scopes, &mut errors);
     }
     errors
 }
 
+/// Alias names must not clash with other types, and expanding an alias must
+/// never lead back to itself.
+fn validate_type_aliases(items: &[Item], errors: &mut Vec<ValidationError>) {
+    let mut types: HashSet<&str> = HashSet::new();
+    for item in items {
+        match item {
+            Item::Struct(def) => {
+                types.insert(&def.name);
+            }
+            Item::Enum(def) => {
+                types.insert(&def.name);
+            }
+            _ => {}
+        }
+    }
+    let mut aliases: HashMap<&str, &TypeAliasDef> = HashMap::new();
+    for item in items {
+        let Item::TypeAlias(alias) = item else {
+            continue;
+        };
+        if types.contains(alias.name.as_str()) || aliases.contains_key(alias.name.as_str()) {
+            errors.push(ValidationError {
+                message: format!("Duplicate type name `{}`", alias.name),
+                span: alias.span,
+            });
+            continue;
+        }
+        aliases.insert(&alias.name, alias);
+    }
+    for item in items {
+        let Item::TypeAlias(alias) = item else {
+            continue;
+        };
+        if alias_reaches(&alias.target, alias, &aliases, &mut Vec::new()) {
+            errors.push(ValidationError {
+                message: format!("Type alias `{}` refers to itself", alias.name),
+                span: alias.span,
+            });
+        }
+    }
+}
+
+/// Every `impl` of a trait declared in this file must define the trait's
+/// methods that have no default body.
+fn validate_trait_impls(items: &[Item], errors: &mut Vec<ValidationError>) {
+    let traits: HashMap<&str, &TraitDef> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Trait(def) => Some((def.name.as_str(), def)),
+            _ => None,
+        })
+        .collect();
+    for item in items {
+        let Item::Impl(imp) = item else {
+            continue;
+        };
+        let Some(trait_name) = imp.trait_type.as_ref().and_then(type_expr_to_name) else {
+            continue;
+        };
+        let Some(def) = traits.get(trait_name.as_str()) else {
+            continue;
+        };
+        for method in &def.methods {
+            let name = &method.signature.name;
+            let implemented = imp.methods.iter().any(|m| &m.signature.name == name);
+            if method.default.is_none() && !implemented {
+                errors.push(ValidationError {
+                    message: format!(
+                        "Missing method `{}` in impl of `{}` for `{}`",
+                        name,
+                        trait_name,
+                        type_expr_to_name(&imp.target).unwrap_or_default()
+                    ),
+                    span: imp.span,
+                });
+            }
+        }
+    }
+}
+
+/// Whether `ty`, with every alias in it expanded, mentions `target`.
+fn alias_reaches<'a>(
+    ty: &TypeExpr,
+    target: &TypeAliasDef,
+    aliases: &HashMap<&str, &'a TypeAliasDef>,
+    visited: &mut Vec<&'a str>,
+) -> bool {
+    match ty {
+        TypeExpr::Named(named) => named.segments.iter().any(|segment| {
+            if segment
+                .generics
+                .iter()
+                .any(|g| alias_reaches(g, target, aliases, visited))
+            {
+                return true;
+            }
+            if segment.name == target.name {
+                return true;
+            }
+            match aliases.get(segment.name.as_str()) {
+                Some(alias) if !visited.contains(&alias.name.as_str()) => {
+                    visited.push(&alias.name);
+                    alias_reaches(&alias.target, target, aliases, visited)
+                }
+                _ => false,
+            }
+        }),
+        TypeExpr::Array { element, .. }
+        | TypeExpr::Slice { element, .. }
+        | TypeExpr::Reference { inner: element, .. } => {
+            alias_reaches(element, target, aliases, visited)
+        }
+        TypeExpr::Tuple { elements, .. } => elements
+            .iter()
+            .any(|e| alias_reaches(e, target, aliases, visited)),
+    }
+}
+
+/// Collects the module-level `const`/`static`/`var` items into the outermost
+/// scope, so function bodies see them wherever they are declared.
+fn declare_globals(items: &[Item], errors: &mut Vec<ValidationError>) -> Scope {
+    let mut scope = Scope::default();
+    let mut functions: HashMap<&str, Span> = HashMap::new();
+    for item in items {
+        match item {
+            Item::Function(func) => {
+                functions.insert(&func.signature.name, func.signature.span);
+            }
+            Item::Enum(def) => {
+                let variants = def.variants.iter().map(|v| v.name.clone()).collect();
+                scope.enums.insert(def.name.clone(), variants);
+            }
+            _ => {}
+        }
+    }
+    for (name, variants) in [("option", ["Some", "None"]), ("result", ["Ok", "Err"])] {
+        let variants = variants.iter().map(|v| v.to_string()).collect();
+        scope.enums.entry(name.to_string()).or_insert(variants);
+    }
+    for item in items {
+        let Item::Global(global) = item else {
+            continue;
+        };
+        if scope.names.contains_key(&global.name) {
+            errors.push(ValidationError {
+                message: format!("Duplicate top-level name `{}`", global.name),
+                span: global.span,
+            });
+            continue;
+        }
+        if functions.contains_key(global.name.as_str()) {
+            errors.push(ValidationError {
+                message: format!("Duplicate top-level name `{}`", global.name),
+                span: global.span,
+            });
+        }
+        scope.names.insert(global.name.clone(), global.span);
+        if global.kind != GlobalKind::Var {
+            scope.readonly.insert(global.name.clone());
+        }
+    }
+    scope
+}
+
+/// Global initializers may only use literals, operators and globals declared
+/// earlier in the file.
+fn validate_const_expr(
+    expr: &Expr,
+    global: &GlobalDef,
+    declared: &HashSet<String>,
+    errors: &mut Vec<ValidationError>,
+) {
+    match expr {
+        Expr::Literal(_) => {}
+        Expr::Identifier { name, span } => {
+            if !declared.contains(name) {
+                errors.push(ValidationError {
+                    message: format!(
+                        "Initializer of `{}` can only refer to `const` and `static` items declared before it, found `{}`",
+                        global.name, name
+                    ),
+                    span: *span,
+                });
+            }
+        }
+        Expr::Unary {
+            op: UnaryOp::Negate | UnaryOp::Not,
+            expr,
+            ..
+        } => validate_const_expr(expr, global, declared, errors),
+        Expr::Binary { left, right, .. } => {
+            validate_const_expr(left, global, declared, errors);
+            validate_const_expr(right, global, declared, errors);
+        }
+        other => errors.push(ValidationError {
+            message: format!(
+                "Initializer of `{}` must be a constant expression",
+                global.name
+            ),
+            span: other.span(),
+        }),
+    }
+}
+
 fn validate_item(item: &Item, scopes: &mut Vec<Scope>, errors: &mut Vec<ValidationError>) {
     match item {
         Item::Function(func) => {
             validate_params_not_self(func, errors);
-            validate_block(&func.body, scopes, 0, func.signature.is_async, errors)
+            validate_function_body(func, scopes, errors);
+        }
+        Item::Trait(def) => {
+            for method in &def.methods {
+                let Some(body) = &method.default else {
+                    continue;
+                };
+                let params = method.signature.params.iter().map(|p| (&p.name, p.span));
+                scopes.push(Scope::with_params(params));
+                validate_block(body, scopes, &[], method.signature.is_async, errors);
+                scopes.pop();
+            }
         }
-        Item::Struct(_) | Item::Enum(_) | Item::Trait(_) | Item::ExternFunction(_) => {}
+        Item::Struct(_) | Item::Enum(_) | Item::ExternFunction(_) | Item::TypeAlias(_) => {}
         Item::Impl(imp) => {
             for method in &imp.methods {
                 validate_impl_method_params(imp, method, errors);
-                validate_block(&method.body, scopes, 0, method.signature.is_async, errors);
+                validate_function_body(method, scopes, errors);
             }
         }
+        Item::Global(global) => {
+            let globals = &scopes[0];
+            let declared: HashSet<String> = globals
+                .names
+                .iter()
+                .filter(|(name, span)| {
+                    span.start < global.span.start && globals.readonly.contains(*name)
+                })
+                .map(|(name, _)| name.clone())
+                .collect();
+            validate_const_expr(&global.value, global, &declared, errors);
+        }
     }
 }
 
+fn validate_function_body(
+    func: &Function,
+    scopes: &mut Vec<Scope>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let params = func.signature.params.iter().map(|p| (&p.name, p.span));
+    scopes.push(Scope::with_params(params));
+    validate_block(&func.body, scopes, &[], func.signature.is_async, errors);
+    scopes.pop();
+}
+
 fn validate_block(
     block: &Block,
     scopes: &mut Vec<Scope>,
-    loop_depth: usize,
+    loops: &[LoopFrame],
     in_async: bool,
     errors: &mut Vec<ValidationError>,
 ) {
     scopes.push(Scope::default());
     for stmt in &block.statements {
-        validate_stmt(stmt, scopes, loop_depth, in_async, errors);
+        validate_stmt(stmt, scopes, loops, in_async, errors);
     }
     scopes.pop();
 }
@@ -68,52 +323,63 @@ fn validate_block(
 fn validate_stmt(
     stmt: &Stmt,
     scopes: &mut Vec<Scope>,
-    loop_depth: usize,
+    loops: &[LoopFrame],
     in_async: bool,
     errors: &mut Vec<ValidationError>,
 ) {
     match stmt {
         Stmt::VarDecl(decl) => {
-            if scopes
-                .last()
-                .and_then(|s| s.names.get(&decl.name))
-                .is_some()
-            {
-                errors.push(ValidationError {
-                    message: format!("Duplicate binding `{}` in the same scope", decl.name),
-                    span: decl.span,
-                });
-            } else if let Some(scope) = scopes.last_mut() {
-                scope.names.insert(decl.name.clone(), decl.span);
+            check_irrefutable(&decl.pattern, "declaration", scopes, errors);
+            if let Some(globals) = scopes.first() {
+                check_or_patterns(&decl.pattern, &globals.enums, errors);
             }
-            validate_expr(&decl.value, scopes, loop_depth, in_async, errors)
+            for (name, _) in decl.pattern.bindings() {
+                let Some(scope) = scopes.last_mut() else {
+                    break;
+                };
+                if scope.names.contains_key(name) {
+                    errors.push(ValidationError {
+                        message: format!("Duplicate binding `{name}` in the same scope"),
+                        span: decl.span,
+                    });
+                    continue;
+                }
+                scope.names.insert(name.to_string(), decl.span);
+                if decl.kind == VarKind::Const {
+                    scope.readonly.insert(name.to_string());
+                }
+            }
+            validate_expr(&decl.value, scopes, loops, in_async, errors)
         }
-        Stmt::Expr(expr) => validate_expr(expr, scopes, loop_depth, in_async, errors),
+        Stmt::Expr(expr) => validate_expr(expr, scopes, loops, in_async, errors),
         Stmt::Return { value, .. } => {
             if let Some(expr) = value {
-                validate_expr(expr, scopes, loop_depth, in_async, errors);
-            }
-        }
-        Stmt::If(stmt) => {
-            validate_expr(&stmt.condition, scopes, loop_depth, in_async, errors);
-            validate_block(&stmt.then_branch, scopes, loop_depth, in_async, errors);
-            for (cond, block) in &stmt.else_if {
-                validate_expr(cond, scopes, loop_depth, in_async, errors);
-                validate_block(block, scopes, loop_depth, in_async, errors);
-            }
-            if let Some(block) = &stmt.else_branch {
-                validate_block(block, scopes, loop_depth, in_async, errors);
+                validate_expr(expr, scopes, loops, in_async, errors);
             }
         }
+        Stmt::If(stmt) => validate_if(stmt, scopes, loops, in_async, errors),
         Stmt::While {
-            condition, body, ..
+            label,
+            condition,
+            body,
+            ..
         } => {
-            validate_expr(condition, scopes, loop_depth, in_async, errors);
-            validate_block(body, scopes, loop_depth + 1, in_async, errors);
+            let inner = enter_loop(loops, label, false);
+            validate_conditional(condition, body, scopes, loops, &inner, in_async, errors);
         }
-        Stmt::For { iterable, body, .. } => {
-            validate_expr(iterable, scopes, loop_depth, in_async, errors);
-            validate_block(body, scopes, loop_depth + 1, in_async, errors);
+        Stmt::For {
+            label,
+            pattern,
+            iterable,
+            body,
+            ..
+        } => {
+            validate_expr(iterable, scopes, loops, in_async, errors);
+            check_irrefutable(pattern, "`for` loop", scopes, errors);
+            scopes.push(Scope::default());
+            bind_pattern(pattern, scopes, errors);
+            validate_block(body, scopes, &enter_loop(loops, label, false), in_async, errors);
+            scopes.pop();
         }
         Stmt::Switch(stmt) => {
             if stmt.arms.is_empty() {
@@ -122,77 +388,491 @@ fn validate_stmt(
                     span: stmt.span,
                 });
             }
-            validate_expr(&stmt.expr, scopes, loop_depth, in_async, errors);
+            validate_expr(&stmt.expr, scopes, loops, in_async, errors);
             for arm in &stmt.arms {
                 scopes.push(Scope::default());
                 bind_pattern(&arm.pattern, scopes, errors);
-                validate_expr(&arm.expr, scopes, loop_depth, in_async, errors);
+                if let Some(guard) = &arm.guard {
+                    validate_expr(guard, scopes, loops, in_async, errors);
+                }
+                validate_expr(&arm.expr, scopes, loops, in_async, errors);
                 scopes.pop();
             }
+            validate_switch_arms(stmt, &scopes[0].enums, errors);
         }
         Stmt::Try(stmt) => {
-            validate_block(&stmt.try_block, scopes, loop_depth, in_async, errors);
+            validate_block(&stmt.try_block, scopes, loops, in_async, errors);
             scopes.push(Scope::default());
             if let Some(binding) = &stmt.catch_binding {
                 if let Some(current) = scopes.last_mut() {
                     current.names.insert(binding.clone(), stmt.span);
                 }
             }
-            validate_block(&stmt.catch_block, scopes, loop_depth, in_async, errors);
+            validate_block(&stmt.catch_block, scopes, loops, in_async, errors);
             scopes.pop();
         }
-        Stmt::Block(block) => validate_block(block, scopes, loop_depth, in_async, errors),
-        Stmt::Unsafe { body, .. } => validate_block(body, scopes, loop_depth, in_async, errors),
+        Stmt::Block(block) => validate_block(block, scopes, loops, in_async, errors),
+        Stmt::Unsafe { body, .. } => validate_block(body, scopes, loops, in_async, errors),
         Stmt::Assembly(_) => {}
-        Stmt::Break(span) => {
-            if loop_depth == 0 {
-                errors.push(ValidationError {
-                    message: "break used outside of a loop".to_string(),
-                    span: *span,
-                });
+        Stmt::Break { label, value, span } => {
+            if let Some(value) = value {
+                validate_expr(value, scopes, loops, in_async, errors);
             }
-        }
-        Stmt::Continue(span) => {
-            if loop_depth == 0 {
-                errors.push(ValidationError {
-                    message: "continue used outside of a loop".to_string(),
-                    span: *span,
-                });
+            match loop_target(loops, label.as_deref(), "break", *span, errors) {
+                Some(target) if value.is_some() && !target.is_loop => {
+                    errors.push(ValidationError {
+                        message: "`break` with a value is only allowed inside `loop`".to_string(),
+                        span: *span,
+                    });
+                }
+                _ => {}
             }
         }
+        Stmt::Continue { label, span } => {
+            loop_target(loops, label.as_deref(), "continue", *span, errors);
+        }
     }
 }
 
-fn bind_pattern(pattern: &Pattern, scopes: &mut Vec<Scope>, errors: &mut Vec<ValidationError>) {
-    if let Some(scope) = scopes.last_mut() {
+/// A loop around the statement being validated.
+#[derive(Clone)]
+struct LoopFrame {
+    label: Option<String>,
+    /// Only a `loop` may be left with `break value`.
+    is_loop: bool,
+}
+
+/// `loops` with one more innermost loop.
+fn enter_loop(loops: &[LoopFrame], label: &Option<String>, is_loop: bool) -> Vec<LoopFrame> {
+    let mut inner = loops.to_vec();
+    inner.push(LoopFrame {
+        label: label.clone(),
+        is_loop,
+    });
+    inner
+}
+
+/// The loop a `break` or `continue` leaves: the one `label` names, or the
+/// innermost one. Reports a missing loop or an unknown label.
+fn loop_target<'a>(
+    loops: &'a [LoopFrame],
+    label: Option<&str>,
+    keyword: &str,
+    span: Span,
+    errors: &mut Vec<ValidationError>,
+) -> Option<&'a LoopFrame> {
+    let target = match label {
+        Some(label) => loops
+            .iter()
+            .rev()
+            .find(|frame| frame.label.as_deref() == Some(label)),
+        None => loops.last(),
+    };
+    if target.is_none() {
+        let message = match label {
+            Some(label) if !loops.is_empty() => format!("Unknown loop label `'{label}`"),
+            _ => format!("{keyword} used outside of a loop"),
+        };
+        errors.push(ValidationError { message, span });
+    }
+    target
+}
+
+fn validate_if(
+    stmt: &IfStmt,
+    scopes: &mut Vec<Scope>,
+    loops: &[LoopFrame],
+    in_async: bool,
+    errors: &mut Vec<ValidationError>,
+) {
+    let branches = std::iter::once((&stmt.condition, &stmt.then_branch))
+        .chain(stmt.else_if.iter().map(|(cond, block)| (cond, block)));
+    for (condition, block) in branches {
+        validate_conditional(condition, block, scopes, loops, loops, in_async, errors);
+    }
+    if let Some(block) = &stmt.else_branch {
+        validate_block(block, scopes, loops, in_async, errors);
+    }
+}
+
+/// Validates an `if` or `while` condition, evaluated inside `loops`, and the
+/// `body` it guards, which runs inside `body_loops` and sees the names a
+/// `let` condition binds.
+fn validate_conditional(
+    condition: &Expr,
+    body: &Block,
+    scopes: &mut Vec<Scope>,
+    loops: &[LoopFrame],
+    body_loops: &[LoopFrame],
+    in_async: bool,
+    errors: &mut Vec<ValidationError>,
+) {
+    scopes.push(Scope::default());
+    if let Expr::Let { pattern, value, .. } = condition {
+        validate_expr(value, scopes, loops, in_async, errors);
+        bind_pattern(pattern, scopes, errors);
+    } else {
+        validate_expr(condition, scopes, loops, in_async, errors);
+    }
+    validate_block(body, scopes, body_loops, in_async, errors);
+    scopes.pop();
+}
+
+/// What a `switch` arm matches.
+enum Coverage<'a> {
+    Everything,
+    /// Every value of these variants.
+    Variants(Vec<&'a str>),
+    Other,
+}
+
+/// The enum a `switch` is over, judged from its arm patterns; `None` when no
+/// pattern names a variant of an enum declared in this file. Unqualified
+/// variants pick the enum that has all of them, and a user enum wins over the
+/// built-in `option`/`result` when both have the names used.
+fn switch_enum<'a>(
+    arms: &[SwitchArm],
+    enums: &'a HashMap<String, Vec<String>>,
+) -> Option<(&'a str, &'a [String])> {
+    fn qualified<'a>(
+        pattern: &Pattern,
+        enums: &'a HashMap<String, Vec<String>>,
+    ) -> Option<(&'a String, &'a Vec<String>)> {
+        match pattern {
+            Pattern::Path { segments: path, .. } | Pattern::Enum { path, .. } if path.len() > 1 => {
+                enums.get_key_value(&path[path.len() - 2])
+            }
+            Pattern::Or { alternatives, .. } => {
+                alternatives.iter().find_map(|p| qualified(p, enums))
+            }
+            _ => None,
+        }
+    }
+    fn unqualified<'p>(
+        pattern: &'p Pattern,
+        enums: &HashMap<String, Vec<String>>,
+        names: &mut Vec<&'p str>,
+    ) {
         match pattern {
-            Pattern::Binding { name, span } => {
-                scope.names.insert(name.clone(), *span);
+            Pattern::Enum { path, .. } if path.len() == 1 => names.push(&path[0]),
+            Pattern::Binding { name, .. } if enums.values().any(|vs| vs.contains(name)) => {
+                names.push(name)
             }
-            Pattern::Enum { bindings, span, .. } => {
-                for b in bindings {
-                    scope.names.insert(b.clone(), *span);
+            Pattern::Or { alternatives, .. } => {
+                for alternative in alternatives {
+                    unqualified(alternative, enums, names);
                 }
             }
             _ => {}
         }
     }
+    if let Some((name, variants)) = arms.iter().find_map(|arm| qualified(&arm.pattern, enums)) {
+        return Some((name.as_str(), variants.as_slice()));
+    }
+    let mut names = Vec::new();
+    for arm in arms {
+        unqualified(&arm.pattern, enums, &mut names);
+    }
+    if names.is_empty() {
+        return None;
+    }
+    let (builtin, user): (Vec<_>, Vec<_>) = enums
+        .iter()
+        .filter(|(_, vs)| names.iter().all(|n| vs.iter().any(|v| v == n)))
+        .partition(|(name, _)| matches!(name.as_str(), "option" | "result"));
+    match (user.as_slice(), builtin.as_slice()) {
+        ([only], _) | ([], [only]) => Some((only.0.as_str(), only.1.as_slice())),
+        _ => None,
+    }
+}
+
+/// Whether `pattern` matches every value of the type it is written against.
+/// A bare name counts as a binding unless some known enum has such a variant.
+fn irrefutable(pattern: &Pattern, enums: &HashMap<String, Vec<String>>) -> bool {
+    match pattern {
+        Pattern::Wildcard { .. } => true,
+        Pattern::Binding { name, .. } => !enums.values().any(|vs| vs.contains(name)),
+        Pattern::Tuple { elements, .. } => elements.iter().all(|p| irrefutable(p, enums)),
+        Pattern::Struct { fields, .. } => fields.iter().all(|f| irrefutable(&f.pattern, enums)),
+        Pattern::Or { alternatives, .. } => alternatives.iter().any(|p| irrefutable(p, enums)),
+        // The only variant of its enum.
+        Pattern::Enum { path, args, .. } => {
+            let variant = &path[path.len() - 1];
+            let owner = match path.len() {
+                1 => enums.values().find(|vs| vs.contains(variant)),
+                n => enums.get(&path[n - 2]),
+            };
+            owner.is_some_and(|vs| vs.len() == 1 && vs.contains(variant))
+                && args.iter().all(|p| irrefutable(p, enums))
+        }
+        Pattern::Path { .. } | Pattern::Literal(_) | Pattern::Range { .. } => false,
+    }
+}
+
+fn switch_coverage<'a>(
+    pattern: &'a Pattern,
+    variants: &[String],
+    enums: &HashMap<String, Vec<String>>,
+) -> Coverage<'a> {
+    let is_variant = |name: &str| variants.iter().any(|v| v == name);
+    match pattern {
+        Pattern::Binding { name, .. } if is_variant(name) => Coverage::Variants(vec![name]),
+        Pattern::Binding { .. } => Coverage::Everything,
+        Pattern::Path { segments: path, .. } | Pattern::Enum { path, .. } => {
+            let variant = path.last().map(String::as_str).unwrap_or_default();
+            let args_irrefutable = match pattern {
+                Pattern::Enum { args, .. } => args.iter().all(|p| irrefutable(p, enums)),
+                _ => true,
+            };
+            if is_variant(variant) && args_irrefutable {
+                Coverage::Variants(vec![variant])
+            } else {
+                Coverage::Other
+            }
+        }
+        Pattern::Or { alternatives, .. } => {
+            let mut covered = Vec::new();
+            for alternative in alternatives {
+                match switch_coverage(alternative, variants, enums) {
+                    Coverage::Everything => return Coverage::Everything,
+                    Coverage::Variants(vs) => covered.extend(vs),
+                    Coverage::Other => {}
+                }
+            }
+            if covered.is_empty() {
+                Coverage::Other
+            } else {
+                Coverage::Variants(covered)
+            }
+        }
+        other if irrefutable(other, enums) => Coverage::Everything,
+        _ => Coverage::Other,
+    }
+}
+
+/// `State::Done`, or just `None` for the built-in `option` and `result`.
+fn variant_label(enum_name: &str, variant: &str) -> String {
+    match enum_name {
+        "option" | "result" => format!("`{variant}`"),
+        _ => format!("`{enum_name}::{variant}`"),
+    }
+}
+
+/// Reports arms no value can reach and, unless some arm matches everything,
+/// the enum variants that no arm handles. Guarded arms never count as covering.
+fn validate_switch_arms(
+    stmt: &SwitchStmt,
+    enums: &HashMap<String, Vec<String>>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let target = switch_enum(&stmt.arms, enums);
+    let (enum_name, variants) = target.unwrap_or_default();
+    let mut covered: Vec<&str> = Vec::new();
+    let mut catch_all = false;
+    for arm in &stmt.arms {
+        let coverage = match &arm.guard {
+            Some(_) => Coverage::Other,
+            None => switch_coverage(&arm.pattern, variants, enums),
+        };
+        let reason = match coverage {
+            _ if catch_all => Some("an earlier arm matches every value".to_string()),
+            Coverage::Everything
+                if target.is_some() && variants.iter().all(|v| covered.contains(&v.as_str())) =>
+            {
+                catch_all = true;
+                Some(format!("every variant of `{enum_name}` is already matched"))
+            }
+            Coverage::Everything => {
+                catch_all = true;
+                None
+            }
+            Coverage::Variants(vs) if vs.iter().all(|v| covered.contains(v)) => {
+                let labels: Vec<String> =
+                    vs.iter().map(|v| variant_label(enum_name, v)).collect();
+                let verb = if labels.len() == 1 { "is" } else { "are" };
+                Some(format!("{} {verb} already matched", labels.join(", ")))
+            }
+            Coverage::Variants(vs) => {
+                covered.extend(vs);
+                None
+            }
+            Coverage::Other => None,
+        };
+        if let Some(reason) = reason {
+            errors.push(ValidationError {
+                message: format!("Unreachable switch arm: {reason}"),
+                span: arm.span,
+            });
+        }
+    }
+    if catch_all {
+        return;
+    }
+    let message = if target.is_some() {
+        let missing: Vec<String> = variants
+            .iter()
+            .filter(|v| !covered.contains(&v.as_str()))
+            .map(|v| variant_label(enum_name, v))
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        format!(
+            "Non-exhaustive switch on `{enum_name}`: missing {}",
+            missing.join(", ")
+        )
+    } else {
+        // Only plain literal switches are known to need `_`; the variants of
+        // enums from other modules are not visible here.
+        fn scalar(pattern: &Pattern) -> bool {
+            match pattern {
+                Pattern::Literal(_) | Pattern::Range { .. } => true,
+                Pattern::Or { alternatives, .. } => alternatives.iter().all(scalar),
+                _ => false,
+            }
+        }
+        if !stmt.arms.iter().all(|arm| arm.guard.is_some() || scalar(&arm.pattern)) {
+            return;
+        }
+        "Non-exhaustive switch: add a `_` arm".to_string()
+    };
+    errors.push(ValidationError {
+        message,
+        span: stmt.span,
+    });
+}
+
+/// `check` arms are tested in order, so only a `_` arm (or a bare `true`
+/// guard) makes one exhaustive; arms after it can never run.
+fn validate_check_arms(check: &CheckExpr, errors: &mut Vec<ValidationError>) {
+    let mut catch_all = false;
+    let mut bools: Vec<bool> = Vec::new();
+    for arm in &check.arms {
+        if catch_all {
+            errors.push(ValidationError {
+                message: "Unreachable check arm: an earlier arm matches every value".to_string(),
+                span: arm.span,
+            });
+            continue;
+        }
+        match &arm.pattern {
+            CheckPattern::Wildcard { .. } => catch_all = true,
+            CheckPattern::Literal(Literal::Bool { value, .. }) if check.target.is_none() => {
+                catch_all = *value;
+            }
+            CheckPattern::Literal(Literal::Bool { value, .. }) => bools.push(*value),
+            _ => {}
+        }
+    }
+    if !catch_all && !(bools.contains(&true) && bools.contains(&false)) {
+        errors.push(ValidationError {
+            message: "Non-exhaustive check: add a `_` arm".to_string(),
+            span: check.span,
+        });
+    }
+}
+
+fn bind_pattern(pattern: &Pattern, scopes: &mut Vec<Scope>, errors: &mut Vec<ValidationError>) {
+    if let Some(globals) = scopes.first() {
+        check_or_patterns(pattern, &globals.enums, errors);
+    }
+    if let Some(scope) = scopes.last_mut() {
+        for (name, span) in pattern.bindings() {
+            if scope.names.insert(name.to_string(), span).is_some() {
+                errors.push(ValidationError {
+                    message: format!("`{name}` is bound more than once in the same pattern"),
+                    span,
+                });
+            }
+        }
+    }
+}
+
+/// Patterns in declarations and `for` loops must match every value; a plain
+/// name always does, even when it spells an enum variant.
+fn check_irrefutable(
+    pattern: &Pattern,
+    context: &str,
+    scopes: &[Scope],
+    errors: &mut Vec<ValidationError>,
+) {
+    let Some(globals) = scopes.first() else {
+        return;
+    };
+    if pattern.binding_name().is_none() && !irrefutable(pattern, &globals.enums) {
+        errors.push(ValidationError {
+            message: format!(
+                "Refutable pattern in {context}: it does not match every value; use `switch`"
+            ),
+            span: pattern.span(),
+        });
+    }
+}
+
+/// Every alternative of an `|` pattern must bind the same names, so the arm
+/// body sees the same variables whichever one matched.
+/// Bare names that are enum variants are not counted.
+fn check_or_patterns(
+    pattern: &Pattern,
+    enums: &HashMap<String, Vec<String>>,
+    errors: &mut Vec<ValidationError>,
+) {
+    match pattern {
+        Pattern::Or { alternatives, span } => {
+            let names = |p: &Pattern| {
+                let mut names: Vec<String> = p
+                    .bindings()
+                    .into_iter()
+                    .filter(|(n, _)| !enums.values().any(|vs| vs.iter().any(|v| v == n)))
+                    .map(|(n, _)| n.to_string())
+                    .collect();
+                names.sort();
+                names
+            };
+            let expected = alternatives.first().map(names).unwrap_or_default();
+            if alternatives.iter().any(|p| names(p) != expected) {
+                errors.push(ValidationError {
+                    message: "All alternatives of an `|` pattern must bind the same names"
+                        .to_string(),
+                    span: *span,
+                });
+            }
+            for alternative in alternatives {
+                check_or_patterns(alternative, enums, errors);
+            }
+        }
+        Pattern::Enum { args: items, .. } | Pattern::Tuple { elements: items, .. } => {
+            for item in items {
+                check_or_patterns(item, enums, errors);
+            }
+        }
+        Pattern::Struct { fields, .. } => {
+            for field in fields {
+                check_or_patterns(&field.pattern, enums, errors);
+            }
+        }
+        Pattern::Wildcard { .. }
+        | Pattern::Binding { .. }
+        | Pattern::Path { .. }
+        | Pattern::Literal(_)
+        | Pattern::Range { .. } => {}
+    }
 }
 
 fn validate_expr(
     expr: &Expr,
     scopes: &mut Vec<Scope>,
-    loop_depth: usize,
+    loops: &[LoopFrame],
     in_async: bool,
     errors: &mut Vec<ValidationError>,
 ) {
     match expr {
         Expr::Literal(_) | Expr::Identifier { .. } => {}
-        Expr::Access { base, .. } => validate_expr(base, scopes, loop_depth, in_async, errors),
+        Expr::Access { base, .. } => validate_expr(base, scopes, loops, in_async, errors),
         Expr::Call { callee, args, .. } => {
-            validate_expr(callee, scopes, loop_depth, in_async, errors);
+            validate_expr(callee, scopes, loops, in_async, errors);
             for arg in args {
-                validate_expr(arg, scopes, loop_depth, in_async, errors);
+                validate_expr(arg, scopes, loops, in_async, errors);
             }
         }
         Expr::Await { expr, span } => {
@@ -202,84 +882,104 @@ fn validate_expr(
                     span: *span,
                 });
             }
-            validate_expr(expr, scopes, loop_depth, in_async, errors)
+            validate_expr(expr, scopes, loops, in_async, errors)
         }
-        Expr::Unary { expr, .. } => validate_expr(expr, scopes, loop_depth, in_async, errors),
+        Expr::Unary { expr, .. } => validate_expr(expr, scopes, loops, in_async, errors),
         Expr::Binary { left, right, .. } => {
-            validate_expr(left, scopes, loop_depth, in_async, errors);
-            validate_expr(right, scopes, loop_depth, in_async, errors);
+            validate_expr(left, scopes, loops, in_async, errors);
+            validate_expr(right, scopes, loops, in_async, errors);
+        }
+        Expr::Range {
+            start, end, step, ..
+        } => {
+            validate_expr(start, scopes, loops, in_async, errors);
+            validate_expr(end, scopes, loops, in_async, errors);
+            if let Some(step) = step {
+                validate_expr(step, scopes, loops, in_async, errors);
+            }
         }
         Expr::Assignment { target, value, .. } => {
-            validate_expr(target, scopes, loop_depth, in_async, errors);
-            validate_expr(value, scopes, loop_depth, in_async, errors);
+            if let Expr::Identifier { name, span } = target.as_ref() {
+                let scope = scopes.iter().rev().find(|s| s.names.contains_key(name));
+                if scope.is_some_and(|s| s.readonly.contains(name)) {
+                    errors.push(ValidationError {
+                        message: format!("Cannot assign to constant `{}`", name),
+                        span: *span,
+                    });
+                }
+            }
+            validate_expr(target, scopes, loops, in_async, errors);
+            validate_expr(value, scopes, loops, in_async, errors);
         }
         Expr::StructLiteral { fields, .. } => {
             for field in fields {
-                validate_expr(&field.expr, scopes, loop_depth, in_async, errors);
+                validate_expr(&field.expr, scopes, loops, in_async, errors);
             }
         }
         Expr::ArrayLiteral { elements, .. } => {
             for elem in elements {
-                validate_expr(elem, scopes, loop_depth, in_async, errors);
+                validate_expr(elem, scopes, loops, in_async, errors);
             }
         }
         Expr::TupleLiteral { elements, .. } => {
             for elem in elements {
-                validate_expr(elem, scopes, loop_depth, in_async, errors);
+                validate_expr(elem, scopes, loops, in_async, errors);
             }
         }
         Expr::Cast { expr, .. } => {
-            validate_expr(expr, scopes, loop_depth, in_async, errors);
+            validate_expr(expr, scopes, loops, in_async, errors);
         }
-        Expr::Block(block) => validate_block(block, scopes, loop_depth, in_async, errors),
-        Expr::If(stmt) => {
-            validate_expr(&stmt.condition, scopes, loop_depth, in_async, errors);
-            validate_block(&stmt.then_branch, scopes, loop_depth, in_async, errors);
-            for (cond, block) in &stmt.else_if {
-                validate_expr(cond, scopes, loop_depth, in_async, errors);
-                validate_block(block, scopes, loop_depth, in_async, errors);
-            }
-            if let Some(block) = &stmt.else_branch {
-                validate_block(block, scopes, loop_depth, in_async, errors);
-            }
-        }
-        Expr::Try { expr, .. } => validate_expr(expr, scopes, loop_depth, in_async, errors),
+        Expr::Block(block) => validate_block(block, scopes, loops, in_async, errors),
+        Expr::If(stmt) => validate_if(stmt, scopes, loops, in_async, errors),
+        Expr::Try { expr, .. } => validate_expr(expr, scopes, loops, in_async, errors),
         Expr::Lambda(lambda) => {
-            // Lambdas inherit async context? Or simple lambdas are sync?
-            // "closure syntax implementation details not fully specified, assuming sync for now unless 'async' keyword added to lambda syntax later."
-            // For now, let's assume lambdas are synchronous unless specified otherwise.
-            // But wait, if I am in an async function, I can await inside a closure?
-            // Generally closures capture environment.
-            // If the user hasn't implemented async closures yet, then `in_async` should probably be false for lambdas, or we need to check lambda properties.
-            // Looking at AST, Lambda struct has `params` and `body`. No `is_async`.
-            // So lambdas are synchronous.
-            validate_block(&lambda.body, scopes, 0, false, errors)
-        },
+            // Lambda bodies see the enclosing scopes (they capture them at runtime), but
+            // `await` is only legal inside `async fun(...)` lambdas.
+            let params = lambda.params.iter().map(|p| (&p.name, p.span));
+            scopes.push(Scope::with_params(params));
+            validate_block(&lambda.body, scopes, &[], lambda.is_async, errors);
+            scopes.pop();
+        }
         Expr::Index { base, index, .. } => {
-            validate_expr(base, scopes, loop_depth, in_async, errors);
-            validate_expr(index, scopes, loop_depth, in_async, errors);
+            validate_expr(base, scopes, loops, in_async, errors);
+            validate_expr(index, scopes, loops, in_async, errors);
         }
         Expr::MethodCall { object, args, .. } => {
-            validate_expr(object, scopes, loop_depth, in_async, errors);
+            validate_expr(object, scopes, loops, in_async, errors);
             for arg in args {
-                validate_expr(arg, scopes, loop_depth, in_async, errors);
+                validate_expr(arg, scopes, loops, in_async, errors);
             }
         }
         Expr::Check(check) => {
             if let Some(target) = &check.target {
-                validate_expr(target, scopes, loop_depth, in_async, errors);
+                validate_expr(target, scopes, loops, in_async, errors);
             }
             for arm in &check.arms {
                 match &arm.pattern {
                     CheckPattern::Literal(lit) => {
-                        validate_expr(&Expr::Literal(lit.clone()), scopes, loop_depth, in_async, errors)
+                        validate_expr(&Expr::Literal(lit.clone()), scopes, loops, in_async, errors)
                     }
-                    CheckPattern::Guard(expr) => validate_expr(expr, scopes, loop_depth, in_async, errors),
+                    CheckPattern::Guard(expr) => validate_expr(expr, scopes, loops, in_async, errors),
                     CheckPattern::Wildcard { .. } => {}
                 }
-                validate_expr(&arm.expr, scopes, loop_depth, in_async, errors);
+                validate_expr(&arm.expr, scopes, loops, in_async, errors);
             }
+            validate_check_arms(check, errors);
+        }
+        Expr::Interpolated { parts, .. } => {
+            for part in parts {
+                if let InterpolationPart::Expr(expr) = part {
+                    validate_expr(expr, scopes, loops, in_async, errors);
+                }
+            }
+        }
+        Expr::Loop { label, body, .. } => {
+            validate_block(body, scopes, &enter_loop(loops, label, true), in_async, errors);
         }
+        Expr::Let { span, .. } => errors.push(ValidationError {
+            message: "`let` is only allowed as an `if` or `while` condition".to_string(),
+            span: *span,
+        }),
     }
 }
 
@@ -0,0 +1,289 @@
+mod common;
+
+use nightscript_android::bytecode::compiler::{compile_file, CompileError};
+use nightscript_android::bytecode::vm::{Vm, VmValue};
+use nightscript_android::bytecode::AfbcModule;
+use nightscript_android::Value;
+
+fn compile(source: &str) -> AfbcModule {
+    match compile_file(&common::parse(source)) {
+        Ok(module) => module,
+        Err(errors) => panic!("compilation failed: {errors:?}"),
+    }
+}
+
+fn compile_errors(source: &str) -> Vec<CompileError> {
+    compile_file(&common::parse(source)).expect_err("compilation should fail")
+}
+
+fn run_int(source: &str) -> i128 {
+    let module = compile(source);
+    let mut vm = Vm::new(&module).unwrap();
+    match vm.run().unwrap() {
+        VmValue::Value(Value::Int(i)) => i,
+        other => panic!("expected int, got {other:?}"),
+    }
+}
+
+#[test]
+fn compiles_recursive_functions() {
+    let source = r#"
+fun fib(n:: i32) -> i32 {
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+
+fun apex() -> i32 {
+    return fib(15);
+}
+"#;
+    assert_eq!(run_int(source), 610);
+}
+
+#[test]
+fn compiles_loops_with_break_and_continue() {
+    let source = r#"
+fun apex() -> i32 {
+    var total = 0;
+    for i in 0..10 {
+        if i % 2 == 0 {
+            continue;
+        }
+        total = total + i;
+    }
+    var n = 0;
+    while true {
+        n = n + 1;
+        if n == 5 {
+            break;
+        }
+    }
+    return total * 100 + n;
+}
+"#;
+    assert_eq!(run_int(source), 2505);
+}
+
+#[test]
+fn labeled_break_and_continue_leave_outer_loops() {
+    let source = r#"
+fun apex() -> i32 {
+    var total = 0;
+    'outer: for i in 0..5 {
+        var j = 0;
+        while true {
+            j = j + 1;
+            if j > i {
+                continue 'outer;
+            }
+            if i == 4 {
+                break 'outer;
+            }
+            total = total + 1;
+        }
+    }
+    var n = 0;
+    'spin: loop {
+        loop {
+            n = n + 1;
+            if n == 3 {
+                break 'spin;
+            }
+        }
+    }
+    return total * 10 + n;
+}
+"#;
+    assert_eq!(run_int(source), 63);
+}
+
+#[test]
+fn stepped_and_inclusive_ranges_compile() {
+    let source = r#"
+fun apex() -> i32 {
+    var total = 0;
+    for i in 0..=10 step 5 {
+        total = total * 100 + i;
+    }
+    return total;
+}
+"#;
+    assert_eq!(run_int(source), 510);
+}
+
+#[test]
+fn closures_capture_enclosing_locals() {
+    let source = r#"
+fun apex() -> i32 {
+    let base = 10;
+    let add = fun(x) {
+        return x + base;
+    };
+    return add(5);
+}
+"#;
+    assert_eq!(run_int(source), 15);
+}
+
+#[test]
+fn struct_literals_compile_to_maps() {
+    let source = r#"
+struct Point {
+    x:: i32,
+    y:: i32,
+}
+
+fun apex() -> i32 {
+    let p = Point { x: 3, y: 4 };
+    return p.x * p.y;
+}
+"#;
+    assert_eq!(run_int(source), 12);
+}
+
+#[test]
+fn builtin_module_calls_resolve_at_run_time() {
+    let source = r#"
+import forge.log as log;
+
+fun apex() -> i32 {
+    let items = [1, 2, 3];
+    log.info("items", items);
+    return vec.len(items);
+}
+"#;
+    assert_eq!(run_int(source), 3);
+}
+
+#[test]
+fn builds_widget_tree_for_gui_programs() {
+    let source = r#"
+import forge.gui.native as ui;
+import forge.log as log;
+
+fun apex() {
+    var count = ui.state(0);
+    ui.window("Counter", fun(ctx) {
+        ctx.text("Count: " + count.get());
+        ui.row([
+            ctx.button("+1", fun() {
+                count.set(count.get() + 1);
+            })
+        ]);
+        ui.container(ui.text("footer"), padding=8);
+    });
+}
+"#;
+    let module = compile(source);
+    let mut vm = Vm::new(&module).unwrap();
+    vm.run().unwrap();
+
+    let root = vm.root_widget().expect("window committed");
+    let root = root.borrow();
+    assert_eq!(root.widget_type, "Window");
+    let children: Vec<String> = root
+        .children
+        .iter()
+        .map(|child| child.borrow().widget_type.clone())
+        .collect();
+    assert_eq!(children, ["Text", "Row", "Container"]);
+    assert_eq!(
+        root.children[0].borrow().props["text"].to_display(),
+        "Count: 0"
+    );
+    assert_eq!(root.children[2].borrow().props["padding"].to_display(), "8");
+
+    let button_id = root.children[1].borrow().children[0].borrow().id.clone();
+    drop(root);
+    assert!(vm.handle_event(&button_id, "click").unwrap());
+}
+
+#[test]
+fn unsupported_constructs_report_their_span() {
+    let source = r#"
+fun apex() {
+    let x = 1;
+    switch x {
+        1 -> log.info("one"),
+        _ -> log.info("other"),
+    }
+}
+"#;
+    let errors = compile_errors(source);
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(
+        errors[0].message,
+        "`switch` statements are not supported by the web target"
+    );
+    assert_eq!((errors[0].span.line, errors[0].span.column), (4, 5));
+}
+
+#[test]
+fn rejects_assignment_to_captured_variable() {
+    let source = r#"
+fun apex() {
+    var count = 0;
+    let inc = fun() {
+        count = count + 1;
+    };
+}
+"#;
+    let errors = compile_errors(source);
+    assert!(
+        errors[0].message.contains("captured variable `count`"),
+        "{errors:?}"
+    );
+    assert_eq!(errors[0].span.line, 5);
+}
+
+#[test]
+fn rejects_non_forge_imports_and_missing_entry_point() {
+    let source = r#"
+import mylib as lib;
+
+fun main() {}
+"#;
+    let errors = compile_errors(source);
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert!(messages[0].starts_with("Import of `mylib`"), "{messages:?}");
+    assert!(
+        messages[1].starts_with("No `apex` function"),
+        "{messages:?}"
+    );
+    assert_eq!(errors[0].span.line, 2);
+}
+
+#[test]
+fn runtime_errors_map_back_to_source() {
+    let source = r#"
+fun apex() -> i32 {
+    let zero = 0;
+    return 10 / zero;
+}
+"#;
+    let module = compile(source);
+    let mut vm = Vm::new(&module).unwrap();
+    let err = vm.run().unwrap_err();
+    assert_eq!(err.message(), "Division by zero at line 4, column 5");
+}
+
+#[test]
+fn globals_are_initialized_before_apex_runs() {
+    let source = r#"
+const STEP = 3;
+var total = STEP * 2;
+
+fun bump() {
+    total = total + STEP;
+}
+
+fun apex() -> i32 {
+    bump();
+    bump();
+    return total;
+}
+"#;
+    assert_eq!(run_int(source), 12);
+}
@@ -0,0 +1,265 @@
+use nightscript_android::bytecode::vm::{Vm, VmValue};
+use nightscript_android::bytecode::{
+    AfbcModule, BytecodeBuilder, Constant, FunctionEntry, Opcode, SourceMapEntry,
+};
+use nightscript_android::runtime::Value;
+
+fn function(module: &mut AfbcModule, name: &str, arity: u16, locals: u16, code: Vec<u8>) {
+    let name_idx = module.add_string(name);
+    let code_offset = module.bytecode.len() as u32;
+    let code_len = code.len() as u32;
+    module.bytecode.extend(code);
+    module.add_function(FunctionEntry {
+        name_idx,
+        arity,
+        locals,
+        code_offset,
+        code_len,
+    });
+}
+
+fn int(value: VmValue) -> i128 {
+    match value {
+        VmValue::Value(Value::Int(i)) => i,
+        other => panic!("expected int, got {other:?}"),
+    }
+}
+
+#[test]
+fn calls_and_returns() {
+    let mut module = AfbcModule::new();
+    let two = module.add_constant(Constant::Int64(2)) as u16;
+    let forty = module.add_constant(Constant::Int64(40)) as u16;
+
+    // fun add(a, b) { return a + b; }
+    let mut add = BytecodeBuilder::new();
+    add.emit_load_local(0);
+    add.emit_load_local(1);
+    add.emit(Opcode::Add);
+    add.emit(Opcode::Ret);
+    function(&mut module, "add", 2, 2, add.finish());
+
+    // fun apex() { return add(40, 2); }
+    let mut apex = BytecodeBuilder::new();
+    apex.emit_const(forty);
+    apex.emit_const(two);
+    apex.emit_call(0, 2);
+    apex.emit(Opcode::Ret);
+    function(&mut module, "apex", 0, 0, apex.finish());
+
+    let mut vm = Vm::new(&module).unwrap();
+    assert_eq!(int(vm.run().unwrap()), 42);
+}
+
+#[test]
+fn recursion_uses_separate_frames() {
+    let mut module = AfbcModule::new();
+    let one = module.add_constant(Constant::Int64(1)) as u16;
+    let ten = module.add_constant(Constant::Int64(10)) as u16;
+
+    // fun fact(n) { if n <= 1 { return 1; } return n * fact(n - 1); }
+    let mut b = BytecodeBuilder::new();
+    b.emit_load_local(0);
+    b.emit_const(one);
+    b.emit(Opcode::Le);
+    b.emit_jump_if_false(0);
+    let patch = b.current_offset() - 2;
+    b.emit_const(one);
+    b.emit(Opcode::Ret);
+    let target = b.current_offset();
+    b.patch_jump(patch, (target - (patch + 2)) as i16);
+    b.emit_load_local(0);
+    b.emit_load_local(0);
+    b.emit_const(one);
+    b.emit(Opcode::Sub);
+    b.emit_call(0, 1);
+    b.emit(Opcode::Mul);
+    b.emit(Opcode::Ret);
+    function(&mut module, "fact", 1, 1, b.finish());
+
+    let mut apex = BytecodeBuilder::new();
+    apex.emit_const(ten);
+    apex.emit_call(0, 1);
+    apex.emit(Opcode::Ret);
+    function(&mut module, "apex", 0, 0, apex.finish());
+
+    let mut vm = Vm::new(&module).unwrap();
+    assert_eq!(int(vm.run().unwrap()), 3_628_800);
+}
+
+#[test]
+fn loops_with_backward_jumps() {
+    let mut module = AfbcModule::new();
+    let zero = module.add_constant(Constant::Int64(0)) as u16;
+    let one = module.add_constant(Constant::Int64(1)) as u16;
+    let ten = module.add_constant(Constant::Int64(10)) as u16;
+
+    // var i = 0; var sum = 0; while i < 10 { sum = sum + i; i = i + 1; } return sum;
+    let mut b = BytecodeBuilder::new();
+    b.emit_const(zero);
+    b.emit_store_local(0);
+    b.emit_const(zero);
+    b.emit_store_local(1);
+    let loop_start = b.current_offset();
+    b.emit_load_local(0);
+    b.emit_const(ten);
+    b.emit(Opcode::Lt);
+    b.emit_jump_if_false(0);
+    let exit_patch = b.current_offset() - 2;
+    b.emit_load_local(1);
+    b.emit_load_local(0);
+    b.emit(Opcode::Add);
+    b.emit_store_local(1);
+    b.emit_load_local(0);
+    b.emit_const(one);
+    b.emit(Opcode::Add);
+    b.emit_store_local(0);
+    let back = loop_start as i16 - (b.current_offset() as i16 + 3);
+    b.emit_jump(back);
+    let exit = b.current_offset();
+    b.patch_jump(exit_patch, (exit - (exit_patch + 2)) as i16);
+    b.emit_load_local(1);
+    b.emit(Opcode::Ret);
+    function(&mut module, "apex", 0, 2, b.finish());
+
+    let mut vm = Vm::new(&module).unwrap();
+    assert_eq!(int(vm.run().unwrap()), 45);
+}
+
+#[test]
+fn closures_receive_captures_before_arguments() {
+    let mut module = AfbcModule::new();
+    let five = module.add_constant(Constant::Int64(5)) as u16;
+    let seven = module.add_constant(Constant::Int64(7)) as u16;
+
+    // closure body: captured n (slot 0) * arg (slot 1)
+    let mut body = BytecodeBuilder::new();
+    body.emit_load_local(0);
+    body.emit_load_local(1);
+    body.emit(Opcode::Mul);
+    body.emit(Opcode::Ret);
+    function(&mut module, "<lambda>", 1, 2, body.finish());
+
+    let mut apex = BytecodeBuilder::new();
+    apex.emit_const(five);
+    apex.emit(Opcode::MakeClosure);
+    apex.emit_u16(0);
+    apex.emit_u8(1);
+    apex.emit_const(seven);
+    apex.emit(Opcode::InvokeClosure);
+    apex.emit_u8(1);
+    apex.emit(Opcode::Ret);
+    function(&mut module, "apex", 0, 0, apex.finish());
+
+    let mut vm = Vm::new(&module).unwrap();
+    assert_eq!(int(vm.run().unwrap()), 35);
+}
+
+#[test]
+fn builtins_resolve_through_globals() {
+    let mut module = AfbcModule::new();
+    let vec_len = module.add_string("vec.len") as u16;
+    let three = module.add_constant(Constant::Int64(3)) as u16;
+
+    let mut apex = BytecodeBuilder::new();
+    apex.emit(Opcode::LoadGlobal);
+    apex.emit_u16(vec_len);
+    apex.emit(Opcode::NewVec);
+    apex.emit_const(three);
+    apex.emit(Opcode::VecPush);
+    apex.emit_const(three);
+    apex.emit(Opcode::VecPush);
+    apex.emit(Opcode::InvokeClosure);
+    apex.emit_u8(1);
+    apex.emit(Opcode::Ret);
+    function(&mut module, "apex", 0, 0, apex.finish());
+
+    let mut vm = Vm::new(&module).unwrap();
+    assert_eq!(int(vm.run().unwrap()), 2);
+}
+
+#[test]
+fn maps_and_globals_round_trip() {
+    let mut module = AfbcModule::new();
+    let key = module.add_string("answer") as u16;
+    let value = module.add_constant(Constant::Int64(42)) as u16;
+    let global = module.add_string("config") as u16;
+
+    let mut apex = BytecodeBuilder::new();
+    apex.emit(Opcode::NewMap);
+    apex.emit_const(key);
+    apex.emit_const(value);
+    apex.emit(Opcode::MapSet);
+    apex.emit(Opcode::StoreGlobal);
+    apex.emit_u16(global);
+    apex.emit(Opcode::LoadGlobal);
+    apex.emit_u16(global);
+    apex.emit(Opcode::GetProp);
+    apex.emit_u16(key);
+    apex.emit(Opcode::Ret);
+    function(&mut module, "apex", 0, 0, apex.finish());
+
+    let mut vm = Vm::new(&module).unwrap();
+    assert_eq!(int(vm.run().unwrap()), 42);
+}
+
+#[test]
+fn implicit_return_yields_null() {
+    let mut module = AfbcModule::new();
+    function(&mut module, "apex", 0, 0, Vec::new());
+
+    let mut vm = Vm::new(&module).unwrap();
+    assert!(matches!(vm.run().unwrap(), VmValue::Value(Value::Null)));
+}
+
+#[test]
+fn module_survives_serialization() {
+    let mut module = AfbcModule::new();
+    let a = module.add_constant(Constant::Int64(6)) as u16;
+    let b = module.add_constant(Constant::Int64(7)) as u16;
+    let mut apex = BytecodeBuilder::new();
+    apex.emit_const(a);
+    apex.emit_const(b);
+    apex.emit(Opcode::Mul);
+    apex.emit(Opcode::Ret);
+    function(&mut module, "apex", 0, 0, apex.finish());
+
+    let mut bytes = Vec::new();
+    module.write(&mut bytes).unwrap();
+    let loaded = AfbcModule::read(&mut bytes.as_slice()).unwrap();
+
+    let mut vm = Vm::new(&loaded).unwrap();
+    assert_eq!(int(vm.run().unwrap()), 42);
+}
+
+#[test]
+fn runtime_errors_report_source_location() {
+    let mut module = AfbcModule::new();
+    let one = module.add_constant(Constant::Int64(1)) as u16;
+    let zero = module.add_constant(Constant::Int64(0)) as u16;
+
+    let mut apex = BytecodeBuilder::new();
+    apex.emit_const(one);
+    apex.emit_const(zero);
+    apex.emit(Opcode::Div);
+    apex.emit(Opcode::Ret);
+    function(&mut module, "apex", 0, 0, apex.finish());
+    module.source_map.push(SourceMapEntry {
+        code_start: 6,
+        code_end: 7,
+        line: 3,
+        column: 12,
+    });
+
+    let mut vm = Vm::new(&module).unwrap();
+    let err = vm.run().unwrap_err();
+    assert_eq!(err.message(), "Division by zero at line 3, column 12");
+}
+
+#[test]
+fn unknown_entry_point_is_an_error() {
+    let module = AfbcModule::new();
+    let mut vm = Vm::new(&module).unwrap();
+    let err = vm.run().unwrap_err();
+    assert!(err.message().contains("apex"), "{}", err.message());
+}
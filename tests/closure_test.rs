@@ -0,0 +1,154 @@
+mod common;
+
+use common::{call, expect_int, validate};
+
+#[test]
+fn closure_mutates_captured_var() {
+    let source = r#"
+    fun counter() -> i64 {
+        var count = 0;
+        let bump = fun() { count = count + 1; };
+        bump();
+        bump();
+        bump();
+        return count;
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "counter")), 3);
+}
+
+#[test]
+fn returned_closure_outlives_its_frame() {
+    let source = r#"
+    fun make_counter() {
+        var count = 0;
+        return fun() {
+            count = count + 1;
+            return count;
+        };
+    }
+
+    fun run() -> i64 {
+        let next = make_counter();
+        next();
+        next();
+        return next();
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "run")), 3);
+}
+
+#[test]
+fn counters_from_one_factory_are_independent() {
+    let source = r#"
+    fun make_counter() {
+        var count = 0;
+        return fun() {
+            count = count + 1;
+            return count;
+        };
+    }
+
+    fun run() -> i64 {
+        let a = make_counter();
+        let b = make_counter();
+        a();
+        a();
+        b();
+        return a() * 10 + b();
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "run")), 32);
+}
+
+#[test]
+fn factory_adder_captures_parameter() {
+    let source = r#"
+    fun make_adder(n: i64) {
+        return fun(x) { return x + n; };
+    }
+
+    fun run() -> i64 {
+        let add5 = make_adder(5);
+        let add10 = make_adder(10);
+        return add5(1) + add10(2);
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "run")), 18);
+}
+
+#[test]
+fn closures_share_captured_binding() {
+    let source = r#"
+    fun run() -> i64 {
+        var total = 0;
+        let add = fun(x) { total = total + x; };
+        let double = fun() { total = total * 2; };
+        add(3);
+        double();
+        add(1);
+        return total;
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "run")), 7);
+}
+
+#[test]
+fn async_callback_sees_captured_scope() {
+    let source = r#"
+    import forge.async as task;
+
+    async fun value() -> i64 {
+        return 20;
+    }
+
+    async fun run() -> i64 {
+        var seen = 0;
+        let offset = 2;
+        let done = task.then(value(), fun(v) {
+            seen = v + offset;
+            return seen;
+        });
+        let result = await done;
+        return result + seen;
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "run")), 44);
+}
+
+#[test]
+fn async_closure_awaits_inside_body() {
+    let source = r#"
+    async fun value() -> i64 {
+        return 4;
+    }
+
+    async fun run() -> i64 {
+        let factor = 3;
+        let compute = async fun() {
+            let v = await value();
+            return v * factor;
+        };
+        return await compute();
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "run")), 12);
+}
+
+#[test]
+fn await_in_sync_closure_is_rejected() {
+    let source = r#"
+    async fun value() -> i64 {
+        return 4;
+    }
+
+    async fun run() {
+        let f = fun() { return await value(); };
+    }
+    "#;
+    let errors = validate(source);
+    assert!(
+        errors.iter().any(|e| e.message.contains("inside `async`")),
+        "expected await error, got: {errors:?}"
+    );
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use common::{call, expect_int, expect_str, parse, run_i64, run_str};
+use nightscript_android::type_checker::check_file;
+
+/// `vec<i64>` of 3, 1, 4, 1, 5 bound to `v`.
+const DIGITS: &str = "var v = vec.new();
+        vec.push(v, 3);
+        vec.push(v, 1);
+        vec.push(v, 4);
+        vec.push(v, 1);
+        vec.push(v, 5);";
+
+/// `body` after the `DIGITS` setup.
+fn digits(body: &str) -> String {
+    format!("{DIGITS}\n        {body}")
+}
+
+#[test]
+fn adapters_chain_through_method_syntax() {
+    assert_eq!(
+        run_i64(&digits(
+            "return v.filter(fun(x) { return x > 2; }).map(fun(x) { return x * 10; }).sum();"
+        )),
+        120
+    );
+    assert_eq!(
+        run_str(&digits(
+            "return v.map(fun(x) { return x * 2; }).join(\"-\");"
+        )),
+        "6-2-8-2-10"
+    );
+    assert_eq!(
+        run_i64(&digits(
+            "return vec.fold(v, 100, fun(acc, x) { return acc - x; });"
+        )),
+        86
+    );
+    assert_eq!(
+        run_str(&digits(
+            "return v.sort_by(fun(x) { return 0 - x; }).join(\"\");"
+        )),
+        "54311"
+    );
+    assert_eq!(run_str(&digits("return v.join(\"\");")), "31415");
+}
+
+#[test]
+fn predicates_stop_at_the_deciding_item() {
+    let body = "var calls = vec.new();
+        let hit = v.any(fun(x) { vec.push(calls, x); return x == 4; });
+        if hit && !v.all(fun(x) { return x > 1; }) { return vec.len(calls); }
+        return 0;";
+    assert_eq!(run_i64(&digits(body)), 3);
+    assert_eq!(
+        run_i64(&digits("return v.count(fun(x) { return x == 1; });")),
+        2
+    );
+    assert_eq!(
+        run_i64(&digits(
+            "if let Some(x) = v.find(fun(x) { return x > 3; }) { return x; } return 0;"
+        )),
+        4
+    );
+    assert_eq!(
+        run_i64(&digits(
+            "if let Some(x) = v.find(fun(x) { return x > 9; }) { return x; } return -1;"
+        )),
+        -1
+    );
+}
+
+#[test]
+fn positional_adapters_build_tuples() {
+    let body = "var total = 0;
+        for (i, x) in v.enumerate() { total = total + i * x; }
+        return total;";
+    assert_eq!(run_i64(&digits(body)), 32);
+    let body = "var total = 0;
+        for (a, b) in v.zip(v.map(fun(x) { return x * x; })) { total = total + a * b; }
+        return total;";
+    assert_eq!(run_i64(&digits(body)), 218);
+    let body = "var w = vec.new();
+        vec.push(w, 7);
+        return v.zip(w).len();";
+    assert_eq!(run_i64(&digits(body)), 1);
+}
+
+#[test]
+fn min_max_and_group_by_use_key_functions() {
+    assert_eq!(
+        run_i64(&digits("if let Some(x) = v.min_by(fun(x) { return (x - 4) * (x - 4); }) { return x; } return 0;")),
+        4
+    );
+    assert_eq!(
+        run_i64(&digits(
+            "if let Some(x) = v.max_by(fun(x) { return x % 4; }) { return x; } return 0;"
+        )),
+        3
+    );
+    let body = "let groups = v.group_by(fun(x) { return x % 2 == 0; });
+        if let Some(odd) = map.get(groups, false) { return odd.sum(); }
+        return 0;";
+    assert_eq!(run_i64(&digits(body)), 10);
+}
+
+#[test]
+fn maps_and_sets_walk_their_items() {
+    let body = r#"var m = map.new();
+        map.put(m, "a", 1);
+        map.put(m, "bb", 20);
+        return m.map(fun(entry) { let (k, n) = entry; return str.len(k) * n; }).sum();"#;
+    assert_eq!(run_i64(&digits(body)), 41);
+    let body = "var s = set.new();
+        set.insert(s, 2);
+        set.insert(s, 6);
+        return s.filter(fun(x) { return x > 3; }).sum() + set.fold(s, 0, fun(a, x) { return a + x; });";
+    assert_eq!(run_i64(&digits(body)), 14);
+}
+
+#[test]
+fn arrays_take_the_collection_methods() {
+    let source = "fun run() -> i64 {
+        let a = [1, 2, 3];
+        return a.map(fun(x) { return x * 2; }).filter(fun(x) { return x > 2; }).sum();
+    }";
+    assert_eq!(expect_int(call(source, "run")), 10);
+    let source = "fun run() -> str { return [3, 1, 2].sort_by(fun(x) { return x; }).join(\",\"); }";
+    assert_eq!(expect_str(call(source, "run")), "1,2,3");
+
+    let err = call("fun run() { [1].push(2); }", "run").unwrap_err();
+    assert!(
+        err.message().starts_with("Unknown method `push` on array"),
+        "{}",
+        err.message()
+    );
+}
+
+#[test]
+fn misuse_reports_the_builtin() {
+    let err = call(
+        &format!("fun run() {{ {DIGITS} v.filter(fun(x) {{ return x; }}); }}"),
+        "run",
+    )
+    .unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("expected bool in vec.filter predicate, got int"),
+        "{}",
+        err.message()
+    );
+
+    let err = call(
+        &format!("fun run() {{ {DIGITS} v.sort_by(fun(x) {{ if x > 2 {{ return \"big\"; }} return x; }}); }}"),
+        "run",
+    )
+    .unwrap_err();
+    assert!(
+        err.message().starts_with("vec.sort_by keys must be"),
+        "{}",
+        err.message()
+    );
+
+    let err = call(
+        "fun run() { var b:: vec<i8> = vec.new(); vec.push(b, 100); vec.push(b, 100); b.sum(); }",
+        "run",
+    )
+    .unwrap_err();
+    assert!(
+        err.message().starts_with("vec.sum overflowed i8"),
+        "{}",
+        err.message()
+    );
+}
+
+#[test]
+fn type_checker_knows_adapter_results() {
+    let errors: Vec<String> = check_file(&parse(
+        "fun run(v:: vec<i64>, xs:: [i64; 3]) {
+            let a:: bool = v.filter(fun(x) { return true; });
+            let b:: str = v.any(fun(x) { return true; });
+            let c:: str = v.join(\", \");
+            let d:: option<str> = vec.find(v, fun(x) { return true; });
+            let e:: str = xs.sum();
+        }",
+    ))
+    .into_iter()
+    .map(|e| e.message)
+    .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "Mismatched types: expected `bool`, found `vec<i64>`",
+            "Mismatched types: expected `str`, found `bool`",
+            "Mismatched types: expected `option<str>`, found `option<i64>`",
+            "Mismatched types: expected `str`, found `i64`",
+        ]
+    );
+}
@@ -0,0 +1,91 @@
+#![allow(dead_code, clippy::result_large_err)]
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub mod native;
+
+use nightscript_android::ir::{build_ir, IrModule};
+use nightscript_android::lexer::lex;
+use nightscript_android::module_loader::ModuleLoader;
+use nightscript_android::parser::parse_tokens_with_diagnostics;
+use nightscript_android::validation::{validate_file, ValidationError};
+use nightscript_android::{File, Interpreter, RuntimeResult, Value};
+
+/// Lex and parse `source`, panicking on any lexer/parser diagnostic.
+pub fn parse(source: &str) -> File {
+    let tokens = lex(source).expect("Lexing failed");
+    let report = parse_tokens_with_diagnostics(source, tokens);
+    assert!(
+        report.errors.is_empty(),
+        "Parsing should succeed, got: {:?}",
+        report.errors
+    );
+    report.file
+}
+
+/// `items` followed by a `run() -> ret` function with `body` as its body.
+pub fn run_source(items: &str, ret: &str, body: &str) -> String {
+    format!("{items}\n    fun run() -> {ret} {{\n        {body}\n    }}\n")
+}
+
+/// Call `run() -> i64` with `body` as its body.
+pub fn run_i64(body: &str) -> i128 {
+    expect_int(call(&run_source("", "i64", body), "run"))
+}
+
+/// Call `run() -> str` with `body` as its body.
+pub fn run_str(body: &str) -> String {
+    expect_str(call(&run_source("", "str", body), "run"))
+}
+
+/// Parse `source` and build its IR, panicking on any build error.
+pub fn lower(source: &str) -> IrModule {
+    build_ir(&parse(source)).unwrap_or_else(|errors| panic!("IR build failed: {errors:?}"))
+}
+
+/// Validation errors for `source`.
+pub fn validate(source: &str) -> Vec<ValidationError> {
+    validate_file(&parse(source))
+}
+
+/// Register every item in `source` and call the zero-argument function `name`.
+/// Futures returned by async functions are awaited before returning.
+pub fn call(source: &str, name: &str) -> RuntimeResult<Value> {
+    call_with_loader(source, name, ModuleLoader::new())
+}
+
+/// Like `call`, but resolves imports through `loader`.
+pub fn call_with_loader(source: &str, name: &str, loader: ModuleLoader) -> RuntimeResult<Value> {
+    let file = parse(source);
+    let errors = validate_file(&file);
+    assert!(
+        errors.is_empty(),
+        "Validation should succeed, got: {:?}",
+        errors
+    );
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to start async runtime");
+    let interpreter = Interpreter::new(loader);
+    interpreter.register_file(&file)?;
+    runtime.block_on(async {
+        match interpreter.call_function_by_name(name, Vec::new()).await? {
+            Value::Future(future) => future.await_value().await,
+            other => Ok(other),
+        }
+    })
+}
+
+pub fn expect_int(value: RuntimeResult<Value>) -> i128 {
+    match value {
+        Ok(Value::Int(n)) => n,
+        other => panic!("expected int, got {other:?}"),
+    }
+}
+
+pub fn expect_str(value: RuntimeResult<Value>) -> String {
+    match value {
+        Ok(Value::String(s)) => s,
+        other => panic!("expected str, got {other:?}"),
+    }
+}
@@ -0,0 +1,73 @@
+//! Helpers that compile programs with the native backends and run them.
+
+use std::path::Path;
+use std::process::{Command, Output};
+use std::thread;
+use std::time::Duration;
+
+use nightscript_android::codegen::x86::{
+    elf_writer::write_elf as write_elf_x86,
+    emitter::{emit_x86, MachineCode},
+    lower::lower_ir as lower_ir_x86,
+};
+use nightscript_android::codegen::x86_64::{
+    elf_writer::write_elf, emitter::emit_x86_64, lower::lower_ir,
+};
+use nightscript_android::ir::{format_ir, verify_module, IrModule, PassManager};
+
+/// Compile `source` to a native x86_64 executable and run it.
+pub fn run_native(source: &str) -> Output {
+    run_module(super::lower(source))
+}
+
+/// Like `run_native`, but with the release optimization pipeline applied.
+pub fn run_native_optimized(source: &str) -> Output {
+    let mut module = super::lower(source);
+    PassManager::release()
+        .run(&mut module)
+        .expect("release pipeline");
+    run_module(module)
+}
+
+pub fn run_module(module: IrModule) -> Output {
+    let errors = verify_module(&module);
+    assert!(errors.is_empty(), "{:?}\n{}", errors, format_ir(&module));
+    let lowered = lower_ir(&module).expect("lowering should succeed");
+    let machine = emit_x86_64(&lowered).expect("emission should succeed");
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let path = dir.path().join("program");
+    write_elf(&machine, &path).expect("failed to write ELF");
+    execute(&path)
+}
+
+/// Compile `source` with the 32-bit x86 backend.
+pub fn compile_x86(source: &str) -> MachineCode {
+    let module = super::lower(source);
+    let lowered = lower_ir_x86(&module).expect("lowering should succeed");
+    emit_x86(&lowered).expect("emission should succeed")
+}
+
+/// Compile `source` with the 32-bit backend and run it. The host kernel has
+/// to be able to execute i386 binaries.
+pub fn run_native_x86(source: &str) -> Output {
+    let machine = compile_x86(source);
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let path = dir.path().join("program");
+    write_elf_x86(&machine, &path).expect("failed to write ELF");
+    execute(&path)
+}
+
+pub fn execute(path: &Path) -> Output {
+    // Another test thread may still hold the freshly written file open across
+    // a fork, which makes exec fail with ETXTBSY for a moment.
+    for _ in 0..50 {
+        match Command::new(path).output() {
+            Ok(output) => return output,
+            Err(err) if err.raw_os_error() == Some(26) => {
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(err) => panic!("failed to run {}: {}", path.display(), err),
+        }
+    }
+    panic!("{} stayed busy", path.display());
+}
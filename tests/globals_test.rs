@@ -0,0 +1,175 @@
+mod common;
+
+use std::fs;
+
+use common::{call, call_with_loader, expect_int, parse, validate};
+use nightscript_android::module_loader::ModuleLoader;
+use nightscript_android::{GlobalKind, Item};
+
+#[test]
+fn parses_const_static_and_var_items() {
+    let file = parse(
+        r#"
+    const MAX_USERS: i64 = 64;
+    static NAME = "server";
+    var hits = 0;
+    "#,
+    );
+    let kinds: Vec<(String, GlobalKind, bool)> = file
+        .items
+        .iter()
+        .map(|item| match item {
+            Item::Global(global) => (global.name.clone(), global.kind, global.ty.is_some()),
+            other => panic!("expected a global, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ("MAX_USERS".to_string(), GlobalKind::Const, true),
+            ("NAME".to_string(), GlobalKind::Static, false),
+            ("hits".to_string(), GlobalKind::Var, false),
+        ]
+    );
+}
+
+#[test]
+fn constants_fold_operators_and_earlier_constants() {
+    let source = r#"
+    const MAX_USERS = 64;
+    const TABLE_SIZE = MAX_USERS * 4 + 1;
+    static ENABLED = !false && TABLE_SIZE > 100;
+
+    fun size() -> i64 {
+        const HALF = TABLE_SIZE / 2;
+        if ENABLED {
+            return HALF;
+        }
+        return 0;
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "size")), 128);
+}
+
+#[test]
+fn var_globals_are_shared_between_functions() {
+    let source = r#"
+    var hits = 0;
+
+    fun bump() {
+        hits = hits + 1;
+    }
+
+    fun run() -> i64 {
+        bump();
+        bump();
+        bump();
+        return hits;
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "run")), 3);
+}
+
+#[test]
+fn locals_shadow_globals() {
+    let source = r#"
+    const LIMIT = 10;
+
+    fun run() -> i64 {
+        var LIMIT = 1;
+        LIMIT = LIMIT + 1;
+        return LIMIT;
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "run")), 2);
+}
+
+#[test]
+fn globals_are_exported_from_modules() {
+    let root = std::env::temp_dir().join(format!("afns-globals-{}", std::process::id()));
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(
+        root.join("src").join("limits.afml"),
+        r#"
+    pub const MAX_USERS = 64;
+    static NAME = "limits";
+
+    pub fun doubled() -> i64 {
+        return MAX_USERS * 2;
+    }
+    "#,
+    )
+    .unwrap();
+    let result = call_with_loader(
+        r#"
+    import limits;
+    import limits::MAX_USERS;
+
+    fun run() -> i64 {
+        return MAX_USERS + limits.doubled();
+    }
+    "#,
+        "run",
+        ModuleLoader::with_root(root.clone()),
+    );
+    fs::remove_dir_all(&root).ok();
+    assert_eq!(expect_int(result), 192);
+}
+
+#[test]
+fn rejects_non_constant_initializers() {
+    let errors = validate(
+        r#"
+    fun load() -> i64 {
+        return 1;
+    }
+
+    const A = load();
+    const B = C + 1;
+    const C = 2;
+    var D = 3;
+    const E = D;
+    "#,
+    );
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Initializer of `A` must be a constant expression",
+            "Initializer of `B` can only refer to `const` and `static` items declared before it, found `C`",
+            "Initializer of `E` can only refer to `const` and `static` items declared before it, found `D`",
+        ]
+    );
+}
+
+#[test]
+fn rejects_assignments_to_constants_and_duplicate_names() {
+    let errors = validate(
+        r#"
+    const LIMIT = 1;
+    static NAME = "x";
+    var count = 0;
+    var count = 1;
+
+    fun LIMIT() {}
+
+    fun run(NAME: str) {
+        LIMIT = 2;
+        NAME = "y";
+        count = 2;
+        const local = 1;
+        local = 2;
+    }
+    "#,
+    );
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Duplicate top-level name `LIMIT`",
+            "Duplicate top-level name `count`",
+            "Cannot assign to constant `LIMIT`",
+            "Cannot assign to constant `local`",
+        ]
+    );
+}
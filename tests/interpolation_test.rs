@@ -0,0 +1,117 @@
+mod common;
+
+use common::{call, expect_str, lower, parse, run_source, run_str};
+use nightscript_android::diagnostics::AfnsError;
+use nightscript_android::ir::{format_ir, verify_module};
+use nightscript_android::parser;
+use nightscript_android::type_checker::check_file;
+
+const POINT: &str = "struct Point { x:: i64, y:: i64 }\n";
+
+#[test]
+fn embedded_expressions_are_converted_to_strings() {
+    assert_eq!(
+        run_str(
+            r#"let name = "ada"; let count = 2;
+        return "user ${name} has ${count + 1} items";"#
+        ),
+        "user ada has 3 items"
+    );
+    assert_eq!(
+        run_str(r#"return "${1 < 2}/${'c'}/${vec.len(vec.new())}";"#),
+        "true/c/0"
+    );
+    assert_eq!(run_str(r#"let n = 4; return "${n}";"#), "4");
+}
+
+#[test]
+fn literals_may_nest_and_escape_the_dollar_sign() {
+    assert_eq!(
+        run_str(r#"let n = 2; return "a ${"b${n}c"} d";"#),
+        "a b2c d"
+    );
+    assert_eq!(
+        run_str(r#"return "\${raw} $ {x} {braces}";"#),
+        "${raw} $ {x} {braces}"
+    );
+}
+
+#[test]
+fn display_impls_render_user_types() {
+    let items = format!(
+        "{POINT}
+    trait Display {{ fun to_string(self:: Point) -> str; }}
+
+    impl Display for Point {{
+        fun to_string(self:: Point) -> str {{
+            return \"(${{self.x}}, ${{self.y}})\";
+        }}
+    }}"
+    );
+    let render = |items: &str| {
+        let body = "let p = Point { x: 1, y: 2 }; return \"p=${p}\";";
+        expect_str(call(&run_source(items, "str", body), "run"))
+    };
+    assert_eq!(render(&items), "p=(1, 2)");
+    let plain = render(POINT);
+    assert!(plain.starts_with("p=") && plain.contains('1'), "{plain}");
+}
+
+#[test]
+fn diagnostics_point_inside_the_literal() {
+    let source = "fun run() {\n    let s = \"sum ${1 + } done\";\n}\n";
+    let Err(AfnsError::Parse(err)) = parser::parse(source) else {
+        panic!("expected a parse error");
+    };
+    let span = err.span().expect("span");
+    assert_eq!(span.line, 2);
+    assert!(source[span.start..].starts_with("} done"), "{span:?}");
+
+    for source in [
+        "fun run() {\n    let s = \"a ${x\";\n}\n",
+        "fun run() {\n    let s = \"a ${x",
+    ] {
+        let Err(AfnsError::Lex(err)) = parser::parse(source) else {
+            panic!("expected a lex error");
+        };
+        assert!(
+            err.to_string()
+                .starts_with("unterminated `${` interpolation"),
+            "{err}"
+        );
+        let span = err.span().expect("span");
+        assert_eq!((span.line, span.column), (2, 16));
+        assert_eq!(&source[span.start..span.end], "${");
+    }
+
+    let source = "fun run() {\n    var n = 0;\n    let s = \"got ${n + true} ${n = 1}\";\n}\n";
+    let errors = check_file(&parse(source));
+    let spans: Vec<&str> = errors
+        .iter()
+        .map(|e| &source[e.span.start..e.span.end])
+        .collect();
+    assert_eq!(spans, vec!["n + true", "n = 1"], "{errors:?}");
+    assert_eq!(
+        errors[1].message,
+        "Cannot interpolate a value of type `unit`"
+    );
+
+    let err = call(
+        "fun run() -> str {\n    let v = vec.new();\n    return \"at ${v[3]}\";\n}\n",
+        "run",
+    )
+    .unwrap_err();
+    assert_eq!(err.span().map(|span| span.line), Some(3));
+}
+
+#[test]
+fn interpolation_lowers_to_string_concat() {
+    let module = lower("fun greet(n:: i32) -> str { return \"${n} items, ${n + 1} later\"; }\n");
+    assert!(verify_module(&module).is_empty());
+    let text = format_ir(&module);
+    assert_eq!(
+        text.matches("call_intrinsic StringConcat").count(),
+        4,
+        "{text}"
+    );
+}
@@ -0,0 +1,386 @@
+mod common;
+
+use nightscript_android::ir::passes::{
+    pass_by_name, ConstFold, CopyProp, DeadBlockElim, DeadCodeElim, Licm, Pass, SimplifyCfg,
+};
+use nightscript_android::ir::{
+    format_ir, CmpOp, IrBuilder, IrFunction, IrInstr, IrModule, IrTerm, IrType, PassManager,
+};
+
+fn instrs(func: &IrFunction) -> Vec<&IrInstr> {
+    func.blocks.iter().flat_map(|block| &block.body).collect()
+}
+
+fn count(func: &IrFunction, pred: impl Fn(&IrInstr) -> bool) -> usize {
+    instrs(func).into_iter().filter(|instr| pred(instr)).count()
+}
+
+fn int(builder: &mut IrBuilder, func: usize, block: u32, value: i128) -> u32 {
+    let dst = builder.next_value(func);
+    builder.emit(
+        func,
+        block,
+        IrInstr::LoadConstInt {
+            dst,
+            value,
+            ty: IrType::I32,
+        },
+    );
+    dst
+}
+
+fn add(builder: &mut IrBuilder, func: usize, block: u32, a: u32, b: u32) -> u32 {
+    let dst = builder.next_value(func);
+    builder.emit(
+        func,
+        block,
+        IrInstr::Add {
+            dst,
+            a,
+            b,
+            ty: IrType::I32,
+        },
+    );
+    dst
+}
+
+/// `fn count(n) -> i32`: a loop from 0 to `n` whose body computes
+/// `n * 3`, which does not depend on the loop.
+fn counting_loop() -> IrModule {
+    let mut b = IrBuilder::new();
+    let f = b.new_function("count", vec![IrType::I32], IrType::I32);
+    let entry = b.new_block(f);
+    let head = b.new_block(f);
+    let body = b.new_block(f);
+    let exit = b.new_block(f);
+    let n = b.next_value(f);
+    let zero = int(&mut b, f, entry, 0);
+    b.set_term(f, entry, IrTerm::Br { target: head });
+    let i = b.next_value(f);
+    let next = b.next_value(f);
+    b.emit(
+        f,
+        head,
+        IrInstr::Phi {
+            dst: i,
+            incomings: vec![(entry, zero), (body, next)],
+        },
+    );
+    let cond = b.next_value(f);
+    b.emit(
+        f,
+        head,
+        IrInstr::Cmp {
+            dst: cond,
+            a: i,
+            b: n,
+            cond: CmpOp::Lt,
+            ty: IrType::I32,
+        },
+    );
+    b.set_term(
+        f,
+        head,
+        IrTerm::CondBr {
+            cond,
+            then_b: body,
+            else_b: exit,
+        },
+    );
+    let three = int(&mut b, f, body, 3);
+    let scaled = b.next_value(f);
+    b.emit(
+        f,
+        body,
+        IrInstr::Mul {
+            dst: scaled,
+            a: n,
+            b: three,
+            ty: IrType::I32,
+        },
+    );
+    let bumped = add(&mut b, f, body, i, scaled);
+    let one = int(&mut b, f, body, 1);
+    b.emit(
+        f,
+        body,
+        IrInstr::Sub {
+            dst: next,
+            a: bumped,
+            b: one,
+            ty: IrType::I32,
+        },
+    );
+    b.set_term(f, body, IrTerm::Br { target: head });
+    b.set_term(f, exit, IrTerm::Ret { value: Some(i) });
+    b.finish()
+}
+
+#[test]
+fn const_fold_folds_arithmetic_and_constant_branches() {
+    let source = r#"
+fun apex() {
+    let x = 2 * 3 + 4;
+    if x > 5 {
+        log.info("big");
+    } else {
+        log.info("small");
+    }
+}
+"#;
+    let mut module = common::lower(source);
+    assert!(ConstFold.run(&mut module));
+    let apex = &module.funcs[0];
+    assert_eq!(
+        count(apex, |i| matches!(
+            i,
+            IrInstr::Mul { .. } | IrInstr::Add { .. }
+        )),
+        0,
+        "{}",
+        format_ir(&module)
+    );
+    let terms: Vec<_> = apex.blocks.iter().filter_map(|b| b.term.as_ref()).collect();
+    assert!(
+        !terms.iter().any(|t| matches!(t, IrTerm::CondBr { .. })),
+        "{}",
+        format_ir(&module)
+    );
+    assert!(
+        !ConstFold.run(&mut module),
+        "folding should reach a fixpoint"
+    );
+}
+
+#[test]
+fn const_fold_leaves_division_by_zero_alone() {
+    let mut b = IrBuilder::new();
+    let f = b.new_function("apex", Vec::new(), IrType::I32);
+    let entry = b.new_block(f);
+    let one = int(&mut b, f, entry, 1);
+    let zero = int(&mut b, f, entry, 0);
+    let dst = b.next_value(f);
+    b.emit(
+        f,
+        entry,
+        IrInstr::Div {
+            dst,
+            a: one,
+            b: zero,
+            ty: IrType::I32,
+            signed: true,
+        },
+    );
+    b.set_term(f, entry, IrTerm::Ret { value: Some(dst) });
+    let mut module = b.finish();
+    assert!(!ConstFold.run(&mut module));
+    assert!(!DeadCodeElim.run(&mut module));
+}
+
+#[test]
+fn copy_prop_forwards_trivial_phis_and_identities() {
+    let mut b = IrBuilder::new();
+    let f = b.new_function("apex", vec![IrType::I32], IrType::I32);
+    let entry = b.new_block(f);
+    let next = b.new_block(f);
+    let arg = b.next_value(f);
+    let zero = int(&mut b, f, entry, 0);
+    let same = add(&mut b, f, entry, arg, zero);
+    b.set_term(f, entry, IrTerm::Br { target: next });
+    let phi = b.next_value(f);
+    b.emit(
+        f,
+        next,
+        IrInstr::Phi {
+            dst: phi,
+            incomings: vec![(entry, same)],
+        },
+    );
+    b.set_term(f, next, IrTerm::Ret { value: Some(phi) });
+    let mut module = b.finish();
+
+    assert!(CopyProp.run(&mut module));
+    let func = &module.funcs[0];
+    assert_eq!(
+        count(func, |i| matches!(
+            i,
+            IrInstr::Phi { .. } | IrInstr::Add { .. }
+        )),
+        0
+    );
+    assert!(matches!(
+        func.blocks[1].term,
+        Some(IrTerm::Ret { value: Some(v) }) if v == arg
+    ));
+}
+
+#[test]
+fn dce_removes_unused_values_but_keeps_effects() {
+    let source = r#"
+fun apex() {
+    let unused = 40 + 2;
+    var spin = 0;
+    while spin < 3 {
+        spin = spin + 1;
+    }
+    log.info("kept");
+}
+"#;
+    let mut module = common::lower(source);
+    assert!(DeadCodeElim.run(&mut module));
+    let apex = &module.funcs[0];
+    assert_eq!(
+        count(apex, |i| matches!(i, IrInstr::Add { .. })),
+        1,
+        "only the loop counter update stays: {}",
+        format_ir(&module)
+    );
+    assert_eq!(count(apex, |i| matches!(i, IrInstr::PrintStr { .. })), 2);
+}
+
+#[test]
+fn dce_removes_dead_loop_cycles() {
+    let source = r#"
+fun apex() {
+    var spin = 0;
+    var junk = 0;
+    while spin < 3 {
+        spin = spin + 1;
+        junk = junk + 5;
+    }
+}
+"#;
+    let mut module = common::lower(source);
+    assert_eq!(
+        count(&module.funcs[0], |i| matches!(i, IrInstr::Phi { .. })),
+        2
+    );
+    assert!(DeadCodeElim.run(&mut module));
+    let apex = &module.funcs[0];
+    // `junk` only feeds itself; `spin` decides the branch and survives.
+    assert_eq!(
+        count(apex, |i| matches!(i, IrInstr::Phi { .. })),
+        1,
+        "{}",
+        format_ir(&module)
+    );
+    assert_eq!(count(apex, |i| matches!(i, IrInstr::Add { .. })), 1);
+}
+
+#[test]
+fn dead_blocks_drops_unreachable_blocks_and_phi_incomings() {
+    let mut b = IrBuilder::new();
+    let f = b.new_function("apex", Vec::new(), IrType::I32);
+    let entry = b.new_block(f);
+    let orphan = b.new_block(f);
+    let join = b.new_block(f);
+    let one = int(&mut b, f, entry, 1);
+    b.set_term(f, entry, IrTerm::Br { target: join });
+    let two = int(&mut b, f, orphan, 2);
+    b.set_term(f, orphan, IrTerm::Br { target: join });
+    let phi = b.next_value(f);
+    b.emit(
+        f,
+        join,
+        IrInstr::Phi {
+            dst: phi,
+            incomings: vec![(entry, one), (orphan, two)],
+        },
+    );
+    b.set_term(f, join, IrTerm::Ret { value: Some(phi) });
+    let mut module = b.finish();
+
+    assert!(DeadBlockElim.run(&mut module));
+    let func = &module.funcs[0];
+    assert_eq!(func.blocks.len(), 2);
+    assert!(func.block(orphan).is_none());
+    match &func.block(join).unwrap().body[0] {
+        IrInstr::Phi { incomings, .. } => assert_eq!(incomings, &vec![(entry, one)]),
+        other => panic!("expected phi, got {:?}", other),
+    }
+}
+
+#[test]
+fn simplify_cfg_merges_straight_line_blocks() {
+    let source = r#"
+fun apex() {
+    if true {
+        log.info("a");
+    }
+    log.info("b");
+}
+"#;
+    let mut module = common::lower(source);
+    ConstFold.run(&mut module);
+    assert!(SimplifyCfg.run(&mut module));
+    let apex = &module.funcs[0];
+    assert_eq!(apex.blocks.len(), 1, "{}", format_ir(&module));
+    assert!(matches!(apex.blocks[0].term, Some(IrTerm::Ret { .. })));
+}
+
+#[test]
+fn licm_hoists_invariant_code_into_the_preheader() {
+    let mut module = counting_loop();
+    assert!(Licm.run(&mut module));
+    let func = &module.funcs[0];
+    let entry = &func.blocks[0];
+    assert!(
+        entry.body.iter().any(|i| matches!(i, IrInstr::Mul { .. })),
+        "{}",
+        format_ir(&module)
+    );
+    let body = func.block(2).unwrap();
+    assert!(
+        body.body
+            .iter()
+            .all(|i| !matches!(i, IrInstr::Mul { .. } | IrInstr::LoadConstInt { .. })),
+        "{}",
+        format_ir(&module)
+    );
+    // The add depends on the loop phi and must stay.
+    assert!(body.body.iter().any(|i| matches!(i, IrInstr::Add { .. })));
+}
+
+#[test]
+fn licm_inserts_a_preheader_for_conditional_entries() {
+    let mut module = counting_loop();
+    let func = &mut module.funcs[0];
+    // Enter the loop from a conditional branch instead of a plain jump.
+    func.blocks[0].term = Some(IrTerm::CondBr {
+        cond: 0,
+        then_b: 1,
+        else_b: 3,
+    });
+    assert!(Licm.run(&mut module));
+    let func = &module.funcs[0];
+    assert_eq!(func.blocks.len(), 5, "{}", format_ir(&module));
+    let preheader = func.blocks.last().unwrap();
+    assert!(matches!(preheader.term, Some(IrTerm::Br { target: 1 })));
+    assert!(preheader
+        .body
+        .iter()
+        .any(|i| matches!(i, IrInstr::Mul { .. })));
+    match &func.block(1).unwrap().body[0] {
+        IrInstr::Phi { incomings, .. } => assert_eq!(incomings[0].0, preheader.id),
+        other => panic!("expected phi, got {:?}", other),
+    }
+}
+
+#[test]
+fn passes_are_selected_by_name() {
+    let manager = PassManager::from_names(&["const-fold", "dce"]).unwrap();
+    assert_eq!(manager.pass_names(), vec!["const-fold", "dce"]);
+    assert!(pass_by_name("licm").is_some());
+    let err = PassManager::from_names(&["inline"]).err().unwrap();
+    assert!(err.to_string().contains("unknown IR pass `inline`"));
+}
+
+#[test]
+fn release_pipeline_reports_every_pass() {
+    let mut module = counting_loop();
+    let mut seen = Vec::new();
+    PassManager::release()
+        .run_with(&mut module, |pass, _| seen.push(pass.to_string()))
+        .unwrap();
+    assert_eq!(seen, PassManager::release().pass_names());
+}
@@ -0,0 +1,353 @@
+mod common;
+
+use nightscript_android::ir::{
+    format_ir, parse_ir, verify_module, GlobalInit, IrGlobal, IrInstr, IrModule, IrTerm, IrType,
+    PassManager,
+};
+
+fn verify_messages(text: &str) -> Vec<String> {
+    let module = parse_ir(text).expect("fixture should parse");
+    verify_module(&module)
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn format_ir_round_trips_through_the_parser() {
+    let source = r#"
+fun pick(flag:: bool, n:: i32) -> i32 {
+    var total = 0;
+    var i = 0;
+    while i < n {
+        if flag {
+            total = total + i * 2;
+        } else {
+            total = total - i / 3 % 2;
+        }
+        i = i + 1;
+    }
+    return total;
+}
+
+fun apex() {
+    log.info("tab\there \"quoted\"", pick(true, 4), pick(false, 9) > 0);
+}
+"#;
+    let mut module = common::lower(source);
+    module.globals.push(IrGlobal {
+        id: 0,
+        name: "limit".to_string(),
+        ty: IrType::I64,
+        mutable: true,
+        init: GlobalInit::Const {
+            value: -3,
+            ty: IrType::I64,
+        },
+    });
+    let text = format_ir(&module);
+    let parsed = parse_ir(&text).unwrap_or_else(|err| panic!("{}\n{}", err, text));
+    assert_eq!(format_ir(&parsed), text);
+    assert!(verify_module(&parsed).is_empty());
+
+    PassManager::release().run(&mut module).unwrap();
+    let optimized = format_ir(&module);
+    assert_eq!(format_ir(&parse_ir(&optimized).unwrap()), optimized);
+}
+
+#[test]
+fn hand_written_ir_may_name_callees_and_parameters() {
+    let module = parse_ir(
+        r#"
+; comments and blank lines are ignored
+
+fn double(%arg0: I32) -> I32 {
+  block0:
+    %1 = add I32 %arg0, %arg0
+    ret %1
+}
+
+fn apex() -> Void {
+  block0:
+    %0 = load_const I32 21
+    %1 = call @double(%0)
+    print_val %1 I32
+    ret
+}
+"#,
+    )
+    .unwrap();
+    assert_eq!(module.funcs.len(), 2);
+    assert_eq!(module.funcs[0].params, vec![IrType::I32]);
+    assert_eq!(module.funcs[0].next_value, 2);
+    assert!(matches!(
+        module.funcs[1].blocks[0].body[1],
+        IrInstr::Call {
+            dst: Some(1),
+            func: 0,
+            ..
+        }
+    ));
+    assert!(matches!(
+        module.funcs[1].blocks[0].term,
+        Some(IrTerm::Ret { value: None })
+    ));
+    assert!(verify_module(&module).is_empty());
+}
+
+#[test]
+fn parse_errors_carry_the_line_number() {
+    let err = parse_ir("fn apex() -> Void {\n  block0:\n    %0 = frobnicate %1\n    ret\n}\n")
+        .unwrap_err();
+    assert_eq!(err.line, 3);
+    assert!(err.message.contains("unknown instruction `frobnicate`"));
+
+    let err = parse_ir("fn apex() -> Void {\n  block0:\n    ret\n    ret\n}\n").unwrap_err();
+    assert_eq!(err.line, 4);
+    assert!(err.message.contains("already has a terminator"));
+
+    let err = parse_ir("fn apex() -> Void {\n  block0:\n    call @missing()\n").unwrap_err();
+    assert!(err.message.contains("unknown function @missing"));
+}
+
+#[test]
+fn verifier_reports_use_before_definition() {
+    let errors = verify_messages(
+        r#"
+fn apex() -> I32 {
+  block0:
+    %0 = add I32 %1, %1
+    %1 = load_const I32 1
+    ret %2
+}
+"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "fn apex, block0: value %1 is used before its definition",
+            "fn apex, block0: value %1 is used before its definition",
+            "fn apex, block0: use of undefined value %2",
+        ]
+    );
+}
+
+#[test]
+fn verifier_reports_values_that_do_not_dominate_their_uses() {
+    let errors = verify_messages(
+        r#"
+fn apex(%arg0: Bool) -> I32 {
+  block0:
+    condbr %arg0, block1, block2
+  block1:
+    %1 = load_const I32 1
+    br block2
+  block2:
+    ret %1
+}
+"#,
+    );
+    assert_eq!(
+        errors,
+        vec!["fn apex, block2: value %1 (defined in block1) does not dominate this use"]
+    );
+}
+
+#[test]
+fn verifier_reports_mismatched_phi_predecessors() {
+    let errors = verify_messages(
+        r#"
+fn apex(%arg0: Bool) -> I32 {
+  block0:
+    %1 = load_const I32 1
+    condbr %arg0, block1, block2
+  block1:
+    br block2
+  block2:
+    %2 = phi (block0, %1), (block3, %1)
+    ret %2
+  block3:
+    br block2
+}
+"#,
+    );
+    assert_eq!(
+        errors,
+        vec!["fn apex, block2: phi %2 has no incoming value for block1"]
+    );
+
+    let errors = verify_messages(
+        r#"
+fn apex() -> I32 {
+  block0:
+    %0 = load_const I32 1
+    br block1
+  block1:
+    %1 = phi (block0, %0), (block0, %0), (block7, %0)
+    ret %1
+}
+"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "fn apex, block1: phi %1 has two incomings from block0",
+            "fn apex, block1: phi %1 has an incoming from block7, which is not a predecessor",
+        ]
+    );
+}
+
+#[test]
+fn verifier_reports_missing_terminators_and_bad_targets() {
+    let mut module = parse_ir(
+        r#"
+fn apex() -> Void {
+  block0:
+    br block4
+  block1:
+    ret
+}
+"#,
+    )
+    .unwrap();
+    module.funcs[0].blocks[1].term = None;
+    let errors: Vec<String> = verify_module(&module)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "fn apex, block0: branch to unknown block4",
+            "fn apex, block1: block has no terminator",
+        ]
+    );
+}
+
+#[test]
+fn verifier_checks_calls_and_returns() {
+    let errors = verify_messages(
+        r#"
+fn log_it(%arg0: I32) -> Void {
+  block0:
+    ret %arg0
+}
+
+fn count() -> I32 {
+  block0:
+    ret
+}
+
+fn apex() -> Void {
+  block0:
+    %0 = load_const I32 1
+    %1 = call @log_it(%0, %0)
+    call %9()
+    ret
+}
+"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "fn log_it, block0: void function returns a value",
+            "fn count, block0: non-void function returns no value",
+            "fn apex, block0: call to log_it passes 2 arguments, expected 1",
+            "fn apex, block0: call to void function log_it has a result",
+            "fn apex, block0: call to unknown function %9",
+        ]
+    );
+}
+
+#[test]
+fn globals_lower_to_ir_globals() {
+    let source = r#"
+const LIMIT = 4 * 8;
+static NAME = "limits";
+var hits = 0;
+
+fun bump() -> i32 {
+    hits = hits + LIMIT;
+    return hits;
+}
+"#;
+    let module = common::lower(source);
+    let globals: Vec<(&str, bool, &GlobalInit)> = module
+        .globals
+        .iter()
+        .map(|g| (g.name.as_str(), g.mutable, &g.init))
+        .collect();
+    assert_eq!(globals.len(), 3);
+    assert!(matches!(
+        globals[0],
+        ("LIMIT", false, GlobalInit::Const { value: 32, .. })
+    ));
+    assert!(matches!(
+        globals[1],
+        ("NAME", false, GlobalInit::FromString(_))
+    ));
+    assert!(matches!(
+        globals[2],
+        ("hits", true, GlobalInit::Const { value: 0, .. })
+    ));
+
+    let text = format_ir(&module);
+    assert!(text.contains("%0 = load_global I64 @hits"), "{}", text);
+    assert!(text.contains("%1 = load_const I64 32"), "{}", text);
+    assert!(text.contains("store_global I64 @hits, %2"), "{}", text);
+    assert_eq!(format_ir(&parse_ir(&text).unwrap()), text);
+    assert!(verify_module(&module).is_empty());
+}
+
+#[test]
+fn verifier_checks_global_references() {
+    let errors = verify_messages(
+        r#"
+@limit : I32 = const I32 3
+
+fn apex() -> Void {
+  block0:
+    %0 = load_global I32 @limit
+    %1 = load_global I32 @7
+    store_global I32 @limit, %0
+    ret
+}
+"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "fn apex, block0: unknown global @7",
+            "fn apex, block0: store to immutable global @limit",
+        ]
+    );
+    let err =
+        parse_ir("fn apex() -> Void {\n  block0:\n    %0 = load_global I32 @nope\n").unwrap_err();
+    assert!(err.message.contains("unknown global @nope"));
+}
+
+#[test]
+fn pass_manager_rejects_passes_that_break_the_ir() {
+    struct Breaker;
+
+    impl nightscript_android::ir::passes::Pass for Breaker {
+        fn name(&self) -> &'static str {
+            "breaker"
+        }
+
+        fn run_on_function(&self, func: &mut nightscript_android::ir::IrFunction) -> bool {
+            func.blocks[0].term = None;
+            true
+        }
+    }
+
+    let mut module: IrModule = parse_ir("fn apex() -> Void {\n  block0:\n    ret\n}\n").unwrap();
+    let err = PassManager::new()
+        .add(Breaker)
+        .run(&mut module)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "IR verification failed after `breaker`: fn apex, block0: block has no terminator"
+    );
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use common::{call, expect_int, expect_str, lower, parse, run_i64};
+use nightscript_android::ir::{format_ir, verify_module};
+use nightscript_android::type_checker::check_file;
+
+#[test]
+fn ranges_take_steps_and_inclusive_ends() {
+    assert_eq!(
+        run_i64("var t = 0; for i in 0..10 step 3 { t = t + i; } return t;"),
+        18
+    );
+    assert_eq!(
+        run_i64("var t = 0; for i in 1..=5 { t = t + i; } return t;"),
+        15
+    );
+    assert_eq!(
+        run_i64("var t = 0; for i in 2..=10 step 4 { t = t * 100 + i; } return t;"),
+        20610
+    );
+    assert_eq!(run_i64("let r = 0..=6 step 2; return vec.len(r);"), 4);
+    assert_eq!(
+        run_i64("var t = 0; for i in 5..5 { t = t + 1; } return t;"),
+        0
+    );
+}
+
+#[test]
+fn large_ranges_are_walked_lazily() {
+    let body = "var n = 0;
+        for i in 0..1000000000000000 {
+            n = n + 1;
+            if n == 3 { break; }
+        }
+        return n;";
+    assert_eq!(run_i64(body), 3);
+}
+
+#[test]
+fn range_steps_must_be_positive() {
+    let source = "fun run() -> i64 {\n    for i in 0..10 step 0 { return i; }\n    return 0;\n}\n";
+    let err = call(source, "run").unwrap_err();
+    assert_eq!(err.message(), "Range step must be positive, got 0");
+    let span = err.span().expect("step span");
+    assert_eq!(&source[span.start..span.end], "0");
+    assert_eq!(span.line, 2);
+}
+
+#[test]
+fn maps_sets_and_strings_are_iterable() {
+    let body = r#"var m = map.new();
+        map.put(m, "a", 1);
+        map.put(m, "bb", 20);
+        var total = 0;
+        for (k, v) in m { total = total + str.len(k) * v; }
+        return total;"#;
+    assert_eq!(run_i64(body), 41);
+
+    let body = "var s = set.new();
+        set.insert(s, 3);
+        set.insert(s, 4);
+        set.insert(s, 3);
+        var total = 0;
+        for x in s { total = total + x; }
+        return total;";
+    assert_eq!(run_i64(body), 7);
+
+    let source = r#"
+        fun run() -> str {
+            var out = "";
+            for c in "héllo" {
+                if c != 'l' { out = "${c}${out}"; }
+            }
+            return out;
+        }
+    "#;
+    assert_eq!(expect_str(call(source, "run")), "oéh");
+}
+
+#[test]
+fn vec_loops_stop_at_the_length_they_started_with() {
+    let body = "var v = vec.new();
+        vec.push(v, 1);
+        vec.push(v, 2);
+        for x in v { vec.push(v, x); }
+        var total = 0;
+        for x in v { total = total + x; }
+        return total;";
+    assert_eq!(run_i64(body), 6);
+}
+
+const COUNTDOWN: &str = "
+    struct Countdown {
+        left: i64,
+    }
+
+    trait Iterator {
+        fun next(self_mut:: Self) -> option<i64>;
+    }
+
+    impl Iterator for Countdown {
+        fun next(self_mut:: Countdown) -> option<i64> {
+            let n = self_mut.left;
+            if n == 0 {
+                return option.none();
+            }
+            self_mut.left = n - 1;
+            return option.some(n);
+        }
+    }
+";
+
+#[test]
+fn user_types_iterate_through_the_iterator_trait() {
+    let source = format!(
+        "{COUNTDOWN}
+        fun run() -> i64 {{
+            let counter = Countdown {{ left: 3 }};
+            var digits = 0;
+            for n in counter {{ digits = digits * 10 + n; }}
+            return digits;
+        }}
+        "
+    );
+    assert_eq!(expect_int(call(&source, "run")), 321);
+}
+
+#[test]
+fn non_iterables_are_rejected() {
+    let err = call("fun run() { for x in true { } }", "run").unwrap_err();
+    assert_eq!(err.message(), "for-loop cannot iterate over bool");
+
+    let errors: Vec<String> = check_file(&parse(
+        "fun run() { for x in 5 { } for (k, v) in map.new() { } for c in \"ab\" { let n:: i64 = c; } }",
+    ))
+    .into_iter()
+    .map(|e| e.message)
+    .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "Cannot iterate over a value of type `integer`",
+            "Mismatched types: expected `i64`, found `char`",
+        ]
+    );
+}
+
+#[test]
+fn stepped_inclusive_ranges_lower_to_ir() {
+    let module = lower(
+        "fun sum(n:: i32) -> i32 { var t = 0; for i in 0..=n step 2 { t = t + i; } return t; }\n",
+    );
+    assert!(verify_module(&module).is_empty());
+    let text = format_ir(&module);
+    assert!(
+        text.contains("cmp Le "),
+        "missing inclusive compare in\n{text}"
+    );
+    assert!(
+        text.contains("add I32 %4, %3"),
+        "missing stepped increment in\n{text}"
+    );
+}
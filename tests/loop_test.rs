@@ -0,0 +1,138 @@
+mod common;
+
+use common::{lower, parse, run_i64, validate};
+use nightscript_android::ir::{format_ir, verify_module};
+use nightscript_android::type_checker::check_file;
+
+fn loop_errors(body: &str) -> Vec<String> {
+    validate(&format!("fun run() {{\n    {body}\n}}\n"))
+        .into_iter()
+        .map(|e| e.message)
+        .collect()
+}
+
+#[test]
+fn labeled_break_and_continue_target_the_outer_loop() {
+    let body = "var total = 0;
+        'outer: for i in [1, 2, 3, 4] {
+            for j in [1, 2, 3] {
+                if j == 2 { continue 'outer; }
+                if i == 4 { break 'outer; }
+                total = total + i * 10 + j;
+            }
+        }
+        return total;";
+    assert_eq!(run_i64(body), 63);
+
+    let body = "var k = 0;
+        'rows: while true {
+            loop {
+                k = k + 1;
+                if k > 3 { break 'rows; }
+            }
+        }
+        return k;";
+    assert_eq!(run_i64(body), 4);
+}
+
+#[test]
+fn loop_yields_the_value_of_break() {
+    let body = "var n = 0;
+        let found = loop {
+            n = n + 1;
+            if n * n > 50 { break n; }
+        };
+        return found;";
+    assert_eq!(run_i64(body), 8);
+
+    let body = "var i = 0;
+        let hit = 'scan: loop {
+            loop {
+                i = i + 1;
+                if i == 5 { break 'scan i * 100; }
+            }
+        };
+        return hit;";
+    assert_eq!(run_i64(body), 500);
+}
+
+#[test]
+fn while_let_and_if_let_bind_on_match() {
+    let body = "var items = vec.new();
+        vec.push(items, 1);
+        vec.push(items, 2);
+        vec.push(items, 4);
+        var sum = 0;
+        while let Some(x) = vec.pop(items) {
+            sum = sum + x;
+        }
+        return sum;";
+    assert_eq!(run_i64(body), 7);
+
+    let body = "let maybe = option.none();
+        if let Some(v) = maybe {
+            return v;
+        } else if let (a, b) = (3, 4) {
+            return a * b;
+        }
+        return 0;";
+    assert_eq!(run_i64(body), 12);
+}
+
+#[test]
+fn unknown_labels_and_misplaced_breaks_are_reported() {
+    assert_eq!(
+        loop_errors("'a: while true { break 'b; }"),
+        vec!["Unknown loop label `'b`"]
+    );
+    assert_eq!(
+        loop_errors("for x in [1] { continue 'outer; }"),
+        vec!["Unknown loop label `'outer`"]
+    );
+    assert_eq!(
+        loop_errors("while true { break 1; }"),
+        vec!["`break` with a value is only allowed inside `loop`"]
+    );
+    assert_eq!(loop_errors("break;"), vec!["break used outside of a loop"]);
+    assert!(loop_errors("'a: loop { 'b: for x in [1] { break 'a; } }").is_empty());
+}
+
+#[test]
+fn let_conditions_type_their_bindings() {
+    let errors: Vec<String> = check_file(&parse(
+        "fun run() { while let (a, b) = (1, true) { let c:: bool = a; let d:: bool = b; } }",
+    ))
+    .into_iter()
+    .map(|e| e.message)
+    .collect();
+    assert_eq!(
+        errors,
+        vec!["Mismatched types: expected `bool`, found `integer`"]
+    );
+}
+
+#[test]
+fn loops_with_labels_and_values_lower_to_ir() {
+    let module = lower(
+        "fun find(limit:: i64) -> i64 {
+            var n = 0;
+            let found = 'search: loop {
+                n = n + 1;
+                while n < limit {
+                    if n == 7 { break 'search n; }
+                    n = n + 1;
+                    continue 'search;
+                }
+                break 0;
+            };
+            return found;
+        }\n",
+    );
+    assert!(
+        verify_module(&module).is_empty(),
+        "{:?}",
+        verify_module(&module)
+    );
+    let text = format_ir(&module);
+    assert!(text.contains("phi"), "missing phi in\n{text}");
+}
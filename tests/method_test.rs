@@ -0,0 +1,129 @@
+mod common;
+
+use common::{call, expect_int, expect_str, parse, run_source};
+use nightscript_android::type_checker::check_file;
+use nightscript_android::Value;
+
+const STATES: &str = r#"
+    enum State { Idle, Running, Done }
+
+    impl State {
+        fun next(self:: State) -> State {
+            if self == State::Idle {
+                return State::Running;
+            }
+            return State::Done;
+        }
+
+        fun is_done(self:: State) -> bool {
+            return self == State::Done;
+        }
+
+        fun reset(self_mut:: State) -> State {
+            return State::Idle;
+        }
+    }
+
+    impl i32 {
+        fun double(self:: i32) -> i32 {
+            return self * 2;
+        }
+    }
+
+    impl str {
+        fun shout(self:: str) -> str {
+            return "loud";
+        }
+    }
+
+    impl tuple(i64, str) {
+        fun second(self:: tuple(i64, str)) -> str {
+            return "pair";
+        }
+    }
+"#;
+
+#[test]
+fn enums_have_inherent_methods() {
+    let done = |body| match call(&run_source(STATES, "bool", body), "run") {
+        Ok(Value::Bool(b)) => b,
+        other => panic!("expected bool, got {other:?}"),
+    };
+    assert!(!done("return State::Idle.next().is_done();"));
+    assert!(done(
+        "let s = State::Idle; return s.next().next().is_done();"
+    ));
+    assert!(!done("var s = State::Done; return s.reset().is_done();"));
+    assert!(check_file(&parse(&run_source(STATES, "bool", "return true;"))).is_empty());
+}
+
+#[test]
+fn primitives_and_tuples_have_inherent_methods() {
+    let int = |body| expect_int(call(&run_source(STATES, "i32", body), "run"));
+    assert_eq!(int("let n:: i32 = 21; return n.double();"), 42);
+    assert_eq!(
+        expect_str(call(
+            &run_source(STATES, "str", "return \"x\".shout();"),
+            "run"
+        )),
+        "loud"
+    );
+    assert_eq!(
+        expect_str(call(
+            &run_source(STATES, "str", "let p = (1, \"a\"); return p.second();"),
+            "run"
+        )),
+        "pair"
+    );
+}
+
+#[test]
+fn self_mut_methods_need_a_mutable_receiver() {
+    let err = call(
+        &run_source(
+            STATES,
+            "bool",
+            "let s = State::Done; return s.reset().is_done();",
+        ),
+        "run",
+    )
+    .unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("cannot borrow immutable value as mutable"),
+        "{}",
+        err.message()
+    );
+}
+
+#[test]
+fn unknown_enum_methods_are_reported() {
+    let err = call(
+        &run_source(STATES, "bool", "return State::Idle.stop();"),
+        "run",
+    )
+    .unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("Unknown method `stop` on enum `State`"),
+        "{}",
+        err.message()
+    );
+
+    let source = run_source(
+        STATES,
+        "str",
+        "let s = State::Idle; s.stop(); let n:: i32 = 2; return n.double();",
+    );
+    let messages: Vec<String> = check_file(&parse(&source))
+        .into_iter()
+        .map(|e| e.message)
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "No method named `stop` found for type `State`",
+            "Mismatched types: expected `str`, found `i32`",
+        ]
+    );
+}
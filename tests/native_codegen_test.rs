@@ -0,0 +1,587 @@
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use std::path::Path;
+
+use common::native::{compile_x86, run_module, run_native, run_native_optimized, run_native_x86};
+use nightscript_android::ir::{build_ir, format_ir, parse_ir, IrInstr};
+
+fn expect_stdout(source: &str, expected: &str) {
+    let output = run_native(source);
+    assert!(output.status.success(), "exit status: {}", output.status);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn build_ir_lowers_every_function() {
+    let source = r#"
+fun add(a:: i32, b:: i32) -> i32 {
+    return a + b;
+}
+
+fun apex() {
+    var total = add(1, 2);
+}
+"#;
+    let module = common::lower(source);
+    assert_eq!(module.funcs.len(), 2);
+    assert_eq!(module.funcs[0].name, "add");
+    assert_eq!(module.funcs[0].params.len(), 2);
+    let calls: Vec<_> = module.funcs[1]
+        .blocks
+        .iter()
+        .flat_map(|block| &block.body)
+        .filter_map(|instr| match instr {
+            IrInstr::Call { func, args, dst } => Some((*func, args.len(), dst.is_some())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(calls, vec![(0, 2, true)], "{}", format_ir(&module));
+}
+
+#[test]
+fn recursive_fibonacci() {
+    let source = r#"
+fun fib(n:: i32) -> i32 {
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+
+fun apex() {
+    if fib(15) == 610 {
+        log.info("ok");
+    } else {
+        log.info("wrong");
+    }
+}
+"#;
+    expect_stdout(source, "ok\n");
+}
+
+#[test]
+fn mutual_recursion_with_bool_results() {
+    let source = r#"
+fun is_even(n:: i32) -> bool {
+    if n == 0 {
+        return true;
+    }
+    return is_odd(n - 1);
+}
+
+fun is_odd(n:: i32) -> bool {
+    if n == 0 {
+        return false;
+    }
+    return is_even(n - 1);
+}
+
+fun apex() {
+    if is_even(10) && is_odd(7) {
+        log.info("parity");
+    }
+    if is_even(3) {
+        log.info("wrong");
+    }
+}
+"#;
+    expect_stdout(source, "parity\n");
+}
+
+#[test]
+fn bool_arguments_are_passed_through() {
+    let source = r#"
+fun choose(flag:: bool, a:: i32, b:: i32) -> i32 {
+    if flag {
+        return a;
+    }
+    return b;
+}
+
+fun apex() {
+    if choose(true, 4, 9) == 4 && choose(false, 4, 9) == 9 {
+        log.info("chosen");
+    }
+}
+"#;
+    expect_stdout(source, "chosen\n");
+}
+
+#[test]
+fn arguments_beyond_six_use_the_stack() {
+    let source = r#"
+fun weigh(a:: i32, b:: i32, c:: i32, d:: i32, e:: i32, f:: i32, g:: i32, h:: i32) -> i32 {
+    return a + b * 2 + c * 3 + d * 4 + e * 5 + f * 6 + g * 7 + h * 8;
+}
+
+fun apex() {
+    if weigh(1, 1, 1, 1, 1, 1, 1, 10) == 108 {
+        log.info("stack args");
+    }
+}
+"#;
+    expect_stdout(source, "stack args\n");
+}
+
+#[test]
+fn globals_are_folded_or_stored_in_the_data_segment() {
+    let source = r#"
+const LIMIT = 5;
+const DOUBLE = LIMIT * 2 + 1;
+static BANNER = "globals";
+var counter = 0;
+var label = "start";
+
+fun bump(by:: i32) {
+    counter = counter + by;
+}
+
+fun apex() {
+    log.info(BANNER, DOUBLE);
+    var i = 0;
+    while i < LIMIT {
+        bump(i);
+        i = i + 1;
+    }
+    log.info(counter, label);
+    label = "done";
+    log.info(counter * 2, label);
+}
+"#;
+    let expected = "globals 11\n10 start\n20 done\n";
+    expect_stdout(source, expected);
+    let output = run_native_optimized(source);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn type_aliases_pick_the_aliased_representation() {
+    let source = r#"
+type Label = str;
+type Flag = bool;
+type Count = i32;
+
+fun describe(label:: Label, on:: Flag, n:: Count) -> Label {
+    if on {
+        log.info(label, n);
+    }
+    return label;
+}
+
+fun apex() {
+    let name: Label = describe("alias", true, 3);
+    log.info(name);
+}
+"#;
+    expect_stdout(source, "alias 3\nalias\n");
+}
+
+#[test]
+fn factorial_division_and_remainder() {
+    let source = r#"
+fun fact(n:: i32) -> i32 {
+    if n <= 1 {
+        return 1;
+    }
+    return n * fact(n - 1);
+}
+
+fun apex() {
+    let f = fact(6);
+    if f / 7 == 102 && f % 7 == 6 {
+        log.info("720");
+    }
+}
+"#;
+    expect_stdout(source, "720\n");
+}
+
+#[test]
+fn else_if_chains_inside_helpers() {
+    let source = r#"
+fun classify(n:: i32) -> i32 {
+    if n < 0 {
+        return 1;
+    } else if n == 0 {
+        return 2;
+    } else {
+        return 3;
+    }
+}
+
+fun greet() {
+    log.info("hello");
+}
+
+fun apex() {
+    greet();
+    if classify(0 - 5) == 1 && classify(0) == 2 && classify(5) == 3 {
+        log.info("classified");
+    }
+}
+"#;
+    expect_stdout(source, "hello\nclassified\n");
+}
+
+const PRINTING: &str = r#"
+fun apex() {
+    let x = 42;
+    let neg = 0 - 1234567;
+    let name = "forge";
+    log.info("x =", x, "neg", neg, "zero", 0);
+    log.info(name, x > 3, x < 3);
+    print("done");
+}
+"#;
+
+const PRINTING_OUTPUT: &str = "x = 42 neg -1234567 zero 0\nforge true false\ndone\n";
+
+#[test]
+fn log_info_prints_values_like_the_interpreter() {
+    expect_stdout(PRINTING, PRINTING_OUTPUT);
+}
+
+const ARITHMETIC: &str = r#"
+fun apex() {
+    let x = 6;
+    log.info(-x, x * 7, 0 - x * x);
+}
+"#;
+
+#[test]
+fn x86_backend_emits_negation_and_multiplication() {
+    let code = compile_x86(ARITHMETIC).code;
+    let imul = code.windows(2).any(|w| w == [0x0F, 0xAF]);
+    let neg = code.windows(2).any(|w| w[0] == 0xF7 && w[1] & 0xF8 == 0xD8);
+    assert!(imul, "no imul in {code:02x?}");
+    assert!(neg, "no neg in {code:02x?}");
+}
+
+#[test]
+#[ignore = "needs a host kernel that can execute i386 binaries"]
+fn x86_backend_prints_the_same_output() {
+    for (source, expected) in [(PRINTING, PRINTING_OUTPUT), (ARITHMETIC, "-6 42 -36\n")] {
+        let output = run_native_x86(source);
+        assert!(output.status.success(), "exit status: {}", output.status);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    }
+}
+
+#[test]
+fn printing_preserves_live_values() {
+    let source = r#"
+fun square(n:: i32) -> i32 {
+    return n * n;
+}
+
+fun label(ok:: bool) -> str {
+    if ok {
+        return "yes";
+    }
+    return "no";
+}
+
+fun apex() {
+    let a = 7;
+    let b = 9;
+    log.info("before", a, b);
+    log.info(square(a) + b, label(a < b), label(a > b));
+}
+"#;
+    expect_stdout(source, "before 7 9\n58 yes no\n");
+}
+
+#[test]
+fn loop_variables_flow_through_phis() {
+    let source = r#"
+fun apex() {
+    var total = 0;
+    var i = 0;
+    let limit = 10;
+    while i < limit {
+        i = i + 1;
+        if i == 3 {
+            continue;
+        }
+        total = total + i;
+    }
+    for j in 0..4 {
+        total = total + j;
+    }
+    log.info(total, i);
+}
+"#;
+    expect_stdout(source, "58 10\n");
+}
+
+#[test]
+fn integer_switches_lower_to_branch_chains() {
+    let source = r#"
+fun classify(n:: i32) -> i32 {
+    var out = 0;
+    switch n {
+        0 -> out = 100,
+        1 | 2 -> out = 12,
+        -5 ..= -1 -> out = -1,
+        k if k > 50 -> out = k * 2,
+        3 .. 10 -> out = n,
+        _ -> out = 7,
+    }
+    return out;
+}
+
+fun apex() {
+    log.info(classify(0), classify(2), classify(-3), classify(60), classify(9), classify(10));
+}
+"#;
+    expect_stdout(source, "100 12 -1 120 9 7\n");
+    let output = run_native_optimized(source);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "100 12 -1 120 9 7\n"
+    );
+}
+
+#[test]
+fn swapped_loop_variables_use_parallel_moves() {
+    let source = r#"
+fun gcd(a:: i32, b:: i32) -> i32 {
+    var x = a;
+    var y = b;
+    while y != 0 {
+        let r = x % y;
+        x = y;
+        y = r;
+    }
+    return x;
+}
+
+fun apex() {
+    var a = 1;
+    var b = 2;
+    var steps = 0;
+    while steps < 5 {
+        let tmp = a;
+        a = b;
+        b = tmp;
+        steps = steps + 1;
+    }
+    log.info(a, b, gcd(1071, 462), gcd(17, 5));
+    var fa = 0;
+    var fb = 1;
+    var n = 0;
+    while n < 40 {
+        let next = fa + fb;
+        fa = fb;
+        fb = next;
+        n = n + 1;
+    }
+    log.info(fa);
+    var total = 0;
+    for i in 0..10 {
+        for j in 0..i {
+            if j == 5 {
+                break;
+            }
+            total = total + gcd(i + 1, j + 1);
+        }
+    }
+    log.info(total);
+}
+"#;
+    expect_stdout(source, "2 1 21 1\n102334155\n52\n");
+}
+
+#[test]
+fn many_live_values_spill_to_the_stack() {
+    let source = r#"
+fun mix(x:: i32) -> i32 {
+    return x * 3 + 1;
+}
+
+fun apex() {
+    let a = mix(1);
+    let b = mix(2);
+    let c = mix(3);
+    let d = mix(4);
+    let e = mix(5);
+    let f = mix(6);
+    let g = mix(7);
+    let h = mix(8);
+    let i = mix(9);
+    let j = mix(10);
+    let k = mix(11);
+    let l = mix(12);
+    let m = mix(13);
+    let n = mix(14);
+    let o = mix(15);
+    let p = mix(16);
+    let q = mix(17);
+    let r = mix(18);
+    let s = mix(19);
+    let t = mix(20);
+    let u = mix(21);
+    let v = mix(22);
+    log.info(a + b + c + d + e + f + g + h + i + j + k, l + m + n + o + p + q + r + s + t + u + v);
+    log.info(a * v - b * u + c * t - d * s, k / 3 + l % 5, q - p, a, v);
+}
+"#;
+    expect_stdout(source, "209 572\n-324 13 3 4 67\n");
+}
+
+#[test]
+fn release_pipeline_preserves_output() {
+    let source = r#"
+fun scale(n:: i32) -> i32 {
+    var total = 0;
+    var i = 0;
+    while i < n {
+        let factor = 4 * 5 - 17;
+        if 1 > 2 {
+            total = total + 1000;
+        }
+        total = total + i * factor;
+        i = i + 1;
+    }
+    return total;
+}
+
+fun apex() {
+    let unused = 7 * 6;
+    var acc = 0;
+    for j in 0..5 {
+        if j == 3 {
+            continue;
+        }
+        acc = acc + scale(j) + 0;
+    }
+    log.info("acc", acc, acc > 10, scale(10) * 1);
+}
+"#;
+    let expected = "acc 21 true 135\n";
+    let output = run_native_optimized(source);
+    assert!(output.status.success(), "exit status: {}", output.status);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    expect_stdout(source, expected);
+}
+
+/// Runs every `tests/ir/*.ir` file and compares its output with the
+/// `; stdout:` comment lines at the top of the file.
+#[test]
+fn textual_ir_programs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("ir");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .expect("tests/ir should exist")
+        .map(|entry| entry.expect("readable entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ir"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    for path in paths {
+        let text = std::fs::read_to_string(&path).expect("readable .ir file");
+        let expected: String = text
+            .lines()
+            .filter_map(|line| line.strip_prefix("; stdout:"))
+            .map(|line| format!("{}\n", line.trim()))
+            .collect();
+        let module = parse_ir(&text).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        let output = run_module(module);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            path.display(),
+            output.status
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            expected,
+            "{}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn interpolated_log_arguments_print_piecewise() {
+    let source = r#"
+fun square(n:: i32) -> i32 {
+    return n * n;
+}
+
+fun apex() {
+    let n = 7;
+    log.info("n=${n} squared is ${square(n)}", "ok ${n > 3}");
+}
+"#;
+    expect_stdout(source, "n=7 squared is 49 ok true\n");
+}
+
+#[test]
+fn unary_operators_match_the_interpreter() {
+    let source = r#"
+fun neg(a:: i64) -> i64 {
+    return -a;
+}
+
+fun apex() {
+    log.info(neg(4) + 10, -neg(3) * ~2);
+    if !(neg(1) > 0) {
+        log.info("flipped");
+    }
+}
+"#;
+    expect_stdout(source, "6 -9\nflipped\n");
+}
+
+#[test]
+fn unlowerable_expressions_fail_the_build() {
+    let source = "fun apex() {\n    let xs = [1, 2];\n    log.info(1);\n}\n";
+    let errors = build_ir(&common::parse(source)).unwrap_err();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(
+        errors[0].message,
+        "Array literals are not supported by native builds"
+    );
+    assert_eq!(&source[errors[0].span.start..errors[0].span.end], "[1, 2]");
+    assert_eq!(errors[0].span.line, 2);
+}
+
+#[test]
+fn unary_arguments_print_with_their_type() {
+    let source = r#"
+fun apex() {
+    let x = 5;
+    let flipped = !(x > 0);
+    log.info(-3, -7 / 2, !(x > 0), flipped, -x % 3);
+}
+"#;
+    expect_stdout(source, "-3 -3 false false -2\n");
+}
+
+#[test]
+fn unprintable_arguments_fail_the_build() {
+    let source = "fun apex() {\n    log.info(\"pi\", 3.5);\n}\n";
+    let errors = build_ir(&common::parse(source)).unwrap_err();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(
+        errors[0].message,
+        "`log.info` cannot print this argument in native builds"
+    );
+    assert_eq!(&source[errors[0].span.start..errors[0].span.end], "3.5");
+}
+
+#[test]
+fn falling_off_a_non_void_function_fails_the_build() {
+    let source = "fun sign(n:: i32) -> i32 {\n    if n > 0 {\n        return 1;\n    }\n}\n";
+    let errors = build_ir(&common::parse(source)).unwrap_err();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(
+        errors[0].message,
+        "`sign` can reach its end without returning a value"
+    );
+    assert_eq!(errors[0].span.line, 1);
+}
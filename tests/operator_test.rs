@@ -0,0 +1,128 @@
+mod common;
+
+use common::{call, lower, parse, run_i64};
+use nightscript_android::ir::{format_ir, verify_module};
+use nightscript_android::type_checker::check_file;
+
+fn type_errors(source: &str) -> Vec<String> {
+    check_file(&parse(source))
+        .into_iter()
+        .map(|e| e.message)
+        .collect()
+}
+
+#[test]
+fn bitwise_operators_follow_two_complement_semantics() {
+    assert_eq!(run_i64("return 240 & 60;"), 48);
+    assert_eq!(run_i64("return 6 | 9;"), 15);
+    assert_eq!(run_i64("return 6 ^ 3;"), 5);
+    assert_eq!(run_i64("return ~0;"), -1);
+    assert_eq!(run_i64("return 1 << 10;"), 1024);
+    assert_eq!(run_i64("return -16 >> 2;"), -4);
+}
+
+#[test]
+fn shifts_wrap_to_the_operand_width() {
+    let unsigned = "let a:: u8 = 200; let one:: i64 = 1;";
+    assert_eq!(run_i64(&format!("{unsigned} return a << one;")), 144);
+    assert_eq!(run_i64(&format!("{unsigned} return a >> one;")), 100);
+    assert_eq!(run_i64(&format!("{unsigned} return ~a;")), 55);
+
+    let signed = "let b:: i8 = -128; let one:: i64 = 1;";
+    assert_eq!(run_i64(&format!("{signed} return b >> one;")), -64);
+    assert_eq!(run_i64(&format!("{signed} return b << one;")), 0);
+    assert_eq!(run_i64(&format!("{signed} return ~b;")), 127);
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[test]
+fn native_shifts_match_the_interpreter() {
+    let source = r#"
+fun apex() {
+    let a = 1 << 40;
+    let b:: i32 = 1;
+    let c:: u8 = 200;
+    let d:: u64 = 1;
+    let e:: i8 = 64;
+    log.info(a, b << 31, c << 1, (d << 63) >> 60, e << 1, ~c, 7 ^ 2, -16 >> 2);
+}
+"#;
+    let expected = "1099511627776 -2147483648 144 8 -128 55 5 -4\n";
+    for output in [
+        common::native::run_native(source),
+        common::native::run_native_optimized(source),
+    ] {
+        assert!(output.status.success(), "exit status: {}", output.status);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    }
+}
+
+#[test]
+fn shift_amounts_must_fit_the_operand_width() {
+    let err = call("fun run() -> i64 { return 1 << 64; }", "run").unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("Shift amount 64 is out of range for i64"),
+        "{}",
+        err.message()
+    );
+}
+
+#[test]
+fn bitwise_operators_bind_tighter_than_comparisons() {
+    assert_eq!(run_i64("if 2 + 3 << 1 == 10 { return 1; } return 0;"), 1);
+    assert_eq!(run_i64("if 1 | 2 ^ 3 & 1 == 3 { return 1; } return 0;"), 1);
+    assert_eq!(run_i64("if (3 < 4) | false { return 1; } return 0;"), 1);
+}
+
+#[test]
+fn compound_assignments_update_in_place() {
+    let body = "var crc = 0;
+        crc ^= 255;
+        crc <<= 4;
+        crc >>= 1;
+        crc |= 1;
+        crc &= 2047;
+        crc += 3;
+        crc -= 1;
+        crc *= 2;
+        crc /= 2;
+        crc %= 1000;
+        return crc;";
+    assert_eq!(run_i64(body), 43);
+    assert_eq!(
+        run_i64("var v = vec.new(); vec.push(v, 4); v[0] *= 3; return v[0];"),
+        12
+    );
+}
+
+#[test]
+fn nested_generic_types_still_close_with_adjacent_angles() {
+    assert_eq!(
+        run_i64("let rows:: vec<vec<i64>> = vec.new(); return vec.len(rows);"),
+        0
+    );
+}
+
+#[test]
+fn type_checker_rejects_mixed_bitwise_operands() {
+    assert_eq!(
+        type_errors("fun run() { let x = 1 & true; let y = \"a\" << 1; let z = ~false; }"),
+        vec![
+            "Bitwise operators expect matching integer or `bool` types, got `integer` and `bool`",
+            "Shift operands must be integers, found `str`",
+            "Cannot apply `~` to `bool`",
+        ]
+    );
+    assert!(type_errors("fun run() { let x = true ^ false; let y = ~7 >> 1; }").is_empty());
+}
+
+#[test]
+fn bitwise_operators_lower_to_ir() {
+    let module = lower("fun mix(a:: i32, b:: i32) -> i32 { return (a ^ b) << 2 | ~a >> 1 & b; }\n");
+    assert!(verify_module(&module).is_empty());
+    let text = format_ir(&module);
+    for op in ["xor", "shl", "ashr", "or", "and", "not"] {
+        assert!(text.contains(&format!(" {op} ")), "missing {op} in\n{text}");
+    }
+}
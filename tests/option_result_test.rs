@@ -0,0 +1,165 @@
+mod common;
+
+use common::{call, expect_str, parse, run_i64};
+use nightscript_android::runtime::{IntType, OptionValue, PrimitiveType, ResultValue, TypeTag};
+use nightscript_android::type_checker::check_file;
+use nightscript_android::Value;
+
+fn tag(name: &str) -> Option<TypeTag> {
+    Some(match name {
+        "i8" => TypeTag::Primitive(PrimitiveType::Int(IntType::I8)),
+        "str" => TypeTag::Primitive(PrimitiveType::String),
+        other => panic!("no tag for {other}"),
+    })
+}
+
+#[test]
+fn options_unwrap_map_and_chain() {
+    assert_eq!(
+        run_i64("let o = option.some(4); if o.is_some() { return o.unwrap(); } return 0;"),
+        4
+    );
+    assert_eq!(
+        run_i64("let n:: option<i64> = option.none(); if n.is_none() { return n.unwrap_or(7); } return 0;"),
+        7
+    );
+    assert_eq!(
+        run_i64(
+            "let n:: option<i64> = option.none(); return n.unwrap_or_else(fun() { return 9; });"
+        ),
+        9
+    );
+    assert_eq!(
+        run_i64("return option.some(4).map(fun(x) { return x * 2; }).and_then(fun(x) { return option.some(x + 1); }).unwrap();"),
+        9
+    );
+    assert_eq!(
+        run_i64("let n:: option<i64> = option.none(); return n.map(fun(x) { return x * 2; }).or_else(fun() { return option.some(3); }).unwrap();"),
+        3
+    );
+    assert_eq!(
+        run_i64("return option.some(option.some(5)).flatten().expect(\"nested\");"),
+        5
+    );
+}
+
+#[test]
+fn results_map_either_side() {
+    let err = "let e:: result<i64, str> = result.err(\"bad\");";
+    assert_eq!(
+        run_i64("return result.ok(3).map(fun(x) { return x + 1; }).unwrap();"),
+        4
+    );
+    assert_eq!(
+        run_i64(&format!(
+            "{err} return e.map_err(fun(s) {{ return str.len(s); }}).err().unwrap();"
+        )),
+        3
+    );
+    assert_eq!(
+        run_i64(&format!(
+            "{err} return e.or_else(fun(s) {{ return result.ok(10); }}).and_then(fun(x) {{ return result.ok(x * 2); }}).unwrap();"
+        )),
+        20
+    );
+    assert_eq!(
+        run_i64(&format!(
+            "{err} if e.is_err() && e.ok().is_none() {{ return e.unwrap_or(1); }} return 0;"
+        )),
+        1
+    );
+    assert_eq!(
+        run_i64("return result.ok(result.ok(6)).flatten().unwrap();"),
+        6
+    );
+    let source = "fun run() -> str { let n:: option<i64> = option.none(); return n.ok_or(\"missing\").err().unwrap(); }";
+    assert_eq!(expect_str(call(source, "run")), "missing");
+}
+
+#[test]
+fn combinators_keep_type_tags() {
+    let source = "fun run() { let r:: result<i8, str> = result.err(\"x\"); return r.ok(); }";
+    match call(source, "run") {
+        Ok(Value::Option(OptionValue::None { elem_type })) => assert_eq!(elem_type, tag("i8")),
+        other => panic!("expected none, got {other:?}"),
+    }
+
+    let source = "fun run() { let o:: option<i8> = option.some(1); return o.ok_or(\"gone\"); }";
+    match call(source, "run") {
+        Ok(Value::Result(ResultValue::Ok {
+            ok_type, err_type, ..
+        })) => {
+            assert_eq!(ok_type, tag("i8"));
+            assert_eq!(err_type, tag("str"));
+        }
+        other => panic!("expected ok, got {other:?}"),
+    }
+
+    let source = "fun run() { let r:: result<i8, str> = result.err(\"x\"); return r.map(fun(x) { return x; }); }";
+    match call(source, "run") {
+        Ok(Value::Result(ResultValue::Err { err_type, .. })) => {
+            assert_eq!(err_type, tag("str"))
+        }
+        other => panic!("expected err, got {other:?}"),
+    }
+}
+
+#[test]
+fn unwrap_failures_point_at_the_call() {
+    let source =
+        "fun run() -> i64 {\n    let n:: option<i64> = option.none();\n    return n.unwrap();\n}\n";
+    let err = call(source, "run").unwrap_err();
+    assert!(
+        err.message().starts_with("option.unwrap called on none"),
+        "{}",
+        err.message()
+    );
+    let span = err.span().expect("unwrap span");
+    assert_eq!(&source[span.start..span.end], "n.unwrap()");
+    assert_eq!(span.line, 3);
+
+    let source = "fun run() -> i64 {\n    let e:: result<i64, str> = result.err(\"bad\");\n    return e.expect(\"needed a value\");\n}\n";
+    let err = call(source, "run").unwrap_err();
+    assert!(
+        err.message().starts_with("needed a value: bad"),
+        "{}",
+        err.message()
+    );
+    assert_eq!(err.span().expect("expect span").line, 3);
+
+    let err = call(
+        "fun run() { option.some(1).map_err(fun(x) { return x; }); }",
+        "run",
+    )
+    .unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("Unknown method `map_err` on option"),
+        "{}",
+        err.message()
+    );
+}
+
+#[test]
+fn type_checker_knows_combinator_results() {
+    let errors: Vec<String> = check_file(&parse(
+        "fun run(o:: option<i64>, r:: result<i64, str>) {
+            let a:: str = o.unwrap();
+            let b:: option<i64> = r.ok();
+            let c:: option<i64> = r.err();
+            let d:: result<i64, bool> = o.ok_or(\"none\");
+            let e:: bool = o.is_some();
+        }",
+    ))
+    .into_iter()
+    .map(|e| e.message)
+    .collect();
+    assert_eq!(
+        errors,
+        vec![
+            "Mismatched types: expected `str`, found `i64`",
+            "Mismatched types: expected `option<i64>`, found `option<str>`",
+            "Mismatched types: expected `result<i64, bool>`, found `result<i64, str>`",
+        ]
+    );
+}
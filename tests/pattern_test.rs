@@ -0,0 +1,347 @@
+mod common;
+
+use std::fs;
+
+use common::{call, call_with_loader, expect_int, expect_str, parse, run_source, validate};
+use nightscript_android::module_loader::ModuleLoader;
+use nightscript_android::type_checker::check_file;
+
+const STATE: &str = "enum State { Idle, Running(i64), Done }\n";
+
+/// Validation messages for `body` as the body of a function over `s:: State`.
+fn switch_errors(body: &str) -> Vec<String> {
+    let source = format!("{STATE}\n    fun run(s:: State) {{\n        {body}\n    }}\n");
+    validate(&source).into_iter().map(|e| e.message).collect()
+}
+
+#[test]
+fn switches_over_enums_must_cover_every_variant() {
+    assert_eq!(
+        switch_errors("switch s { Idle -> 1, State::Running(n) -> n, }"),
+        vec!["Non-exhaustive switch on `State`: missing `State::Done`"]
+    );
+    assert!(switch_errors("switch s { Idle -> 1, Running(n) -> n, Done -> 3, }").is_empty());
+    assert!(switch_errors("switch s { Done -> 1, other -> 2, }").is_empty());
+    assert_eq!(
+        switch_errors("switch 2 { 1 -> 1, 2 -> 2, }"),
+        vec!["Non-exhaustive switch: add a `_` arm"]
+    );
+}
+
+#[test]
+fn unreachable_arms_are_reported() {
+    assert_eq!(
+        switch_errors("switch s { _ -> 1, Done -> 2, }"),
+        vec!["Unreachable switch arm: an earlier arm matches every value"]
+    );
+    assert_eq!(
+        switch_errors("switch s { Idle -> 1, State::Idle -> 2, _ -> 3, }"),
+        vec!["Unreachable switch arm: `State::Idle` is already matched"]
+    );
+    assert_eq!(
+        switch_errors("switch s { Idle -> 1, Running(_) -> 2, Done -> 3, _ -> 4, }"),
+        vec!["Unreachable switch arm: every variant of `State` is already matched"]
+    );
+}
+
+#[test]
+fn user_enums_win_over_builtin_variant_names() {
+    let errors = |body: &str| -> Vec<String> {
+        let source = format!(
+            "enum Status {{ Ok, Error(str) }}\n    fun run(s:: Status) {{\n        {body}\n    }}\n"
+        );
+        validate(&source).into_iter().map(|e| e.message).collect()
+    };
+    assert_eq!(
+        errors("switch s { Ok -> 1, }"),
+        vec!["Non-exhaustive switch on `Status`: missing `Status::Error`"]
+    );
+    assert!(errors("switch s { Ok -> 1, Error(_) -> 2, }").is_empty());
+    assert_eq!(
+        errors("let r = result.ok(1); switch r { Ok(n) -> n, Err(_) -> 0, }"),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn check_expressions_need_a_catch_all_arm() {
+    assert_eq!(
+        switch_errors("let n = 3; let size = check n { it > 2 -> 1, it > 1 -> 2, };"),
+        vec!["Non-exhaustive check: add a `_` arm"]
+    );
+    assert_eq!(
+        switch_errors("let n = 3; let size = check { _ -> 1, n > 2 -> 2, };"),
+        vec!["Unreachable check arm: an earlier arm matches every value"]
+    );
+    assert!(switch_errors("let n = 3; let size = check n { it > 2 -> 1, _ -> 2, };").is_empty());
+}
+
+#[test]
+fn bare_variant_names_match_only_that_variant() {
+    let run = |value: &str| {
+        let source = format!(
+            "{STATE}
+    fun run() -> i64 {{
+        var out = 0;
+        switch {value} {{
+            Idle -> out = 1,
+            Running(n) -> out = n,
+            Done -> out = 3,
+        }}
+        return out;
+    }}
+    "
+        );
+        expect_int(call(&source, "run"))
+    };
+    assert_eq!(run("State::Idle"), 1);
+    assert_eq!(run("State::Running(7)"), 7);
+    assert_eq!(run("State::Done"), 3);
+}
+
+#[test]
+fn qualified_first_arms_after_a_bare_scrutinee() {
+    assert!(
+        switch_errors("switch s { State::Idle -> 1, State::Running(n) -> n, Done -> 3, }")
+            .is_empty()
+    );
+    let source = format!(
+        "{STATE}
+    fun run() -> i64 {{
+        let s = State::Running(5);
+        var out = 0;
+        switch s {{
+            State::Running(n) -> out = n,
+            _ -> out = 1,
+        }}
+        return out;
+    }}
+    "
+    );
+    assert_eq!(expect_int(call(&source, "run")), 5);
+}
+
+#[test]
+fn non_exhaustive_switches_fail_at_runtime() {
+    // Validation cannot see the variants of an enum from another module.
+    let root = std::env::temp_dir().join(format!("afns-patterns-{}", std::process::id()));
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(
+        root.join("src").join("lights.afml"),
+        "pub enum Light { Red, Green }\n",
+    )
+    .unwrap();
+    let result = call_with_loader(
+        r#"
+    import lights::Light;
+
+    fun run() -> i64 {
+        let light = Light::Green;
+        switch light {
+            Red -> 1,
+        }
+        return 0;
+    }
+    "#,
+        "run",
+        ModuleLoader::with_root(root.clone()),
+    );
+    fs::remove_dir_all(&root).ok();
+    let err = result.unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("switch: non-exhaustive (no arm matched for `Light::Green`)"),
+        "{}",
+        err.message()
+    );
+    assert_eq!(err.span().map(|span| span.line), Some(6));
+}
+
+const POINTS: &str = r#"
+    struct Point { x:: i64, y:: i64 }
+
+    fun pick(p:: option<Point>) -> i64 {
+        var out = 0;
+        switch p {
+            Some(Point { x, y: 0 }) -> out = x,
+            Some(Point { x, y }) -> out = x * y,
+            None -> out = 99,
+        }
+        return out;
+    }
+
+    fun third(t:: tuple(i64, i64, i64)) -> i64 {
+        var out = 0;
+        switch t {
+            (a, _, 3) -> out = a,
+            (_, b, _) -> out = b,
+        }
+        return out;
+    }
+
+    fun grade(n:: i64) -> str {
+        var out = "";
+        switch n {
+            -9 ..= -1 -> out = "negative",
+            0 | 1 -> out = "tiny",
+            k if k > 100 -> out = "huge",
+            2 .. 10 -> out = "small",
+            _ -> out = "big",
+        }
+        return out;
+    }
+
+    fun letter(c:: char) -> str {
+        var out = "";
+        switch c {
+            'a' ..= 'z' -> out = "lower",
+            _ -> out = "other",
+        }
+        return out;
+    }
+"#;
+
+#[test]
+fn nested_struct_and_tuple_patterns_destructure() {
+    let int = |body| expect_int(call(&run_source(POINTS, "i64", body), "run"));
+    assert_eq!(int("return pick(option.some(Point { x: 4, y: 0 }));"), 4);
+    assert_eq!(int("return pick(option.some(Point { x: 4, y: 5 }));"), 20);
+    assert_eq!(int("return pick(option.none());"), 99);
+    assert_eq!(int("return third((7, 8, 3));"), 7);
+    assert_eq!(int("return third((7, 8, 9));"), 8);
+    assert!(validate(&run_source(POINTS, "i64", "return 0;")).is_empty());
+}
+
+#[test]
+fn range_or_and_guarded_patterns() {
+    let text = |body| expect_str(call(&run_source(POINTS, "str", body), "run"));
+    assert_eq!(text("return grade(-3);"), "negative");
+    assert_eq!(text("return grade(1);"), "tiny");
+    assert_eq!(text("return grade(500);"), "huge");
+    assert_eq!(text("return grade(9);"), "small");
+    assert_eq!(text("return grade(10);"), "big");
+    assert_eq!(text("return letter('q');"), "lower");
+    assert_eq!(text("return letter('Q');"), "other");
+}
+
+#[test]
+fn nested_patterns_take_part_in_exhaustiveness() {
+    assert_eq!(
+        switch_errors("switch s { Idle | Done -> 1, Running(0) -> 2, }"),
+        vec!["Non-exhaustive switch on `State`: missing `State::Running`"]
+    );
+    assert!(switch_errors("switch s { Idle | Done -> 1, Running(_) -> 2, }").is_empty());
+    assert_eq!(
+        switch_errors("switch s { Idle -> 1, Running(n) if n > 2 -> n, Done -> 3, }"),
+        vec!["Non-exhaustive switch on `State`: missing `State::Running`"]
+    );
+    assert_eq!(
+        switch_errors("switch s { Idle | Done -> 1, Done | Idle -> 2, _ -> 3, }"),
+        vec!["Unreachable switch arm: `State::Done`, `State::Idle` are already matched"]
+    );
+    assert_eq!(
+        switch_errors("let p = (1, 2); switch p { (a, b) -> a, (1, _) -> 1, }"),
+        vec!["Unreachable switch arm: an earlier arm matches every value"]
+    );
+    assert_eq!(
+        switch_errors("let o = option.none(); switch o { Some(n) -> n, }"),
+        vec!["Non-exhaustive switch on `option`: missing `None`"]
+    );
+}
+
+#[test]
+fn pattern_bindings_are_checked() {
+    assert_eq!(
+        switch_errors("let p = (1, 2); switch p { (a, 1) | (1, b) -> 1, _ -> 2, }"),
+        vec!["All alternatives of an `|` pattern must bind the same names"]
+    );
+    assert_eq!(
+        switch_errors("let p = (1, 2); switch p { (a, a) -> 1, }"),
+        vec!["`a` is bound more than once in the same pattern"]
+    );
+    let source = format!("{STATE}\n    fun run(s:: State) {{\n        switch s {{ Running(n) if 3 -> n, _ -> 2, }}\n    }}\n");
+    let messages: Vec<String> = check_file(&parse(&source))
+        .into_iter()
+        .map(|e| e.message)
+        .collect();
+    assert_eq!(messages, vec!["Condition must be `bool`, found `integer`"]);
+}
+
+#[test]
+fn declarations_and_for_loops_destructure() {
+    let int = |body| expect_int(call(&run_source(POINTS, "i64", body), "run"));
+    assert_eq!(int("let (a, b) = (3, 4); return a * b;"), 12);
+    assert_eq!(
+        int("let Point { x, y: down } = Point { x: 5, y: 2 }; return x - down;"),
+        3
+    );
+    assert_eq!(int("var (a, _, c) = (1, 2, 3); a = a + c; return a;"), 4);
+    assert_eq!(
+        int(
+            "let m = map.new(); map.put(m, \"a\", 1); map.put(m, \"b\", 2);
+        var total = 0;
+        for (k, v) in m.items() { total = total + v; }
+        return total;"
+        ),
+        3
+    );
+    let source = run_source(POINTS,
+        "i64",
+        "let (a, b) = (1, 2); for (i, Point { x, y }) in [(1, Point { x: 2, y: 3 })] { return i + x + y; } return a + b;",
+    );
+    assert!(validate(&source).is_empty());
+    assert!(check_file(&parse(&source)).is_empty());
+}
+
+#[test]
+fn refutable_declaration_patterns_are_rejected() {
+    assert_eq!(
+        switch_errors("let o = option.none(); let Some(n) = o;"),
+        vec!["Refutable pattern in declaration: it does not match every value; use `switch`"]
+    );
+    assert_eq!(
+        switch_errors("for (a, 1) in [(1, 1)] {}"),
+        vec!["Refutable pattern in `for` loop: it does not match every value; use `switch`"]
+    );
+    assert_eq!(
+        switch_errors("let (a, b) = (1, 2); let (b, c) = (3, 4);"),
+        vec!["Duplicate binding `b` in the same scope"]
+    );
+    assert_eq!(
+        switch_errors("let Running(n) = s;"),
+        vec!["Refutable pattern in declaration: it does not match every value; use `switch`"]
+    );
+    let source = "enum Meters { Meters(i64) }\nfun run(m:: Meters) { let Meters(n) = m; }\n";
+    assert!(validate(source).is_empty());
+}
+
+#[test]
+fn mismatched_destructuring_is_reported() {
+    let source = run_source(
+        POINTS,
+        "i64",
+        "let (a, b) = (1, 2, 3); let Point { x, z } = Point { x: 1, y: 2 }; return 0;",
+    );
+    let messages: Vec<String> = check_file(&parse(&source))
+        .into_iter()
+        .map(|e| e.message)
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Mismatched types: pattern expects a tuple of 2 elements, found `(integer, integer, integer)`",
+            "Struct `Point` has no field `z`",
+        ]
+    );
+    let err = call(
+        &run_source(POINTS, "i64", "let (a, b) = 5; return a;"),
+        "run",
+    )
+    .unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("declaration pattern does not match value of type `int`"),
+        "{}",
+        err.message()
+    );
+}
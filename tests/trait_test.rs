@@ -0,0 +1,345 @@
+mod common;
+
+use std::fs;
+
+use common::{call, call_with_loader, expect_int, expect_str, parse, run_source, validate};
+use nightscript_android::lexer::lex;
+use nightscript_android::module_loader::ModuleLoader;
+use nightscript_android::parser::parse_tokens_with_diagnostics;
+use nightscript_android::type_checker::check_file;
+use nightscript_android::{Item, TypeExpr};
+
+const SHAPES: &str = r#"
+    trait Shape {
+        fun area(self:: Self) -> i64;
+
+        fun name(self:: Self) -> str {
+            return "shape";
+        }
+
+        fun doubled(self:: Self) -> i64 {
+            return Shape::area(self) * 2;
+        }
+    }
+
+    struct Square { side:: i64 }
+    struct Dot { x:: i64 }
+
+    impl Shape for Square {
+        fun area(self:: Square) -> i64 {
+            return self.side * self.side;
+        }
+
+        fun name(self:: Square) -> str {
+            return "square";
+        }
+    }
+
+    impl Shape for Dot {
+        fun area(self:: Dot) -> i64 {
+            return 0;
+        }
+    }
+"#;
+
+#[test]
+fn trait_methods_may_carry_default_bodies() {
+    let file = parse(SHAPES);
+    let Item::Trait(def) = &file.items[0] else {
+        panic!("expected a trait");
+    };
+    let defaults: Vec<(&str, bool)> = def
+        .methods
+        .iter()
+        .map(|m| (m.signature.name.as_str(), m.default.is_some()))
+        .collect();
+    assert_eq!(
+        defaults,
+        vec![("area", false), ("name", true), ("doubled", true)]
+    );
+    assert!(validate(SHAPES).is_empty());
+    assert!(check_file(&file).is_empty());
+}
+
+#[test]
+fn impls_fall_back_to_defaults_and_may_override_them() {
+    let name = |body| expect_str(call(&run_source(SHAPES, "str", body), "run"));
+    assert_eq!(name("return Shape::name(Square { side: 3 });"), "square");
+    assert_eq!(name("return Shape::name(Dot { x: 1 });"), "shape");
+}
+
+#[test]
+fn default_bodies_dispatch_to_the_implementor() {
+    let doubled = |body| expect_int(call(&run_source(SHAPES, "i64", body), "run"));
+    assert_eq!(doubled("return Shape::doubled(Square { side: 3 });"), 18);
+    assert_eq!(doubled("return Shape::doubled(Dot { x: 1 });"), 0);
+}
+
+#[test]
+fn required_methods_must_be_implemented() {
+    let errors = validate(
+        r#"
+    trait Shape {
+        fun area(self:: Self) -> i64;
+        fun perimeter(self:: Self) -> i64;
+        fun name(self:: Self) -> str { return "shape"; }
+    }
+
+    struct Square { side:: i64 }
+
+    impl Shape for Square {
+        fun perimeter(self:: Square) -> i64 { return self.side * 4; }
+    }
+    "#,
+    );
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec!["Missing method `area` in impl of `Shape` for `Square`"]
+    );
+}
+
+const BOUNDED: &str = r#"
+    trait Show {
+        fun show(self:: Self) -> str;
+    }
+
+    struct Point { x:: i64 }
+    struct Blob { x:: i64 }
+
+    impl Show for Point {
+        fun show(self:: Point) -> str {
+            return "point";
+        }
+    }
+
+    struct Labeled<T> where T: Show { item:: T }
+
+    fun render<T: Show>(item:: T) -> str {
+        return Show::show(item);
+    }
+"#;
+
+fn trait_name(bound: &TypeExpr) -> &str {
+    match bound {
+        TypeExpr::Named(named) => &named.segments[0].name,
+        other => panic!("expected a trait name, got {other:?}"),
+    }
+}
+
+#[test]
+fn bounds_come_from_angle_brackets_and_where_clauses() {
+    let file = parse(
+        r#"
+    fun pick<T: Show + Eq, U>(a:: T, b:: U) -> T where U: Show, T: Hash {
+        return a;
+    }
+    "#,
+    );
+    let Item::Function(func) = &file.items[0] else {
+        panic!("expected a function");
+    };
+    let bounds: Vec<(&str, Vec<&str>)> = func
+        .signature
+        .type_params
+        .iter()
+        .map(|p| (p.name.as_str(), p.bounds.iter().map(trait_name).collect()))
+        .collect();
+    assert_eq!(
+        bounds,
+        vec![("T", vec!["Show", "Eq", "Hash"]), ("U", vec!["Show"])]
+    );
+
+    let source = "fun f<T>(a:: T) where U: Show {}\n";
+    let report = parse_tokens_with_diagnostics(source, lex(source).unwrap());
+    assert_eq!(report.errors.len(), 1, "{:?}", report.errors);
+}
+
+#[test]
+fn type_checker_reports_unsatisfied_bounds() {
+    let source = format!(
+        "{BOUNDED}
+    fun run(p:: Point, b:: Blob) -> str {{
+        let ok = render(p);
+        let labeled = Labeled<Blob> {{ item: b }};
+        return render(b);
+    }}
+
+    fun eq<T: Eq>(a:: T) {{}}
+    "
+    );
+    let messages: Vec<String> = check_file(&parse(&source))
+        .into_iter()
+        .map(|e| e.message)
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "struct `Labeled`: trait bound `T: Show` is not satisfied; missing `impl Show for Blob`",
+            "function `render`: trait bound `T: Show` is not satisfied; missing `impl Show for Blob`",
+            "Unknown trait `Eq`",
+        ]
+    );
+}
+
+#[test]
+fn bounds_are_checked_when_generic_functions_run() {
+    let program =
+        |body: &str| format!("{BOUNDED}\n    fun run() -> str {{\n        {body}\n    }}\n");
+    assert_eq!(
+        expect_str(call(&program("return render(Point { x: 1 });"), "run")),
+        "point"
+    );
+    let err = call(&program("return render(Blob { x: 1 });"), "run").unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("trait bound `T: Show` is not satisfied: missing `impl Show for Blob`"),
+        "{}",
+        err.message()
+    );
+    let source = program("let l = Labeled<Blob> { item: Blob { x: 1 } }; return \"\";");
+    let err = call(&source, "run").unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("trait bound `T: Show` is not satisfied: missing `impl Show for Blob`"),
+        "{}",
+        err.message()
+    );
+}
+
+const SHOW: &str = r#"
+    trait Show {
+        fun show(self:: Self) -> str;
+
+        fun describe(self:: Self) -> str {
+            return self.show();
+        }
+    }
+
+    trait Loud {
+        fun show(self:: Self) -> str;
+    }
+
+    struct Point { x:: i64 }
+    enum Color { Red, Green }
+
+    impl Point {
+        fun label(self:: Point) -> str {
+            return "inherent";
+        }
+    }
+
+    impl Show for Point {
+        fun show(self:: Point) -> str {
+            return "point";
+        }
+    }
+
+    impl Show for Color {
+        fun show(self:: Color) -> str {
+            return "color";
+        }
+    }
+
+    impl Show for i64 {
+        fun show(self:: i64) -> str {
+            return "int";
+        }
+    }
+
+    impl Show for vec<i64> {
+        fun show(self:: vec<i64>) -> str {
+            return "vec";
+        }
+    }
+"#;
+
+#[test]
+fn trait_methods_can_be_called_with_method_syntax() {
+    let show = |body: &str| {
+        let source = format!("{SHOW}\n    fun run() -> str {{\n        {body}\n    }}\n");
+        assert!(check_file(&parse(&source)).is_empty());
+        expect_str(call(&source, "run"))
+    };
+    assert_eq!(show("return Point { x: 1 }.show();"), "point");
+    assert_eq!(show("return Color::Green.show();"), "color");
+    assert_eq!(show("let n = 5; return n.show();"), "int");
+    assert_eq!(
+        show("let v = vec.new(); v.push(1); return v.show();"),
+        "vec"
+    );
+    assert_eq!(show("return Point { x: 1 }.label();"), "inherent");
+    assert_eq!(show("return Point { x: 1 }.describe();"), "point");
+}
+
+#[test]
+fn methods_provided_by_two_traits_are_ambiguous() {
+    let source = format!(
+        "{SHOW}
+    impl Loud for Point {{
+        fun show(self:: Point) -> str {{
+            return \"loud\";
+        }}
+    }}
+
+    fun run() -> str {{
+        return Point {{ x: 1 }}.show();
+    }}
+    "
+    );
+    let expected = "Ambiguous method `show` on `Point`: it is provided by traits `Loud`, `Show`; call it as `Loud::show(...)`";
+    let messages: Vec<String> = check_file(&parse(&source))
+        .into_iter()
+        .map(|e| e.message)
+        .collect();
+    assert_eq!(messages, vec![expected.to_string()]);
+    let err = call(&source, "run").unwrap_err();
+    assert!(err.message().starts_with(expected), "{}", err.message());
+}
+
+#[test]
+fn method_syntax_only_sees_traits_in_scope() {
+    let root = std::env::temp_dir().join(format!("afns-traits-{}", std::process::id()));
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(
+        root.join("src").join("shapes.afml"),
+        r#"
+    pub trait Area {
+        fun area(self:: Self) -> i64;
+    }
+
+    pub struct Square { pub side:: i64 }
+
+    impl Area for Square {
+        fun area(self:: Square) -> i64 {
+            return self.side * self.side;
+        }
+    }
+    "#,
+    )
+    .unwrap();
+    let program = |imports: &str| {
+        format!(
+            "{imports}\n    fun run() -> i64 {{\n        return Square {{ side: 3 }}.area();\n    }}\n"
+        )
+    };
+    let in_scope = call_with_loader(
+        &program("import shapes;\n    import shapes::Area;"),
+        "run",
+        ModuleLoader::with_root(root.clone()),
+    );
+    let out_of_scope = call_with_loader(
+        &program("import shapes;"),
+        "run",
+        ModuleLoader::with_root(root.clone()),
+    );
+    fs::remove_dir_all(&root).ok();
+    assert_eq!(expect_int(in_scope), 9);
+    let err = out_of_scope.unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("Unknown method `area` on struct `Square`"),
+        "{}",
+        err.message()
+    );
+}
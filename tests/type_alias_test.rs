@@ -0,0 +1,160 @@
+mod common;
+
+use std::fs;
+
+use common::{call, call_with_loader, expect_int, parse, validate};
+use nightscript_android::module_loader::ModuleLoader;
+use nightscript_android::type_checker::check_file;
+use nightscript_android::Item;
+
+#[test]
+fn parses_plain_and_generic_aliases() {
+    let file = parse(
+        r#"
+    type UserId = i64;
+    type Pair<T> = tuple(T, T);
+    type Index = map<str, vec<result<UserId, str>>>;
+    "#,
+    );
+    let aliases: Vec<(String, usize)> = file
+        .items
+        .iter()
+        .map(|item| match item {
+            Item::TypeAlias(alias) => (alias.name.clone(), alias.type_params.len()),
+            other => panic!("expected a type alias, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        aliases,
+        vec![
+            ("UserId".to_string(), 0),
+            ("Pair".to_string(), 1),
+            ("Index".to_string(), 0),
+        ]
+    );
+}
+
+#[test]
+fn aliases_resolve_in_signatures_and_struct_fields() {
+    let source = r#"
+    type UserId = i64;
+    type Pair<T> = tuple(T, T);
+
+    struct User { id:: UserId, name:: str }
+
+    fun swap<T>(p:: Pair<T>) -> Pair<T> {
+        return (p[1], p[0]);
+    }
+
+    fun run() -> UserId {
+        let user = User { id: 40, name: "ada" };
+        let pair: Pair<i64> = (2, user.id);
+        let swapped = swap(pair);
+        return swapped[0] + swapped[1];
+    }
+    "#;
+    assert_eq!(expect_int(call(source, "run")), 42);
+}
+
+#[test]
+fn aliased_types_are_enforced_at_run_time() {
+    let source = r#"
+    type Names = vec<str>;
+
+    fun first(names:: Names) -> str {
+        return names[0];
+    }
+
+    fun run() -> str {
+        return first(5);
+    }
+    "#;
+    let err = call(source, "run").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("function argument: expected value of type vec<str>"),
+        "{err}"
+    );
+}
+
+#[test]
+fn aliases_are_visible_through_imports() {
+    let root = std::env::temp_dir().join(format!("afns-aliases-{}", std::process::id()));
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(
+        root.join("src").join("models.afml"),
+        r#"
+    type Score = i64;
+
+    pub fun double(score:: Score) -> Score {
+        return score * 2;
+    }
+    "#,
+    )
+    .unwrap();
+    let result = call_with_loader(
+        r#"
+    import models;
+
+    fun run() -> i64 {
+        let base: Score = 21;
+        return models.double(base);
+    }
+    "#,
+        "run",
+        ModuleLoader::with_root(root.clone()),
+    );
+    fs::remove_dir_all(&root).ok();
+    assert_eq!(expect_int(result), 42);
+}
+
+#[test]
+fn type_checker_expands_aliases() {
+    let errors = check_file(&parse(
+        r#"
+    type UserId = i64;
+    type Pair<T> = tuple(T, T);
+
+    fun apex() {
+        let id: UserId = "seven";
+        let names: Pair<str> = ("a", "b");
+        let wrong: Pair<i64, str> = (1, 2);
+        let mixed: Pair<bool> = (true, 1);
+    }
+    "#,
+    ));
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Mismatched types: expected `i64`, found `str`",
+            "Type alias `Pair` expects 1 type argument, got 2",
+            "Mismatched types: expected `(bool, bool)`, found `(bool, integer)`",
+        ]
+    );
+}
+
+#[test]
+fn rejects_cyclic_and_duplicate_aliases() {
+    let errors = validate(
+        r#"
+    struct User { id:: i64 }
+
+    type User = i64;
+    type Tree = vec<Tree>;
+    type A = option<B>;
+    type B = tuple(A, i64);
+    type Fine<T> = vec<T>;
+    "#,
+    );
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Duplicate type name `User`",
+            "Type alias `Tree` refers to itself",
+            "Type alias `A` refers to itself",
+            "Type alias `B` refers to itself",
+        ]
+    );
+}
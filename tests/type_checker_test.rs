@@ -0,0 +1,306 @@
+mod common;
+
+use common::parse;
+use nightscript_android::type_checker::{check_file, TypeError};
+
+fn check(source: &str) -> Vec<TypeError> {
+    check_file(&parse(source))
+}
+
+fn assert_error(source: &str, needle: &str) -> TypeError {
+    let errors = check(source);
+    errors
+        .iter()
+        .find(|e| e.message.contains(needle))
+        .cloned()
+        .unwrap_or_else(|| panic!("expected error containing {needle:?}, got: {errors:?}"))
+}
+
+fn assert_clean(source: &str) {
+    let errors = check(source);
+    assert!(errors.is_empty(), "unexpected type errors: {errors:?}");
+}
+
+#[test]
+fn well_typed_program_passes() {
+    assert_clean(
+        r#"
+    import forge.log as log;
+
+    struct Point { x:: i32, y:: i32 }
+
+    impl Point {
+        fun sum(self:: Point) -> i32 { return self.x + self.y; }
+    }
+
+    fun add(a:: i32, b:: i32) -> i32 { return a + b; }
+
+    fun apex() {
+        let p = Point { x: 1, y: 2 };
+        let total: i32 = add(p.x, p.sum());
+        log.info("total", total);
+    }
+    "#,
+    );
+}
+
+#[test]
+fn let_annotation_mismatch_points_at_value() {
+    let source = r#"
+    fun apex() {
+        let n: i32 = "five";
+    }
+    "#;
+    let err = assert_error(source, "expected `i32`, found `str`");
+    assert_eq!(err.span.line, 3);
+    assert_eq!(&source[err.span.start..err.span.end], "\"five\"");
+}
+
+#[test]
+fn inferred_let_type_flows_into_calls() {
+    assert_error(
+        r#"
+    fun square(n:: i64) -> i64 { return n * n; }
+    fun apex() {
+        let label = "x";
+        square(label);
+    }
+    "#,
+        "Argument 1 of function `square`: expected `i64`, found `str`",
+    );
+}
+
+#[test]
+fn call_arity_is_checked() {
+    assert_error(
+        r#"
+    fun add(a:: i32, b:: i32) -> i32 { return a + b; }
+    fun apex() { add(1); }
+    "#,
+        "function `add` expects 2 arguments, got 1",
+    );
+}
+
+#[test]
+fn return_value_must_match_signature() {
+    assert_error(
+        r#"
+    fun name() -> str { return 42; }
+    "#,
+        "expected `str`, found `integer`",
+    );
+}
+
+#[test]
+fn struct_literal_fields_are_checked() {
+    let source = r#"
+    struct Point { x:: i32, y:: i32 }
+    fun apex() {
+        let a = Point { x: 1 };
+        let b = Point { x: 1, y: 2, z: 3 };
+        let c = Point { x: 1, y: true };
+    }
+    "#;
+    let errors = check(source);
+    let messages = errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>();
+    assert!(messages.contains(&"Missing field `y` for struct `Point`"), "{messages:?}");
+    assert!(messages.contains(&"Struct `Point` has no field `z`"), "{messages:?}");
+    assert!(
+        messages.contains(&"Mismatched types: expected `i32`, found `bool`"),
+        "{messages:?}"
+    );
+}
+
+#[test]
+fn unknown_types_are_reported() {
+    assert_error(
+        r#"
+    fun f(x:: Strng) {}
+    "#,
+        "Unknown type `Strng`",
+    );
+}
+
+#[test]
+fn generic_arity_is_checked() {
+    assert_error(
+        r#"
+    struct Boxed<T> { value:: T }
+    fun f(b:: Boxed<i32, str>) {}
+    "#,
+        "Struct `Boxed` expects 1 type argument, got 2",
+    );
+    assert_error(
+        r#"
+    fun f(m:: map<str>) {}
+    "#,
+        "Type `map` expects 2 type arguments, got 1",
+    );
+}
+
+#[test]
+fn generic_parameters_bind_consistently() {
+    assert_error(
+        r#"
+    fun pick<T>(a:: T, b:: T) -> T { return a; }
+    fun apex() { pick(1, "one"); }
+    "#,
+        "type parameter `T` is `integer` here, found `str`",
+    );
+    assert_clean(
+        r#"
+    fun pick<T>(a:: T, b:: T) -> T { return a; }
+    fun apex() {
+        let s: str = pick("a", "b");
+    }
+    "#,
+    );
+}
+
+#[test]
+fn generic_return_type_is_instantiated() {
+    assert_error(
+        r#"
+    fun first<T>(items:: vec<T>) -> T { return items[0]; }
+    fun apex() {
+        let n: bool = first([1, 2, 3]);
+    }
+    "#,
+        "expected `bool`, found `integer`",
+    );
+}
+
+#[test]
+fn question_mark_requires_result_or_option() {
+    assert_error(
+        r#"
+    fun f() -> result<i32, str> {
+        let n = 5?;
+        return result.ok(n);
+    }
+    "#,
+        "can only be applied to `result` or `option`",
+    );
+}
+
+#[test]
+fn question_mark_requires_matching_return_type() {
+    assert_error(
+        r#"
+    fun parse(s:: str) -> result<i32, str> { return result.ok(1); }
+    fun f() -> i32 {
+        return parse("1")?;
+    }
+    "#,
+        "requires the function to return `result`",
+    );
+    assert_clean(
+        r#"
+    fun parse(s:: str) -> result<i32, str> { return result.ok(1); }
+    fun f() -> result<i32, str> {
+        let n: i32 = parse("1")?;
+        return result.ok(n + 1);
+    }
+    "#,
+    );
+}
+
+#[test]
+fn unknown_method_on_struct_is_reported() {
+    let source = r#"
+    struct Counter { n:: i32 }
+    impl Counter {
+        fun value(self:: Counter) -> i32 { return self.n; }
+    }
+    fun apex() {
+        let c = Counter { n: 1 };
+        c.value();
+        c.reset();
+    }
+    "#;
+    let err = assert_error(source, "No method named `reset` found for type `Counter`");
+    assert_eq!(&source[err.span.start..err.span.end], "c.reset()");
+}
+
+#[test]
+fn method_arguments_are_checked() {
+    assert_error(
+        r#"
+    struct Counter { n:: i32 }
+    impl Counter {
+        fun add(self:: Counter, by:: i32) -> i32 { return self.n + by; }
+    }
+    fun apex() {
+        let c = Counter { n: 1 };
+        c.add("two");
+    }
+    "#,
+        "Argument 1 of method `add`: expected `i32`, found `str`",
+    );
+}
+
+#[test]
+fn enum_variants_are_checked() {
+    assert_error(
+        r#"
+    enum Shape { Circle(f64), Square(f64) }
+    fun apex() {
+        let s = Shape::Triangle(1.0);
+    }
+    "#,
+        "Enum `Shape` has no variant `Triangle`",
+    );
+}
+
+#[test]
+fn conditions_must_be_bool() {
+    assert_error(
+        r#"
+    fun apex() {
+        let n = 3;
+        if n { }
+    }
+    "#,
+        "Condition must be `bool`, found `integer`",
+    );
+}
+
+#[test]
+fn async_calls_must_be_awaited() {
+    assert_error(
+        r#"
+    async fun load() -> i32 { return 1; }
+    async fun apex() {
+        let n: i32 = load();
+    }
+    "#,
+        "expected `i32`, found `future<i32>`",
+    );
+    assert_clean(
+        r#"
+    async fun load() -> i32 { return 1; }
+    async fun apex() {
+        let n: i32 = await load();
+    }
+    "#,
+    );
+}
+
+#[test]
+fn globals_are_checked_against_their_annotation() {
+    assert_error(
+        r#"
+    const LIMIT: i32 = "many";
+    "#,
+        "expected `i32`, found `str`",
+    );
+    assert_error(
+        r#"
+    static NAME = "server";
+    fun apex() {
+        let n: i32 = NAME;
+    }
+    "#,
+        "expected `i32`, found `str`",
+    );
+}
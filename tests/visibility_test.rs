@@ -0,0 +1,194 @@
+mod common;
+
+use std::fs;
+
+use common::{call_with_loader, expect_int};
+use nightscript_android::lexer::lex;
+use nightscript_android::module_loader::ModuleLoader;
+use nightscript_android::parser::parse_tokens_with_diagnostics;
+use nightscript_android::RuntimeError;
+
+const GEO: &str = r#"
+    pub struct Point { pub x:: i64, y:: i64 }
+
+    impl Point {
+        pub fun sum(self:: Point) -> i64 {
+            return self.x + self.y;
+        }
+
+        fun secret(self:: Point) -> i64 {
+            return self.y;
+        }
+    }
+
+    pub fun origin() -> Point {
+        return Point { x: 1, y: scale(2) };
+    }
+
+    fun scale(n:: i64) -> i64 {
+        return n * 10;
+    }
+"#;
+
+/// Runs `run` with the `geo` module above on the import path.
+fn with_geo<T>(tag: &str, run: impl FnOnce(ModuleLoader) -> T) -> T {
+    let dir = format!("afns-visibility-{}-{}", tag, std::process::id());
+    let root = std::env::temp_dir().join(dir);
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src").join("geo.afml"), GEO).unwrap();
+    let result = run(ModuleLoader::with_root(root.clone()));
+    fs::remove_dir_all(&root).ok();
+    result
+}
+
+/// The error from calling `run` in `source`, which must fail.
+fn geo_error(tag: &str, source: &str) -> RuntimeError {
+    with_geo(tag, |loader| {
+        call_with_loader(source, "run", loader).unwrap_err()
+    })
+}
+
+#[test]
+fn public_items_are_reachable_and_may_use_private_helpers() {
+    let result = with_geo("public", |loader| {
+        expect_int(call_with_loader(
+            r#"
+    import geo;
+    import geo::origin;
+
+    fun run() -> i64 {
+        let p = origin();
+        return p.x + p.sum() + geo.origin().x;
+    }
+    "#,
+            "run",
+            loader,
+        ))
+    });
+    assert_eq!(result, 1 + 21 + 1);
+}
+
+#[test]
+fn importing_a_private_function_points_at_the_import() {
+    let err = geo_error(
+        "import",
+        r#"
+    import geo::origin;
+    import geo::scale;
+
+    fun run() -> i64 {
+        return scale(1);
+    }
+    "#,
+    );
+    assert_eq!(err.message(), "`scale` is private to module `geo`");
+    assert_eq!(err.span().map(|span| span.line), Some(3));
+}
+
+#[test]
+fn private_functions_are_hidden_behind_the_module_value() {
+    let err = geo_error(
+        "access",
+        r#"
+    import geo;
+
+    fun run() -> i64 {
+        return geo.scale(1);
+    }
+    "#,
+    );
+    assert!(
+        err.message()
+            .starts_with("`scale` is private to module `geo`"),
+        "{}",
+        err.message()
+    );
+    assert_eq!(err.span().map(|span| span.line), Some(5));
+}
+
+#[test]
+fn private_fields_cannot_be_read_written_or_initialized() {
+    let read = geo_error(
+        "read",
+        r#"
+    import geo::origin;
+
+    fun run() -> i64 {
+        let p = origin();
+        return p.y;
+    }
+    "#,
+    );
+    assert!(
+        read.message()
+            .starts_with("Field `y` of struct `Point` is private to module `geo`"),
+        "{}",
+        read.message()
+    );
+
+    let write = geo_error(
+        "write",
+        r#"
+    import geo::origin;
+
+    fun run() -> i64 {
+        var p = origin();
+        p.y = 5;
+        return p.x;
+    }
+    "#,
+    );
+    assert!(
+        write
+            .message()
+            .starts_with("Field `y` of struct `Point` is private to module `geo`"),
+        "{}",
+        write.message()
+    );
+
+    let literal = geo_error(
+        "literal",
+        r#"
+    import geo;
+
+    fun run() -> i64 {
+        let p = Point { x: 1, y: 2 };
+        return p.x;
+    }
+    "#,
+    );
+    assert!(
+        literal
+            .message()
+            .starts_with("Field `y` of struct `Point` is private to module `geo`"),
+        "{}",
+        literal.message()
+    );
+}
+
+#[test]
+fn private_methods_cannot_be_called_from_other_modules() {
+    let err = geo_error(
+        "method",
+        r#"
+    import geo::origin;
+
+    fun run() -> i64 {
+        return origin().secret();
+    }
+    "#,
+    );
+    assert!(
+        err.message()
+            .starts_with("Method `secret` of `Point` is private to module `geo`"),
+        "{}",
+        err.message()
+    );
+}
+
+#[test]
+fn pub_is_rejected_on_impl_blocks() {
+    let source = "struct Point { x:: i64 }\npub impl Point {}\n";
+    let report = parse_tokens_with_diagnostics(source, lex(source).unwrap());
+    assert_eq!(report.errors.len(), 1, "{:?}", report.errors);
+}